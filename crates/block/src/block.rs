@@ -81,6 +81,24 @@ impl Block {
             Block::Genesis { block } => block.hash.clone(),
         }
     }
+
+    pub fn round(&self) -> u128 {
+        match self {
+            Block::Convergence { block } => block.header.round,
+            Block::Proposal { block } => block.round,
+            Block::Genesis { block } => block.header.round,
+        }
+    }
+
+    /// Returns the height of the block within the chain of confirmed blocks.
+    /// `ProposalBlock`s are not part of that chain and have no height.
+    pub fn height(&self) -> Option<u128> {
+        match self {
+            Block::Convergence { block } => Some(block.header.block_height),
+            Block::Proposal { .. } => None,
+            Block::Genesis { block } => Some(block.header.block_height),
+        }
+    }
 }
 
 impl fmt::Display for ConvergenceBlock {
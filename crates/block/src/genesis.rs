@@ -16,6 +16,12 @@ pub struct GenesisRewards(pub LinkedHashMap<GenesisReceiver, u128>);
 pub struct GenesisBlock {
     pub header: BlockHeader,
     pub genesis_rewards: GenesisRewards,
+    /// Hex encoded public keys of the publishers whose packages contracts
+    /// may be deployed from and called, any signed or unsigned package may
+    /// be when `None`. Every node applies the same genesis block, so they
+    /// all accept and reject the same packages
+    #[serde(default)]
+    pub trusted_package_publishers: Option<Vec<String>>,
     pub claims: ClaimList,
     pub hash: BlockHash,
     pub certificate: Option<Certificate>,
@@ -161,6 +161,7 @@ impl From<RunOpts> for NodeConfig {
             // and use that if its available thus making this generation wasteful. This is a bit of
             // a hack, but it works for now.
             keypair: default_node_config.keypair,
+            keystore: default_node_config.keystore,
             disable_networking: opts.disable_networking,
             enable_ui: opts.enable_ui,
             rendezvous_server_address: opts.rendezvous_server_address,
@@ -168,6 +169,11 @@ impl From<RunOpts> for NodeConfig {
             quorum_config: default_node_config.quorum_config,
             enable_block_indexing: default_node_config.enable_block_indexing,
             threshold_config: default_node_config.threshold_config,
+            mempool_config: default_node_config.mempool_config,
+            state_history_retention: default_node_config.state_history_retention,
+            javy_provider_package: default_node_config.javy_provider_package,
+            package_store_path: default_node_config.package_store_path,
+            trusted_package_publishers: default_node_config.trusted_package_publishers,
             whitelisted_nodes: default_node_config.whitelisted_nodes,
         }
     }
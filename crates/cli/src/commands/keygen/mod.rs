@@ -1,21 +1,110 @@
 use crate::result::{CliError, Result};
-use clap::Parser;
-use std::path::PathBuf;
+use clap::{Parser, Subcommand};
+use std::path::{Path, PathBuf};
 use telemetry::{info, warn};
-use vrrb_core::keypair::{read_keypair_file, write_keypair_file, Keypair};
+use vrrb_config::{KeystoreConfig, PassphraseSource};
+use vrrb_core::{
+    keypair::{read_keypair_file, write_keypair_file, Keypair},
+    keystore::{self, read_keystore_file, write_keystore_file, Keystore},
+};
+
+/// Environment variable the new passphrase of a keystore being re-encrypted
+/// is read from, unless a file is given
+pub const NEW_PASSPHRASE_ENV: &str = "VRRB_KEYSTORE_NEW_PASSPHRASE";
 
 #[derive(Debug, Parser)]
 pub struct KeygenCmd {
     /// Overwrite the existing keypair if it exists.
-    #[clap(long)]
+    #[clap(long, global = true)]
     force: bool,
+
+    /// File holding the keystore passphrase, read from
+    /// VRRB_KEYSTORE_PASSPHRASE when omitted
+    #[clap(long, global = true)]
+    passphrase_file: Option<PathBuf>,
+
+    #[clap(subcommand)]
+    subcommand: Option<KeystoreCmd>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum KeystoreCmd {
+    /// Encrypt the node's keypair into a password protected keystore
+    Export {
+        /// Where to write the keystore, the node's keystore by default
+        #[clap(long)]
+        out: Option<PathBuf>,
+    },
+
+    /// Make a keystore the node's keystore, once its passphrase checks out
+    Import {
+        #[clap(long)]
+        keystore: PathBuf,
+    },
+
+    /// Encrypt a keystore again under a new passphrase
+    Reencrypt {
+        /// Keystore to re-encrypt, the node's keystore by default
+        #[clap(long)]
+        path: Option<PathBuf>,
+
+        /// File holding the new passphrase, read from
+        /// VRRB_KEYSTORE_NEW_PASSPHRASE when omitted
+        #[clap(long)]
+        new_passphrase_file: Option<PathBuf>,
+    },
 }
 
 pub fn exec(args: KeygenCmd) -> Result<()> {
-    println!(
-        "PublicKey: {}",
-        keygen(args.force)?.miner_public_key_owned()
-    );
+    let subcommand = match args.subcommand {
+        Some(subcommand) => subcommand,
+        None => {
+            println!(
+                "PublicKey: {}",
+                keygen(args.force)?.miner_public_key_owned()
+            );
+
+            return Ok(());
+        }
+    };
+
+    let passphrase = keystore::read_passphrase(args.passphrase_file.as_deref())?;
+
+    match subcommand {
+        KeystoreCmd::Export { out } => {
+            let out = out.map(Ok).unwrap_or_else(keystore_path)?;
+            check_overwrite(&out, args.force)?;
+
+            let keypair = keygen(false)?;
+            write_keystore_file(&Keystore::from_keypair(&keypair, &passphrase)?, &out)?;
+
+            info!("Successfully wrote keystore to {}", out.display());
+            println!("PublicKey: {}", keypair.miner_public_key_owned());
+        }
+        KeystoreCmd::Import { keystore } => {
+            let out = keystore_path()?;
+            check_overwrite(&out, args.force)?;
+
+            let keystore = read_keystore_file(&keystore)?;
+            let keypair = keystore.to_keypair(&passphrase)?;
+            write_keystore_file(&keystore, &out)?;
+
+            info!("Successfully imported keystore to {}", out.display());
+            println!("PublicKey: {}", keypair.miner_public_key_owned());
+        }
+        KeystoreCmd::Reencrypt {
+            path,
+            new_passphrase_file,
+        } => {
+            let path = path.map(Ok).unwrap_or_else(keystore_path)?;
+            let new_passphrase = read_new_passphrase(new_passphrase_file.as_deref())?;
+
+            let keystore = read_keystore_file(&path)?.reencrypt(&passphrase, &new_passphrase)?;
+            write_keystore_file(&keystore, &path)?;
+
+            info!("Successfully re-encrypted keystore {}", path.display());
+        }
+    }
 
     Ok(())
 }
@@ -44,6 +133,69 @@ pub fn keygen(overwrite: bool) -> Result<Keypair> {
     }
 }
 
+/// Returns the keystore the node's keypair should be loaded from: the one at
+/// `path` if given, otherwise the node's keystore if it has one
+pub fn keystore_config(
+    path: Option<PathBuf>,
+    passphrase_file: Option<PathBuf>,
+) -> Result<Option<KeystoreConfig>> {
+    let path = match path {
+        Some(path) => path,
+        None => {
+            let path = keystore_path()?;
+            if !path.exists() {
+                return Ok(None);
+            }
+            path
+        }
+    };
+
+    Ok(Some(KeystoreConfig {
+        path,
+        passphrase: passphrase_file
+            .map(PassphraseSource::File)
+            .unwrap_or_default(),
+    }))
+}
+
+/// Loads the node's keypair from `keystore`, or from the plaintext keypair
+/// file when there is no keystore
+pub fn load_keypair(keystore: Option<&KeystoreConfig>) -> Result<Keypair> {
+    match keystore {
+        Some(keystore) => {
+            info!("Loading keypair from keystore {}", keystore.path.display());
+            Ok(keystore.load_keypair()?)
+        }
+        None => keygen(false),
+    }
+}
+
+/// Reads the new passphrase of a keystore being re-encrypted
+pub fn read_new_passphrase(path: Option<&Path>) -> Result<Vec<u8>> {
+    match path {
+        Some(path) => Ok(keystore::read_passphrase(Some(path))?),
+        None => Ok(keystore::read_passphrase_env(NEW_PASSPHRASE_ENV)?),
+    }
+}
+
+/// Path of the node's keystore
+fn keystore_path() -> Result<PathBuf> {
+    let data_dir = vrrb_core::storage_utils::get_node_data_dir()?;
+
+    Ok(PathBuf::from(data_dir).join("keystore.json"))
+}
+
+fn check_overwrite(path: &Path, overwrite: bool) -> Result<()> {
+    if path.exists() && !overwrite {
+        return Err(CliError::Other(format!(
+            "keystore {} already exists, pass --force to overwrite it",
+            path.display()
+        )));
+    }
+
+    Ok(())
+}
+
 fn write_new_keypair(outfile: &PathBuf) -> Result<Keypair> {
     let keypair = Keypair::random();
     write_keypair_file(&keypair, outfile)
@@ -10,7 +10,7 @@ use std::{
 use telemetry::{error, info, tracing};
 
 use uuid::Uuid;
-use vrrb_config::NodeConfig;
+use vrrb_config::{EvictionPolicy, MempoolConfig, NodeConfig};
 
 use crate::{
     commands::{
@@ -97,6 +97,41 @@ pub struct RunOpts {
 
     #[clap(long)]
     pub whitelist_path: Option<String>,
+
+    /// Encrypted keystore to load the node's keypair from. Defaults to the
+    /// node's keystore, if it has one
+    #[clap(long, value_parser)]
+    pub keystore: Option<PathBuf>,
+
+    /// File holding the keystore passphrase, read from
+    /// VRRB_KEYSTORE_PASSPHRASE when omitted
+    #[clap(long, value_parser)]
+    pub keystore_passphrase_file: Option<PathBuf>,
+
+    /// Maximum number of pending transactions kept in the mempool
+    #[clap(long, value_parser)]
+    pub mempool_max_txns: Option<usize>,
+
+    /// Maximum combined size of the pending transactions, in bytes
+    #[clap(long, value_parser)]
+    pub mempool_max_size_bytes: Option<usize>,
+
+    /// Seconds a transaction may wait in the mempool before it expires
+    #[clap(long, value_parser)]
+    pub mempool_txn_ttl_secs: Option<i64>,
+
+    /// Maximum number of pending transactions a single address may have
+    #[clap(long, value_parser)]
+    pub mempool_max_txns_per_sender: Option<usize>,
+
+    /// Which pending transactions are dropped first once the mempool is
+    /// full, either lowest-fee or oldest
+    #[clap(long, value_parser)]
+    pub mempool_eviction_policy: Option<EvictionPolicy>,
+
+    /// Writes pending transactions to disk so they survive a restart
+    #[clap(long, action, default_value = "false")]
+    pub mempool_persist: bool,
 }
 
 impl From<RunOpts> for NodeConfig {
@@ -114,6 +149,8 @@ impl From<RunOpts> for NodeConfig {
             default_node_config.http_api_title.clone()
         };
 
+        let mempool_config = opts.mempool_config(default_node_config.mempool_config.clone());
+
         Self {
             id: opts.id.unwrap_or(default_node_config.id),
             data_dir: opts.data_dir,
@@ -138,6 +175,7 @@ impl From<RunOpts> for NodeConfig {
             // and use that if its available thus making this generation wasteful. This is a bit of
             // a hack, but it works for now.
             keypair: default_node_config.keypair,
+            keystore: default_node_config.keystore,
             disable_networking: opts.disable_networking,
             enable_ui: opts.enable_ui,
             rendezvous_server_address: opts.rendezvous_server_address,
@@ -145,6 +183,11 @@ impl From<RunOpts> for NodeConfig {
             quorum_config: default_node_config.quorum_config,
             enable_block_indexing: default_node_config.enable_block_indexing,
             threshold_config: default_node_config.threshold_config,
+            mempool_config,
+            state_history_retention: default_node_config.state_history_retention,
+            javy_provider_package: default_node_config.javy_provider_package,
+            package_store_path: default_node_config.package_store_path,
+            trusted_package_publishers: default_node_config.trusted_package_publishers,
             whitelisted_nodes: default_node_config.whitelisted_nodes,
         }
     }
@@ -177,6 +220,14 @@ impl Default for RunOpts {
             rendezvous_server_address: ipv4_localhost_with_random_port,
             public_ip_address: ipv4_localhost_with_random_port,
             whitelist_path: None,
+            keystore: None,
+            keystore_passphrase_file: None,
+            mempool_max_txns: None,
+            mempool_max_size_bytes: None,
+            mempool_txn_ttl_secs: None,
+            mempool_max_txns_per_sender: None,
+            mempool_eviction_policy: None,
+            mempool_persist: Default::default(),
         }
     }
 }
@@ -199,12 +250,31 @@ impl RunOpts {
             .set_default("debug_config", false)?
             .set_default("bootstrap", false)?
             .set_default("detached", false)?
+            .set_default("mempool_persist", false)?
             .add_source(File::with_name(config_path))
             .build()?;
 
         Ok(s.try_deserialize().unwrap_or_default())
     }
 
+    /// Overrides the given mempool limits with the ones passed as options
+    fn mempool_config(&self, defaults: MempoolConfig) -> MempoolConfig {
+        MempoolConfig {
+            max_txns: self.mempool_max_txns.unwrap_or(defaults.max_txns),
+            max_size_bytes: self
+                .mempool_max_size_bytes
+                .unwrap_or(defaults.max_size_bytes),
+            txn_ttl_secs: self.mempool_txn_ttl_secs.unwrap_or(defaults.txn_ttl_secs),
+            max_txns_per_sender: self
+                .mempool_max_txns_per_sender
+                .unwrap_or(defaults.max_txns_per_sender),
+            eviction_policy: self
+                .mempool_eviction_policy
+                .unwrap_or(defaults.eviction_policy),
+            persist: self.mempool_persist || defaults.persist,
+        }
+    }
+
     pub fn merge(&self, other: &Self) -> Self {
         let node_type = match self.node_type.parse::<NodeType>() {
             Ok(_) => self.node_type.clone(),
@@ -264,16 +334,34 @@ impl RunOpts {
             rendezvous_server_address: other.rendezvous_server_address,
             public_ip_address: other.public_ip_address,
             whitelist_path: other.whitelist_path.clone(),
+            keystore: other.keystore.clone().or(self.keystore.clone()),
+            keystore_passphrase_file: other
+                .keystore_passphrase_file
+                .clone()
+                .or(self.keystore_passphrase_file.clone()),
+            mempool_max_txns: other.mempool_max_txns.or(self.mempool_max_txns),
+            mempool_max_size_bytes: other.mempool_max_size_bytes.or(self.mempool_max_size_bytes),
+            mempool_txn_ttl_secs: other.mempool_txn_ttl_secs.or(self.mempool_txn_ttl_secs),
+            mempool_max_txns_per_sender: other
+                .mempool_max_txns_per_sender
+                .or(self.mempool_max_txns_per_sender),
+            mempool_eviction_policy: other
+                .mempool_eviction_policy
+                .or(self.mempool_eviction_policy),
+            mempool_persist: other.mempool_persist,
         }
     }
 }
 
 /// Configures and runs a VRRB Node
 pub async fn run(args: RunOpts) -> Result<()> {
-    let keypair = keygen::keygen(false)?;
+    let keystore =
+        keygen::keystore_config(args.keystore.clone(), args.keystore_passphrase_file.clone())?;
+    let keypair = keygen::load_keypair(keystore.as_ref())?;
 
     let mut node_config = NodeConfig::from(args.clone());
     node_config.keypair = keypair;
+    node_config.keystore = keystore;
 
     let derived_kademlia_peer_id = derive_kademlia_peer_id_from_node_id(&node_config.id)?;
     node_config.kademlia_peer_id = Some(derived_kademlia_peer_id);
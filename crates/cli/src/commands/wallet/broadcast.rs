@@ -0,0 +1,25 @@
+use std::path::Path;
+
+use vrrb_core::transactions::{RpcTransactionDigest, TransactionKind, UnsignedTransaction};
+use wallet::v2::Wallet;
+
+use crate::result::{CliError, Result};
+
+/// Submits the signed transaction at `path` through `createTxn`. Multisig
+/// transactions written by cosign are assembled first, once enough keys
+/// have signed them.
+pub(crate) async fn exec(wallet: &mut Wallet, path: &Path) -> Result<RpcTransactionDigest> {
+    let ser_txn = std::fs::read_to_string(path)?;
+
+    let txn = match serde_json::from_str::<UnsignedTransaction>(&ser_txn) {
+        Ok(unsigned) => unsigned
+            .assemble()
+            .map_err(|err| CliError::Other(format!("unable to assemble transaction: {err}")))?,
+        Err(_) => serde_json::from_str::<TransactionKind>(&ser_txn)
+            .map_err(|err| CliError::Other(format!("unable to read signed transaction: {err}")))?,
+    };
+
+    let digest = wallet.broadcast_transaction(txn).await?;
+
+    Ok(digest)
+}
@@ -0,0 +1,38 @@
+use std::path::Path;
+
+use primitives::Address;
+use vrrb_core::transactions::Token;
+use wallet::v2::Wallet;
+
+use crate::result::{CliError, Result};
+
+/// Writes an unsigned transfer to `out`, to be signed by `wallet sign`, or
+/// by `wallet cosign` when sent from the multisig account at `multisig`
+pub(crate) async fn exec(
+    wallet: &Wallet,
+    from: &str,
+    multisig: Option<Address>,
+    to: Address,
+    amount: u128,
+    token: Token,
+    out: &Path,
+) -> Result<()> {
+    let timestamp = chrono::Utc::now().timestamp();
+
+    let unsigned = match multisig {
+        Some(multisig) => {
+            wallet
+                .build_multisig_transaction_from(from, multisig, to, amount, token, timestamp)
+                .await?
+        }
+        None => wallet.build_transaction_from(from, to, amount, token, timestamp)?,
+    };
+    let ser_unsigned =
+        serde_json::to_string_pretty(&unsigned).map_err(|e| CliError::Other(e.to_string()))?;
+
+    std::fs::write(out, ser_unsigned)?;
+
+    println!("wrote unsigned transaction to {}", out.display());
+
+    Ok(())
+}
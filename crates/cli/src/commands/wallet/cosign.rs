@@ -0,0 +1,65 @@
+use std::{collections::BTreeMap, path::Path};
+
+use vrrb_core::transactions::UnsignedTransaction;
+use wallet::v2::AddressAlias;
+
+use super::keys::IdentitySecret;
+use crate::result::{CliError, Result};
+
+/// Adds the identity's partial signature to the multisig transaction at
+/// `path`, writing it back in place for the next signer. Like
+/// [sign::exec](super::sign::exec), the transaction is only signed once
+/// confirmed, unless `yes` is set. Never touches the network.
+pub(crate) fn exec(
+    secret: &IdentitySecret,
+    aliases: &BTreeMap<AddressAlias, u32>,
+    path: &Path,
+    yes: bool,
+) -> Result<()> {
+    let ser_unsigned = std::fs::read_to_string(path)?;
+    let mut unsigned: UnsignedTransaction = serde_json::from_str(&ser_unsigned)
+        .map_err(|err| CliError::Other(format!("unable to read unsigned transaction: {err}")))?;
+
+    let policy = unsigned.multisig.clone().ok_or_else(|| {
+        CliError::Other("transaction is not sent from a multisig account".to_string())
+    })?;
+
+    super::sign::confirm(&unsigned, yes)?;
+
+    // NOTE: identities holding several keys of the policy sign with the
+    // first one that has not signed yet
+    let secret_key = policy
+        .public_keys()
+        .iter()
+        .filter(|public_key| {
+            !unsigned
+                .signatures
+                .iter()
+                .any(|signature| &signature.public_key == *public_key)
+        })
+        .find_map(|public_key| secret.secret_key_of(public_key, aliases).ok())
+        .ok_or_else(|| {
+            CliError::Other(
+                "identity holds none of the keys of the multisig account left to sign".to_string(),
+            )
+        })?;
+
+    let signature = unsigned
+        .cosign(&secret_key)
+        .map_err(|err| CliError::Other(format!("unable to cosign transaction: {err}")))?;
+
+    let ser_unsigned =
+        serde_json::to_string_pretty(&unsigned).map_err(|e| CliError::Other(e.to_string()))?;
+
+    std::fs::write(path, ser_unsigned)?;
+
+    println!(
+        "added the signature of {} to {}, {} of {} required signatures collected",
+        signature.public_key,
+        path.display(),
+        unsigned.signatures.len(),
+        policy.threshold()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,27 @@
+use primitives::Address;
+use secp256k1::PublicKey;
+use vrrb_core::{multisig::MultisigPolicy, transactions::RpcTransactionDigest};
+use wallet::v2::Wallet;
+
+use crate::result::{CliError, Result};
+
+/// Creates a multisig account spendable by `threshold` of `keys`, funding it
+/// with `amount` from the address named `from`. Returns the account's address
+/// and the digest of the transfer creating it.
+pub async fn exec(
+    wallet: &mut Wallet,
+    from: &str,
+    keys: Vec<PublicKey>,
+    threshold: u8,
+    amount: u128,
+) -> Result<(Address, RpcTransactionDigest)> {
+    let policy = MultisigPolicy::new(keys, threshold)
+        .map_err(|err| CliError::Other(format!("invalid multisig policy: {err}")))?;
+    let timestamp = chrono::Utc::now().timestamp();
+
+    let created = wallet
+        .create_multisig_account(from, policy, amount, timestamp)
+        .await?;
+
+    Ok(created)
+}
@@ -0,0 +1,25 @@
+use std::path::Path;
+
+use wallet::v2::Wallet;
+
+use crate::result::{CliError, Result};
+
+pub(crate) async fn exec(wallet: &Wallet, path: &Path) -> Result<()> {
+    let snapshot = wallet
+        .export_mempool()
+        .await
+        .map_err(|err| CliError::Other(err.to_string()))?;
+
+    let ser_snapshot =
+        serde_json::to_string_pretty(&snapshot).map_err(|e| CliError::Other(e.to_string()))?;
+
+    std::fs::write(path, ser_snapshot)?;
+
+    println!(
+        "exported {} transactions to {}",
+        snapshot.len(),
+        path.display()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,22 @@
+use std::path::Path;
+
+use vrrb_rpc::rpc::api::MempoolSnapshot;
+use wallet::v2::Wallet;
+
+use crate::result::{CliError, Result};
+
+pub(crate) async fn exec(wallet: &Wallet, path: &Path) -> Result<()> {
+    let ser_snapshot = std::fs::read_to_string(path)?;
+
+    let snapshot: MempoolSnapshot =
+        serde_json::from_str(&ser_snapshot).map_err(|e| CliError::Other(e.to_string()))?;
+
+    let count = wallet
+        .import_mempool(snapshot)
+        .await
+        .map_err(|err| CliError::Other(err.to_string()))?;
+
+    println!("imported {count} transactions from {}", path.display());
+
+    Ok(())
+}
@@ -0,0 +1,205 @@
+use std::{collections::BTreeMap, fs::OpenOptions, io::Write, path::Path};
+
+use secp256k1::{PublicKey, Secp256k1, SecretKey};
+use vrrb_core::keystore::{
+    self, write_keystore_file, Keystore, KeystoreKind, KEYSTORE_PASSPHRASE_ENV,
+};
+use wallet::v2::{
+    hd::{self, HdKeychain, Mnemonic},
+    AddressAlias,
+};
+
+use crate::result::{CliError, Result};
+
+/// The secret an identity's keys come from
+pub(crate) enum IdentitySecret {
+    /// Phrase the keys of every address of the identity are derived from
+    Mnemonic(Mnemonic),
+    /// Single key of identities created before wallets were restored from
+    /// mnemonic phrases
+    SecretKey(SecretKey, PublicKey),
+}
+
+impl IdentitySecret {
+    pub(crate) fn encrypt(&self, passphrase: &[u8]) -> Result<Keystore> {
+        let keystore = match self {
+            IdentitySecret::Mnemonic(mnemonic) => Keystore::encrypt(
+                KeystoreKind::Mnemonic,
+                mnemonic.to_string().as_bytes(),
+                None,
+                passphrase,
+            )?,
+            IdentitySecret::SecretKey(secret_key, public_key) => Keystore::encrypt(
+                KeystoreKind::SecretKey,
+                &secret_key.secret_bytes(),
+                Some(public_key.to_string()),
+                passphrase,
+            )?,
+        };
+
+        Ok(keystore)
+    }
+
+    /// Returns the secret key of `public_key`, looking through the default
+    /// address and the named addresses of the identity
+    pub(crate) fn secret_key_of(
+        &self,
+        public_key: &PublicKey,
+        aliases: &BTreeMap<AddressAlias, u32>,
+    ) -> Result<SecretKey> {
+        match self {
+            IdentitySecret::SecretKey(secret_key, key) if key == public_key => {
+                return Ok(*secret_key)
+            }
+            IdentitySecret::SecretKey(..) => {}
+            IdentitySecret::Mnemonic(mnemonic) => {
+                let keychain = HdKeychain::from_mnemonic(mnemonic, "")?;
+                for index in std::iter::once(0).chain(aliases.values().copied()) {
+                    let (secret_key, key) = keychain.derive(index)?;
+                    if &key == public_key {
+                        return Ok(secret_key);
+                    }
+                }
+            }
+        }
+
+        Err(CliError::Other(format!(
+            "identity holds no secret key of {public_key}"
+        )))
+    }
+
+    pub(crate) fn decrypt(keystore: &Keystore, passphrase: &[u8]) -> Result<Self> {
+        let secret = keystore.decrypt(passphrase)?;
+
+        match keystore.kind {
+            KeystoreKind::Mnemonic => {
+                let phrase = String::from_utf8(secret)
+                    .map_err(|_| CliError::Other("invalid mnemonic phrase in keystore".into()))?;
+
+                Ok(IdentitySecret::Mnemonic(hd::parse_mnemonic(&phrase)?))
+            }
+            KeystoreKind::SecretKey => {
+                let secret_key = SecretKey::from_slice(&secret)
+                    .map_err(|err| CliError::Other(format!("invalid secret key: {err}")))?;
+                let public_key = PublicKey::from_secret_key(&Secp256k1::new(), &secret_key);
+
+                Ok(IdentitySecret::SecretKey(secret_key, public_key))
+            }
+            KeystoreKind::Keypair => Err(CliError::Other(
+                "keystore holds a node keypair, not wallet keys".to_string(),
+            )),
+        }
+    }
+}
+
+/// Reads the passphrase of the identity's keystore from `path` or the
+/// environment, none when neither is set
+pub(crate) fn read_optional_passphrase(path: Option<&Path>) -> Result<Option<Vec<u8>>> {
+    if path.is_none() && std::env::var_os(KEYSTORE_PASSPHRASE_ENV).is_none() {
+        return Ok(None);
+    }
+
+    Ok(Some(keystore::read_passphrase(path)?))
+}
+
+/// Reads the mnemonic phrase at `path`, generating and writing a new one
+/// when there is none yet. Returns whether the phrase was generated.
+pub(crate) fn read_or_generate_mnemonic(
+    path: &Path,
+    keystore_path: &Path,
+    passphrase: Option<&[u8]>,
+) -> Result<(Mnemonic, bool)> {
+    if path.exists() {
+        return Ok((read_mnemonic(path)?, false));
+    }
+
+    telemetry::info!("Generating new mnemonic phrase");
+
+    let mnemonic = hd::generate_mnemonic(hd::DEFAULT_MNEMONIC_WORD_COUNT)?;
+    write_mnemonic(path, keystore_path, &mnemonic, passphrase)?;
+
+    Ok((mnemonic, true))
+}
+
+pub(crate) fn read_mnemonic(path: &Path) -> Result<Mnemonic> {
+    let phrase = std::fs::read_to_string(path)
+        .map_err(|err| CliError::Other(format!("unable to read mnemonic file: {err}")))?;
+
+    Ok(hd::parse_mnemonic(&phrase)?)
+}
+
+/// Writes the mnemonic phrase to the keystore at `keystore_path` when there
+/// is a passphrase to encrypt it with, and in plaintext to `path` otherwise
+pub(crate) fn write_mnemonic(
+    path: &Path,
+    keystore_path: &Path,
+    mnemonic: &Mnemonic,
+    passphrase: Option<&[u8]>,
+) -> Result<()> {
+    if let Some(passphrase) = passphrase {
+        let keystore = IdentitySecret::Mnemonic(mnemonic.clone()).encrypt(passphrase)?;
+        write_keystore_file(&keystore, keystore_path)?;
+
+        return Ok(());
+    }
+
+    telemetry::warn!(
+        "Writing mnemonic phrase unencrypted, set {KEYSTORE_PASSPHRASE_ENV} or pass \
+         --passphrase-file to encrypt it"
+    );
+    write_private_file(path, mnemonic.to_string().as_bytes())
+        .map_err(|err| CliError::Other(format!("unable to write mnemonic file: {err}")))?;
+
+    Ok(())
+}
+
+/// Writes `contents` to a file readable only by its owner, tightening the
+/// permissions of a file that already exists
+fn write_private_file(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut file = {
+        #[cfg(not(unix))]
+        {
+            OpenOptions::new()
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            OpenOptions::new().mode(0o600)
+        }
+    }
+    .write(true)
+    .truncate(true)
+    .create(true)
+    .open(path)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+
+    file.write_all(contents)
+}
+
+/// Reads the derivation index of every named address, none when the file
+/// does not exist yet
+pub(crate) fn read_aliases(path: &Path) -> Result<BTreeMap<AddressAlias, u32>> {
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+
+    let contents = std::fs::read(path)?;
+    let aliases = serde_json::from_slice(&contents)
+        .map_err(|err| CliError::Other(format!("unable to read address aliases: {err}")))?;
+
+    Ok(aliases)
+}
+
+pub(crate) fn write_aliases(path: &Path, aliases: &BTreeMap<AddressAlias, u32>) -> Result<()> {
+    let contents = serde_json::to_string_pretty(aliases)
+        .map_err(|err| CliError::Other(format!("unable to serialize address aliases: {err}")))?;
+
+    std::fs::write(path, contents)?;
+
+    Ok(())
+}
@@ -1,19 +1,37 @@
+mod broadcast;
+mod build_transfer;
+mod cosign;
+mod create_multisig;
+mod export_mempool;
 mod get;
 mod get_mempool;
+mod import_mempool;
 mod info;
+mod keys;
 mod new;
+mod sign;
+mod sync;
 mod transfer;
 
 use std::{net::SocketAddr, path::PathBuf, str::FromStr};
 
 use clap::{Parser, Subcommand};
 use primitives::Address;
+use secp256k1::PublicKey;
 use serde_json;
 use vrrb_core::helpers::read_or_generate_keypair_file;
+use vrrb_core::keystore::{self, read_keystore_file, write_keystore_file};
 use vrrb_core::transactions::Token;
-use wallet::v2::{Wallet, WalletConfig};
+use wallet::v2::{
+    hd::{self, HdKeychain},
+    AddressAlias, Wallet, WalletConfig, DEFAULT_ADDRESS_ALIAS,
+};
 
-use crate::result::{CliError, Result};
+use self::keys::IdentitySecret;
+use crate::{
+    commands::keygen,
+    result::{CliError, Result},
+};
 
 #[derive(Parser, Debug)]
 pub struct WalletOpts {
@@ -24,6 +42,11 @@ pub struct WalletOpts {
     #[clap(long, default_value = "default")]
     pub identity: String,
 
+    /// File holding the passphrase of the identity's keystore, read from
+    /// VRRB_KEYSTORE_PASSPHRASE when omitted
+    #[clap(long)]
+    pub passphrase_file: Option<PathBuf>,
+
     #[clap(subcommand)]
     pub subcommand: WalletCmd,
 }
@@ -35,10 +58,26 @@ pub enum WalletCmd {
 
     /// Transfer objects between accounts
     Transfer {
+        /// Alias of the address to send from
+        #[clap(long, default_value = DEFAULT_ADDRESS_ALIAS)]
+        from: AddressAlias,
+
+        #[clap(long)]
+        to: Address,
+
         #[clap(long)]
-        //TODO: revise this when hierarchically deterministic accounts are implemented
-        // TODO: replace u32 with address aliases so they're easier to use
-        // from: AddressAlias,
+        amount: u128,
+
+        #[clap(long)]
+        token: Option<Token>,
+    },
+
+    /// Build a transfer without signing it, to be signed by the sign command
+    BuildTransfer {
+        /// Alias of the address to send from
+        #[clap(long, default_value = DEFAULT_ADDRESS_ALIAS)]
+        from: AddressAlias,
+
         #[clap(long)]
         to: Address,
 
@@ -47,17 +86,117 @@ pub enum WalletCmd {
 
         #[clap(long)]
         token: Option<Token>,
+
+        /// Multisig account to send from. The address named by --from must
+        /// hold one of its keys
+        #[clap(long)]
+        multisig: Option<Address>,
+
+        /// Where to write the unsigned transaction
+        #[clap(long)]
+        out: PathBuf,
+    },
+
+    /// Sign a transaction written by build-transfer. Works offline, so it
+    /// can be run on a machine that never connects to a node
+    Sign {
+        /// The unsigned transaction
+        #[clap(long)]
+        txn: PathBuf,
+
+        /// Where to write the signed transaction
+        #[clap(long)]
+        out: PathBuf,
+
+        /// Sign without showing the transaction and asking for confirmation
+        #[clap(long)]
+        yes: bool,
+    },
+
+    /// Add the identity's signature to a multisig transaction written by
+    /// build-transfer, in place. Works offline, like the sign command
+    Cosign {
+        /// The multisig transaction
+        #[clap(long)]
+        txn: PathBuf,
+
+        /// Sign without showing the transaction and asking for confirmation
+        #[clap(long)]
+        yes: bool,
+    },
+
+    /// Submit a transaction signed by the sign command, or a multisig
+    /// transaction cosigned by enough of its keys
+    Broadcast {
+        /// The signed transaction
+        #[clap(long)]
+        txn: PathBuf,
+    },
+
+    /// Create an account spendable by a threshold of several keys, through a
+    /// transfer that funds it
+    CreateMultisig {
+        /// Alias of the address funding the account and paying the fee
+        #[clap(long, default_value = DEFAULT_ADDRESS_ALIAS)]
+        from: AddressAlias,
+
+        /// Public key allowed to sign for the account, repeated for each key
+        #[clap(long = "key", required = true)]
+        keys: Vec<PublicKey>,
+
+        /// Number of keys that must sign to spend from the account
+        #[clap(long)]
+        threshold: u8,
+
+        /// Amount sent to the account when it is created
+        #[clap(long, default_value_t = 0)]
+        amount: u128,
+    },
+
+    /// Refresh the nonce, balance and history of the wallet's addresses
+    /// from the node
+    Sync {
+        /// Alias of the address to sync, every address when omitted
+        #[clap(long)]
+        alias: Option<AddressAlias>,
+    },
+
+    /// Derive a new address from the wallet's mnemonic phrase
+    New {
+        /// Name to refer to the address by
+        #[clap(long)]
+        alias: AddressAlias,
+    },
+
+    /// Restore the identity's keys from a BIP-39 mnemonic phrase
+    Restore {
+        #[clap(long)]
+        phrase: String,
+    },
+
+    /// Write the identity's keys to a password protected keystore
+    Export {
+        #[clap(long)]
+        out: PathBuf,
     },
 
-    //TODO: revise this when hierarchically deterministic accounts are implemented
-    /// Create a new account
-    New, /*{
-             #[clap(long)]
-             alias: AddressAlias,
-         }*/
+    /// Restore the identity's keys from a keystore written by export
+    Import {
+        #[clap(long)]
+        keystore: PathBuf,
+    },
+
+    /// Encrypt the identity's keystore again under a new passphrase
+    Reencrypt {
+        /// File holding the new passphrase, read from
+        /// VRRB_KEYSTORE_NEW_PASSPHRASE when omitted
+        #[clap(long)]
+        new_passphrase_file: Option<PathBuf>,
+    },
 
     /// Gets information about an account
     Get {
+        /// Alias of one of the wallet's addresses, or any address
         #[clap(long)]
         address: String,
     },
@@ -67,6 +206,18 @@ pub enum WalletCmd {
         #[clap(long)]
         limit: Option<usize>,
     },
+
+    /// Writes every pending transaction within mempool to a JSON file
+    ExportMempool {
+        #[clap(long)]
+        path: PathBuf,
+    },
+
+    /// Submits every transaction within a JSON file written by export-mempool
+    ImportMempool {
+        #[clap(long)]
+        path: PathBuf,
+    },
 }
 
 pub async fn exec(args: WalletOpts) -> Result<()> {
@@ -82,60 +233,216 @@ pub async fn exec(args: WalletOpts) -> Result<()> {
     std::fs::create_dir_all(&data_dir)?;
     std::fs::create_dir_all(&accounts_data_dir)?;
 
-    // NOTE: master keypair
-    let keypair_file_path = PathBuf::from(&data_dir).join(args.identity);
+    // NOTE: master keypair, for identities created before wallets were
+    // restored from mnemonic phrases
+    let keypair_file_path = PathBuf::from(&data_dir).join(&args.identity);
+    let mnemonic_file_path = PathBuf::from(&data_dir).join(format!("{}.mnemonic", args.identity));
+    let keystore_file_path = PathBuf::from(&data_dir).join(format!("{}.keystore", args.identity));
+    let aliases_file_path = accounts_data_dir.join(format!("{}.json", args.identity));
+    let cache_file_path = accounts_data_dir.join(format!("{}.cache.json", args.identity));
+
+    let passphrase_file = args.passphrase_file.as_deref();
+    let has_keys =
+        keypair_file_path.exists() || mnemonic_file_path.exists() || keystore_file_path.exists();
+
+    match &sub_cmd {
+        WalletCmd::Restore { phrase } => {
+            if has_keys {
+                return Err(CliError::Other(format!(
+                    "identity {} already has keys",
+                    args.identity
+                )));
+            }
+
+            let mnemonic = hd::parse_mnemonic(phrase)?;
+            keys::write_mnemonic(
+                &mnemonic_file_path,
+                &keystore_file_path,
+                &mnemonic,
+                keys::read_optional_passphrase(passphrase_file)?.as_deref(),
+            )?;
+            println!("Restored identity {}", args.identity);
+
+            return Ok(());
+        },
+        WalletCmd::Import { keystore } => {
+            if has_keys {
+                return Err(CliError::Other(format!(
+                    "identity {} already has keys",
+                    args.identity
+                )));
+            }
+
+            let keystore = read_keystore_file(keystore)?;
+            IdentitySecret::decrypt(&keystore, &keystore::read_passphrase(passphrase_file)?)?;
+            write_keystore_file(&keystore, &keystore_file_path)?;
+            println!("Imported identity {}", args.identity);
+
+            return Ok(());
+        },
+        WalletCmd::Reencrypt {
+            new_passphrase_file,
+        } => {
+            let keystore = read_keystore_file(&keystore_file_path)?.reencrypt(
+                &keystore::read_passphrase(passphrase_file)?,
+                &keygen::read_new_passphrase(new_passphrase_file.as_deref())?,
+            )?;
+            write_keystore_file(&keystore, &keystore_file_path)?;
+            println!("Re-encrypted keystore of identity {}", args.identity);
+
+            return Ok(());
+        },
+        _ => {},
+    }
+
+    let secret = if keystore_file_path.exists() {
+        let keystore = read_keystore_file(&keystore_file_path)?;
+
+        IdentitySecret::decrypt(&keystore, &keystore::read_passphrase(passphrase_file)?)?
+    } else if keypair_file_path.exists() {
+        let (secret_key, public_key) = read_or_generate_keypair_file(&keypair_file_path)?;
 
-    let keypair = read_or_generate_keypair_file(&keypair_file_path)?;
+        IdentitySecret::SecretKey(secret_key, public_key)
+    } else {
+        let (mnemonic, generated) = keys::read_or_generate_mnemonic(
+            &mnemonic_file_path,
+            &keystore_file_path,
+            keys::read_optional_passphrase(passphrase_file)?.as_deref(),
+        )?;
+        if generated {
+            eprintln!(
+                "Generated a new mnemonic phrase for identity {}. Write it down, it is the only \
+                 way to recover the wallet:\n{mnemonic}",
+                args.identity
+            );
+        }
 
-    let (secret_key, public_key) = keypair;
+        IdentitySecret::Mnemonic(mnemonic)
+    };
+
+    if let WalletCmd::Sign { txn, out, yes } = &sub_cmd {
+        let aliases = keys::read_aliases(&aliases_file_path)?;
+
+        return sign::exec(&secret, &aliases, txn, out, *yes);
+    }
 
-    // let (accounts, addresses) = restore_accounts_and_addresses(&accounts_data_dir)?;
+    if let WalletCmd::Cosign { txn, yes } = &sub_cmd {
+        let aliases = keys::read_aliases(&aliases_file_path)?;
 
-    let wallet_config = WalletConfig {
-        rpc_server_address,
-        secret_key,
-        public_key,
-        // accounts,
-        // addresses,
+        return cosign::exec(&secret, &aliases, txn, *yes);
+    }
+
+    if let WalletCmd::Export { out } = &sub_cmd {
+        let keystore = secret.encrypt(&keystore::read_passphrase(passphrase_file)?)?;
+        write_keystore_file(&keystore, out)?;
+        println!("Exported identity {} to {}", args.identity, out.display());
+
+        return Ok(());
+    }
+
+    let wallet_config = match secret {
+        IdentitySecret::SecretKey(secret_key, public_key) => WalletConfig {
+            rpc_server_address,
+            secret_key,
+            public_key,
+            ..Default::default()
+        },
+        IdentitySecret::Mnemonic(mnemonic) => {
+            let keychain = HdKeychain::from_mnemonic(&mnemonic, "")?;
+            let aliases = keys::read_aliases(&aliases_file_path)?;
+
+            WalletConfig::from_keychain(rpc_server_address, keychain, aliases)?
+        },
     };
 
     let mut wallet = Wallet::new(wallet_config)
         .await
         .map_err(|err| CliError::Other(format!("unable to create wallet: {err}")))?;
+    wallet.restore_cache(sync::read_cache(&cache_file_path)?);
 
-    match sub_cmd {
+    let result = match sub_cmd {
         WalletCmd::Info => info::exec(&wallet).await,
         WalletCmd::Transfer {
-            //TODO: revise this when hierarchically deterministic accounts are implemented
-            // from: address_number,
+            from,
             to,
             amount,
             token,
         } => {
-            let digest = transfer::exec(
-                &mut wallet,
-                // address_number,
+            wallet.sync_address(&from).await?;
+            let digest =
+                transfer::exec(&mut wallet, &from, to, amount, token.unwrap_or_default()).await?;
+
+            println!("{digest}");
+
+            Ok(())
+        },
+        WalletCmd::BuildTransfer {
+            from,
+            to,
+            amount,
+            token,
+            multisig,
+            out,
+        } => {
+            wallet.sync_address(&from).await?;
+
+            build_transfer::exec(
+                &wallet,
+                &from,
+                multisig,
                 to,
                 amount,
                 token.unwrap_or_default(),
+                &out,
             )
-            .await?;
+            .await
+        },
+        WalletCmd::Broadcast { txn } => {
+            let digest = broadcast::exec(&mut wallet, &txn).await?;
 
             println!("{digest}");
 
             Ok(())
         },
-        //TODO: revise this when hierarchically deterministic accounts are implemented
-        //
-        WalletCmd::New { /*alias*/ } => {
-            new::exec(/*&mut wallet,*/ &accounts_data_dir /*, alias*/).await?;
+        WalletCmd::CreateMultisig {
+            from,
+            keys,
+            threshold,
+            amount,
+        } => {
+            wallet.sync_address(&from).await?;
+            let (address, digest) =
+                create_multisig::exec(&mut wallet, &from, keys, threshold, amount).await?;
+
+            println!("{address}");
+            println!("{digest}");
 
             Ok(())
         },
-        WalletCmd::Get { address } => {
-            let address = Address::from_str(&address)
+        WalletCmd::Sync { alias } => sync::exec(&mut wallet, alias.as_deref()).await,
+        WalletCmd::New { alias } => {
+            let address = new::exec(&mut wallet, &aliases_file_path, alias).await?;
+            let address_info = serde_json::to_string_pretty(&address)
                 .map_err(|err| CliError::Other(err.to_string()))?;
 
+            println!("{address_info}");
+
+            Ok(())
+        },
+        WalletCmd::Restore { .. }
+        | WalletCmd::Import { .. }
+        | WalletCmd::Reencrypt { .. }
+        | WalletCmd::Export { .. }
+        | WalletCmd::Sign { .. }
+        | WalletCmd::Cosign { .. } => Ok(()),
+        WalletCmd::Get { address } => {
+            let address = match wallet.get_address(&address) {
+                Some(wallet_address) => wallet_address.address.clone(),
+                None => {
+                    Address::from_str(&address).map_err(|err| CliError::Other(err.to_string()))?
+                },
+            };
+
             if let Ok(account) = get::exec(&mut wallet, address).await {
                 let account_info = serde_json::to_string_pretty(&account)
                     .map_err(|err| CliError::Other(err.to_string()))?;
@@ -150,5 +457,19 @@ pub async fn exec(args: WalletOpts) -> Result<()> {
 
             Ok(())
         },
-    }
+        WalletCmd::ExportMempool { path } => {
+            export_mempool::exec(&wallet, &path).await?;
+
+            Ok(())
+        },
+        WalletCmd::ImportMempool { path } => {
+            import_mempool::exec(&wallet, &path).await?;
+
+            Ok(())
+        },
+    };
+
+    sync::write_cache(&cache_file_path, wallet.cache())?;
+
+    result
 }
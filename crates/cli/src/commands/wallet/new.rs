@@ -1,43 +1,20 @@
 use std::path::Path;
 
-use primitives::Address;
-use secp256k1::{generate_keypair, rand};
-use vrrb_core::account::Account;
-use vrrb_core::helpers::write_keypair_file;
+use wallet::v2::{AddressAlias, Wallet, WalletAddress};
 
-use crate::result::CliError;
+use super::keys;
+use crate::result::Result;
 
+/// Derives a new address named `alias` and records its index alongside the
+/// other aliases of the wallet
 pub async fn exec(
-    /*wallet: &mut Wallet, */ path: &Path, /*alias: AddressAlias*/
-) -> Result<(), CliError> {
-    // TODO: revise when hierarchically deterministic accounts are implemented
-    // TODO: read keypair from file
+    wallet: &mut Wallet,
+    aliases_path: &Path,
+    alias: AddressAlias,
+) -> Result<WalletAddress> {
+    let address = wallet.derive_address(alias)?;
 
-    let (secret_key, public_key) = generate_keypair(&mut rand::thread_rng());
+    keys::write_aliases(aliases_path, &wallet.address_indices())?;
 
-    let account_data_dir = path.join("account");
-    // let account_data_dir = path.join(format!("{alias}"));
-
-    std::fs::create_dir_all(&account_data_dir)?;
-
-    let key_path = account_data_dir.join("keys");
-    let account_path = account_data_dir.join("account.json");
-
-    // let (_, account) = wallet
-    //     .create_account(alias, public_key)
-    //     .await
-    //     .map_err(|err| CliError::Other(format!("unable to create account in state: {err}")))?;
-    let address = Address::from(public_key);
-    let account = Account::new(address);
-
-    write_keypair_file(key_path, &(secret_key, public_key))
-        .map_err(|err| CliError::Other(format!("unable to write keypair file: {err}")))?;
-
-    let account_ser = serde_json::to_string_pretty(&account)
-        .map_err(|err| CliError::Other(format!("unable to serialize account data: {err}")))?;
-
-    std::fs::write(account_path, account_ser)
-        .map_err(|err| CliError::Other(format!("unable to write account file: {err}")))?;
-
-    Ok(())
+    Ok(address)
 }
@@ -0,0 +1,85 @@
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, Write},
+    path::Path,
+};
+
+use vrrb_core::transactions::{Transaction, UnsignedTransaction};
+use wallet::v2::AddressAlias;
+
+use super::keys::IdentitySecret;
+use crate::result::{CliError, Result};
+
+/// Signs the unsigned transaction at `path` with the identity's key of its
+/// sender, writing the signed transaction to `out`. The transaction is shown
+/// and only signed once confirmed, unless `yes` is set. Never touches the
+/// network.
+pub(crate) fn exec(
+    secret: &IdentitySecret,
+    aliases: &BTreeMap<AddressAlias, u32>,
+    path: &Path,
+    out: &Path,
+    yes: bool,
+) -> Result<()> {
+    let ser_unsigned = std::fs::read_to_string(path)?;
+    let unsigned: UnsignedTransaction = serde_json::from_str(&ser_unsigned)
+        .map_err(|err| CliError::Other(format!("unable to read unsigned transaction: {err}")))?;
+
+    confirm(&unsigned, yes)?;
+
+    let secret_key = secret.secret_key_of(&unsigned.txn.sender_public_key(), aliases)?;
+    let txn = unsigned
+        .sign(&secret_key)
+        .map_err(|err| CliError::Other(format!("unable to sign transaction: {err}")))?;
+
+    let ser_txn = serde_json::to_string_pretty(&txn).map_err(|e| CliError::Other(e.to_string()))?;
+
+    std::fs::write(out, ser_txn)?;
+
+    println!(
+        "signed transaction {} and wrote it to {}",
+        txn.id().digest_string(),
+        out.display()
+    );
+
+    Ok(())
+}
+
+/// Shows what signing `unsigned` commits to and asks for confirmation on
+/// stdin, unless `yes` is set. The fields shown are read from the
+/// transaction itself, which the signing payload is checked against before
+/// anything is signed.
+pub(crate) fn confirm(unsigned: &UnsignedTransaction, yes: bool) -> Result<()> {
+    let txn = &unsigned.txn;
+    let token = txn.token();
+
+    eprintln!("sender:   {}", txn.sender_address());
+    if let Some(policy) = &unsigned.multisig {
+        eprintln!(
+            "          multisig account, {} of {} keys must sign",
+            policy.threshold(),
+            policy.public_keys().len()
+        );
+    }
+    eprintln!("receiver: {}", txn.receiver_address());
+    eprintln!("amount:   {} {}", txn.amount(), token.symbol);
+    eprintln!("fee:      {}", txn.fee());
+    eprintln!("nonce:    {}", txn.nonce());
+
+    if yes {
+        return Ok(());
+    }
+
+    eprint!("Sign this transaction? [y/N] ");
+    std::io::stderr().flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+
+    match answer.trim().to_lowercase().as_str() {
+        "y" | "yes" => Ok(()),
+        _ => Err(CliError::Other(
+            "transaction was not signed, pass --yes to sign without confirming".to_string(),
+        )),
+    }
+}
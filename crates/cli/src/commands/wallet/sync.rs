@@ -0,0 +1,46 @@
+use std::path::Path;
+
+use wallet::v2::{sync::WalletCache, Wallet};
+
+use crate::result::{CliError, Result};
+
+/// Refreshes the cached state of the address named `alias`, or of every
+/// address of the wallet, and prints it
+pub async fn exec(wallet: &mut Wallet, alias: Option<&str>) -> Result<()> {
+    let state = match alias {
+        Some(alias) => serde_json::to_string_pretty(wallet.sync_address(alias).await?),
+        None => {
+            wallet.sync().await?;
+
+            serde_json::to_string_pretty(&wallet.cache().addresses)
+        }
+    }
+    .map_err(|err| CliError::Other(format!("unable to serialize account state: {err}")))?;
+
+    println!("{state}");
+
+    Ok(())
+}
+
+/// Reads the wallet's cached account state, empty when the file does not
+/// exist yet
+pub(crate) fn read_cache(path: &Path) -> Result<WalletCache> {
+    if !path.exists() {
+        return Ok(WalletCache::default());
+    }
+
+    let contents = std::fs::read(path)?;
+    let cache = serde_json::from_slice(&contents)
+        .map_err(|err| CliError::Other(format!("unable to read wallet cache: {err}")))?;
+
+    Ok(cache)
+}
+
+pub(crate) fn write_cache(path: &Path, cache: &WalletCache) -> Result<()> {
+    let contents = serde_json::to_string_pretty(cache)
+        .map_err(|err| CliError::Other(format!("unable to serialize wallet cache: {err}")))?;
+
+    std::fs::write(path, contents)?;
+
+    Ok(())
+}
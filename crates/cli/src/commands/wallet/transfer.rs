@@ -6,7 +6,7 @@ use crate::result::CliError;
 
 pub async fn exec(
     wallet: &mut Wallet,
-    // address_number: u32,
+    from: &str,
     to: Address,
     amount: u128,
     token: Token,
@@ -16,7 +16,7 @@ pub async fn exec(
     // TODO: We need a faucet to first receive tokens from
     // or we need to initialize accounts with tokens on testnet
     let digest = wallet
-        .send_transaction(/*address_number,*/ to, amount, token, timestamp)
+        .send_transaction_from(from, to, amount, token, timestamp)
         .await
         .map_err(|err| CliError::Other(err.to_string()))?;
 
@@ -32,6 +32,12 @@ pub enum CliError {
     #[error("core error: {0}")]
     CoreError(#[from] vrrb_core::result::Error),
 
+    #[error("keystore error: {0}")]
+    Keystore(#[from] vrrb_core::keystore::KeystoreError),
+
+    #[error("config error: {0}")]
+    Config(#[from] vrrb_config::ConfigError),
+
     #[error("{0}")]
     Other(String),
 }
@@ -59,6 +59,11 @@ pub enum Event {
     /// that has been added to the mempool.
     TxnAddedToMempool(TransactionDigest),
 
+    /// `TxnEvictedFromMempool(TransactionDigest)` is an event that is
+    /// triggered when a pending transaction is dropped from the mempool,
+    /// either because it expired or because the mempool was over capacity.
+    TxnEvictedFromMempool(TransactionDigest),
+
     /// `MempoolSizeThesholdReached` is an event that is triggered when the size
     /// of the confirmed transaction mempool reaches a certain threshold.
     /// The `cutoff_transaction` parameter contains the digest of
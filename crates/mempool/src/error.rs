@@ -1,4 +1,5 @@
-use vrrb_core::transactions::TransactionDigest;
+use primitives::Address;
+use vrrb_core::transactions::{TransactionDigest, TxNonce};
 
 #[derive(thiserror::Error, PartialEq, Eq, Debug)]
 pub enum MempoolError {
@@ -14,4 +15,19 @@ pub enum MempoolError {
 
     #[error("transaction {0} already exists")]
     TransactionExists(TransactionDigest),
+
+    #[error("address {0} has too many pending transactions")]
+    SenderQuotaExceeded(Address),
+
+    #[error("transaction {0} uses nonce {1}, which was already used")]
+    NonceTooLow(TransactionDigest, TxNonce),
+
+    #[error("transaction {0} must pay a higher fee than the transaction it replaces")]
+    ReplacementFeeTooLow(TransactionDigest),
+
+    #[error("mempool is full and transaction {0} does not pay enough to evict another")]
+    MempoolFull(TransactionDigest),
+
+    #[error("invalid mempool config: {0}")]
+    InvalidConfig(String),
 }
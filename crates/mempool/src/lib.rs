@@ -39,10 +39,12 @@ mod tests {
     use rand::{thread_rng, Rng};
     use secp256k1::ecdsa;
     use tokio;
+    use vrrb_config::{EvictionPolicy, MempoolConfig};
     use vrrb_core::keypair::KeyPair;
     use vrrb_core::transactions::{Transaction, TransactionKind};
 
-    use crate::mempool::{LeftRightMempool, TxnRecord, TxnStatus};
+    use crate::error::MempoolError;
+    use crate::mempool::{sort_by_priority, FetchFiltered, LeftRightMempool, TxnRecord, TxnStatus};
 
     fn mock_txn_signature() -> Signature {
         ecdsa::Signature::from_compact(&[
@@ -538,4 +540,426 @@ mod tests {
                 handle.join().unwrap();
             });
     }
+
+    #[test]
+    fn selects_highest_paying_txns_first() {
+        let keypair = KeyPair::random();
+        let recv_keypair = KeyPair::random();
+
+        let transfer_builder = TransactionKind::transfer_builder()
+            .timestamp(0)
+            .sender_address(Address::new(keypair.get_miner_public_key().clone()))
+            .sender_public_key(keypair.get_miner_public_key().clone())
+            .receiver_address(Address::new(recv_keypair.get_miner_public_key().clone()))
+            .amount(0)
+            .validators(HashMap::<String, bool>::new())
+            .signature(mock_txn_signature());
+
+        let mut lrmpooldb = LeftRightMempool::new();
+        for (nonce, fee) in [(0u128, 10u128), (1, 30), (2, 20)] {
+            let txn = transfer_builder
+                .clone()
+                .nonce(nonce)
+                .fee(fee)
+                .build_kind()
+                .expect("Failed to build transaction");
+
+            lrmpooldb.insert(txn).unwrap();
+        }
+
+        let fees = lrmpooldb
+            .select_by_fee(2)
+            .into_iter()
+            .map(|record| record.txn.fee())
+            .collect::<Vec<u128>>();
+
+        assert_eq!(fees, vec![30, 20]);
+        assert_eq!(lrmpooldb.factory().select_by_fee(10).len(), 3);
+    }
+
+    fn transfer_from(keypair: &KeyPair, nonce: u128, fee: u128) -> TransactionKind {
+        let recv_keypair = KeyPair::random();
+        let receiver = Address::new(recv_keypair.get_miner_public_key().clone());
+
+        transfer_to(keypair, receiver, nonce, fee)
+    }
+
+    fn transfer_to(
+        keypair: &KeyPair,
+        receiver: Address,
+        nonce: u128,
+        fee: u128,
+    ) -> TransactionKind {
+        TransactionKind::transfer_builder()
+            .timestamp(0)
+            .sender_address(Address::new(keypair.get_miner_public_key().clone()))
+            .sender_public_key(keypair.get_miner_public_key().clone())
+            .receiver_address(receiver)
+            .amount(0)
+            .validators(HashMap::<String, bool>::new())
+            .nonce(nonce)
+            .fee(fee)
+            .signature(mock_txn_signature())
+            .build_kind()
+            .expect("Failed to build transaction")
+    }
+
+    #[test]
+    fn rejects_txns_past_the_sender_quota() {
+        let keypair = KeyPair::random();
+        let mut lrmpooldb = LeftRightMempool::with_config(MempoolConfig {
+            max_txns_per_sender: 2,
+            ..Default::default()
+        })
+        .unwrap();
+
+        lrmpooldb.insert(transfer_from(&keypair, 0, 10)).unwrap();
+        lrmpooldb.insert(transfer_from(&keypair, 1, 10)).unwrap();
+
+        let sender = Address::new(keypair.get_miner_public_key().clone());
+        assert_eq!(
+            lrmpooldb.insert(transfer_from(&keypair, 2, 10)),
+            Err(MempoolError::SenderQuotaExceeded(sender))
+        );
+
+        lrmpooldb
+            .insert(transfer_from(&KeyPair::random(), 0, 10))
+            .unwrap();
+
+        assert_eq!(lrmpooldb.size(), 3);
+    }
+
+    #[test]
+    fn evicts_expired_txns() {
+        let keypair = KeyPair::random();
+        let mut lrmpooldb = LeftRightMempool::new();
+
+        let mut expired = TxnRecord::new(transfer_from(&keypair, 0, 10));
+        expired.added_timestamp = 0;
+        let fresh = TxnRecord::new(transfer_from(&keypair, 1, 10));
+
+        lrmpooldb
+            .extend_with_records(HashSet::from([expired.clone(), fresh.clone()]))
+            .unwrap();
+
+        let evicted = lrmpooldb.evict();
+
+        assert_eq!(evicted, vec![expired]);
+        assert_eq!(lrmpooldb.size(), 1);
+        assert!(lrmpooldb.get(&fresh.txn_id).is_some());
+    }
+
+    #[test]
+    fn evicts_lowest_paying_txns_when_over_capacity() {
+        let mut lrmpooldb = LeftRightMempool::with_config(MempoolConfig {
+            max_txns: 2,
+            max_txns_per_sender: 2,
+            ..Default::default()
+        })
+        .unwrap();
+
+        for fee in [20u128, 10, 30] {
+            lrmpooldb
+                .insert(transfer_from(&KeyPair::random(), 0, fee))
+                .unwrap();
+        }
+
+        let evicted = lrmpooldb.evict();
+
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0].txn.fee(), 10);
+        assert_eq!(lrmpooldb.size(), 2);
+    }
+
+    #[test]
+    fn evicts_oldest_txns_when_over_byte_capacity() {
+        let keypair = KeyPair::random();
+        let mut older = TxnRecord::new(transfer_from(&keypair, 0, 30));
+        older.added_timestamp -= 10;
+        let newer = TxnRecord::new(transfer_from(&keypair, 1, 10));
+
+        let mut lrmpooldb = LeftRightMempool::with_config(MempoolConfig {
+            max_size_bytes: newer.size_in_bytes() + 1,
+            eviction_policy: EvictionPolicy::Oldest,
+            ..Default::default()
+        })
+        .unwrap();
+
+        lrmpooldb
+            .extend_with_records(HashSet::from([older.clone(), newer]))
+            .unwrap();
+
+        let evicted = lrmpooldb.evict();
+
+        assert_eq!(evicted, vec![older]);
+        assert_eq!(lrmpooldb.size(), 1);
+    }
+
+    #[test]
+    fn rejects_lower_paying_txns_when_full() {
+        let mut lrmpooldb = LeftRightMempool::with_config(MempoolConfig {
+            max_txns: 1,
+            max_txns_per_sender: 1,
+            ..Default::default()
+        })
+        .unwrap();
+
+        lrmpooldb
+            .insert(transfer_from(&KeyPair::random(), 0, 20))
+            .unwrap();
+        let cheaper = transfer_from(&KeyPair::random(), 0, 10);
+
+        assert_eq!(
+            lrmpooldb.insert(cheaper.clone()),
+            Err(MempoolError::MempoolFull(cheaper.id()))
+        );
+        assert_eq!(lrmpooldb.size(), 1);
+        assert!(lrmpooldb.evict().is_empty());
+    }
+
+    #[test]
+    fn keeps_a_running_count_of_pending_bytes() {
+        let mut lrmpooldb = LeftRightMempool::new();
+        let txns = (0..3)
+            .map(|nonce| transfer_from(&KeyPair::random(), nonce, 10))
+            .collect::<Vec<TransactionKind>>();
+
+        for txn in txns.iter() {
+            lrmpooldb.insert(txn.clone()).unwrap();
+        }
+        lrmpooldb.remove(&txns[1].id()).unwrap();
+
+        let expected: usize = lrmpooldb
+            .pool()
+            .values()
+            .map(TxnRecord::size_in_bytes)
+            .sum();
+
+        assert_eq!(lrmpooldb.size_in_bytes(), expected);
+        assert_eq!(lrmpooldb.size(), 2);
+    }
+
+    #[test]
+    fn rejects_invalid_configs() {
+        let config = MempoolConfig {
+            max_txns: 0,
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            LeftRightMempool::with_config(config),
+            Err(MempoolError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn fetch_filtered_returns_at_most_the_available_txns() {
+        let mut lrmpooldb = LeftRightMempool::new();
+        lrmpooldb
+            .insert(transfer_from(&KeyPair::random(), 0, 10))
+            .unwrap();
+
+        assert_eq!(lrmpooldb.read.fetch_filtered(10, |_, _| true).len(), 1);
+        assert!(lrmpooldb.read.fetch_filtered(10, |_, _| false).is_empty());
+    }
+
+    #[test]
+    fn releases_only_contiguous_nonces() {
+        let keypair = KeyPair::random();
+        let mut lrmpooldb = LeftRightMempool::new();
+
+        let txn0 = transfer_from(&keypair, 0, 10);
+        let txn1 = transfer_from(&keypair, 1, 10);
+        let txn2 = transfer_from(&keypair, 2, 10);
+
+        assert_eq!(
+            lrmpooldb.insert_with_nonce(txn0.clone(), 0).unwrap(),
+            vec![txn0.id()]
+        );
+        assert!(lrmpooldb
+            .insert_with_nonce(txn2.clone(), 0)
+            .unwrap()
+            .is_empty());
+        assert_eq!(lrmpooldb.size(), 1);
+        assert_eq!(lrmpooldb.queued_len(), 1);
+
+        assert_eq!(
+            lrmpooldb.insert_with_nonce(txn1.clone(), 0).unwrap(),
+            vec![txn1.id(), txn2.id()]
+        );
+        assert_eq!(lrmpooldb.size(), 3);
+        assert_eq!(lrmpooldb.queued_len(), 0);
+    }
+
+    #[test]
+    fn counts_queued_txns_towards_the_pool_limits() {
+        let mut lrmpooldb = LeftRightMempool::with_config(MempoolConfig {
+            max_txns: 2,
+            max_txns_per_sender: 2,
+            ..Default::default()
+        })
+        .unwrap();
+
+        // Future nonces of different senders all wait in their queues
+        for fee in [20u128, 30] {
+            assert!(lrmpooldb
+                .insert_with_nonce(transfer_from(&KeyPair::random(), 5, fee), 0)
+                .unwrap()
+                .is_empty());
+        }
+        assert_eq!(lrmpooldb.queued_len(), 2);
+
+        let cheaper = transfer_from(&KeyPair::random(), 5, 10);
+        assert_eq!(
+            lrmpooldb.insert_with_nonce(cheaper.clone(), 0),
+            Err(MempoolError::MempoolFull(cheaper.id()))
+        );
+
+        // Better paying txns take the place of the lowest paying queued one
+        let pricier = transfer_from(&KeyPair::random(), 0, 40);
+        assert_eq!(
+            lrmpooldb.insert_with_nonce(pricier.clone(), 0).unwrap(),
+            vec![pricier.id()]
+        );
+        assert_eq!(lrmpooldb.size(), 1);
+        assert_eq!(lrmpooldb.queued_len(), 1);
+
+        let evicted = lrmpooldb.evict();
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0].txn.fee(), 20);
+    }
+
+    #[test]
+    fn rejects_nonces_already_used() {
+        let keypair = KeyPair::random();
+        let mut lrmpooldb = LeftRightMempool::new();
+
+        let txn = transfer_from(&keypair, 3, 10);
+
+        assert_eq!(
+            lrmpooldb.insert_with_nonce(txn.clone(), 5),
+            Err(MempoolError::NonceTooLow(txn.id(), 3))
+        );
+    }
+
+    #[test]
+    fn replaces_same_nonce_txns_paying_a_higher_fee() {
+        let keypair = KeyPair::random();
+        let sender = Address::new(keypair.get_miner_public_key().clone());
+        let mut lrmpooldb = LeftRightMempool::new();
+
+        lrmpooldb
+            .insert_with_nonce(transfer_from(&keypair, 0, 10), 0)
+            .unwrap();
+
+        let cheaper = transfer_from(&keypair, 0, 10);
+        assert_eq!(
+            lrmpooldb.insert_with_nonce(cheaper.clone(), 0),
+            Err(MempoolError::ReplacementFeeTooLow(cheaper.id()))
+        );
+
+        let pricier = transfer_from(&keypair, 0, 20);
+        lrmpooldb.insert_with_nonce(pricier.clone(), 0).unwrap();
+
+        assert_eq!(lrmpooldb.size(), 1);
+        assert!(lrmpooldb.get(&pricier.id()).is_some());
+
+        lrmpooldb
+            .insert_with_nonce(transfer_from(&keypair, 2, 10), 0)
+            .unwrap();
+        lrmpooldb
+            .insert_with_nonce(transfer_from(&keypair, 2, 30), 0)
+            .unwrap();
+
+        let queued = lrmpooldb.queued(&sender);
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].txn.fee(), 30);
+    }
+
+    #[test]
+    fn replaces_txns_differing_only_in_fee() {
+        let keypair = KeyPair::random();
+        let receiver = Address::new(KeyPair::random().get_miner_public_key().clone());
+        let mut lrmpooldb = LeftRightMempool::new();
+
+        let original = transfer_to(&keypair, receiver.clone(), 0, 10);
+        lrmpooldb.insert_with_nonce(original.clone(), 0).unwrap();
+
+        let sender = Address::new(keypair.get_miner_public_key().clone());
+        assert_eq!(
+            lrmpooldb
+                .get_by_nonce(&sender, 0)
+                .map(|record| record.txn_id),
+            Some(original.id())
+        );
+
+        let replacement = transfer_to(&keypair, receiver, 0, 20);
+        assert_eq!(
+            lrmpooldb.insert_with_nonce(replacement.clone(), 0).unwrap(),
+            vec![replacement.id()]
+        );
+
+        assert_eq!(lrmpooldb.size(), 1);
+        assert!(lrmpooldb.get(&original.id()).is_none());
+        assert!(lrmpooldb.get(&replacement.id()).is_some());
+    }
+
+    #[test]
+    fn accepts_nonces_again_once_their_txns_are_removed() {
+        let keypair = KeyPair::random();
+        let mut lrmpooldb = LeftRightMempool::new();
+
+        let txns = (0..3)
+            .map(|nonce| transfer_from(&keypair, nonce, 10))
+            .collect::<Vec<TransactionKind>>();
+        for txn in txns.iter() {
+            lrmpooldb.insert_with_nonce(txn.clone(), 0).unwrap();
+        }
+
+        lrmpooldb.remove(&txns[1].id()).unwrap();
+
+        let resent = transfer_from(&keypair, 1, 20);
+        assert_eq!(
+            lrmpooldb.insert_with_nonce(resent.clone(), 0).unwrap(),
+            vec![resent.id()]
+        );
+
+        // The next nonce moves past the ones still pending
+        let next = transfer_from(&keypair, 3, 10);
+        assert_eq!(
+            lrmpooldb.insert_with_nonce(next.clone(), 0).unwrap(),
+            vec![next.id()]
+        );
+
+        let pending = lrmpooldb.pool().keys().cloned().collect();
+        lrmpooldb.remove_txns(&pending).unwrap();
+
+        // Senders left without pending txns start over from their account nonce
+        let first = transfer_from(&keypair, 0, 10);
+        assert_eq!(
+            lrmpooldb.insert_with_nonce(first.clone(), 0).unwrap(),
+            vec![first.id()]
+        );
+    }
+
+    #[test]
+    fn priority_order_keeps_each_sender_in_nonce_order() {
+        let keypair = KeyPair::random();
+        let other = KeyPair::random();
+
+        let mut txns = vec![
+            transfer_from(&keypair, 0, 10),
+            transfer_from(&keypair, 1, 50),
+            transfer_from(&other, 0, 30),
+        ];
+
+        sort_by_priority(&mut txns);
+
+        let order = txns
+            .iter()
+            .map(|txn| (txn.nonce(), txn.fee()))
+            .collect::<Vec<(u128, u128)>>();
+
+        assert_eq!(order, vec![(0, 10), (0, 30), (1, 50)]);
+    }
 }
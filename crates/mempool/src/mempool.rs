@@ -1,5 +1,6 @@
 use std::{
-    collections::{HashMap, HashSet},
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap, HashSet},
     hash::Hash,
     result::Result as StdResult,
 };
@@ -7,8 +8,12 @@ use std::{
 use fxhash::FxBuildHasher;
 use indexmap::IndexMap;
 use left_right::{Absorb, ReadHandle, ReadHandleFactory, WriteHandle};
+use primitives::Address;
 use serde::{Deserialize, Serialize};
-use vrrb_core::transactions::{Transaction, TransactionDigest, TransactionKind, TxTimestamp};
+use vrrb_config::{EvictionPolicy, MempoolConfig};
+use vrrb_core::transactions::{
+    Transaction, TransactionDigest, TransactionKind, TxNonce, TxTimestamp,
+};
 
 use super::error::MempoolError;
 
@@ -46,10 +51,63 @@ impl TxnRecord {
             ..Default::default()
         }
     }
+
+    /// Size of the record once serialized, used to enforce the mempool's
+    /// byte capacity.
+    pub fn size_in_bytes(&self) -> usize {
+        serde_json::to_vec(self)
+            .map(|bytes| bytes.len())
+            .unwrap_or_default()
+    }
 }
 
 pub type PoolType = IndexMap<TransactionDigest, TxnRecord, FxBuildHasher>;
 
+/// Orders transactions so the highest paying ones come first. Ties are
+/// broken by the lowest nonce and then by the transaction id so every node
+/// ends up with the same ordering for the same set of transactions.
+pub fn fee_priority(a: &TransactionKind, b: &TransactionKind) -> Ordering {
+    b.fee()
+        .cmp(&a.fee())
+        .then_with(|| a.nonce().cmp(&b.nonce()))
+        .then_with(|| a.id().cmp(&b.id()))
+}
+
+/// Orders transactions highest paying first while keeping every sender's
+/// transactions in nonce order: each sender's transactions are placed, lowest
+/// nonce first, into the positions their fees earned them.
+pub fn sort_by_priority(txns: &mut [TransactionKind]) {
+    txns.sort_by(fee_priority);
+
+    let mut by_sender: HashMap<Address, Vec<TransactionKind>> = HashMap::new();
+    for txn in txns.iter() {
+        by_sender
+            .entry(txn.sender_address())
+            .or_default()
+            .push(txn.clone());
+    }
+
+    // Highest nonce first so that popping yields the lowest one
+    by_sender.values_mut().for_each(|sender_txns| {
+        sender_txns.sort_by(|a, b| b.nonce().cmp(&a.nonce()).then_with(|| b.id().cmp(&a.id())))
+    });
+
+    for slot in txns.iter_mut() {
+        if let Some(txn) = by_sender.get_mut(&slot.sender_address()).and_then(Vec::pop) {
+            *slot = txn;
+        }
+    }
+}
+
+/// Returns up to `limit` records from the pool, highest paying first.
+fn select_by_fee(pool: PoolType, limit: usize) -> Vec<TxnRecord> {
+    let mut records = pool.into_values().collect::<Vec<TxnRecord>>();
+    records.sort_by(|a, b| fee_priority(&a.txn, &b.txn));
+    records.truncate(limit);
+
+    records
+}
+
 #[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TxnStatus {
     #[default]
@@ -129,8 +187,8 @@ impl FetchFiltered for ReadHandle<Mempool> {
             for (_, v) in &result {
                 returned.push(v.clone());
             }
-            // TODO:  Error - length
-            return returned[0..amount as usize].to_vec();
+            returned.truncate(amount as usize);
+            return returned;
         };
         Vec::<TxnRecord>::new()
     }
@@ -140,13 +198,49 @@ impl FetchFiltered for ReadHandle<Mempool> {
 pub struct LeftRightMempool {
     pub read: ReadHandle<Mempool>,
     pub write: WriteHandle<Mempool, MempoolOp>,
+    config: MempoolConfig,
+    /// Transactions waiting for the nonces before them, per sender
+    queued: HashMap<Address, BTreeMap<TxNonce, QueuedEntry>>,
+    /// Number of queued transactions. They count towards the pool's limits
+    /// just like pending ones.
+    queued_count: usize,
+    /// Combined serialized size of the queued transactions
+    queued_size_in_bytes: usize,
+    /// Nonce following the last transaction released to the pool, per sender
+    /// with pending transactions. Rewound whenever one of them is removed.
+    next_nonces: HashMap<Address, TxNonce>,
+    /// Sender, nonce, serialized size and arrival time of every pending
+    /// transaction, so limits are enforced without re-measuring the pool
+    accounted: HashMap<TransactionDigest, PendingEntry>,
+    /// Combined serialized size of the pending transactions
+    size_in_bytes: usize,
+    /// Number of pending transactions per sender and nonce
+    pending_nonces: HashMap<Address, BTreeMap<TxNonce, usize>>,
+    /// Transactions evicted to make room for new ones, returned by the next
+    /// call to `evict`
+    evicted: Vec<TxnRecord>,
+}
+
+/// What the mempool keeps track of for every pending transaction
+#[derive(Debug, Clone)]
+struct PendingEntry {
+    sender: Address,
+    nonce: TxNonce,
+    size_in_bytes: usize,
+    added_timestamp: TxTimestamp,
+}
+
+/// A transaction waiting in its sender's queue, along with its serialized
+/// size
+#[derive(Debug, Clone)]
+struct QueuedEntry {
+    record: TxnRecord,
+    size_in_bytes: usize,
 }
 
 impl Default for LeftRightMempool {
     fn default() -> Self {
-        let (write, read) = left_right::new::<Mempool, MempoolOp>();
-
-        LeftRightMempool { read, write }
+        Self::build(MempoolConfig::default())
     }
 }
 
@@ -156,6 +250,38 @@ impl LeftRightMempool {
         Self::default()
     }
 
+    /// Creates new Mempool DB bounded by the given capacity limits. Fails if
+    /// the limits are invalid.
+    pub fn with_config(config: MempoolConfig) -> Result<Self> {
+        config
+            .validate()
+            .map_err(|err| MempoolError::InvalidConfig(err.to_string()))?;
+
+        Ok(Self::build(config))
+    }
+
+    fn build(config: MempoolConfig) -> Self {
+        let (write, read) = left_right::new::<Mempool, MempoolOp>();
+
+        LeftRightMempool {
+            read,
+            write,
+            config,
+            queued: HashMap::new(),
+            queued_count: 0,
+            queued_size_in_bytes: 0,
+            next_nonces: HashMap::new(),
+            accounted: HashMap::new(),
+            size_in_bytes: 0,
+            pending_nonces: HashMap::new(),
+            evicted: Vec::new(),
+        }
+    }
+
+    pub fn config(&self) -> &MempoolConfig {
+        &self.config
+    }
+
     /// Getter for Mempool DB
     pub fn pool(&self) -> PoolType {
         self.read
@@ -181,11 +307,11 @@ impl LeftRightMempool {
     /// Return the number of key-value pairs in the map.
     ///
     pub fn len(&self) -> usize {
-        self.pool().len()
+        self.accounted.len()
     }
 
     pub fn is_empty(&self) -> bool {
-        self.pool().is_empty()
+        self.accounted.is_empty()
     }
 
     /// Adds a new transaction, makes sure it is unique in db.
@@ -196,15 +322,190 @@ impl LeftRightMempool {
         Ok(())
     }
 
+    /// Adds a new transaction. Fails if the sender already has the maximum
+    /// number of pending transactions allowed by the mempool config, or if
+    /// the pool is full and no transaction can be evicted in its favour.
     pub fn insert(&mut self, txn: TransactionKind) -> Result<usize> {
         let txn_record = TxnRecord::new(txn);
-        self.write
-            .append(MempoolOp::Add(Box::new(txn_record)))
-            .publish();
+
+        if !self.accounted.contains_key(&txn_record.txn_id) {
+            let sender = txn_record.txn.sender_address();
+            if self.txns_of(&sender) >= self.config.max_txns_per_sender {
+                return Err(MempoolError::SenderQuotaExceeded(sender));
+            }
+
+            self.make_room(&txn_record)?;
+        }
+
+        self.append_add(txn_record);
+        self.publish();
 
         Ok(self.size_in_kilobytes())
     }
 
+    /// Adds a transaction while keeping every sender's transactions in nonce
+    /// order. `account_nonce` is the sender's nonce in the current state.
+    ///
+    /// Transactions are released to the pending pool only once every nonce
+    /// before theirs has been released, otherwise they wait in the sender's
+    /// queue. Queued transactions count towards the pool's limits, so room is
+    /// made for a transaction as soon as it arrives. A transaction reusing
+    /// the nonce of a pending or queued one replaces it if it pays a higher
+    /// fee.
+    ///
+    /// Returns the ids of the transactions released to the pending pool, in
+    /// nonce order.
+    pub fn insert_with_nonce(
+        &mut self,
+        txn: TransactionKind,
+        account_nonce: TxNonce,
+    ) -> Result<Vec<TransactionDigest>> {
+        let sender = txn.sender_address();
+        let nonce = txn.nonce();
+
+        let lowest_nonce = self.next_nonce(&sender, account_nonce);
+        if nonce < lowest_nonce.unwrap_or(account_nonce) || self.is_pending(&sender, nonce) {
+            return self.replace_pending(txn);
+        }
+
+        // Queued transactions made obsolete by the account's nonce moving on
+        let obsolete = self
+            .queued
+            .get(&sender)
+            .map(|queue| {
+                queue
+                    .range(..lowest_nonce.unwrap_or(account_nonce))
+                    .map(|(queued_nonce, _)| *queued_nonce)
+                    .collect::<Vec<TxNonce>>()
+            })
+            .unwrap_or_default();
+        for queued_nonce in obsolete {
+            self.dequeue(&sender, queued_nonce);
+        }
+
+        let queued = self.queued.get(&sender).and_then(|queue| queue.get(&nonce));
+        let replaced = match queued {
+            Some(entry) if entry.record.txn_id == txn.id() => {
+                return Err(MempoolError::TransactionExists(txn.id()));
+            }
+            Some(entry) if entry.record.txn.fee() >= txn.fee() => {
+                return Err(MempoolError::ReplacementFeeTooLow(txn.id()));
+            }
+            Some(_) => self.dequeue(&sender, nonce),
+            None => {
+                if self.txns_of(&sender) >= self.config.max_txns_per_sender {
+                    return Err(MempoolError::SenderQuotaExceeded(sender));
+                }
+                None
+            }
+        };
+
+        let record = TxnRecord::new(txn);
+        if let Err(err) = self.make_room(&record) {
+            if let Some(replaced) = replaced {
+                self.enqueue(replaced);
+            }
+            return Err(err);
+        }
+        self.enqueue(record);
+
+        // Making room may have evicted the sender's own transactions, so the
+        // next nonce is only looked up once it has
+        let mut next_nonce = match self.next_nonce(&sender, account_nonce) {
+            Some(next_nonce) => next_nonce,
+            // Like `Account::update`, a sender with nothing in flight may
+            // start at either its account nonce or the one right after it
+            None if self.get_queued(&sender, account_nonce).is_none()
+                && self.get_queued(&sender, account_nonce + 1).is_some() =>
+            {
+                account_nonce + 1
+            }
+            None => account_nonce,
+        };
+
+        let mut released = vec![];
+        loop {
+            let record = match self.dequeue(&sender, next_nonce) {
+                Some(record) => record,
+                // Nonces left pending when an earlier one was removed
+                None if self.is_pending(&sender, next_nonce) => {
+                    next_nonce += 1;
+                    continue;
+                }
+                None => break,
+            };
+
+            released.push(record.txn_id.clone());
+            self.append_add(record);
+            next_nonce += 1;
+        }
+
+        if !released.is_empty() {
+            self.next_nonces.insert(sender, next_nonce);
+        }
+        self.publish();
+
+        Ok(released)
+    }
+
+    /// Replaces the pending transaction sharing `txn`'s sender and nonce,
+    /// provided `txn` pays a higher fee.
+    fn replace_pending(&mut self, txn: TransactionKind) -> Result<Vec<TransactionDigest>> {
+        let sender = txn.sender_address();
+
+        match self.get_pending(&sender, txn.nonce()) {
+            Some(record) if record.txn_id == txn.id() => {
+                Err(MempoolError::TransactionExists(txn.id()))
+            }
+            Some(record) if record.txn.fee() >= txn.fee() => {
+                Err(MempoolError::ReplacementFeeTooLow(txn.id()))
+            }
+            Some(record) => {
+                let txn_id = txn.id();
+                let replacement = TxnRecord::new(txn);
+                // The replacement takes over the nonce, so it is not rewound
+                let next_nonce = self.next_nonces.get(&sender).copied();
+
+                self.append_remove(&record.txn_id);
+                let result = self.make_room(&replacement);
+                if result.is_ok() {
+                    self.append_add(replacement);
+                } else {
+                    self.append_add(record);
+                }
+                if let Some(next_nonce) = next_nonce {
+                    self.next_nonces.insert(sender, next_nonce);
+                }
+                self.publish();
+
+                result.map(|_| vec![txn_id])
+            }
+            None => Err(MempoolError::NonceTooLow(txn.id(), txn.nonce())),
+        }
+    }
+
+    /// Returns the transactions `address` has waiting for earlier nonces,
+    /// lowest nonce first.
+    pub fn queued(&self, address: &Address) -> Vec<TxnRecord> {
+        self.queued
+            .get(address)
+            .map(|queue| queue.values().map(|entry| entry.record.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns the pending or queued transaction `sender` sent with `nonce`,
+    /// which a new transaction with the same nonce would replace.
+    pub fn get_by_nonce(&self, sender: &Address, nonce: TxNonce) -> Option<TxnRecord> {
+        self.get_queued(sender, nonce)
+            .cloned()
+            .or_else(|| self.get_pending(sender, nonce))
+    }
+
+    /// Returns the number of transactions waiting for earlier nonces.
+    pub fn queued_len(&self) -> usize {
+        self.queued_count
+    }
+
     /// Retrieves a single transaction identified by id, makes sure it exists in
     /// db
     pub fn get_txn(&mut self, txn_hash: &TransactionDigest) -> Option<TransactionKind> {
@@ -220,7 +521,9 @@ impl LeftRightMempool {
             return None;
         }
 
-        self.pool().get(txn_id).cloned()
+        self.read
+            .enter()
+            .and_then(|mempool| mempool.pool.get(txn_id).cloned())
     }
 
     /// It fetches the transactions from the pool and returns them.
@@ -245,6 +548,12 @@ impl LeftRightMempool {
         txns_records
     }
 
+    /// Returns up to `limit` pending transactions, highest paying first,
+    /// without removing them from the pool.
+    pub fn select_by_fee(&self, limit: usize) -> Vec<TxnRecord> {
+        select_by_fee(self.pool(), limit)
+    }
+
     /// Adds a batch of new transaction, makes sure that each is unique in db.
     /// Pushes to ReadHandle after processing of the entire batch.
     #[deprecated(note = "use extend instead")]
@@ -258,8 +567,7 @@ impl LeftRightMempool {
 
     pub fn extend(&mut self, txn_batch: HashSet<TransactionKind>) -> Result<()> {
         txn_batch.into_iter().for_each(|t| {
-            self.write
-                .append(MempoolOp::Add(Box::new(TxnRecord::new(t))));
+            self.append_add(TxnRecord::new(t));
         });
 
         self.publish();
@@ -268,7 +576,7 @@ impl LeftRightMempool {
 
     pub fn extend_with_records(&mut self, record_batch: HashSet<TxnRecord>) -> Result<()> {
         record_batch.into_iter().for_each(|t| {
-            self.write.append(MempoolOp::Add(Box::new(t)));
+            self.append_add(t);
         });
 
         self.publish();
@@ -290,9 +598,8 @@ impl LeftRightMempool {
     }
 
     pub fn remove(&mut self, id: &TransactionDigest) -> Result<()> {
-        self.write
-            .append(MempoolOp::Remove(id.to_owned()))
-            .publish();
+        self.append_remove(id);
+        self.publish();
         Ok(())
     }
 
@@ -305,7 +612,7 @@ impl LeftRightMempool {
         _txns_status: TxnStatus,
     ) -> Result<()> {
         txn_batch.iter().for_each(|t| {
-            self.write.append(MempoolOp::Remove(t.id()));
+            self.append_remove(&t.id());
         });
 
         self.publish();
@@ -315,7 +622,7 @@ impl LeftRightMempool {
 
     pub fn remove_txns(&mut self, txn_batch: &HashSet<TransactionDigest>) -> Result<()> {
         txn_batch.iter().for_each(|t| {
-            self.write.append(MempoolOp::Remove(t.to_owned()));
+            self.append_remove(t);
         });
 
         self.publish();
@@ -323,6 +630,334 @@ impl LeftRightMempool {
         Ok(())
     }
 
+    /// Drops every transaction that outlived the configured TTL, then evicts
+    /// pending and queued transactions according to the configured
+    /// `EvictionPolicy` until the pool fits within its count and byte limits.
+    /// Returns the records that were dropped, along with those evicted to
+    /// make room for new transactions since the last call.
+    pub fn evict(&mut self) -> Vec<TxnRecord> {
+        let now = chrono::offset::Utc::now().timestamp();
+        let ttl = self.config.txn_ttl_secs;
+
+        let mut evicted = std::mem::take(&mut self.evicted);
+
+        let expired_queued = self
+            .queued
+            .values()
+            .flat_map(BTreeMap::values)
+            .filter(|entry| now - entry.record.added_timestamp > ttl)
+            .map(|entry| entry.record.clone())
+            .collect::<Vec<TxnRecord>>();
+        for record in expired_queued.iter() {
+            self.dequeue(&record.txn.sender_address(), record.txn.nonce());
+        }
+
+        let has_expired = self
+            .accounted
+            .values()
+            .any(|entry| now - entry.added_timestamp > ttl);
+
+        if has_expired || self.is_over_capacity(0, 0) {
+            let expired = self
+                .read
+                .enter()
+                .map(|mempool| {
+                    mempool
+                        .pool
+                        .values()
+                        .filter(|record| self.accounted.contains_key(&record.txn_id))
+                        .filter(|record| now - record.added_timestamp > ttl)
+                        .cloned()
+                        .collect::<Vec<TxnRecord>>()
+                })
+                .unwrap_or_default();
+
+            expired
+                .iter()
+                .for_each(|record| self.append_remove(&record.txn_id));
+            evicted.extend(expired);
+
+            let (evicting, _) = self.pick_evictions(0, 0, |_| true);
+            evicting.iter().for_each(|record| self.discard(record));
+            evicted.extend(evicting);
+
+            self.publish();
+        }
+
+        evicted.extend(expired_queued);
+
+        evicted
+    }
+
+    /// Returns whether the pool would exceed its count or byte limits with
+    /// `count` more transactions taking `size_in_bytes` more bytes. Both
+    /// pending and queued transactions count towards the limits.
+    fn is_over_capacity(&self, count: usize, size_in_bytes: usize) -> bool {
+        self.accounted.len() + self.queued_count + count > self.config.max_txns
+            || self.size_in_bytes + self.queued_size_in_bytes + size_in_bytes
+                > self.config.max_size_bytes
+    }
+
+    /// Moves the records evicted first under the configured `EvictionPolicy`
+    /// to the front
+    fn sort_by_eviction_order(&self, records: &mut [(&TxnRecord, usize)]) {
+        match self.config.eviction_policy {
+            EvictionPolicy::LowestFee => {
+                records.sort_by(|(a, _), (b, _)| fee_priority(&b.txn, &a.txn))
+            }
+            EvictionPolicy::Oldest => records.sort_by_key(|(record, _)| record.added_timestamp),
+        }
+    }
+
+    /// Picks pending and queued transactions to evict, in eviction order,
+    /// until `count` more transactions taking `size_in_bytes` more bytes fit
+    /// within the pool's limits. Picking stops at the first transaction
+    /// `can_evict` spares. Returns the picked records and whether the pool
+    /// fits once they are evicted.
+    ///
+    /// The pool is read through its read handle rather than cloned, so only
+    /// the picked records are copied.
+    fn pick_evictions<F>(
+        &self,
+        count: usize,
+        size_in_bytes: usize,
+        mut can_evict: F,
+    ) -> (Vec<TxnRecord>, bool)
+    where
+        F: FnMut(&TxnRecord) -> bool,
+    {
+        let mut count = self.accounted.len() + self.queued_count + count;
+        let mut total_size = self.size_in_bytes + self.queued_size_in_bytes + size_in_bytes;
+        let fits = |count: usize, total_size: usize| {
+            count <= self.config.max_txns && total_size <= self.config.max_size_bytes
+        };
+
+        let mempool = self.read.enter();
+        let pending = mempool
+            .iter()
+            .flat_map(|mempool| mempool.pool.values())
+            .filter_map(|record| {
+                self.accounted
+                    .get(&record.txn_id)
+                    .map(|entry| (record, entry.size_in_bytes))
+            });
+        let queued = self
+            .queued
+            .values()
+            .flat_map(BTreeMap::values)
+            .map(|entry| (&entry.record, entry.size_in_bytes));
+
+        let mut candidates = pending.chain(queued).collect::<Vec<(&TxnRecord, usize)>>();
+        self.sort_by_eviction_order(&mut candidates);
+
+        let mut picked = vec![];
+        for (candidate, candidate_size) in candidates {
+            if fits(count, total_size) || !can_evict(candidate) {
+                break;
+            }
+
+            count -= 1;
+            total_size -= candidate_size;
+            picked.push(candidate.clone());
+        }
+
+        (picked, fits(count, total_size))
+    }
+
+    /// Evicts pending and queued transactions until `record` fits within the
+    /// pool's limits. Under `EvictionPolicy::LowestFee` only transactions
+    /// paying less than `record` are evicted. Nothing is evicted and the pool
+    /// is reported full if `record` cannot be made to fit.
+    fn make_room(&mut self, record: &TxnRecord) -> Result<()> {
+        let size_in_bytes = record.size_in_bytes();
+        if !self.is_over_capacity(1, size_in_bytes) {
+            return Ok(());
+        }
+
+        let lowest_fee = self.config.eviction_policy == EvictionPolicy::LowestFee;
+        let (evicting, fits) = self.pick_evictions(1, size_in_bytes, |candidate| {
+            !lowest_fee || fee_priority(&record.txn, &candidate.txn) == Ordering::Less
+        });
+
+        if !fits {
+            return Err(MempoolError::MempoolFull(record.txn_id.clone()));
+        }
+
+        for candidate in evicting {
+            self.discard(&candidate);
+            self.evicted.push(candidate);
+        }
+
+        Ok(())
+    }
+
+    /// Removes `record` from the pending pool or from its sender's queue,
+    /// wherever it is
+    fn discard(&mut self, record: &TxnRecord) {
+        if self.accounted.contains_key(&record.txn_id) {
+            self.append_remove(&record.txn_id);
+        } else {
+            self.dequeue(&record.txn.sender_address(), record.txn.nonce());
+        }
+    }
+
+    /// Returns the nonce following the last transaction `sender` has in
+    /// flight, if it is ahead of `account_nonce`
+    fn next_nonce(&self, sender: &Address, account_nonce: TxNonce) -> Option<TxNonce> {
+        self.next_nonces
+            .get(sender)
+            .copied()
+            .filter(|next_nonce| *next_nonce > account_nonce)
+    }
+
+    /// Returns the number of pending and queued transactions sent by `sender`
+    fn txns_of(&self, sender: &Address) -> usize {
+        self.pending_of(sender)
+            + self
+                .queued
+                .get(sender)
+                .map(BTreeMap::len)
+                .unwrap_or_default()
+    }
+
+    /// Returns the transaction `sender` queued with `nonce`
+    fn get_queued(&self, sender: &Address, nonce: TxNonce) -> Option<&TxnRecord> {
+        self.queued
+            .get(sender)
+            .and_then(|queue| queue.get(&nonce))
+            .map(|entry| &entry.record)
+    }
+
+    /// Returns the pending transaction `sender` sent with `nonce`, read
+    /// through the pool's read handle
+    fn get_pending(&self, sender: &Address, nonce: TxNonce) -> Option<TxnRecord> {
+        if !self.is_pending(sender, nonce) {
+            return None;
+        }
+
+        let mempool = self.read.enter()?;
+        let txn_id = self
+            .accounted
+            .iter()
+            .find(|(_, entry)| entry.sender == *sender && entry.nonce == nonce)
+            .map(|(txn_id, _)| txn_id)?;
+
+        mempool.pool.get(txn_id).cloned()
+    }
+
+    /// Adds `record` to its sender's queue and accounts for it, returning the
+    /// record it replaced
+    fn enqueue(&mut self, record: TxnRecord) -> Option<TxnRecord> {
+        let sender = record.txn.sender_address();
+        let nonce = record.txn.nonce();
+        let size_in_bytes = record.size_in_bytes();
+
+        self.queued_count += 1;
+        self.queued_size_in_bytes += size_in_bytes;
+
+        let replaced = self.queued.entry(sender).or_default().insert(
+            nonce,
+            QueuedEntry {
+                record,
+                size_in_bytes,
+            },
+        )?;
+
+        self.queued_count -= 1;
+        self.queued_size_in_bytes -= replaced.size_in_bytes;
+
+        Some(replaced.record)
+    }
+
+    /// Removes the transaction `sender` queued with `nonce` and stops
+    /// accounting for it
+    fn dequeue(&mut self, sender: &Address, nonce: TxNonce) -> Option<TxnRecord> {
+        let queue = self.queued.get_mut(sender)?;
+        let entry = queue.remove(&nonce);
+        if queue.is_empty() {
+            self.queued.remove(sender);
+        }
+
+        let entry = entry?;
+        self.queued_count -= 1;
+        self.queued_size_in_bytes -= entry.size_in_bytes;
+
+        Some(entry.record)
+    }
+
+    /// Returns the number of pending transactions sent by `sender`
+    fn pending_of(&self, sender: &Address) -> usize {
+        self.pending_nonces
+            .get(sender)
+            .map(|nonces| nonces.values().sum())
+            .unwrap_or_default()
+    }
+
+    /// Returns whether `sender` has a pending transaction with `nonce`
+    fn is_pending(&self, sender: &Address, nonce: TxNonce) -> bool {
+        self.pending_nonces
+            .get(sender)
+            .map(|nonces| nonces.contains_key(&nonce))
+            .unwrap_or_default()
+    }
+
+    /// Queues `record` to be added to the pool and accounts for it
+    fn append_add(&mut self, record: TxnRecord) {
+        self.unaccount(&record.txn_id);
+
+        let entry = PendingEntry {
+            sender: record.txn.sender_address(),
+            nonce: record.txn.nonce(),
+            size_in_bytes: record.size_in_bytes(),
+            added_timestamp: record.added_timestamp,
+        };
+        self.size_in_bytes += entry.size_in_bytes;
+        *self
+            .pending_nonces
+            .entry(entry.sender.clone())
+            .or_default()
+            .entry(entry.nonce)
+            .or_default() += 1;
+        self.accounted.insert(record.txn_id.clone(), entry);
+
+        self.write.append(MempoolOp::Add(Box::new(record)));
+    }
+
+    /// Queues the transaction `id` to be removed from the pool and stops
+    /// accounting for it. The sender's next nonce is rewound to the removed
+    /// one so it can be sent again, and forgotten once the sender has no
+    /// pending transactions left.
+    fn append_remove(&mut self, id: &TransactionDigest) {
+        if let Some(entry) = self.unaccount(id) {
+            if !self.pending_nonces.contains_key(&entry.sender) {
+                self.next_nonces.remove(&entry.sender);
+            } else if let Some(next_nonce) = self.next_nonces.get_mut(&entry.sender) {
+                *next_nonce = (*next_nonce).min(entry.nonce);
+            }
+        }
+
+        self.write.append(MempoolOp::Remove(id.to_owned()));
+    }
+
+    fn unaccount(&mut self, id: &TransactionDigest) -> Option<PendingEntry> {
+        let entry = self.accounted.remove(id)?;
+        self.size_in_bytes -= entry.size_in_bytes;
+
+        if let Some(nonces) = self.pending_nonces.get_mut(&entry.sender) {
+            if let Some(pending) = nonces.get_mut(&entry.nonce) {
+                *pending -= 1;
+                if *pending == 0 {
+                    nonces.remove(&entry.nonce);
+                }
+            }
+            if nonces.is_empty() {
+                self.pending_nonces.remove(&entry.sender);
+            }
+        }
+
+        Some(entry)
+    }
+
     /// Was the Txn validated ? And when ?
     // TODO: rethink validated txn storage
     pub fn is_txn_validated(&mut self, txn: &TransactionKind) -> Result<TxTimestamp> {
@@ -336,15 +971,17 @@ impl LeftRightMempool {
 
     /// Retrieves actual size of the mempooldb.
     pub fn size(&self) -> usize {
-        self.pool().len()
+        self.accounted.len()
+    }
+
+    /// Retrieves the combined serialized size of the pending transactions.
+    pub fn size_in_bytes(&self) -> usize {
+        self.size_in_bytes
     }
 
     /// Retrieves actual size of the mempooldb in Kilobytes.
     pub fn size_in_kilobytes(&self) -> usize {
-        let txn_size_factor = std::mem::size_of::<TransactionKind>();
-        let mempool_items = self.size();
-
-        (mempool_items * txn_size_factor) / 1024
+        self.size_in_bytes / 1024
     }
 
     /// Pushes changes to Reader.
@@ -355,8 +992,7 @@ impl LeftRightMempool {
 
 impl From<PoolType> for LeftRightMempool {
     fn from(pool: PoolType) -> Self {
-        let (write, read) = left_right::new::<Mempool, MempoolOp>();
-        let mut mempool_db = Self { read, write };
+        let mut mempool_db = Self::build(MempoolConfig::default());
 
         let records = pool.values().cloned().collect::<HashSet<TxnRecord>>();
 
@@ -368,7 +1004,15 @@ impl From<PoolType> for LeftRightMempool {
 
 impl Clone for LeftRightMempool {
     fn clone(&self) -> Self {
-        Self::from(self.pool())
+        let mut mempool_db = Self::from(self.pool());
+        mempool_db.config = self.config.clone();
+        mempool_db.queued = self.queued.clone();
+        mempool_db.queued_count = self.queued_count;
+        mempool_db.queued_size_in_bytes = self.queued_size_in_bytes;
+        mempool_db.next_nonces = self.next_nonces.clone();
+        mempool_db.evicted = self.evicted.clone();
+
+        mempool_db
     }
 }
 
@@ -411,4 +1055,10 @@ impl MempoolReadHandleFactory {
         }
         None
     }
+
+    /// Returns up to `limit` transactions within the mempool, highest paying
+    /// first
+    pub fn select_by_fee(&self, limit: usize) -> Vec<TxnRecord> {
+        select_by_fee(self.handle(), limit)
+    }
 }
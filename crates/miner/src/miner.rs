@@ -326,6 +326,7 @@ impl Miner {
         let genesis = GenesisBlock {
             header,
             genesis_rewards,
+            trusted_package_publishers: None,
             claims,
             hash: format!("{block_hash:x}"),
             certificate: None,
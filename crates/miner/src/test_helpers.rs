@@ -178,6 +178,8 @@ pub(crate) fn create_txns(n: usize) -> impl Iterator<Item = (TransactionDigest,
             txn.token(),
             txn.amount(),
             txn.nonce(),
+            txn.fee(),
+            None,
         );
 
         let digest = TransactionDigest::from(txn_digest_vec);
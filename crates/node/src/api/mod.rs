@@ -1,10 +1,15 @@
-use std::net::SocketAddr;
+use std::{
+    net::SocketAddr,
+    sync::{Arc, RwLock},
+};
 
-use events::{Event, EventPublisher, EventSubscriber};
+use block::Block;
+use bulldag::graph::BullDag;
+use events::{Event, EventPublisher, EventSubscriber, DEFAULT_BUFFER};
 use mempool::MempoolReadHandleFactory;
 use storage::vrrbdb::VrrbDbReadHandle;
 use telemetry::info;
-use tokio::task::JoinHandle;
+use tokio::{sync::broadcast, task::JoinHandle};
 use vrrb_config::NodeConfig;
 use vrrb_rpc::rpc::{JsonRpcServer, JsonRpcServerConfig};
 
@@ -15,14 +20,36 @@ pub async fn setup_rpc_api_server(
     events_tx: EventPublisher,
     vrrbdb_read_handle: VrrbDbReadHandle,
     mempool_read_handle_factory: MempoolReadHandleFactory,
+    dag: Arc<RwLock<BullDag<Block, String>>>,
     mut jsonrpc_events_rx: EventSubscriber,
+    mut subscription_events_rx: EventSubscriber,
 ) -> Result<(JoinHandle<Result<()>>, SocketAddr)> {
+    let (subscription_events_tx, _) = broadcast::channel(DEFAULT_BUFFER);
+
+    // Forwards events from the node's event bus to the JSON-RPC subscriptions
+    let forwarded_events_tx = subscription_events_tx.clone();
+    tokio::spawn(async move {
+        loop {
+            match subscription_events_rx.recv().await {
+                Ok(message) => {
+                    // Sending only fails when no client is subscribed
+                    let _ = forwarded_events_tx.send(message);
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+
     let jsonrpc_server_config = JsonRpcServerConfig {
         address: config.jsonrpc_server_address,
         node_type: config.node_type,
         events_tx,
+        subscription_events_tx,
         vrrbdb_read_handle,
         mempool_read_handle_factory,
+        dag,
+        membership_config: config.quorum_config.clone(),
     };
 
     let (jsonrpc_server_handle, resolved_jsonrpc_server_addr) =
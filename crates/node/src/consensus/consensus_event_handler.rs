@@ -15,6 +15,7 @@ use std::{
 use vrrb_config::QuorumMember;
 use vrrb_config::QuorumMembershipConfig;
 use vrrb_core::claim::Claim;
+use vrrb_core::staking::CertifyingQuorum;
 use vrrb_core::transactions::TransactionDigest;
 
 impl ConsensusModule {
@@ -186,6 +187,17 @@ impl ConsensusModule {
 
         let quorums = unique_quorums.into_iter().collect::<Vec<_>>();
         self.sig_engine.set_quorum_members(quorums);
+
+        // Stake updates are certified by the harvester quorum
+        let quorum_members = self.sig_engine.quorum_members();
+        if let Some(harvesters) = quorum_members.get_harvester_data() {
+            self.validator_core_manager
+                .set_certifying_quorum(CertifyingQuorum {
+                    members: harvesters.members,
+                    threshold: quorum_members.get_harvester_threshold(),
+                });
+        }
+
         Ok(())
     }
 
@@ -1,4 +1,8 @@
-use events::{EventPublisher, EventSubscriber};
+use std::sync::{Arc, RwLock};
+
+use block::Block;
+use bulldag::graph::BullDag;
+use events::{Event, EventPublisher, EventSubscriber};
 use mempool::MempoolReadHandleFactory;
 use storage::vrrbdb::VrrbDbReadHandle;
 use theater::{Actor, ActorImpl};
@@ -18,6 +22,7 @@ pub struct NodeRuntimeComponentResolvedData {
     pub node_config: NodeConfig,
     pub state_read_handle: VrrbDbReadHandle,
     pub mempool_read_handle_factory: MempoolReadHandleFactory,
+    pub dag: Arc<RwLock<BullDag<Block, String>>>,
 }
 
 #[async_trait::async_trait]
@@ -28,12 +33,16 @@ impl RuntimeComponent<NodeRuntimeComponentConfig, NodeRuntimeComponentResolvedDa
         args: NodeRuntimeComponentConfig,
     ) -> crate::Result<RuntimeComponentHandle<NodeRuntimeComponentResolvedData>> {
         let mut events_rx = args.events_rx;
-        let node_runtime = NodeRuntime::new(&args.config, args.events_tx)
+        let events_tx = args.events_tx.clone();
+        let mut node_runtime = NodeRuntime::new(&args.config, args.events_tx)
             .await
             .map_err(|err| NodeError::Other(err.to_string()))?;
 
+        let restored_txns = node_runtime.restore_mempool()?;
+
         let state_read_handle = node_runtime.state_read_handle();
         let mempool_read_handle_factory = node_runtime.mempool_read_handle_factory();
+        let dag = node_runtime.state_driver.dag();
 
         let mut node_runtime_actor = ActorImpl::new(node_runtime);
 
@@ -44,12 +53,26 @@ impl RuntimeComponent<NodeRuntimeComponentConfig, NodeRuntimeComponentResolvedDa
                 .map_err(|err| NodeError::Other(err.to_string()))
         });
 
+        // NOTE: restored transactions go through validation again once the
+        // runtime is listening for events
+        tokio::spawn(async move {
+            for txn_hash in restored_txns {
+                if let Err(err) = events_tx
+                    .send(Event::TxnAddedToMempool(txn_hash).into())
+                    .await
+                {
+                    telemetry::error!("could not revalidate restored transaction: {err}");
+                }
+            }
+        });
+
         telemetry::info!("NodeRuntime module is operational");
 
         let node_runtime_resolved_data = NodeRuntimeComponentResolvedData {
             node_config: args.config,
             state_read_handle,
             mempool_read_handle_factory,
+            dag,
         };
 
         let component_handle = RuntimeComponentHandle::new(
@@ -25,6 +25,17 @@ impl NodeRuntime {
         }
     }
 
+    /// Fetches the packages of the contracts a convergence block calls, so
+    /// they can be executed while it is applied
+    pub async fn fetch_contract_packages(&self, block: &Block) {
+        if let Block::Convergence { block } = block {
+            let package_cids = self.state_driver.contract_packages(block);
+            if let Err(err) = self.package_resolver.fetch_packages(package_cids).await {
+                telemetry::warn!("Failed to fetch contract packages: {err}");
+            }
+        }
+    }
+
     fn handle_genesis_block_received(&mut self, block: GenesisBlock) -> Result<ApplyBlockResult> {
         self.verify_genesis_block_origin(block.clone())?;
 
@@ -432,6 +443,12 @@ impl NodeRuntime {
         address: Address,
         account_bytes: AccountBytes,
     ) -> Result<()> {
+        if self.state_driver.get_account(&address).is_ok() {
+            return Err(NodeError::Other(format!(
+                "an account already exists at {address}"
+            )));
+        }
+
         let account = bincode::deserialize(&account_bytes).map_err(|err| {
             NodeError::Other(format!("unable to deserialize account bytes: {err}"))
         })?;
@@ -2,9 +2,11 @@ pub mod component;
 pub mod handler_helpers;
 pub mod node_runtime;
 pub mod node_runtime_handler;
+mod package_resolver;
 mod setup;
 
 pub use handler_helpers::*;
+pub use package_resolver::*;
 pub use setup::*;
 
 #[cfg(test)]
@@ -2,6 +2,7 @@ use crate::{
     consensus::{ConsensusModule, ConsensusModuleConfig},
     result::{NodeError, Result},
     state_manager::{StateManager, StateManagerConfig},
+    Web3PackageResolver,
 };
 
 use block::{
@@ -10,7 +11,7 @@ use block::{
 };
 use bulldag::graph::BullDag;
 use events::{Event, EventMessage, EventPublisher, Vote};
-use mempool::{LeftRightMempool, MempoolReadHandleFactory, TxnRecord};
+use mempool::{sort_by_priority, LeftRightMempool, MempoolReadHandleFactory, TxnRecord};
 use miner::{Miner, MinerConfig};
 use primitives::{
     Address, Epoch, NodeId, NodeType, PublicKey, QuorumKind, Round, Signature, NETWORK_TOPIC_STR,
@@ -23,7 +24,9 @@ use std::{
     collections::HashMap,
     sync::{Arc, RwLock},
 };
-use storage::vrrbdb::{StateStoreReadHandleFactory, VrrbDbConfig, VrrbDbReadHandle};
+use storage::vrrbdb::{
+    StateStoreReadHandleFactory, VrrbDbConfig, VrrbDbReadHandle, JAVY_PROVIDER_NAMESPACE,
+};
 use theater::{ActorId, ActorState};
 use tokio::task::JoinHandle;
 use utils::payload::digest_data_to_bytes;
@@ -49,6 +52,7 @@ pub struct NodeRuntime {
     pub mining_driver: Miner,
     pub claim: Claim,
     pub pending_quorum: Option<InaugaratedMembers>,
+    pub package_resolver: Arc<Web3PackageResolver>,
 }
 
 impl NodeRuntime {
@@ -85,8 +89,19 @@ impl NodeRuntime {
             vrrbdb_config.with_path(config.db_path().to_path_buf());
         }
 
-        let database = storage::vrrbdb::VrrbDb::new(vrrbdb_config);
-        let mempool = LeftRightMempool::new();
+        vrrbdb_config.persist_mempool = config.mempool_config.persist;
+        vrrbdb_config.stale_node_retention = config.state_history_retention;
+
+        let mut database = storage::vrrbdb::VrrbDb::new(vrrbdb_config);
+        let mut package_resolver = Web3PackageResolver::deferred(config.package_store_path.clone());
+        if let Some(package_cid) = &config.javy_provider_package {
+            package_resolver =
+                package_resolver.with_runtime_package(JAVY_PROVIDER_NAMESPACE, package_cid);
+        }
+        let package_resolver = Arc::new(package_resolver);
+        database.set_package_resolver(package_resolver.clone());
+        let mempool = LeftRightMempool::with_config(config.mempool_config.clone())
+            .map_err(|err| NodeError::Other(err.to_string()))?;
 
         let state_driver = StateManager::new(StateManagerConfig {
             database: database.clone(),
@@ -130,6 +145,7 @@ impl NodeRuntime {
             mining_driver: miner,
             claim,
             pending_quorum: None,
+            package_resolver,
         })
     }
 
@@ -304,6 +320,7 @@ impl NodeRuntime {
         let genesis = GenesisBlock {
             header: block_header,
             genesis_rewards,
+            trusted_package_publishers: self.config.trusted_package_publishers.clone(),
             claims,
             hash: hex::encode(block_hash),
             certificate: None,
@@ -383,11 +400,17 @@ impl NodeRuntime {
         sig_engine: SignerEngine,
     ) -> Result<ProposalBlock> {
         self.consensus_driver.is_harvester()?;
-        let txns = self
+        // NOTE: Pick the highest paying certified transactions first, keeping
+        // each sender's transactions in nonce order
+        let mut txns = self
             .consensus_driver
             .quorum_certified_txns
-            .iter()
-            .take(PULL_TXN_BATCH_SIZE);
+            .values()
+            //TODO: variable _cert is not being used.
+            .map(|(txn, _cert)| txn.clone())
+            .collect::<Vec<TransactionKind>>();
+        sort_by_priority(&mut txns);
+        txns.truncate(PULL_TXN_BATCH_SIZE);
 
         // NOTE: Read updated claims
         // let claim_map = self.vrrbdb_read_handle.claim_store_values();
@@ -396,10 +419,9 @@ impl NodeRuntime {
             .map(|from| (from.hash, from.clone()))
             .collect();
 
-        //TODO: variable _cert is not being used.
         let txns_list: LinkedHashMap<TransactionDigest, TransactionKind> = txns
             .into_iter()
-            .map(|(digest, (txn, _cert))| {
+            .map(|txn| {
                 //                if let Err(err) = self
                 //                    .consensus_driver
                 //                    .certified_txns_filter
@@ -407,7 +429,7 @@ impl NodeRuntime {
                 //                {
                 //                    telemetry::error!("Error pushing txn to certified txns filter: {err}");
                 //                }
-                (digest.clone(), txn.clone())
+                (txn.id(), txn)
             })
             .collect();
 
@@ -525,7 +547,10 @@ impl NodeRuntime {
         self.state_driver.get_claims(claim_hashes)
     }
 
-    pub fn insert_txn_to_mempool(&mut self, txn: TransactionKind) -> Result<TransactionDigest> {
+    pub fn insert_txn_to_mempool(
+        &mut self,
+        txn: TransactionKind,
+    ) -> Result<Vec<TransactionDigest>> {
         self.state_driver.insert_txn_to_mempool(txn)
     }
 
@@ -533,6 +558,12 @@ impl NodeRuntime {
         self.state_driver.extend_mempool(txns)
     }
 
+    /// Reloads the mempool persisted by a previous run, if mempool
+    /// persistence is enabled
+    pub fn restore_mempool(&mut self) -> Result<Vec<TransactionDigest>> {
+        self.state_driver.restore_mempool()
+    }
+
     pub fn memmpol_len(&self) -> usize {
         self.state_driver.mempool_len()
     }
@@ -161,15 +161,24 @@ impl Handler<EventMessage> for NodeRuntime {
                     .map_err(|err| TheaterError::Other(err.to_string()))?;
             }
             Event::NewTxnCreated(txn) => {
-                let txn_hash = self
+                let released = self
                     .state_driver
                     .insert_txn_to_mempool(txn)
                     .map_err(|err| TheaterError::Other(err.to_string()))?;
 
-                self.events_tx
-                    .send(Event::TxnAddedToMempool(txn_hash.clone()).into())
-                    .await
-                    .map_err(|err| TheaterError::Other(err.to_string()))?;
+                for txn_hash in released {
+                    self.events_tx
+                        .send(Event::TxnAddedToMempool(txn_hash).into())
+                        .await
+                        .map_err(|err| TheaterError::Other(err.to_string()))?;
+                }
+
+                for record in self.state_driver.evict_from_mempool() {
+                    self.events_tx
+                        .send(Event::TxnEvictedFromMempool(record.txn_id).into())
+                        .await
+                        .map_err(|err| TheaterError::Other(err.to_string()))?;
+                }
             }
 
             Event::TxnValidated(txn) => {
@@ -178,7 +187,11 @@ impl Handler<EventMessage> for NodeRuntime {
             Event::CreateAccountRequested((address, account_bytes)) => {
                 // I think we can get rid of this, as we now add accounts
                 // when they are a receiver of a transaction
-                self.handle_create_account_requested(address.clone(), account_bytes)?;
+                if let Err(err) =
+                    self.handle_create_account_requested(address.clone(), account_bytes)
+                {
+                    telemetry::error!("could not create account {}: {}", address, err);
+                }
             }
             Event::AccountUpdateRequested((_address, _account_bytes)) => {
                 todo!()
@@ -242,6 +255,8 @@ impl Handler<EventMessage> for NodeRuntime {
                     .handle_block_received(&mut block, self.consensus_driver.sig_engine.clone())
                     .map_err(|err| TheaterError::Other(err.to_string()))?;
 
+                self.fetch_contract_packages(&block).await;
+
                 let apply_result = self.handle_block_received(block)?;
 
                 telemetry::info!(
@@ -0,0 +1,426 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
+
+use storage::{
+    storage_utils::{Result, StorageError},
+    vrrbdb::PackageResolver,
+};
+use tokio::sync::OnceCell;
+use web3_pkg::{
+    web3_pkg::{Web3Package, Web3PackageArchitecture, Web3PackageObject, Web3PackageType},
+    web3_store::Web3Store,
+};
+
+/// The module of a fetched package along with the publisher who signed it,
+/// or why the package cannot be executed
+type FetchedModule = std::result::Result<(Arc<Vec<u8>>, Option<String>), String>;
+
+/// Fetches the WASM modules of deployed contracts from the `web3_pkg`
+/// content store, by reading the package's DAG and then its
+/// `wasm32-wasi` object. Runtime packages, such as the QuickJS provider
+/// JavaScript contracts link against, are fetched the same way. Signed
+/// packages are verified, and the publisher who signed them is resolved
+/// along with their module, so the chain's package policy can be applied
+/// to them.
+///
+/// Blocks are applied synchronously, so the modules a block needs are
+/// fetched beforehand with [Web3PackageResolver::fetch_packages] and
+/// resolved from memory while it is applied. The store is only opened once
+/// packages are first fetched, so the node starts even when it is
+/// unreachable.
+pub struct Web3PackageResolver {
+    /// Directory of the local store packages are read from, packages are
+    /// read from the local IPFS node when unset
+    store_path: Option<PathBuf>,
+    store: OnceCell<Web3Store>,
+    /// CIDs of the runtime packages providing each import namespace
+    runtime_packages: HashMap<String, String>,
+    /// Modules fetched for the next block, by package CID
+    modules: RwLock<HashMap<String, FetchedModule>>,
+}
+
+impl Web3PackageResolver {
+    /// Reads packages from `store`
+    pub fn new(store: Web3Store) -> Self {
+        Self::with_store(None, OnceCell::new_with(Some(store)))
+    }
+
+    /// Reads packages from the local store in `store_path`, or from the
+    /// local IPFS node when `None`. The store is opened the first time
+    /// packages are fetched.
+    pub fn deferred(store_path: Option<PathBuf>) -> Self {
+        Self::with_store(store_path, OnceCell::new())
+    }
+
+    fn with_store(store_path: Option<PathBuf>, store: OnceCell<Web3Store>) -> Self {
+        Self {
+            store_path,
+            store,
+            runtime_packages: HashMap::new(),
+            modules: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Serves the imports under `namespace` from the smart contract runtime
+    /// package `package_cid`
+    pub fn with_runtime_package(
+        mut self,
+        namespace: impl Into<String>,
+        package_cid: impl Into<String>,
+    ) -> Self {
+        self.runtime_packages
+            .insert(namespace.into(), package_cid.into());
+        self
+    }
+
+    async fn store(&self) -> anyhow::Result<&Web3Store> {
+        self.store
+            .get_or_try_init(|| async {
+                match &self.store_path {
+                    Some(path) => Web3Store::local_dir(path),
+                    None => Web3Store::local(),
+                }
+            })
+            .await
+    }
+
+    /// Fetches the modules of the contract packages `package_cids`, along
+    /// with the runtime packages, so they can be resolved while the next
+    /// block is applied. Modules fetched for earlier blocks are dropped.
+    /// Packages that cannot be read are left out, and applying a block
+    /// calling them fails until they can be.
+    pub async fn fetch_packages(
+        &self,
+        package_cids: impl IntoIterator<Item = String>,
+    ) -> anyhow::Result<()> {
+        let store = self.store().await?;
+        let fetched = self
+            .modules
+            .read()
+            .map(|modules| modules.clone())
+            .unwrap_or_default();
+
+        let contracts = package_cids
+            .into_iter()
+            .map(|package_cid| (package_cid, false));
+        let runtimes = self
+            .runtime_packages
+            .values()
+            .map(|package_cid| (package_cid.clone(), true));
+
+        let mut modules = HashMap::new();
+        for (package_cid, is_runtime) in contracts.chain(runtimes) {
+            if let Some(module) = fetched.get(&package_cid) {
+                modules.insert(package_cid, module.clone());
+                continue;
+            }
+
+            match self.fetch_module(store, &package_cid, is_runtime).await {
+                Ok(module) => {
+                    modules.insert(package_cid, module);
+                }
+                Err(err) => telemetry::warn!("Failed to fetch package {package_cid}: {err}"),
+            }
+        }
+
+        *self
+            .modules
+            .write()
+            .map_err(|_| anyhow::anyhow!("package modules lock poisoned"))? = modules;
+
+        Ok(())
+    }
+
+    /// Reads the module of a package. Packages that were read but cannot be
+    /// executed are fetched as the reason why, every node reading the same
+    /// package comes to the same conclusion.
+    async fn fetch_module(
+        &self,
+        store: &Web3Store,
+        package_cid: &str,
+        is_runtime: bool,
+    ) -> anyhow::Result<FetchedModule> {
+        let package = store.read_package(package_cid).await?;
+
+        let checked = if is_runtime {
+            check_runtime(&package)
+        } else {
+            check_contract(&package)
+        };
+        let (object, publisher) =
+            match checked.and_then(|_| Ok((wasm_object(&package)?, publisher(&package)?))) {
+                Ok(checked) => checked,
+                Err(err) => return Ok(Err(err.to_string())),
+            };
+
+        let module = store.read_object(&object.object_cid.cid).await?;
+
+        Ok(Ok((Arc::new(module), publisher)))
+    }
+
+    fn fetched(&self, package_cid: &str) -> Result<(Arc<Vec<u8>>, Option<String>)> {
+        let modules = self
+            .modules
+            .read()
+            .map_err(|_| StorageError::Other("package modules lock poisoned".to_string()))?;
+
+        match modules.get(package_cid) {
+            Some(Ok(fetched)) => Ok(fetched.clone()),
+            Some(Err(err)) => Err(StorageError::Other(format!(
+                "failed to resolve package {package_cid}: {err}"
+            ))),
+            None => Err(StorageError::NotFound(format!("package {package_cid}"))),
+        }
+    }
+
+    fn module(&self, package_cid: &str) -> Result<Vec<u8>> {
+        self.fetched(package_cid).map(|(module, _)| module.to_vec())
+    }
+}
+
+/// Only smart contract packages can be deployed and called, whatever
+/// objects other packages carry
+fn check_contract(package: &Web3Package) -> anyhow::Result<()> {
+    if !matches!(package.pkg_type, Web3PackageType::SmartContract) {
+        anyhow::bail!("package is not a smart contract");
+    }
+    Ok(())
+}
+
+fn check_runtime(package: &Web3Package) -> anyhow::Result<()> {
+    if !matches!(package.pkg_type, Web3PackageType::SmartContractRuntime) {
+        anyhow::bail!("package is not a smart contract runtime");
+    }
+    Ok(())
+}
+
+/// Verifies the package's signature, returning the publisher who signed it,
+/// or `None` when it is unsigned
+fn publisher(package: &Web3Package) -> anyhow::Result<Option<String>> {
+    if package.pkg_signature.is_none() {
+        return Ok(None);
+    }
+
+    Ok(Some(package.verify()?.to_lowercase()))
+}
+
+fn wasm_object(package: &Web3Package) -> anyhow::Result<&Web3PackageObject> {
+    package
+        .pkg_objects
+        .iter()
+        .find(|object| matches!(object.object_arch, Web3PackageArchitecture::Wasm32Wasi))
+        .ok_or_else(|| anyhow::anyhow!("package has no wasm32-wasi object"))
+}
+
+impl fmt::Debug for Web3PackageResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Web3PackageResolver")
+            .field("store_path", &self.store_path)
+            .field("runtime_packages", &self.runtime_packages)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PackageResolver for Web3PackageResolver {
+    fn resolve(&self, package_cid: &str) -> Result<Vec<u8>> {
+        self.module(package_cid)
+    }
+
+    fn publisher(&self, package_cid: &str) -> Result<Option<String>> {
+        self.fetched(package_cid).map(|(_, publisher)| publisher)
+    }
+
+    fn is_available(&self, package_cid: &str) -> bool {
+        self.modules
+            .read()
+            .map(|modules| modules.contains_key(package_cid))
+            .unwrap_or_default()
+    }
+
+    fn resolve_runtime(&self, namespace: &str) -> Result<Vec<u8>> {
+        let package_cid = self.runtime_packages.get(namespace).ok_or_else(|| {
+            StorageError::Other(format!("no runtime package provides {namespace}"))
+        })?;
+
+        self.module(package_cid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use vrrb_core::keypair::KeyPair;
+    use web3_pkg::web3_pkg::{
+        Web3ContentId, Web3ObjectType, Web3PackageBuilder, Web3PackageObjectBuilder,
+    };
+
+    use super::*;
+
+    fn store_dir(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("node-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        path
+    }
+
+    /// Publishes a package of `pkg_type` to `store`, with `module` as its
+    /// `wasm32-wasi` object when there is one
+    async fn publish(
+        store: &Web3Store,
+        pkg_type: Web3PackageType,
+        module: Option<&[u8]>,
+    ) -> String {
+        let package = package(store, pkg_type, module).await;
+        write_package(store, &package).await
+    }
+
+    async fn package(
+        store: &Web3Store,
+        pkg_type: Web3PackageType,
+        module: Option<&[u8]>,
+    ) -> Web3Package {
+        let mut objects = vec![];
+        if let Some(module) = module {
+            let object_cid = store.write_object(module.to_vec()).await.unwrap();
+            objects.push(
+                Web3PackageObjectBuilder::default()
+                    .object_arch(Web3PackageArchitecture::Wasm32Wasi)
+                    .object_path("contract.wasm".to_string())
+                    .object_type(Web3ObjectType::Executable)
+                    .object_cid(Web3ContentId { cid: object_cid })
+                    .build()
+                    .unwrap(),
+            );
+        }
+
+        Web3PackageBuilder::default()
+            .pkg_version(1)
+            .pkg_name("contract".to_string())
+            .pkg_author("node tests".to_string())
+            .pkg_type(pkg_type)
+            .pkg_objects(objects)
+            .pkg_replaces(vec![])
+            .build()
+            .unwrap()
+    }
+
+    async fn write_package(store: &Web3Store, package: &Web3Package) -> String {
+        store
+            .write_dag(serde_json::to_vec(package).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn only_resolves_the_packages_fetched_for_the_next_block() {
+        let path = store_dir("only_resolves_the_packages_fetched_for_the_next_block");
+        let resolver = Web3PackageResolver::deferred(Some(path.clone()));
+
+        let store = Web3Store::local_dir(&path).unwrap();
+        let contract_cid = publish(&store, Web3PackageType::SmartContract, Some(b"\0asm")).await;
+        let empty_cid = publish(&store, Web3PackageType::SmartContract, None).await;
+
+        assert!(!resolver.is_available(&contract_cid));
+        assert!(matches!(
+            resolver.resolve(&contract_cid),
+            Err(StorageError::NotFound(_))
+        ));
+
+        resolver
+            .fetch_packages(vec![contract_cid.clone(), empty_cid.clone()])
+            .await
+            .unwrap();
+        assert_eq!(resolver.resolve(&contract_cid).unwrap(), b"\0asm");
+        // Every node reading the package rejects it the same way
+        assert!(resolver.is_available(&empty_cid));
+        assert!(matches!(
+            resolver.resolve(&empty_cid),
+            Err(StorageError::Other(_))
+        ));
+
+        resolver.fetch_packages(vec![]).await.unwrap();
+        assert!(!resolver.is_available(&contract_cid));
+    }
+
+    #[tokio::test]
+    async fn fetches_runtime_packages_with_every_block() {
+        let path = store_dir("fetches_runtime_packages_with_every_block");
+        let store = Web3Store::local_dir(&path).unwrap();
+        let runtime_cid = publish(
+            &store,
+            Web3PackageType::SmartContractRuntime,
+            Some(b"\0asm"),
+        )
+        .await;
+        let contract_cid = publish(&store, Web3PackageType::SmartContract, Some(b"\0asm")).await;
+
+        let resolver = Web3PackageResolver::deferred(Some(path))
+            .with_runtime_package("provider", runtime_cid)
+            .with_runtime_package("contract", contract_cid);
+        assert!(matches!(
+            resolver.resolve_runtime("provider"),
+            Err(StorageError::NotFound(_))
+        ));
+
+        resolver.fetch_packages(vec![]).await.unwrap();
+        assert_eq!(resolver.resolve_runtime("provider").unwrap(), b"\0asm");
+        assert!(matches!(
+            resolver.resolve_runtime("contract"),
+            Err(StorageError::Other(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_contracts_that_are_not_smart_contract_packages() {
+        let path = store_dir("rejects_contracts_that_are_not_smart_contract_packages");
+        let store = Web3Store::local_dir(&path).unwrap();
+        let untyped_cid = publish(&store, Web3PackageType::None, Some(b"\0asm")).await;
+        let runtime_cid = publish(
+            &store,
+            Web3PackageType::SmartContractRuntime,
+            Some(b"\0asm"),
+        )
+        .await;
+
+        let resolver = Web3PackageResolver::deferred(Some(path));
+        resolver
+            .fetch_packages(vec![untyped_cid.clone(), runtime_cid.clone()])
+            .await
+            .unwrap();
+
+        for package_cid in [untyped_cid, runtime_cid] {
+            assert!(resolver.is_available(&package_cid));
+            assert!(matches!(
+                resolver.resolve(&package_cid),
+                Err(StorageError::Other(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn resolves_the_publishers_of_signed_packages() {
+        let path = store_dir("resolves_the_publishers_of_signed_packages");
+        let store = Web3Store::local_dir(&path).unwrap();
+        let keypair = KeyPair::random();
+        let mut signed = package(&store, Web3PackageType::SmartContract, Some(b"\0asm")).await;
+        signed.sign(&keypair).unwrap();
+        let signed_cid = write_package(&store, &signed).await;
+        let unsigned_cid = publish(&store, Web3PackageType::SmartContract, Some(b"\0asm")).await;
+
+        let resolver = Web3PackageResolver::deferred(Some(path));
+        resolver
+            .fetch_packages(vec![signed_cid.clone(), unsigned_cid.clone()])
+            .await
+            .unwrap();
+
+        let public_key = hex::encode(keypair.to_miner_pk_bytes().unwrap());
+        assert_eq!(resolver.publisher(&signed_cid).unwrap(), Some(public_key));
+        assert_eq!(resolver.publisher(&unsigned_cid).unwrap(), None);
+        assert!(matches!(
+            resolver.publisher("unfetched"),
+            Err(StorageError::NotFound(_))
+        ));
+    }
+}
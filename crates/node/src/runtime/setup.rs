@@ -32,6 +32,7 @@ pub async fn setup_runtime_components(
     let network_events_rx = router.subscribe(Some(NETWORK_TOPIC_STR.into()))?;
     let jsonrpc_events_rx = router.subscribe(Some(JSON_RPC_API_TOPIC_STR.into()))?;
     let indexer_events_rx = router.subscribe(None)?;
+    let jsonrpc_subscription_events_rx = router.subscribe(None)?;
 
     let mut runtime_manager = RuntimeComponentManager::new();
 
@@ -50,6 +51,7 @@ pub async fn setup_runtime_components(
 
     let mempool_read_handle_factory = handle_data.mempool_read_handle_factory;
     let state_read_handle = handle_data.state_read_handle;
+    let dag = handle_data.dag;
 
     runtime_manager.register_component(
         node_runtime_component_handle.label(),
@@ -85,7 +87,9 @@ pub async fn setup_runtime_components(
         events_tx.clone(),
         state_read_handle.clone(),
         mempool_read_handle_factory.clone(),
+        dag,
         jsonrpc_events_rx,
+        jsonrpc_subscription_events_rx,
     )
     .await?;
 
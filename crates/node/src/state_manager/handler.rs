@@ -44,18 +44,26 @@ impl Handler<EventMessage> for StateManager {
 
                 let txn_hash = txn.id();
 
-                let _mempool_size = self
-                    .mempool
-                    .insert(txn)
+                let released = self
+                    .insert_txn_to_mempool(txn)
                     .map_err(|err| TheaterError::Other(err.to_string()))?;
 
-                self.events_tx
-                    .send(Event::TxnAddedToMempool(txn_hash.clone()).into())
-                    .await
-                    .map_err(|err| TheaterError::Other(err.to_string()))?;
+                for released_hash in released {
+                    self.events_tx
+                        .send(Event::TxnAddedToMempool(released_hash).into())
+                        .await
+                        .map_err(|err| TheaterError::Other(err.to_string()))?;
+                }
 
                 info!("Transaction {} sent to mempool", txn_hash);
 
+                for record in self.evict_from_mempool() {
+                    self.events_tx
+                        .send(Event::TxnEvictedFromMempool(record.txn_id).into())
+                        .await
+                        .map_err(|err| TheaterError::Other(err.to_string()))?;
+                }
+
                 // if self.mempool.size_in_kilobytes() >= MEMPOOL_THRESHOLD_SIZE
                 //     && self.cutoff_transaction.is_none()
                 // {
@@ -1,5 +1,5 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
     sync::{Arc, RwLock},
 };
 
@@ -12,7 +12,7 @@ use bulldag::{
 };
 use ethereum_types::U256;
 use events::Event;
-use mempool::{LeftRightMempool, MempoolReadHandleFactory};
+use mempool::{fee_priority, LeftRightMempool, MempoolReadHandleFactory, TxnRecord};
 use primitives::{Address, NodeId, Round};
 use signer::engine::{QuorumMembers, SignerEngine};
 use storage::vrrbdb::{types::*, ApplyBlockResult};
@@ -20,8 +20,9 @@ use storage::{
     storage_utils::StorageError,
     vrrbdb::{Claims, VrrbDb, VrrbDbReadHandle},
 };
-use telemetry::info;
+use telemetry::{error, info, warn};
 use theater::{ActorId, ActorState};
+use validator::txn_validator::TxnValidator;
 use vrrb_core::{account::Account, claim::Claim};
 use vrrb_core::{
     account::UpdateArgs,
@@ -85,14 +86,7 @@ impl StateManager {
     ) -> GraphResult<ApplyBlockResult> {
         let opt = self.dag.append_convergence(convergence)?;
         if let Some(cblock) = opt {
-            let ref_blocks = self.dag.get_convergence_reference_blocks(convergence);
-            let proposals: Vec<ProposalBlock> = ref_blocks
-                .iter()
-                .filter_map(|vertex| match vertex.get_data() {
-                    Block::Proposal { block } => Some(block.clone()),
-                    _ => None,
-                })
-                .collect();
+            let proposals = self.convergence_proposals(convergence);
 
             let res = self.apply_convergence_block(&cblock, &proposals)?;
             return Ok(res);
@@ -103,6 +97,27 @@ impl StateManager {
         ))
     }
 
+    /// Returns the proposal blocks `convergence` references that are in the
+    /// DAG
+    fn convergence_proposals(&self, convergence: &ConvergenceBlock) -> Vec<ProposalBlock> {
+        self.dag
+            .get_convergence_reference_blocks(convergence)
+            .iter()
+            .filter_map(|vertex| match vertex.get_data() {
+                Block::Proposal { block } => Some(block.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the CIDs of the packages of the contracts `convergence` calls,
+    /// which have to be fetched before it can be applied
+    pub fn contract_packages(&self, convergence: &ConvergenceBlock) -> BTreeSet<String> {
+        let proposals = self.convergence_proposals(convergence);
+
+        self.database.contract_packages(convergence, &proposals)
+    }
+
     pub fn apply_convergence_block(
         &mut self,
         convergence: &ConvergenceBlock,
@@ -408,14 +423,105 @@ impl StateManager {
         Ok(apply_result)
     }
 
-    pub fn insert_txn_to_mempool(&mut self, txn: TransactionKind) -> Result<TransactionDigest> {
-        let txn_hash = txn.id();
+    /// Adds a transaction to the mempool, holding it back until every
+    /// earlier nonce of its sender has been released. Returns the
+    /// transactions released for validation.
+    pub fn insert_txn_to_mempool(
+        &mut self,
+        txn: TransactionKind,
+    ) -> Result<Vec<TransactionDigest>> {
+        let account_nonce = self
+            .get_account(&txn.sender_address())
+            .map(|account| account.nonce())
+            .unwrap_or_default();
+
+        let replaced = self
+            .mempool
+            .get_by_nonce(&txn.sender_address(), txn.nonce())
+            .filter(|record| record.txn_id != txn.id());
+
+        let released = self
+            .mempool
+            .insert_with_nonce(txn.clone(), account_nonce)
+            .map_err(|err| NodeError::Other(err.to_string()))?;
 
-        self.mempool
-            .insert(txn)
+        if let Some(store) = self.database.mempool_store() {
+            if let Err(err) = store.insert(&txn) {
+                error!("could not persist transaction {}: {}", txn.id(), err);
+            }
+        }
+
+        // Replaced by fee
+        if let Some(record) = replaced {
+            self.unpersist_txns([&record.txn_id]);
+        }
+
+        Ok(released)
+    }
+
+    /// Reloads the transactions persisted to disk by a previous run into the
+    /// mempool, dropping those that are no longer valid against the current
+    /// state from disk too. Returns the transactions released for validation.
+    pub fn restore_mempool(&mut self) -> Result<Vec<TransactionDigest>> {
+        let store = match self.database.mempool_store() {
+            Some(store) => store.clone(),
+            None => return Ok(vec![]),
+        };
+
+        let mut txns = store
+            .values()
             .map_err(|err| NodeError::Other(err.to_string()))?;
 
-        Ok(txn_hash)
+        // Lowest nonce first and, for a given nonce, highest fee first so that
+        // transactions replaced by fee stay replaced
+        txns.sort_by(|a, b| a.nonce().cmp(&b.nonce()).then_with(|| fee_priority(a, b)));
+
+        // The certifying quorum is not known yet while the node starts, and
+        // the validator core checks stake certificates again before the
+        // transactions are included in a block
+        let validator = TxnValidator::new()
+            .without_stake_certificates()
+            .with_claim_reader(Some(self.database.claim_store_factory()));
+        let mut released = vec![];
+
+        for txn in txns {
+            let txn_hash = txn.id();
+
+            if let Err(err) = validator.validate(self.database.state_store_factory(), &txn) {
+                warn!("dropping persisted transaction {}: {}", txn_hash, err);
+                self.unpersist_txns([&txn_hash]);
+                continue;
+            }
+
+            match self.insert_txn_to_mempool(txn) {
+                Ok(txn_hashes) => released.extend(txn_hashes),
+                Err(err) => {
+                    warn!("dropping persisted transaction {}: {}", txn_hash, err);
+                    self.unpersist_txns([&txn_hash]);
+                }
+            }
+        }
+
+        info!(
+            "restored {} transactions into the mempool",
+            self.mempool.len()
+        );
+
+        Ok(released)
+    }
+
+    /// Removes transactions from the on-disk copy of the mempool, if any.
+    fn unpersist_txns<'a>(&self, txn_hashes: impl IntoIterator<Item = &'a TransactionDigest>) {
+        if let Some(store) = self.database.mempool_store() {
+            for txn_hash in txn_hashes {
+                if let Err(err) = store.remove(txn_hash) {
+                    error!(
+                        "could not remove persisted transaction {}: {}",
+                        txn_hash, err
+                    );
+                }
+            }
+        }
     }
 
     pub fn extend_mempool(&mut self, txns: &[TransactionKind]) -> Result<()> {
@@ -427,6 +533,15 @@ impl StateManager {
         Ok(())
     }
 
+    /// Drops expired transactions from the mempool and evicts pending ones
+    /// until it fits within its configured limits.
+    pub fn evict_from_mempool(&mut self) -> Vec<TxnRecord> {
+        let evicted = self.mempool.evict();
+        self.unpersist_txns(evicted.iter().map(|record| &record.txn_id));
+
+        evicted
+    }
+
     /// Return the number of key-value pairs in the map.
     ///
     pub fn mempool_len(&self) -> usize {
@@ -436,7 +551,10 @@ impl StateManager {
     #[deprecated = "use insert_txn_to_mempool instead"]
     pub fn handle_new_txn_created(&mut self, txn: TransactionKind) -> Result<TransactionDigest> {
         info!("Storing transaction in mempool for validation");
-        self.insert_txn_to_mempool(txn)
+        let txn_hash = txn.id();
+        self.insert_txn_to_mempool(txn)?;
+
+        Ok(txn_hash)
     }
 
     pub async fn handle_transaction_validated(&mut self, txn: TransactionKind) -> Result<()> {
@@ -444,6 +562,8 @@ impl StateManager {
             .remove(&txn.id())
             .map_err(|err| NodeError::Other(err.to_string()))?;
 
+        self.unpersist_txns([&txn.id()]);
+
         self.confirm_txn(txn).await?;
 
         Ok(())
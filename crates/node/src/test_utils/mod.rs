@@ -238,6 +238,7 @@ pub fn create_txn_from_accounts(
             .sign_ecdsa(Message::from_hashed_data::<secp256k1::hashes::sha256::Hash>(b"vrrb")),
         validators: Some(validators),
         nonce: sender.1.unwrap().nonce() + 1,
+        fee: None,
     };
 
     let mut txn = TransactionKind::Transfer(Transfer::new(txn_args));
@@ -252,6 +253,8 @@ pub fn create_txn_from_accounts(
         txn.token().clone(),
         txn.amount(),
         txn.nonce(),
+        txn.fee(),
+        None,
     );
 
     let _digest = TransactionDigest::from(txn_digest_vec);
@@ -288,6 +291,7 @@ pub fn create_txn_from_accounts_invalid_signature(
             .sign_ecdsa(Message::from_hashed_data::<secp256k1::hashes::sha256::Hash>(b"vrrb")),
         validators: Some(validators),
         nonce: sender.1.unwrap().nonce() + 1,
+        fee: None,
     };
 
     let mut txn = TransactionKind::Transfer(Transfer::new(txn_args));
@@ -302,6 +306,8 @@ pub fn create_txn_from_accounts_invalid_signature(
         txn.token().clone(),
         txn.amount(),
         txn.nonce(),
+        txn.fee(),
+        None,
     );
 
     let _digest = TransactionDigest::from(txn_digest_vec);
@@ -336,6 +342,7 @@ pub fn create_txn_from_accounts_invalid_timestamp(
             .sign_ecdsa(Message::from_hashed_data::<secp256k1::hashes::sha256::Hash>(b"vrrb")),
         validators: Some(validators),
         nonce: sender.1.unwrap().nonce() + 1,
+        fee: None,
     };
 
     let mut txn = TransactionKind::Transfer(Transfer::new(txn_args));
@@ -350,6 +357,8 @@ pub fn create_txn_from_accounts_invalid_timestamp(
         txn.token().clone(),
         txn.amount(),
         txn.nonce(),
+        txn.fee(),
+        None,
     );
 
     let _digest = TransactionDigest::from(txn_digest_vec);
@@ -436,6 +445,7 @@ pub fn create_mock_transaction_args(n: usize) -> NewTransferArgs {
             .sign_ecdsa(Message::from_hashed_data::<secp256k1::hashes::sha256::Hash>(b"vrrb")),
         validators: None,
         nonce: n as u128,
+        fee: None,
     }
 }
 
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 
+use ethereum_types::U256;
 use integral_db::{JellyfishMerkleTreeWrapper, ReadHandleFactory};
 use patriecia::{JellyfishMerkleTree, Version};
 use primitives::NodeId;
@@ -7,7 +8,7 @@ use sha2::Sha256;
 use storage_utils::{Result, StorageError};
 use vrrb_core::claim::Claim;
 
-use crate::RocksDbAdapter;
+use crate::{proofs, MerkleProof, RocksDbAdapter};
 
 #[derive(Debug, Clone)]
 pub struct ClaimStoreReadHandle {
@@ -43,10 +44,15 @@ impl ClaimStoreReadHandle {
     }
 
     pub fn entries(&self) -> Result<HashMap<NodeId, Claim>> {
+        self.entries_at_version(self.inner.version())
+    }
+
+    /// Returns every entry as it was at the given version of the trie
+    pub fn entries_at_version(&self, version: Version) -> Result<HashMap<NodeId, Claim>> {
         // TODO: revisit and refactor into inner wrapper
         Ok(self
             .inner
-            .iter(self.inner.version())
+            .iter(version)
             .map_err(|err| {
                 StorageError::Other(format!("unable to create iterator from trie: {}", err))
             })?
@@ -62,6 +68,11 @@ impl ClaimStoreReadHandle {
             .collect())
     }
 
+    /// Returns the latest committed version of the trie
+    pub fn version(&self) -> Version {
+        self.inner.version()
+    }
+
     /// Returns a number of initialized claims in the database
     pub fn len(&self) -> usize {
         self.inner.len()
@@ -83,16 +94,23 @@ impl ClaimStoreReadHandleFactory {
         Self { inner }
     }
 
-    pub fn handle(&self) -> ClaimStoreReadHandle {
-        let handle = self
-            .inner
+    fn tree(&self) -> JellyfishMerkleTree<RocksDbAdapter, Sha256> {
+        self.inner
             .handle()
             .enter()
             .map(|guard| guard.clone())
-            .unwrap_or_default();
+            .unwrap_or_default()
+    }
 
-        let inner = JellyfishMerkleTreeWrapper::new(handle);
+    pub fn handle(&self) -> ClaimStoreReadHandle {
+        let inner = JellyfishMerkleTreeWrapper::new(self.tree());
 
         ClaimStoreReadHandle { inner }
     }
+
+    /// Returns a proof that `key` is present in, or absent from, the trie as
+    /// it was at the given version
+    pub fn get_proof(&self, key: &U256, version: Version) -> Result<MerkleProof> {
+        proofs::prove(&self.tree(), key, version)
+    }
 }
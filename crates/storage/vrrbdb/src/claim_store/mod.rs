@@ -7,7 +7,7 @@ use sha2::Sha256;
 use storage_utils::{Result, StorageError};
 use vrrb_core::claim::Claim;
 
-use crate::RocksDbAdapter;
+use crate::{MerkleProof, RocksDbAdapter};
 
 mod claim_store_rh;
 pub use claim_store_rh::*;
@@ -18,6 +18,7 @@ pub type FailedClaimUpdates = Vec<(U256, Claims, Result<()>)>;
 #[derive(Debug, Clone)]
 pub struct ClaimStore {
     trie: LeftRightTrie<'static, U256, Claim, RocksDbAdapter, Sha256>,
+    db: Arc<RocksDbAdapter>,
 }
 
 impl Default for ClaimStore {
@@ -27,22 +28,31 @@ impl Default for ClaimStore {
             .join("db")
             .join("claim");
 
-        let db_adapter = RocksDbAdapter::new(db_path, "claims").unwrap_or_default();
+        let db = Arc::new(RocksDbAdapter::new(db_path, "claims").unwrap_or_default());
 
-        let trie = LeftRightTrie::new(Arc::new(db_adapter));
+        let trie = LeftRightTrie::new(db.clone());
 
-        Self { trie }
+        Self { trie, db }
     }
 }
 
 impl ClaimStore {
     /// Returns new, empty instance of ClaimDb
     pub fn new(path: &Path) -> Self {
+        Self::with_retention(path, None)
+    }
+
+    /// Returns new, empty instance of ClaimStore whose stale trie nodes are
+    /// pruned once they have been stale for `retention` versions
+    pub fn with_retention(path: &Path, retention: Option<u64>) -> Self {
         let path = path.join("claims");
-        let db_adapter = RocksDbAdapter::new(path, "claims").unwrap_or_default();
-        let trie = LeftRightTrie::new(Arc::new(db_adapter));
+        let db_adapter = RocksDbAdapter::new(path, "claims")
+            .map(|adapter| adapter.with_stale_node_retention(retention))
+            .unwrap_or_default();
+        let db = Arc::new(db_adapter);
+        let trie = LeftRightTrie::new(db.clone());
 
-        Self { trie }
+        Self { trie, db }
     }
 
     /// Returns new ReadHandle to the VrrDb data. As long as the returned value
@@ -58,6 +68,19 @@ impl ClaimStore {
         self.trie.publish();
     }
 
+    /// Returns the database backing the claim trie
+    pub(crate) fn db(&self) -> Arc<RocksDbAdapter> {
+        self.db.clone()
+    }
+
+    /// Stages the preimage of `key`, so it is written along with the trie
+    /// nodes the key ends up in
+    fn stage_preimage(&self, key: &U256) -> Result<()> {
+        self.db
+            .put_key_preimage(key)
+            .map_err(|err| StorageError::Other(err.to_string()))
+    }
+
     // Maybe initialize is better name for that?
     fn insert_uncommited(&mut self, claim: Claim) -> Result<()> {
         //        if claim.debits != 0 {
@@ -72,6 +95,7 @@ impl ClaimStore {
         //            ));
         //        }
 
+        self.stage_preimage(&claim.hash)?;
         self.trie.insert(claim.hash, claim);
 
         Ok(())
@@ -281,7 +305,21 @@ impl ClaimStore {
             .map_err(|e| StorageError::Other(e.to_string()))
     }
 
+    /// Returns a proof that a claim is present in, or absent from, the latest
+    /// committed version of the trie
+    pub fn get_proof(&self, key: &U256) -> Result<MerkleProof> {
+        let factory = self.factory();
+
+        factory.get_proof(key, factory.handle().version())
+    }
+
     pub fn extend(&mut self, claims: Vec<(U256, Option<Claim>)>) {
+        for (key, _) in claims.iter() {
+            if let Err(err) = self.stage_preimage(key) {
+                telemetry::error!("failed to stage preimage of claim {key}: {err}");
+            }
+        }
+
         self.trie.extend(claims)
     }
 
@@ -0,0 +1,612 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    sync::{Arc, Mutex},
+};
+
+use primitives::Address;
+use serde::{Deserialize, Serialize};
+use storage_utils::{Result, StorageError};
+use vrrb_core::transactions::{RpcTransactionDigest, TxAmount};
+use wasm_loader::wasm_loader::{WasmLoader, WasmLoaderBuilder};
+pub use wasm_runtime::javy::JAVY_PROVIDER_NAMESPACE;
+use wasm_runtime::{
+    deterministic::DeterministicContext,
+    host_abi::{ContractState, HostAddress, HostContext},
+    metering::GasSchedule,
+    module_cache::ModuleCache,
+    wasm_runtime::WasmRuntime,
+};
+use wasmer::{Cranelift, Target};
+
+/// Version of the JSON document contracts receive on stdin
+pub const CONTRACT_INPUT_VERSION: i32 = 1;
+
+/// Fetches the WASM module of a `web3_pkg` package by the package's CID, so
+/// contracts can be executed while blocks are applied.
+///
+/// Blocks are applied synchronously, so resolvers are not expected to wait
+/// on the network. Packages are fetched ahead of the blocks calling them,
+/// and the ones that could not be are reported as
+/// [StorageError::NotFound]. Whether a package is available depends on the
+/// node rather than on the chain, so blocks calling unavailable packages are
+/// not applied at all, rather than recording a failed call other nodes may
+/// not agree with.
+pub trait PackageResolver: Debug + Send + Sync {
+    fn resolve(&self, package_cid: &str) -> Result<Vec<u8>>;
+
+    /// Whether the module of `package_cid` has been fetched and can be
+    /// resolved
+    fn is_available(&self, _package_cid: &str) -> bool {
+        true
+    }
+
+    /// Hex encoded public key of the publisher whose signature over
+    /// `package_cid` was verified when it was fetched, or `None` when the
+    /// package is unsigned
+    fn publisher(&self, _package_cid: &str) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Fetches the module of the runtime package providing the imports under
+    /// `namespace`, such as the QuickJS provider that JavaScript contracts
+    /// compiled by Javy link against.
+    fn resolve_runtime(&self, namespace: &str) -> Result<Vec<u8>> {
+        Err(StorageError::Other(format!(
+            "no runtime package provides {namespace}"
+        )))
+    }
+}
+
+/// Document written to a contract's stdin when it is called
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContractInput {
+    pub version: i32,
+    pub tx_id: RpcTransactionDigest,
+    pub last_block_time: i64,
+    pub block_height: u128,
+    pub sender: Address,
+    pub contract_address: Address,
+    pub amount: TxAmount,
+    /// The contract account's storage as left by its previous call
+    pub state: Option<String>,
+    pub input: String,
+}
+
+/// Document a contract is expected to write to its stdout before exiting
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContractOutput {
+    /// New storage for the contract account, left untouched when `None`
+    #[serde(default)]
+    pub state: Option<String>,
+    #[serde(default)]
+    pub result: Option<String>,
+}
+
+/// Looks up the balance of an account that is not the executing contract
+pub type BalanceLookup = Box<dyn Fn(&Address) -> u128 + Send>;
+
+/// The state of a single contract account exposed to its module through the
+/// VRRB host ABI. Storage written through the ABI is kept on the account as
+/// a JSON object mapping hex encoded keys to hex encoded values, and
+/// transfers are only recorded here so they can be applied once the call
+/// succeeds.
+pub struct ContractAccountState {
+    contract_address: Address,
+    balance: u128,
+    storage: BTreeMap<String, String>,
+    dirty: bool,
+    transfers: Vec<(Address, u128)>,
+    balance_lookup: BalanceLookup,
+}
+
+impl ContractAccountState {
+    /// Creates the state of the contract at `contract_address`, which can
+    /// spend up to `balance`. Storage that is not a JSON object of hex keys
+    /// and values, such as state returned on stdout by contracts that do
+    /// not use the ABI, is treated as empty.
+    pub fn new(
+        contract_address: Address,
+        balance: u128,
+        storage: Option<&str>,
+        balance_lookup: BalanceLookup,
+    ) -> Self {
+        let storage = storage
+            .and_then(|storage| serde_json::from_str(storage).ok())
+            .unwrap_or_default();
+
+        Self {
+            contract_address,
+            balance,
+            storage,
+            dirty: false,
+            transfers: vec![],
+            balance_lookup,
+        }
+    }
+
+    /// The account storage after execution, or `None` when the contract did
+    /// not write to it through the ABI
+    pub fn storage(&self) -> Option<String> {
+        if !self.dirty {
+            return None;
+        }
+
+        serde_json::to_string(&self.storage).ok()
+    }
+
+    /// Transfers out of the contract account made during execution
+    pub fn transfers(&self) -> &[(Address, u128)] {
+        &self.transfers
+    }
+
+    fn transferred_to(&self, address: &Address) -> u128 {
+        self.transfers
+            .iter()
+            .filter(|(to, _)| to == address)
+            .map(|(_, amount)| amount)
+            .sum()
+    }
+}
+
+impl Debug for ContractAccountState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ContractAccountState")
+            .field("contract_address", &self.contract_address)
+            .field("balance", &self.balance)
+            .field("storage", &self.storage)
+            .field("transfers", &self.transfers)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ContractState for ContractAccountState {
+    fn storage_get(&self, contract: &HostAddress, key: &[u8]) -> Option<Vec<u8>> {
+        if contract != &self.contract_address.0 {
+            return None;
+        }
+
+        self.storage
+            .get(&hex::encode(key))
+            .and_then(|value| hex::decode(value).ok())
+    }
+
+    fn storage_set(&mut self, contract: &HostAddress, key: &[u8], value: &[u8]) {
+        if contract != &self.contract_address.0 {
+            return;
+        }
+
+        self.storage.insert(hex::encode(key), hex::encode(value));
+        self.dirty = true;
+    }
+
+    fn balance(&self, address: &HostAddress) -> u128 {
+        if address == &self.contract_address.0 {
+            return self.balance;
+        }
+
+        let address = Address(*address);
+        (self.balance_lookup)(&address).saturating_add(self.transferred_to(&address))
+    }
+
+    fn transfer(
+        &mut self,
+        from: &HostAddress,
+        to: &HostAddress,
+        amount: u128,
+    ) -> std::result::Result<(), String> {
+        if from != &self.contract_address.0 {
+            return Err("contracts can only transfer their own balance".to_string());
+        }
+
+        if to == from {
+            return Err("contracts cannot transfer to themselves".to_string());
+        }
+
+        self.balance = self
+            .balance
+            .checked_sub(amount)
+            .ok_or_else(|| format!("insufficient balance to transfer {amount}"))?;
+        self.transfers.push((Address(*to), amount));
+
+        Ok(())
+    }
+}
+
+/// What running a contract produced
+#[derive(Debug)]
+pub struct ContractExecution {
+    pub output: Result<ContractOutput>,
+    pub points_used: u64,
+    /// Transfers out of the contract account requested through the host ABI,
+    /// only meant to be applied when `output` is `Ok`
+    pub transfers: Vec<(Address, u128)>,
+}
+
+impl ContractExecution {
+    fn failed(err: StorageError) -> Self {
+        Self {
+            output: Err(err),
+            points_used: 0,
+            transfers: vec![],
+        }
+    }
+}
+
+/// Runs a contract's WASM module with the given input, returning what it
+/// wrote to stdout along with the metering points it consumed under
+/// `schedule`. Execution failures are reported alongside the points consumed
+/// until they happened.
+///
+/// Modules that declare the VRRB host ABI access `state` through it. When
+/// they write to their storage, the resulting storage replaces whatever
+/// state they returned on stdout.
+///
+/// Contracts run in the runtime's deterministic mode, seeded from the block
+/// and transaction, and modules using nondeterministic floating point
+/// operators are rejected before they run.
+///
+/// JavaScript contracts compiled by Javy are linked against the QuickJS
+/// provider `runtimes` resolves for [JAVY_PROVIDER_NAMESPACE]. A provider
+/// that is unavailable is reported as [StorageError::NotFound].
+///
+/// Compiled modules are kept in `module_cache` when there is one, so
+/// contracts are only compiled the first time they are called.
+pub fn execute_contract(
+    wasm_bytes: &[u8],
+    input: &ContractInput,
+    state: ContractAccountState,
+    meter_limit: u64,
+    schedule: GasSchedule,
+    runtimes: Option<&dyn PackageResolver>,
+    module_cache: Option<&ModuleCache>,
+) -> ContractExecution {
+    let mut stdin =
+        match serde_json::to_vec(input).map_err(|err| StorageError::Other(err.to_string())) {
+            Ok(stdin) => stdin,
+            Err(err) => return ContractExecution::failed(err),
+        };
+    // Contracts read their input a line at a time
+    stdin.push(b'\n');
+
+    let loader = match deterministic_loader(wasm_bytes) {
+        Ok(loader) => loader,
+        Err(err) => {
+            return ContractExecution::failed(StorageError::Other(format!(
+                "contract module rejected: {err}"
+            )))
+        }
+    };
+
+    let javy_provider = if loader.needs_javy {
+        let provider = runtimes
+            .ok_or_else(|| StorageError::Other("no package resolver configured".to_string()))
+            .and_then(|runtimes| runtimes.resolve_runtime(JAVY_PROVIDER_NAMESPACE));
+        match provider {
+            Ok(provider) => Some(provider),
+            Err(err) => return ContractExecution::failed(err),
+        }
+    } else {
+        None
+    };
+    // The provider runs as part of the contract, so it is held to the same
+    // determinism checks
+    if let Some(Err(err)) = javy_provider.as_deref().map(deterministic_loader) {
+        return ContractExecution::failed(StorageError::Other(format!(
+            "javy provider rejected: {err}"
+        )));
+    }
+
+    let context = HostContext {
+        caller: input.sender.0,
+        contract_address: input.contract_address.0,
+        block_height: input.block_height as u64,
+        block_time: input.last_block_time,
+    };
+    let state = Arc::new(Mutex::new(state));
+
+    let metering_config = schedule.metering_config(meter_limit);
+    let runtime = match module_cache {
+        Some(module_cache) => WasmRuntime::new_cached::<Cranelift>(
+            &Target::default(),
+            wasm_bytes,
+            metering_config,
+            module_cache,
+        ),
+        None => WasmRuntime::new::<Cranelift>(&Target::default(), wasm_bytes, metering_config),
+    }
+    .and_then(|runtime| match &javy_provider {
+        Some(provider) => runtime.javy_provider(provider),
+        None => Ok(runtime),
+    });
+    let mut runtime = match runtime {
+        Ok(runtime) => runtime
+            .stdin(&stdin)
+            .host_state(context, state.clone())
+            .deterministic(DeterministicContext::new(
+                input.last_block_time,
+                input.tx_id.as_bytes(),
+            )),
+        Err(err) => return ContractExecution::failed(StorageError::Other(err.to_string())),
+    };
+
+    let output = runtime
+        .execute()
+        .map_err(|err| StorageError::Other(format!("contract execution failed: {err}")))
+        .and_then(|_| {
+            serde_json::from_str::<ContractOutput>(runtime.stdout().trim()).map_err(|err| {
+                StorageError::Other(format!("contract returned invalid output: {err}"))
+            })
+        });
+    let points_used = runtime.consumed_points();
+
+    // The runtime holds on to the state until it is dropped
+    drop(runtime);
+    let state = match Arc::try_unwrap(state).map(|state| state.into_inner()) {
+        Ok(Ok(state)) => state,
+        _ => {
+            return ContractExecution {
+                output: Err(StorageError::Other(
+                    "contract state is still in use".to_string(),
+                )),
+                points_used,
+                transfers: vec![],
+            }
+        }
+    };
+
+    let output = output.map(|mut output| {
+        if let Some(storage) = state.storage() {
+            output.state = Some(storage);
+        }
+        output
+    });
+
+    ContractExecution {
+        output,
+        points_used,
+        transfers: state.transfers,
+    }
+}
+
+/// Loads `wasm_bytes` in the loader's deterministic mode, rejecting modules
+/// that may not execute the same way on every validator
+fn deterministic_loader(wasm_bytes: &[u8]) -> anyhow::Result<WasmLoader> {
+    WasmLoaderBuilder::default()
+        .wasm_bytes(wasm_bytes.to_vec())
+        .deterministic(true)
+        .parse()
+        .and_then(|builder| Ok(builder.build()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Spins for a while before returning a result, without using floats
+    const TEST_CONTRACT: &str = r#"
+        (module
+          (import "wasi_snapshot_preview1" "fd_write"
+            (func $fd_write (param i32 i32 i32 i32) (result i32)))
+          (memory (export "memory") 1)
+          (data (i32.const 16) "{\"result\":\"ok\"}\n")
+          (func (export "_start")
+            (local $i i32)
+            (loop $spin
+              (local.set $i (i32.add (local.get $i) (i32.const 1)))
+              (br_if $spin (i32.lt_u (local.get $i) (i32.const 100))))
+            (i32.store (i32.const 0) (i32.const 16))
+            (i32.store (i32.const 4) (i32.const 16))
+            (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))))
+    "#;
+
+    /// Shaped like a module compiled by `javy compile -d`, whose "bytecode"
+    /// is the output the stand-in provider below writes to stdout
+    const JAVY_CONTRACT: &str = r#"
+        (module
+          (import "javy_quickjs_provider_v1" "canonical_abi_realloc"
+            (func $realloc (param i32 i32 i32 i32) (result i32)))
+          (import "javy_quickjs_provider_v1" "eval_bytecode"
+            (func $eval_bytecode (param i32 i32)))
+          (import "javy_quickjs_provider_v1" "memory" (memory 0))
+          (data $bytecode "{\"result\":\"js\"}\n")
+          (func (export "_start")
+            (local $ptr i32)
+            (local.set $ptr (call $realloc (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 16)))
+            (memory.init $bytecode (local.get $ptr) (i32.const 0) (i32.const 16))
+            (call $eval_bytecode (local.get $ptr) (i32.const 16))))
+    "#;
+
+    const JAVY_PROVIDER: &str = r#"
+        (module
+          (import "wasi_snapshot_preview1" "fd_write"
+            (func $fd_write (param i32 i32 i32 i32) (result i32)))
+          (memory (export "memory") 1)
+          (func (export "canonical_abi_realloc") (param i32 i32 i32 i32) (result i32)
+            (i32.const 1024))
+          (func (export "eval_bytecode") (param $ptr i32) (param $len i32)
+            (i32.store (i32.const 0) (local.get $ptr))
+            (i32.store (i32.const 4) (local.get $len))
+            (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))))
+    "#;
+
+    /// Like [JAVY_PROVIDER], but its float arithmetic may produce different
+    /// NaNs on different platforms
+    const NONDETERMINISTIC_JAVY_PROVIDER: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "canonical_abi_realloc") (param i32 i32 i32 i32) (result i32)
+            (i32.const 1024))
+          (func (export "eval_bytecode") (param $ptr i32) (param $len i32)
+            (drop (f64.add (f64.const 1) (f64.const 2)))))
+    "#;
+
+    /// Serves the provider in its WAT text as the Javy runtime package
+    #[derive(Debug)]
+    struct JavyRuntime(&'static str);
+
+    impl PackageResolver for JavyRuntime {
+        fn resolve(&self, package_cid: &str) -> Result<Vec<u8>> {
+            Err(StorageError::Other(format!(
+                "unknown package {package_cid}"
+            )))
+        }
+
+        fn resolve_runtime(&self, namespace: &str) -> Result<Vec<u8>> {
+            assert_eq!(namespace, JAVY_PROVIDER_NAMESPACE);
+            Ok(wasmer::wat2wasm(self.0.as_bytes()).unwrap().into_owned())
+        }
+    }
+
+    fn test_module() -> Vec<u8> {
+        wasmer::wat2wasm(TEST_CONTRACT.as_bytes())
+            .unwrap()
+            .into_owned()
+    }
+
+    fn contract_input() -> ContractInput {
+        ContractInput {
+            version: CONTRACT_INPUT_VERSION,
+            tx_id: "81b067ac86934".to_string(),
+            last_block_time: 1689897402,
+            block_height: 1,
+            sender: Address::default(),
+            contract_address: Address::default(),
+            amount: 0,
+            state: None,
+            input: "{}".to_string(),
+        }
+    }
+
+    fn contract_state(balance: u128, storage: Option<&str>) -> ContractAccountState {
+        ContractAccountState::new(Address::default(), balance, storage, Box::new(|_| 0))
+    }
+
+    #[test]
+    fn executes_contracts_and_reports_consumed_points() {
+        let execution = execute_contract(
+            &test_module(),
+            &contract_input(),
+            contract_state(0, None),
+            1_000_000,
+            GasSchedule::default(),
+            None,
+            None,
+        );
+
+        assert_eq!(execution.output.unwrap().result, Some("ok".to_string()));
+        assert!(execution.points_used > 0);
+    }
+
+    #[test]
+    fn rejects_contracts_using_nondeterministic_operators() {
+        let wasm_bytes = wasmer::wat2wasm(
+            br#"(module
+                  (memory (export "memory") 1)
+                  (func (export "_start")
+                    (drop (f64.add (f64.const 1) (f64.const 2)))))"#,
+        )
+        .unwrap()
+        .into_owned();
+
+        let execution = execute_contract(
+            &wasm_bytes,
+            &contract_input(),
+            contract_state(0, None),
+            1_000_000,
+            GasSchedule::default(),
+            None,
+            None,
+        );
+
+        assert!(execution.output.is_err());
+        assert_eq!(execution.points_used, 0);
+    }
+
+    #[test]
+    fn reports_failures_of_contracts_running_out_of_points() {
+        let execution = execute_contract(
+            &test_module(),
+            &contract_input(),
+            contract_state(0, None),
+            10,
+            GasSchedule::default(),
+            None,
+            None,
+        );
+
+        assert!(execution.output.is_err());
+        assert_eq!(execution.points_used, 10);
+    }
+
+    #[test]
+    fn links_javy_contracts_against_the_provider_runtime_package() {
+        let wasm_bytes = wasmer::wat2wasm(JAVY_CONTRACT.as_bytes())
+            .unwrap()
+            .into_owned();
+
+        let execution = execute_contract(
+            &wasm_bytes,
+            &contract_input(),
+            contract_state(0, None),
+            1_000_000,
+            GasSchedule::default(),
+            None,
+            None,
+        );
+        assert!(execution.output.is_err());
+
+        let execution = execute_contract(
+            &wasm_bytes,
+            &contract_input(),
+            contract_state(0, None),
+            1_000_000,
+            GasSchedule::default(),
+            Some(&JavyRuntime(JAVY_PROVIDER)),
+            None,
+        );
+        assert_eq!(execution.output.unwrap().result, Some("js".to_string()));
+    }
+
+    #[test]
+    fn rejects_javy_providers_using_nondeterministic_operators() {
+        let wasm_bytes = wasmer::wat2wasm(JAVY_CONTRACT.as_bytes())
+            .unwrap()
+            .into_owned();
+
+        let execution = execute_contract(
+            &wasm_bytes,
+            &contract_input(),
+            contract_state(0, None),
+            1_000_000,
+            GasSchedule::default(),
+            Some(&JavyRuntime(NONDETERMINISTIC_JAVY_PROVIDER)),
+            None,
+        );
+        let err = execution.output.unwrap_err().to_string();
+        assert!(err.contains("javy provider rejected"), "{err}");
+        assert_eq!(execution.points_used, 0);
+    }
+
+    #[test]
+    fn contract_account_state_keeps_storage_as_hex_and_records_transfers() {
+        let contract = Address::default().0;
+        let receiver = [1u8; 20];
+        let mut state = contract_state(10, Some(r#"{"6b6579":"01"}"#));
+
+        assert_eq!(state.storage_get(&contract, b"key"), Some(vec![1]));
+        assert_eq!(state.storage(), None);
+
+        state.storage_set(&contract, b"key", &[2]);
+        assert_eq!(state.storage(), Some(r#"{"6b6579":"02"}"#.to_string()));
+
+        assert!(state.transfer(&contract, &receiver, 4).is_ok());
+        assert!(state.transfer(&contract, &receiver, 7).is_err());
+        assert!(state.transfer(&receiver, &contract, 1).is_err());
+        assert_eq!(state.balance(&contract), 6);
+        assert_eq!(state.balance(&receiver), 4);
+        assert_eq!(state.transfers(), &[(Address(receiver), 4)]);
+    }
+}
@@ -1,6 +1,12 @@
 mod claim_store;
+mod contracts;
+mod mempool_store;
+mod package_policy;
+mod proofs;
+mod receipt_store;
 pub mod result;
 mod rocksdb_adapter;
+mod state_checkpoints;
 mod state_store;
 pub mod test_utils;
 mod transaction_store;
@@ -10,7 +16,13 @@ mod vrrbdb_read_handle;
 mod vrrbdb_serialized_values;
 
 pub use claim_store::*;
+pub use contracts::*;
+pub use mempool_store::*;
+pub use package_policy::*;
+pub use proofs::*;
+pub use receipt_store::*;
 pub use rocksdb_adapter::*;
+pub use state_checkpoints::*;
 pub use state_store::*;
 pub use transaction_store::*;
 pub use types::*;
@@ -0,0 +1,80 @@
+use std::{path::Path, sync::Arc};
+
+use rocksdb::{ColumnFamily, IteratorMode, DB};
+use storage_utils::{Result, StorageError};
+use vrrb_core::transactions::{Transaction, TransactionDigest, TransactionKind};
+
+use crate::{base_db_options, new_db_instance};
+
+pub const MEMPOOL_COLUMN_FAMILY: &str = "mempool";
+
+/// Write-through copy of the pending transactions held in the mempool, so
+/// they can be reloaded after a node restart.
+#[derive(Debug, Clone)]
+pub struct MempoolStore {
+    db: Arc<DB>,
+}
+
+impl MempoolStore {
+    pub fn new(path: &Path) -> Result<Self> {
+        let path = path.join("mempool");
+
+        let mut options = base_db_options();
+        options.set_error_if_exists(false);
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+
+        let db = new_db_instance(options, path, MEMPOOL_COLUMN_FAMILY)?;
+
+        Ok(Self { db: Arc::new(db) })
+    }
+
+    fn column_family(&self) -> Result<&ColumnFamily> {
+        self.db.cf_handle(MEMPOOL_COLUMN_FAMILY).ok_or_else(|| {
+            StorageError::Other(format!(
+                "column family {MEMPOOL_COLUMN_FAMILY} does not exist"
+            ))
+        })
+    }
+
+    pub fn insert(&self, txn: &TransactionKind) -> Result<()> {
+        let value = serde_json::to_vec(txn).map_err(|err| StorageError::Other(err.to_string()))?;
+
+        self.db
+            .put_cf(self.column_family()?, txn.id().digest_string(), value)
+            .map_err(|err| StorageError::Other(err.to_string()))
+    }
+
+    pub fn remove(&self, txn_id: &TransactionDigest) -> Result<()> {
+        self.db
+            .delete_cf(self.column_family()?, txn_id.digest_string())
+            .map_err(|err| StorageError::Other(err.to_string()))
+    }
+
+    /// Returns every persisted transaction. Entries that can no longer be
+    /// decoded are skipped.
+    pub fn values(&self) -> Result<Vec<TransactionKind>> {
+        let txns = self
+            .db
+            .iterator_cf(self.column_family()?, IteratorMode::Start)
+            .flatten()
+            .filter_map(|(_, value)| serde_json::from_slice::<TransactionKind>(&value).ok())
+            .collect();
+
+        Ok(txns)
+    }
+
+    /// Removes every persisted transaction.
+    pub fn clear(&self) -> Result<()> {
+        let column_family = self.column_family()?;
+
+        self.db
+            .iterator_cf(column_family, IteratorMode::Start)
+            .flatten()
+            .try_for_each(|(key, _)| {
+                self.db
+                    .delete_cf(column_family, key)
+                    .map_err(|err| StorageError::Other(err.to_string()))
+            })
+    }
+}
@@ -0,0 +1,112 @@
+use std::{collections::BTreeSet, sync::Arc};
+
+use parking_lot::RwLock;
+use storage_utils::{Result, StorageError};
+
+use crate::RocksDbAdapter;
+
+/// Name of the chain parameter the trusted publishers are stored under
+const TRUSTED_PUBLISHERS_PARAM: &str = "trusted_package_publishers";
+
+/// Which publishers contracts may be deployed from and called, as set by the
+/// genesis block. The policy is part of the chain rather than of each node's
+/// configuration, so every node accepts and rejects the same packages and
+/// records the same receipts. It is also written to the state store's
+/// database when one is given, so it survives restarts.
+#[derive(Debug, Clone, Default)]
+pub struct PackagePolicy {
+    trusted_publishers: Arc<RwLock<Option<BTreeSet<String>>>>,
+    db: Option<Arc<RocksDbAdapter>>,
+}
+
+impl PackagePolicy {
+    /// Loads the policy previously written to `db`, persisting changes to it
+    /// as well
+    pub fn open(db: Arc<RocksDbAdapter>) -> Result<Self> {
+        let trusted_publishers = db
+            .chain_param(TRUSTED_PUBLISHERS_PARAM)
+            .map_err(|err| StorageError::Other(err.to_string()))?
+            .map(|bytes| bincode::deserialize::<Option<BTreeSet<String>>>(&bytes))
+            .transpose()
+            .map_err(|err| StorageError::Other(err.to_string()))?
+            .flatten();
+
+        Ok(Self {
+            trusted_publishers: Arc::new(RwLock::new(trusted_publishers)),
+            db: Some(db),
+        })
+    }
+
+    /// Only lets contracts be deployed from and call packages signed by one
+    /// of `publishers`, given as hex encoded public keys. Any package is
+    /// allowed when `None`.
+    pub fn set_trusted_publishers(&self, publishers: Option<&[String]>) -> Result<()> {
+        let trusted_publishers = publishers.map(|publishers| {
+            publishers
+                .iter()
+                .map(|publisher| publisher.to_lowercase())
+                .collect::<BTreeSet<_>>()
+        });
+
+        if let Some(db) = &self.db {
+            let bytes = bincode::serialize(&trusted_publishers)
+                .map_err(|err| StorageError::Other(err.to_string()))?;
+
+            db.put_chain_param(TRUSTED_PUBLISHERS_PARAM, &bytes)
+                .map_err(|err| StorageError::Other(err.to_string()))?;
+        }
+
+        *self.trusted_publishers.write() = trusted_publishers;
+
+        Ok(())
+    }
+
+    /// Fails unless a package signed by `publisher`, or an unsigned one when
+    /// `None`, may be executed
+    pub fn check_publisher(&self, publisher: Option<&str>) -> Result<()> {
+        let trusted_publishers = self.trusted_publishers.read();
+        let trusted_publishers = match trusted_publishers.as_ref() {
+            Some(trusted_publishers) => trusted_publishers,
+            None => return Ok(()),
+        };
+
+        match publisher.map(str::to_lowercase) {
+            Some(publisher) if trusted_publishers.contains(&publisher) => Ok(()),
+            Some(publisher) => Err(StorageError::Other(format!(
+                "package was published by untrusted publisher {publisher}"
+            ))),
+            None => Err(StorageError::Other(
+                "package is unsigned and only packages of trusted publishers are executed"
+                    .to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allows_any_package_until_publishers_are_set() {
+        let policy = PackagePolicy::default();
+        assert!(policy.check_publisher(None).is_ok());
+        assert!(policy.check_publisher(Some("02ab")).is_ok());
+
+        policy
+            .set_trusted_publishers(Some(&["02AB".to_string()]))
+            .unwrap();
+        assert!(policy.check_publisher(Some("02ab")).is_ok());
+        assert!(policy.check_publisher(Some("03cd")).is_err());
+        assert!(policy.check_publisher(None).is_err());
+    }
+
+    #[test]
+    fn shares_the_policy_between_clones() {
+        let policy = PackagePolicy::default();
+        let clone = policy.clone();
+
+        policy.set_trusted_publishers(Some(&[])).unwrap();
+        assert!(clone.check_publisher(Some("02ab")).is_err());
+    }
+}
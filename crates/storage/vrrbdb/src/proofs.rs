@@ -0,0 +1,33 @@
+use patriecia::{JellyfishMerkleTree, Version};
+use serde::Serialize;
+use sha2::Sha256;
+use storage_utils::{Result, StorageError};
+use vrrb_core::proofs::key_hash;
+
+use crate::RocksDbAdapter;
+
+pub use vrrb_core::proofs::MerkleProof;
+
+/// Generates a proof for `key` against the given trie as it was at `version`
+pub(crate) fn prove<K: Serialize>(
+    tree: &JellyfishMerkleTree<RocksDbAdapter, Sha256>,
+    key: &K,
+    version: Version,
+) -> Result<MerkleProof> {
+    let key_hash = key_hash(key).map_err(|err| StorageError::Other(err.to_string()))?;
+
+    let (value, proof) = tree
+        .get_with_proof(key_hash, version)
+        .map_err(|err| StorageError::Other(format!("failed to generate proof: {err}")))?;
+
+    let root_hash = tree
+        .get_root_hash(version)
+        .map_err(|err| StorageError::Other(err.to_string()))?;
+
+    Ok(MerkleProof {
+        root_hash: hex::encode(root_hash.0),
+        version,
+        value,
+        proof,
+    })
+}
@@ -0,0 +1,58 @@
+use std::{path::Path, sync::Arc};
+
+use rocksdb::{ColumnFamily, DB};
+use storage_utils::{Result, StorageError};
+use vrrb_core::transactions::{TransactionDigest, TransactionReceipt};
+
+use crate::{base_db_options, new_db_instance};
+
+pub const RECEIPTS_COLUMN_FAMILY: &str = "receipts";
+
+/// Receipts of the contract transactions applied to the ledger, keyed by
+/// transaction digest.
+#[derive(Debug, Clone)]
+pub struct ReceiptStore {
+    db: Arc<DB>,
+}
+
+impl ReceiptStore {
+    pub fn new(path: &Path) -> Result<Self> {
+        let path = path.join("receipts");
+
+        let mut options = base_db_options();
+        options.set_error_if_exists(false);
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+
+        let db = new_db_instance(options, path, RECEIPTS_COLUMN_FAMILY)?;
+
+        Ok(Self { db: Arc::new(db) })
+    }
+
+    fn column_family(&self) -> Result<&ColumnFamily> {
+        self.db.cf_handle(RECEIPTS_COLUMN_FAMILY).ok_or_else(|| {
+            StorageError::Other(format!(
+                "column family {RECEIPTS_COLUMN_FAMILY} does not exist"
+            ))
+        })
+    }
+
+    pub fn insert(&self, receipt: &TransactionReceipt) -> Result<()> {
+        let value =
+            serde_json::to_vec(receipt).map_err(|err| StorageError::Other(err.to_string()))?;
+
+        self.db
+            .put_cf(self.column_family()?, receipt.txn_id.digest_string(), value)
+            .map_err(|err| StorageError::Other(err.to_string()))
+    }
+
+    pub fn get(&self, txn_id: &TransactionDigest) -> Result<TransactionReceipt> {
+        let value = self
+            .db
+            .get_cf(self.column_family()?, txn_id.digest_string())
+            .map_err(|err| StorageError::Other(err.to_string()))?
+            .ok_or_else(|| StorageError::Other(format!("no receipt found for {txn_id}")))?;
+
+        serde_json::from_slice(&value).map_err(|err| StorageError::Other(err.to_string()))
+    }
+}
@@ -4,39 +4,238 @@ use std::collections::{hash_map::Entry, BTreeSet, HashMap};
 use anyhow::{bail, Result};
 use parking_lot::RwLock;
 use patriecia::{
-    KeyHash, LeafNode, Node, NodeBatch, NodeKey, OwnedValue, Preimage, StaleNodeIndex, TreeReader,
-    TreeUpdateBatch, TreeWriter, Vers, VersionedDatabase,
+    KeyHash, LeafNode, Node, NodeBatch, NodeKey, OwnedValue, StaleNodeIndex, TreeReader,
+    TreeUpdateBatch, TreeWriter, Vers, Version, VersionedDatabase,
 };
 use primitives::{get_vrrb_environment, Environment, DEFAULT_VRRB_DB_PATH};
-use rocksdb::{IteratorMode, DB, DEFAULT_COLUMN_FAMILY_NAME};
+use rocksdb::{ColumnFamily, IteratorMode, WriteBatch, DB, DEFAULT_COLUMN_FAMILY_NAME};
+use serde::Serialize;
+use sha2::Sha256;
 use std::sync::Arc;
 use storage_utils::{get_node_data_dir, StorageError};
 use telemetry::error;
 
+pub const STALE_NODES_COLUMN_FAMILY: &str = "stale_nodes";
+pub const VALUE_HISTORY_COLUMN_FAMILY: &str = "value_history";
+pub const PREIMAGES_COLUMN_FAMILY: &str = "preimages";
+pub const CHECKPOINTS_COLUMN_FAMILY: &str = "checkpoints";
+pub const CHAIN_PARAMS_COLUMN_FAMILY: &str = "chain_params";
+
+type ValueHistory = Vec<(Vers, Option<OwnedValue>)>;
+
 #[derive(Debug)]
 pub struct RocksDbAdapter {
     data: Arc<RwLock<RocksDbInner>>,
     column: String,
+    /// Number of versions a stale node is kept around for before it gets
+    /// pruned. Stale nodes are never pruned when `None`.
+    stale_node_retention: Option<u64>,
 }
+
+/// Trie nodes live in the default column family while stale node indices,
+/// value history, key preimages, state checkpoints and the parameters set by
+/// the genesis block each have a column family of their own, so all of them
+/// survive restarts.
 #[derive(Debug)]
 pub struct RocksDbInner {
     db: DB,
-    stale_nodes: BTreeSet<StaleNodeIndex>,
-    value_history: HashMap<KeyHash, Vec<(Vers, Option<OwnedValue>)>>,
-    preimages: HashMap<KeyHash, Preimage>,
+    /// Versions that stay readable no matter how long ago they went stale
+    pinned_versions: BTreeSet<Version>,
+    /// Preimages of the keys inserted since the last tree update, written
+    /// along with it
+    staged_preimages: HashMap<KeyHash, Vec<u8>>,
 }
+
 impl RocksDbInner {
     fn new(instance: DB) -> Self {
         Self {
             db: instance,
-            stale_nodes: BTreeSet::new(),
-            value_history: HashMap::new(),
-            preimages: HashMap::new(),
+            pinned_versions: BTreeSet::new(),
+            staged_preimages: HashMap::new(),
+        }
+    }
+
+    fn column_family(&self, name: &str) -> Result<&ColumnFamily> {
+        self.db
+            .cf_handle(name)
+            .ok_or_else(|| anyhow::anyhow!("column family {name} does not exist"))
+    }
+
+    fn value_history_of(&self, key_hash: &KeyHash) -> Result<ValueHistory> {
+        let column_family = self.column_family(VALUE_HISTORY_COLUMN_FAMILY)?;
+
+        match self.db.get_cf(column_family, key_hash.0)? {
+            Some(bytes) => Ok(bincode::deserialize(&bytes)?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Adds the nodes and values within `node_batch` to `batch`. The history
+    /// of every value written is pruned of the entries no version at or
+    /// after `prune_before`, nor any pinned version, reads anymore.
+    fn stage_node_batch(
+        &self,
+        batch: &mut WriteBatch,
+        node_batch: &NodeBatch,
+        prune_before: Option<Version>,
+    ) -> Result<()> {
+        for (node_key, node) in node_batch.nodes() {
+            let node_key_bytes = bincode::serialize(&node_key)?;
+            let node_bytes = bincode::serialize(&node)?;
+            batch.put(node_key_bytes, node_bytes);
+        }
+
+        let mut value_history: HashMap<KeyHash, ValueHistory> = HashMap::new();
+        for ((version, key_hash), value) in node_batch.values() {
+            if !value_history.contains_key(key_hash) {
+                value_history.insert(*key_hash, self.value_history_of(key_hash)?);
+            }
+
+            put_value(&mut value_history, version.into(), *key_hash, value.clone())?
+        }
+
+        let column_family = self.column_family(VALUE_HISTORY_COLUMN_FAMILY)?;
+        for (key_hash, mut history) in value_history {
+            if let Some(prune_before) = prune_before {
+                prune_value_history(&mut history, prune_before, &self.pinned_versions);
+            }
+            batch.put_cf(column_family, key_hash.0, bincode::serialize(&history)?);
+        }
+
+        Ok(())
+    }
+
+    /// Adds the staged key preimages to `batch`
+    fn stage_preimages(&self, batch: &mut WriteBatch) -> Result<()> {
+        let column_family = self.column_family(PREIMAGES_COLUMN_FAMILY)?;
+
+        for (key_hash, preimage) in self.staged_preimages.iter() {
+            batch.put_cf(column_family, key_hash.0, preimage);
+        }
+
+        Ok(())
+    }
+
+    /// Adds `index` to `batch`. Indices are keyed by the big endian version
+    /// the node went stale at, so they can be pruned in version order.
+    fn stage_stale_node_index(&self, batch: &mut WriteBatch, index: &StaleNodeIndex) -> Result<()> {
+        let column_family = self.column_family(STALE_NODES_COLUMN_FAMILY)?;
+        let key = stale_node_index_key(index)?;
+
+        anyhow::ensure!(
+            self.db.get_cf(column_family, &key)?.is_none(),
+            "Duplicated retire log"
+        );
+
+        batch.put_cf(column_family, key, b"");
+
+        Ok(())
+    }
+
+    /// Adds the removal of every node that went stale at or before
+    /// `max_stale_since_version` to `batch`. Nodes a pinned version still
+    /// reads are kept for good, only their stale index is removed.
+    fn stage_stale_node_pruning(
+        &self,
+        batch: &mut WriteBatch,
+        max_stale_since_version: u64,
+    ) -> Result<()> {
+        let column_family = self.column_family(STALE_NODES_COLUMN_FAMILY)?;
+
+        for (key, _) in self
+            .db
+            .iterator_cf(column_family, IteratorMode::Start)
+            .flatten()
+        {
+            let (version_bytes, node_key_bytes) = key.split_at(8);
+            let stale_since_version = u64::from_be_bytes(version_bytes.try_into()?);
+
+            if stale_since_version > max_stale_since_version {
+                break;
+            }
+
+            let node_key = bincode::deserialize::<NodeKey>(node_key_bytes)?;
+            let pinned = self
+                .pinned_versions
+                .range(node_key.version()..stale_since_version)
+                .next()
+                .is_some();
+
+            if !pinned {
+                batch.delete(node_key_bytes);
+            }
+            batch.delete_cf(column_family, &key);
+        }
+
+        Ok(())
+    }
+}
+
+/// Drops the entries of a value's history that are superseded before
+/// `prune_before` and are not the value any pinned version reads.
+fn prune_value_history(
+    history: &mut ValueHistory,
+    prune_before: Version,
+    pinned_versions: &BTreeSet<Version>,
+) {
+    let prune_before = Vers::from(prune_before);
+    let pinned_versions = pinned_versions
+        .iter()
+        .map(|pinned| Vers::from(*pinned))
+        .collect::<Vec<Vers>>();
+
+    // Each entry is read by every version from its own up to the next one
+    let mut read_until = history
+        .iter()
+        .skip(1)
+        .map(|(version, _)| Some(*version))
+        .chain([None])
+        .collect::<Vec<Option<Vers>>>()
+        .into_iter();
+
+    history.retain(|(version, _)| {
+        let read_until = read_until.next().flatten();
+        let is_read_at = |at: &Vers| version <= at && read_until.map_or(true, |until| *at < until);
+
+        read_until.map_or(true, |until| until > prune_before)
+            || pinned_versions.iter().any(is_read_at)
+    });
+}
+
+fn stale_node_index_key(index: &StaleNodeIndex) -> Result<Vec<u8>> {
+    let mut key = index.stale_since_version.to_be_bytes().to_vec();
+    key.extend(bincode::serialize(&index.node_key)?);
+
+    Ok(key)
+}
+
+/// Opens the database backing a `RocksDbAdapter`, making sure the column
+/// families holding the tree's history exist
+fn new_adapter_db_instance(
+    options: rocksdb::Options,
+    path: std::path::PathBuf,
+    column_family: &str,
+) -> storage_utils::Result<DB> {
+    let mut instance = new_db_instance(options.clone(), path, column_family)?;
+
+    for name in [
+        STALE_NODES_COLUMN_FAMILY,
+        VALUE_HISTORY_COLUMN_FAMILY,
+        PREIMAGES_COLUMN_FAMILY,
+        CHECKPOINTS_COLUMN_FAMILY,
+        CHAIN_PARAMS_COLUMN_FAMILY,
+    ] {
+        if instance.cf_handle(name).is_none() {
+            instance
+                .create_cf(name, &options)
+                .map_err(|err| StorageError::Other(err.to_string()))?;
         }
     }
+
+    Ok(instance)
 }
 
-fn base_db_options() -> rocksdb::Options {
+pub(crate) fn base_db_options() -> rocksdb::Options {
     let mut options = rocksdb::Options::default();
 
     let environ = get_vrrb_environment();
@@ -61,7 +260,7 @@ fn base_db_options() -> rocksdb::Options {
     options
 }
 
-fn new_db_instance(
+pub(crate) fn new_db_instance(
     options: rocksdb::Options,
     path: std::path::PathBuf,
     column_family: &str,
@@ -99,30 +298,153 @@ impl RocksDbAdapter {
         options.create_if_missing(true);
         options.create_missing_column_families(true);
 
-        let instance = new_db_instance(options, path, column_family)
+        let instance = new_adapter_db_instance(options, path, column_family)
             .map_err(|err| StorageError::Other(err.to_string()))?;
 
         Ok(Self {
             data: Arc::new(RwLock::new(RocksDbInner::new(instance))),
             column: column_family.to_string(),
+            stale_node_retention: None,
         })
     }
 
-    pub fn write_tree_update_batch(&self, batch: TreeUpdateBatch) -> Result<()> {
-        self.write_node_batch(&batch.node_batch)?;
-        batch
+    /// Prunes stale nodes once they have been stale for more than
+    /// `stale_node_retention` versions. Historical reads further back than
+    /// that are no longer possible.
+    pub fn with_stale_node_retention(mut self, stale_node_retention: Option<u64>) -> Self {
+        self.stale_node_retention = stale_node_retention;
+        self
+    }
+
+    /// Writes the nodes, values and stale node indices of a tree update, along
+    /// with the preimages of the keys inserted since the last update, in a
+    /// single atomic batch, pruning the nodes and values that fell out of the
+    /// retention window in that same batch.
+    pub fn write_tree_update_batch(&self, tree_update_batch: TreeUpdateBatch) -> Result<()> {
+        let mut locked = self.data.write();
+        let mut batch = WriteBatch::default();
+
+        let latest_stale_since_version = tree_update_batch
             .stale_node_index_batch
-            .into_iter()
-            .map(|i| self.put_stale_node_index(i))
-            .collect::<Result<Vec<_>>>()?;
+            .iter()
+            .map(|index| index.stale_since_version)
+            .max();
+
+        let max_stale_since_version = self
+            .stale_node_retention
+            .zip(latest_stale_since_version)
+            .and_then(|(retention, latest)| latest.checked_sub(retention));
+
+        locked.stage_node_batch(
+            &mut batch,
+            &tree_update_batch.node_batch,
+            max_stale_since_version,
+        )?;
+
+        for index in tree_update_batch.stale_node_index_batch.iter() {
+            locked.stage_stale_node_index(&mut batch, index)?;
+        }
+
+        if let Some(max_stale_since_version) = max_stale_since_version {
+            locked.stage_stale_node_pruning(&mut batch, max_stale_since_version)?;
+        }
+
+        locked.stage_preimages(&mut batch)?;
+        locked.db.write(batch)?;
+        locked.staged_preimages.clear();
+
         Ok(())
     }
 
+    /// Keeps `version` readable regardless of the retention window, e.g.
+    /// because a state checkpoint refers to it
+    pub fn pin_version(&self, version: Version) {
+        self.data.write().pinned_versions.insert(version);
+    }
+
     pub fn put_stale_node_index(&self, index: StaleNodeIndex) -> Result<()> {
-        let is_new_entry = self.data.write().stale_nodes.insert(index);
-        anyhow::ensure!(is_new_entry, "Duplicated retire log");
+        let locked = self.data.write();
+        let mut batch = WriteBatch::default();
+
+        locked.stage_stale_node_index(&mut batch, &index)?;
+        locked.db.write(batch)?;
+
+        Ok(())
+    }
+
+    /// Stages the preimage of `key` so the key can be recovered from its hash
+    /// later on. It is written along with the next tree update, so the key
+    /// is never stored without its preimage.
+    pub fn put_key_preimage<K: Serialize>(&self, key: &K) -> Result<()> {
+        // Keys are hashed the same way the tries hash them
+        let preimage = bincode::serialize(key)?;
+        let key_hash = KeyHash::with::<Sha256>(&preimage);
+
+        self.data
+            .write()
+            .staged_preimages
+            .insert(key_hash, preimage);
+
+        Ok(())
+    }
+
+    /// Returns the serialized key whose hash is `key_hash`, if its preimage
+    /// was stored
+    pub fn get_preimage(&self, key_hash: KeyHash) -> Result<Option<Vec<u8>>> {
+        let locked = self.data.read();
+        if let Some(preimage) = locked.staged_preimages.get(&key_hash) {
+            return Ok(Some(preimage.clone()));
+        }
+
+        let column_family = locked.column_family(PREIMAGES_COLUMN_FAMILY)?;
+
+        Ok(locked.db.get_cf(column_family, key_hash.0)?)
+    }
+
+    /// Stores a state checkpoint under its big endian block height, so
+    /// checkpoints are iterated in height order
+    pub fn put_checkpoint(&self, block_height: u128, checkpoint: &[u8]) -> Result<()> {
+        let locked = self.data.write();
+        let column_family = locked.column_family(CHECKPOINTS_COLUMN_FAMILY)?;
+
+        locked
+            .db
+            .put_cf(column_family, block_height.to_be_bytes(), checkpoint)?;
+
+        Ok(())
+    }
+
+    /// Returns every stored state checkpoint, lowest block height first
+    pub fn checkpoints(&self) -> Result<Vec<Vec<u8>>> {
+        let locked = self.data.read();
+        let column_family = locked.column_family(CHECKPOINTS_COLUMN_FAMILY)?;
+
+        let checkpoints = locked
+            .db
+            .iterator_cf(column_family, IteratorMode::Start)
+            .map(|entry| entry.map(|(_, value)| value.into_vec()))
+            .collect::<std::result::Result<Vec<Vec<u8>>, rocksdb::Error>>()?;
+
+        Ok(checkpoints)
+    }
+
+    /// Stores a chain parameter set by the genesis block under `name`
+    pub fn put_chain_param(&self, name: &str, value: &[u8]) -> Result<()> {
+        let locked = self.data.write();
+        let column_family = locked.column_family(CHAIN_PARAMS_COLUMN_FAMILY)?;
+
+        locked.db.put_cf(column_family, name.as_bytes(), value)?;
+
         Ok(())
     }
+
+    /// Returns the chain parameter stored under `name`, if it was ever set
+    pub fn chain_param(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        let locked = self.data.read();
+        let column_family = locked.column_family(CHAIN_PARAMS_COLUMN_FAMILY)?;
+
+        Ok(locked.db.get_cf(column_family, name.as_bytes())?)
+    }
 }
 
 // TODO: handle these unwrap
@@ -132,16 +454,13 @@ impl Clone for RocksDbAdapter {
         let mut options = base_db_options();
         options.set_error_if_exists(false);
 
-        let db = new_db_instance(options, locked.db.path().into(), self.column.as_str()).unwrap();
+        let db = new_adapter_db_instance(options, locked.db.path().into(), self.column.as_str())
+            .unwrap();
 
         Self {
-            data: Arc::new(RwLock::new(RocksDbInner {
-                db,
-                stale_nodes: locked.stale_nodes.clone(),
-                value_history: locked.value_history.clone(),
-                preimages: locked.preimages.clone(),
-            })),
+            data: Arc::new(RwLock::new(RocksDbInner::new(db))),
             column: self.column.clone(),
+            stale_node_retention: self.stale_node_retention,
         }
     }
 }
@@ -155,7 +474,7 @@ impl Default for RocksDbAdapter {
 
         //
         // TODO: fix this unwrap
-        let db = new_db_instance(
+        let db = new_adapter_db_instance(
             options,
             DEFAULT_VRRB_DB_PATH.into(),
             DEFAULT_COLUMN_FAMILY_NAME,
@@ -163,13 +482,9 @@ impl Default for RocksDbAdapter {
         .unwrap();
 
         Self {
-            data: Arc::new(RwLock::new(RocksDbInner {
-                db,
-                stale_nodes: BTreeSet::new(),
-                value_history: HashMap::new(),
-                preimages: HashMap::new(),
-            })),
+            data: Arc::new(RwLock::new(RocksDbInner::new(db))),
             column: DEFAULT_COLUMN_FAMILY_NAME.to_string(),
+            stale_node_retention: None,
         }
     }
 }
@@ -210,7 +525,22 @@ impl VersionedDatabase for RocksDbAdapter {
         patriecia::KeyHash,
         Vec<(Self::Version, Option<patriecia::OwnedValue>)>,
     > {
-        self.data.read().value_history.clone().into_iter()
+        let locked = self.data.read();
+        let mut map = HashMap::new();
+
+        if let Ok(column_family) = locked.column_family(VALUE_HISTORY_COLUMN_FAMILY) {
+            let iter = locked.db.iterator_cf(column_family, IteratorMode::Start);
+            for (boxed_key, boxed_history) in iter.flatten() {
+                let key_hash: Option<[u8; 32]> = boxed_key.as_ref().try_into().ok();
+                let history = bincode::deserialize::<ValueHistory>(&boxed_history);
+
+                if let (Some(key_hash), Ok(history)) = (key_hash, history) {
+                    map.insert(KeyHash(key_hash), history);
+                }
+            }
+        }
+
+        map.into_iter()
     }
 }
 impl TreeReader for RocksDbAdapter {
@@ -234,17 +564,15 @@ impl TreeReader for RocksDbAdapter {
         max_version: Vers,
         key_hash: patriecia::KeyHash,
     ) -> Result<Option<patriecia::OwnedValue>> {
-        match self.data.read().value_history.get(&key_hash) {
-            Some(version_history) => {
-                for (version, value) in version_history.iter().rev() {
-                    if *version <= max_version {
-                        return Ok(value.clone());
-                    }
-                }
-                Ok(None)
+        let version_history = self.data.read().value_history_of(&key_hash)?;
+
+        for (version, value) in version_history.into_iter().rev() {
+            if version <= max_version {
+                return Ok(value);
             }
-            None => Ok(None),
         }
+
+        Ok(None)
     }
 
     fn get_rightmost_leaf(&self) -> Result<Option<(NodeKey, LeafNode)>> {
@@ -270,20 +598,13 @@ impl TreeReader for RocksDbAdapter {
 impl TreeWriter for RocksDbAdapter {
     fn write_node_batch(&self, node_batch: &NodeBatch) -> Result<()> {
         let mut locked = self.data.write();
-        for (node_key, node) in node_batch.nodes() {
-            let node_key_bytes = bincode::serialize(&node_key)?;
-            let node_bytes = bincode::serialize(&node)?;
-            locked.db.put(node_key_bytes, node_bytes)?;
-        }
+        let mut batch = WriteBatch::default();
+
+        locked.stage_node_batch(&mut batch, node_batch, None)?;
+        locked.stage_preimages(&mut batch)?;
+        locked.db.write(batch)?;
+        locked.staged_preimages.clear();
 
-        for ((version, key_hash), value) in node_batch.values() {
-            put_value(
-                &mut locked.value_history,
-                version.into(),
-                *key_hash,
-                value.clone(),
-            )?
-        }
         Ok(())
     }
 }
@@ -315,3 +636,56 @@ pub fn put_value(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(versions: &[u64]) -> ValueHistory {
+        versions
+            .iter()
+            .map(|version| (Vers::from(*version), Some(version.to_be_bytes().to_vec())))
+            .collect()
+    }
+
+    #[test]
+    fn value_history_keeps_the_values_still_readable() {
+        let mut pruned = history(&[1, 3, 5, 8]);
+        prune_value_history(&mut pruned, 6, &BTreeSet::new());
+
+        // Version 6 still reads the value written at 5
+        assert_eq!(pruned, history(&[5, 8]));
+    }
+
+    #[test]
+    fn key_preimages_survive_reopening_the_database() {
+        let path = std::env::temp_dir().join(format!("preimages-{}", rand::random::<u64>()));
+        let key = "some key".to_string();
+        let preimage = bincode::serialize(&key).unwrap();
+        let key_hash = KeyHash::with::<Sha256>(&preimage);
+
+        {
+            let adapter = RocksDbAdapter::new(path.clone(), DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+            adapter.put_key_preimage(&key).unwrap();
+            assert_eq!(
+                adapter.get_preimage(key_hash).unwrap(),
+                Some(preimage.clone())
+            );
+
+            adapter
+                .write_tree_update_batch(TreeUpdateBatch::default())
+                .unwrap();
+        }
+
+        let adapter = RocksDbAdapter::new(path, DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+        assert_eq!(adapter.get_preimage(key_hash).unwrap(), Some(preimage));
+    }
+
+    #[test]
+    fn value_history_keeps_the_values_pinned_versions_read() {
+        let mut pruned = history(&[1, 3, 5, 8]);
+        prune_value_history(&mut pruned, 8, &BTreeSet::from([2]));
+
+        assert_eq!(pruned, history(&[1, 8]));
+    }
+}
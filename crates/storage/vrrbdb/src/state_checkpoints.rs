@@ -0,0 +1,190 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use parking_lot::RwLock;
+use patriecia::Version;
+use serde::{Deserialize, Serialize};
+use storage_utils::{Result, StorageError};
+
+use crate::RocksDbAdapter;
+
+/// Selects a past point of the ledger, either by the height of the block
+/// applied last or by the state root hash it produced.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StateAt {
+    Height(u128),
+    StateRoot(String),
+}
+
+/// The version each store was at right after a block was applied, which is
+/// all that is needed to read the state as it was at that block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateCheckpoint {
+    pub block_height: u128,
+    pub state_root_hash: String,
+    pub state_version: Version,
+    pub transactions_version: Version,
+    pub claims_version: Version,
+}
+
+#[derive(Debug, Default)]
+struct CheckpointIndex {
+    by_height: BTreeMap<u128, StateCheckpoint>,
+    heights_by_state_root: HashMap<String, u128>,
+}
+
+impl CheckpointIndex {
+    fn insert(&mut self, checkpoint: StateCheckpoint) {
+        if let Some(replaced) = self.by_height.get(&checkpoint.block_height) {
+            self.heights_by_state_root.remove(&replaced.state_root_hash);
+        }
+
+        self.heights_by_state_root
+            .insert(checkpoint.state_root_hash.clone(), checkpoint.block_height);
+        self.by_height.insert(checkpoint.block_height, checkpoint);
+    }
+}
+
+/// Shared index of the checkpoints recorded every time a block is applied,
+/// ordered by block height and indexed by state root hash. Checkpoints are
+/// also written to the state store's database when one is given, so they
+/// survive restarts.
+#[derive(Debug, Clone, Default)]
+pub struct StateCheckpoints {
+    inner: Arc<RwLock<CheckpointIndex>>,
+    db: Option<Arc<RocksDbAdapter>>,
+}
+
+impl StateCheckpoints {
+    /// Loads the checkpoints previously written to `db`, persisting new ones
+    /// to it as well
+    pub fn open(db: Arc<RocksDbAdapter>) -> Result<Self> {
+        let mut index = CheckpointIndex::default();
+
+        for bytes in db
+            .checkpoints()
+            .map_err(|err| StorageError::Other(err.to_string()))?
+        {
+            let checkpoint = bincode::deserialize::<StateCheckpoint>(&bytes)
+                .map_err(|err| StorageError::Other(err.to_string()))?;
+            index.insert(checkpoint);
+        }
+
+        Ok(Self {
+            inner: Arc::new(RwLock::new(index)),
+            db: Some(db),
+        })
+    }
+
+    pub fn insert(&self, checkpoint: StateCheckpoint) -> Result<()> {
+        if let Some(db) = &self.db {
+            let bytes = bincode::serialize(&checkpoint)
+                .map_err(|err| StorageError::Other(err.to_string()))?;
+
+            db.put_checkpoint(checkpoint.block_height, &bytes)
+                .map_err(|err| StorageError::Other(err.to_string()))?;
+        }
+
+        self.inner.write().insert(checkpoint);
+
+        Ok(())
+    }
+
+    /// Returns the checkpoint of the last block applied at or below `height`,
+    /// or `None` when `height` is above the highest checkpointed block, since
+    /// that block has not been applied yet
+    pub fn at_height(&self, height: u128) -> Option<StateCheckpoint> {
+        let index = self.inner.read();
+
+        match index.by_height.keys().next_back() {
+            Some(highest) if height <= *highest => index
+                .by_height
+                .range(..=height)
+                .next_back()
+                .map(|(_, checkpoint)| checkpoint.clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns the checkpoint of the block that produced `state_root_hash`
+    pub fn at_state_root(&self, state_root_hash: &str) -> Option<StateCheckpoint> {
+        let index = self.inner.read();
+
+        index
+            .heights_by_state_root
+            .get(state_root_hash)
+            .and_then(|height| index.by_height.get(height))
+            .cloned()
+    }
+
+    pub fn get(&self, at: &StateAt) -> Result<StateCheckpoint> {
+        let checkpoint = match at {
+            StateAt::Height(height) => self.at_height(*height),
+            StateAt::StateRoot(state_root_hash) => self.at_state_root(state_root_hash),
+        };
+
+        checkpoint.ok_or_else(|| StorageError::NotFound(format!("state at {at:?}")))
+    }
+
+    /// Returns every checkpoint, lowest block height first
+    pub fn values(&self) -> Vec<StateCheckpoint> {
+        self.inner.read().by_height.values().cloned().collect()
+    }
+
+    pub fn latest(&self) -> Option<StateCheckpoint> {
+        self.inner.read().by_height.values().next_back().cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checkpoint(block_height: u128, state_root_hash: &str) -> StateCheckpoint {
+        StateCheckpoint {
+            block_height,
+            state_root_hash: state_root_hash.to_string(),
+            state_version: block_height as Version,
+            transactions_version: block_height as Version,
+            claims_version: block_height as Version,
+        }
+    }
+
+    #[test]
+    fn finds_checkpoints_by_height_and_state_root() {
+        let checkpoints = StateCheckpoints::default();
+        checkpoints.insert(checkpoint(0, "genesis")).unwrap();
+        checkpoints.insert(checkpoint(2, "second")).unwrap();
+
+        assert_eq!(checkpoints.at_height(1), Some(checkpoint(0, "genesis")));
+        assert_eq!(checkpoints.at_height(2), Some(checkpoint(2, "second")));
+        assert_eq!(
+            checkpoints
+                .get(&StateAt::StateRoot("second".to_string()))
+                .unwrap(),
+            checkpoint(2, "second")
+        );
+        assert!(checkpoints
+            .get(&StateAt::StateRoot("unknown".to_string()))
+            .is_err());
+        assert_eq!(checkpoints.latest(), Some(checkpoint(2, "second")));
+    }
+
+    #[test]
+    fn does_not_find_checkpoints_above_the_highest_height() {
+        let checkpoints = StateCheckpoints::default();
+        assert_eq!(checkpoints.at_height(0), None);
+
+        checkpoints.insert(checkpoint(0, "genesis")).unwrap();
+        checkpoints.insert(checkpoint(2, "second")).unwrap();
+
+        assert_eq!(checkpoints.at_height(3), None);
+        assert!(matches!(
+            checkpoints.get(&StateAt::Height(5)),
+            Err(StorageError::NotFound(_))
+        ));
+    }
+}
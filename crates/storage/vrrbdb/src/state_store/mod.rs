@@ -7,7 +7,7 @@ use sha2::Sha256;
 use storage_utils::{Result, StorageError};
 use vrrb_core::account::{Account, UpdateArgs};
 
-use crate::RocksDbAdapter;
+use crate::{MerkleProof, RocksDbAdapter};
 
 mod state_store_rh;
 pub use state_store_rh::*;
@@ -18,6 +18,7 @@ pub type FailedAccountUpdates = Vec<(Address, Vec<UpdateArgs>, Result<()>)>;
 #[derive(Debug, Clone)]
 pub struct StateStore {
     trie: LeftRightTrie<'static, Address, Account, RocksDbAdapter, Sha256>,
+    db: Arc<RocksDbAdapter>,
 }
 
 impl Default for StateStore {
@@ -27,11 +28,11 @@ impl Default for StateStore {
             .join("db")
             .join("state");
 
-        let db_adapter = RocksDbAdapter::new(db_path, "state").unwrap_or_default();
+        let db = Arc::new(RocksDbAdapter::new(db_path, "state").unwrap_or_default());
 
-        let trie = LeftRightTrie::new(Arc::new(db_adapter));
+        let trie = LeftRightTrie::new(db.clone());
 
-        Self { trie }
+        Self { trie, db }
     }
 }
 
@@ -39,11 +40,20 @@ impl StateStore {
     /// Returns new, empty instance of StateDb
 
     pub fn new(path: &Path) -> Self {
+        Self::with_retention(path, None)
+    }
+
+    /// Returns new, empty instance of StateStore whose stale trie nodes are
+    /// pruned once they have been stale for `retention` versions
+    pub fn with_retention(path: &Path, retention: Option<u64>) -> Self {
         let path = path.join("state");
-        let db_adapter = RocksDbAdapter::new(path, "state").unwrap_or_default();
-        let trie = LeftRightTrie::new(Arc::new(db_adapter));
+        let db_adapter = RocksDbAdapter::new(path, "state")
+            .map(|adapter| adapter.with_stale_node_retention(retention))
+            .unwrap_or_default();
+        let db = Arc::new(db_adapter);
+        let trie = LeftRightTrie::new(db.clone());
 
-        Self { trie }
+        Self { trie, db }
     }
 
     /// Returns new ReadHandle to the VrrDb data. As long as the returned value
@@ -57,6 +67,11 @@ impl StateStore {
         self.trie.publish();
     }
 
+    /// Returns the database backing the state trie
+    pub(crate) fn db(&self) -> Arc<RocksDbAdapter> {
+        self.db.clone()
+    }
+
     pub fn get_account(&self, key: &Address) -> Result<Account> {
         let read_handle = self.read_handle();
         read_handle.get(key)
@@ -68,6 +83,14 @@ impl StateStore {
         self.trie.publish();
     }
 
+    /// Stages the preimage of `key`, so it is written along with the trie
+    /// nodes the key ends up in
+    fn stage_preimage(&self, key: &Address) -> Result<()> {
+        self.db
+            .put_key_preimage(key)
+            .map_err(|err| StorageError::Other(err.to_string()))
+    }
+
     // Maybe initialize is better name for that?
     fn insert_uncommited(&mut self, key: Address, account: Account) -> Result<()> {
         if account.debits() != 0 {
@@ -82,6 +105,7 @@ impl StateStore {
             ));
         }
 
+        self.stage_preimage(&key)?;
         self.trie.insert(key, account);
 
         Ok(())
@@ -288,7 +312,21 @@ impl StateStore {
             .map_err(|e| StorageError::Other(e.to_string()))
     }
 
+    /// Returns a proof that an account is present in, or absent from, the latest
+    /// committed version of the trie
+    pub fn get_proof(&self, key: &Address) -> Result<MerkleProof> {
+        let factory = self.factory();
+
+        factory.get_proof(key, factory.handle().version())
+    }
+
     pub fn extend(&mut self, accounts: Vec<(Address, Option<Account>)>) {
+        for (key, _) in accounts.iter() {
+            if let Err(err) = self.stage_preimage(key) {
+                telemetry::error!("failed to stage preimage of account {key}: {err}");
+            }
+        }
+
         self.trie.extend(accounts)
     }
 
@@ -1,13 +1,13 @@
 use std::collections::HashMap;
 
 use integral_db::{JellyfishMerkleTreeWrapper, ReadHandleFactory};
-use patriecia::JellyfishMerkleTree;
+use patriecia::{JellyfishMerkleTree, Version};
 use primitives::Address;
 use sha2::Sha256;
 use storage_utils::{Result, StorageError};
 use vrrb_core::account::Account;
 
-use crate::RocksDbAdapter;
+use crate::{proofs, MerkleProof, RocksDbAdapter};
 
 #[derive(Debug, Clone)]
 pub struct StateStoreReadHandle {
@@ -28,6 +28,14 @@ impl StateStoreReadHandle {
             .map_err(|err| StorageError::Other(err.to_string()))
     }
 
+    /// Returns the account stored under `key` as it was at the given version
+    /// of the trie
+    pub fn get_at_version(&self, key: &Address, version: Version) -> Result<Account> {
+        self.inner
+            .get(key, version)
+            .map_err(|err| StorageError::Other(err.to_string()))
+    }
+
     /// Get a batch of accounts by providing Vec of PublicKeysHash
     ///
     /// Returns HashMap indexed by PublicKeys and containing either
@@ -44,11 +52,16 @@ impl StateStoreReadHandle {
     }
 
     pub fn entries(&self) -> Result<HashMap<Address, Account>> {
+        self.entries_at_version(self.inner.version())
+    }
+
+    /// Returns every entry as it was at the given version of the trie
+    pub fn entries_at_version(&self, version: Version) -> Result<HashMap<Address, Account>> {
         // TODO: revisit and refactor into inner wrapper
 
         Ok(self
             .inner
-            .iter(self.inner.version())
+            .iter(version)
             .map_err(|err| {
                 StorageError::Other(format!("unable to create iterator from trie: {}", err))
             })?
@@ -62,6 +75,11 @@ impl StateStoreReadHandle {
             .collect())
     }
 
+    /// Returns the latest committed version of the trie
+    pub fn version(&self) -> Version {
+        self.inner.version()
+    }
+
     /// Returns a number of initialized accounts in the database
     pub fn len(&self) -> usize {
         self.inner.len()
@@ -83,16 +101,23 @@ impl StateStoreReadHandleFactory {
         Self { inner }
     }
 
-    pub fn handle(&self) -> StateStoreReadHandle {
-        let handle = self
-            .inner
+    fn tree(&self) -> JellyfishMerkleTree<RocksDbAdapter, Sha256> {
+        self.inner
             .handle()
             .enter()
             .map(|guard| guard.clone())
-            .unwrap_or_default();
+            .unwrap_or_default()
+    }
 
-        let inner = JellyfishMerkleTreeWrapper::new(handle);
+    pub fn handle(&self) -> StateStoreReadHandle {
+        let inner = JellyfishMerkleTreeWrapper::new(self.tree());
 
         StateStoreReadHandle { inner }
     }
+
+    /// Returns a proof that `key` is present in, or absent from, the trie as
+    /// it was at the given version
+    pub fn get_proof(&self, key: &Address, version: Version) -> Result<MerkleProof> {
+        proofs::prove(&self.tree(), key, version)
+    }
 }
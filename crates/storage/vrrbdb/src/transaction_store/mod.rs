@@ -1,11 +1,11 @@
 use std::{path::Path, sync::Arc};
 
-use integral_db::{LeftRightTrie, Proof, H256};
+use integral_db::LeftRightTrie;
 use patriecia::RootHash;
 use sha2::Sha256;
 use storage_utils::{Result, StorageError};
 
-use crate::RocksDbAdapter;
+use crate::{MerkleProof, RocksDbAdapter};
 
 mod transaction_store_rh;
 pub use transaction_store_rh::*;
@@ -14,6 +14,7 @@ use vrrb_core::transactions::{Transaction, TransactionDigest, TransactionKind};
 #[derive(Debug, Clone)]
 pub struct TransactionStore {
     trie: LeftRightTrie<'static, TransactionDigest, TransactionKind, RocksDbAdapter, Sha256>,
+    db: Arc<RocksDbAdapter>,
 }
 
 impl Default for TransactionStore {
@@ -23,22 +24,31 @@ impl Default for TransactionStore {
             .join("db")
             .join("transactions");
 
-        let db_adapter = RocksDbAdapter::new(db_path, "transactions").unwrap_or_default();
+        let db = Arc::new(RocksDbAdapter::new(db_path, "transactions").unwrap_or_default());
 
-        let trie = LeftRightTrie::new(Arc::new(db_adapter));
+        let trie = LeftRightTrie::new(db.clone());
 
-        Self { trie }
+        Self { trie, db }
     }
 }
 
 impl TransactionStore {
     /// Returns new, empty instance of TransactionStore
     pub fn new(path: &Path) -> Self {
+        Self::with_retention(path, None)
+    }
+
+    /// Returns new, empty instance of TransactionStore whose stale trie nodes are
+    /// pruned once they have been stale for `retention` versions
+    pub fn with_retention(path: &Path, retention: Option<u64>) -> Self {
         let path = path.join("transactions");
-        let db_adapter = RocksDbAdapter::new(path, "transactions").unwrap_or_default();
-        let trie = LeftRightTrie::new(Arc::new(db_adapter));
+        let db_adapter = RocksDbAdapter::new(path, "transactions")
+            .map(|adapter| adapter.with_stale_node_retention(retention))
+            .unwrap_or_default();
+        let db = Arc::new(db_adapter);
+        let trie = LeftRightTrie::new(db.clone());
 
-        Self { trie }
+        Self { trie, db }
     }
 
     pub fn factory(&self) -> TransactionStoreReadHandleFactory {
@@ -51,12 +61,26 @@ impl TransactionStore {
         self.trie.publish();
     }
 
+    /// Returns the database backing the transaction trie
+    pub(crate) fn db(&self) -> Arc<RocksDbAdapter> {
+        self.db.clone()
+    }
+
     pub fn read_handle(&self) -> TransactionStoreReadHandle {
         let inner = self.trie.handle();
         TransactionStoreReadHandle::new(inner)
     }
 
+    /// Stages the preimage of `key`, so it is written along with the trie
+    /// nodes the key ends up in
+    fn stage_preimage(&self, key: &TransactionDigest) -> Result<()> {
+        self.db
+            .put_key_preimage(key)
+            .map_err(|err| StorageError::Other(err.to_string()))
+    }
+
     pub fn insert(&mut self, txn: TransactionKind) -> Result<()> {
+        self.stage_preimage(&txn.id())?;
         self.trie.insert(txn.id(), txn);
         Ok(())
     }
@@ -65,7 +89,13 @@ impl TransactionStore {
         let transactions = transactions
             .into_iter()
             .map(|txn| (txn.id(), Some(txn)))
-            .collect();
+            .collect::<Vec<_>>();
+
+        for (key, _) in transactions.iter() {
+            if let Err(err) = self.stage_preimage(key) {
+                telemetry::error!("failed to stage preimage of transaction {key}: {err}");
+            }
+        }
 
         self.trie.extend(transactions)
     }
@@ -76,11 +106,11 @@ impl TransactionStore {
             .map_err(|e| StorageError::Other(e.to_string()))
     }
 
-    pub fn get_proof(&self) -> Result<Vec<Proof>> {
-        todo!()
-    }
+    /// Returns a proof that a transaction is present in, or absent from, the
+    /// latest committed version of the trie
+    pub fn get_proof(&self, key: &TransactionDigest) -> Result<MerkleProof> {
+        let factory = self.factory();
 
-    pub fn verify_proof(&self) -> Option<H256> {
-        todo!()
+        factory.get_proof(key, factory.handle().version())
     }
 }
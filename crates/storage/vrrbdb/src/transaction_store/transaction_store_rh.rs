@@ -6,7 +6,7 @@ use sha2::Sha256;
 use storage_utils::{Result, StorageError};
 use vrrb_core::transactions::{Transaction, TransactionDigest, TransactionKind};
 
-use crate::RocksDbAdapter;
+use crate::{proofs, MerkleProof, RocksDbAdapter};
 
 #[derive(Debug, Clone)]
 pub struct TransactionStoreReadHandle {
@@ -40,10 +40,18 @@ impl TransactionStoreReadHandle {
     }
 
     pub fn entries(&self) -> Result<HashMap<TransactionDigest, TransactionKind>> {
+        self.entries_at_version(self.inner.version())
+    }
+
+    /// Returns every entry as it was at the given version of the trie
+    pub fn entries_at_version(
+        &self,
+        version: Version,
+    ) -> Result<HashMap<TransactionDigest, TransactionKind>> {
         // TODO: revisit and refactor into inner wrapper
         Ok(self
             .inner
-            .iter(self.inner.version())
+            .iter(version)
             .map_err(|err| {
                 StorageError::Other(format!("unable to create iterator from trie: {}", err))
             })?
@@ -58,6 +66,11 @@ impl TransactionStoreReadHandle {
             .collect())
     }
 
+    /// Returns the latest committed version of the trie
+    pub fn version(&self) -> Version {
+        self.inner.version()
+    }
+
     /// Returns a number of transactions in the ledger
     pub fn len(&self) -> usize {
         self.inner.len()
@@ -79,16 +92,23 @@ impl TransactionStoreReadHandleFactory {
         Self { inner }
     }
 
-    pub fn handle(&self) -> TransactionStoreReadHandle {
-        let handle = self
-            .inner
+    fn tree(&self) -> JellyfishMerkleTree<RocksDbAdapter, Sha256> {
+        self.inner
             .handle()
             .enter()
             .map(|guard| guard.clone())
-            .unwrap_or_default();
+            .unwrap_or_default()
+    }
 
-        let inner = JellyfishMerkleTreeWrapper::new(handle);
+    pub fn handle(&self) -> TransactionStoreReadHandle {
+        let inner = JellyfishMerkleTreeWrapper::new(self.tree());
 
         TransactionStoreReadHandle { inner }
     }
+
+    /// Returns a proof that `key` is present in, or absent from, the trie as
+    /// it was at the given version
+    pub fn get_proof(&self, key: &TransactionDigest, version: Version) -> Result<MerkleProof> {
+        proofs::prove(&self.tree(), key, version)
+    }
 }
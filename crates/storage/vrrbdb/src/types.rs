@@ -16,7 +16,7 @@ pub struct RoundBlocks {
 
 /// Provides variants to parse to ensure state module handles updates
 /// properly, whether it be an Account receiving tokens, and
-/// account sending tokens, a new claim, claim staking (TODO),
+/// account sending tokens, a new claim, claim staking,
 /// fees or rewards (TODO).
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub enum UpdateAccount {
@@ -30,7 +30,8 @@ pub enum UpdateAccount {
 /// Provides a wrapper around a given account update to
 /// conveniently access the data needed to produce UpdateArgs
 /// which can then be consolidated into a single UpdateArgs struct
-/// for each account.
+/// for each account. `storage` and `package_address` are left
+/// untouched on the account when `None`.
 #[derive(Debug, PartialEq, Eq, Hash)]
 pub struct StateUpdate {
     pub address: Address,
@@ -62,10 +63,6 @@ impl From<(Address, u128)> for StateUpdate {
 /// a Transaction to two StateUpdates, one for the
 /// sender, one for the receiver. Can also provide some
 /// verification around this struct.
-// TODO: receiver update here can be used to provide
-// ClaimStaking functionality, in which the `update_account`
-// field for the `receiver_update` herein, can be used to
-// produce a `Claim` update instead of only `Account` updates
 #[derive(Debug)]
 pub struct IntoUpdates {
     pub sender_update: StateUpdate,
@@ -96,8 +93,8 @@ impl From<StateUpdate> for UpdateArgs {
                     nonce: item.nonce,
                     credits: None,
                     debits: Some(item.amount),
-                    storage: Some(item.storage.clone()),
-                    package_address: Some(item.package_address.clone()),
+                    storage: item.storage.clone().map(Some),
+                    package_address: item.package_address.clone().map(Some),
                     digests: Some(digest.clone()),
                 }
             }
@@ -108,8 +105,8 @@ impl From<StateUpdate> for UpdateArgs {
                     nonce: item.nonce,
                     credits: Some(item.amount),
                     debits: None,
-                    storage: Some(item.storage.clone()),
-                    package_address: Some(item.package_address.clone()),
+                    storage: item.storage.clone().map(Some),
+                    package_address: item.package_address.clone().map(Some),
                     digests: Some(digest.clone()),
                 }
             }
@@ -157,14 +154,13 @@ impl FromBlock for HashSet<StateUpdate> {
         let mut proposer_fees = 0u128;
 
         block.txns.into_iter().for_each(|(_digest, txn)| {
-            let fee = txn.proposer_fee_share();
-            proposer_fees += fee;
+            let (validator_fees, unclaimed) = validator_fee_updates(&txn);
+            proposer_fees += txn.proposer_fee_share() + unclaimed;
 
             let updates = IntoUpdates::from_txn(txn.clone());
             set.insert(updates.sender_update);
             set.insert(updates.receiver_update);
 
-            let validator_fees = HashSet::<StateUpdate>::from_txn(txn.clone());
             set.extend(validator_fees);
         });
 
@@ -187,13 +183,45 @@ impl FromBlock for HashSet<StateUpdate> {
 
 /// Converts a Transaction into an `IntoUpdate`
 /// which is a simple wrapper around 2 `StateUpdate`s
-/// one for the sender and one for the receiver
+/// one for the sender and one for the receiver.
+///
+/// The sender is always debited the transaction fee. Staking
+/// and delegation also debit the staked amount and record the
+/// stake against the receiving claim's account, unstaking
+/// credits the withdrawn amount back to the sender and
+/// slashing only records the stake digest. Contract deployments
+/// and calls move the amount to the contract account the same
+/// way transfers do.
 impl FromTxn for IntoUpdates {
     fn from_txn(txn: TransactionKind) -> IntoUpdates {
+        let (sender_amount, receiver_address, receiver_amount, receiver_account) = match &txn {
+            TransactionKind::Transfer(_)
+            | TransactionKind::Deploy(_)
+            | TransactionKind::Call(_) => (
+                txn.amount(),
+                txn.receiver_address(),
+                txn.amount(),
+                UpdateAccount::Receiver,
+            ),
+            TransactionKind::Stake(_) | TransactionKind::Delegate(_) => (
+                txn.amount(),
+                txn.receiver_address(),
+                0,
+                UpdateAccount::Claim,
+            ),
+            TransactionKind::Unstake(_) => (
+                0,
+                txn.sender_address(),
+                txn.amount(),
+                UpdateAccount::Receiver,
+            ),
+            TransactionKind::Slash(_) => (0, txn.receiver_address(), 0, UpdateAccount::Claim),
+        };
+
         let sender_update = StateUpdate {
             address: txn.sender_address(),
             token: Some(txn.token()),
-            amount: txn.amount(),
+            amount: sender_amount.saturating_add(txn.fee()),
             nonce: Some(txn.nonce()),
             storage: None,
             package_address: None,
@@ -202,14 +230,14 @@ impl FromTxn for IntoUpdates {
         };
 
         let receiver_update = StateUpdate {
-            address: txn.receiver_address(),
+            address: receiver_address,
             token: Some(txn.token()),
-            amount: txn.amount(),
+            amount: receiver_amount,
             nonce: None,
             storage: None,
             package_address: None,
             digest: txn.id(),
-            update_account: UpdateAccount::Receiver,
+            update_account: receiver_account,
         };
 
         IntoUpdates {
@@ -223,28 +251,99 @@ impl FromTxn for IntoUpdates {
 /// for fee distribution among the validators of a given tx
 impl FromTxn for HashSet<StateUpdate> {
     fn from_txn(txn: TransactionKind) -> HashSet<StateUpdate> {
-        let mut set = HashSet::new();
-        let fees = txn.validator_fee_share();
-        if let Some(mut validator_set) = txn.validators() {
-            validator_set.retain(|_, vote| *vote);
-            let validator_share = fees / (validator_set.len() as u128);
-            validator_set.iter().for_each(|(k, _v)| {
-                let address = Address::from_str(k);
-                if let Ok(addr) = address {
-                    set.insert(StateUpdate {
-                        address: addr,
-                        token: None,
-                        amount: validator_share,
-                        nonce: None,
-                        storage: None,
-                        package_address: None,
-                        digest: TransactionDigest::default(),
-                        update_account: UpdateAccount::Fee,
-                    });
-                }
-            });
-        }
+        validator_fee_updates(&txn).0
+    }
+}
 
-        set
+/// Splits the validator fee share of a transaction evenly among the
+/// validators that voted for it. Also returns the part of the share that
+/// could not be paid out, because no validator voted for the transaction, a
+/// validator address does not parse or the share does not divide evenly, so
+/// it can be credited to the block proposer instead of being burned.
+pub fn validator_fee_updates(txn: &TransactionKind) -> (HashSet<StateUpdate>, u128) {
+    let mut set = HashSet::new();
+    let fees = txn.validator_fee_share();
+
+    let mut addresses: Vec<Address> = txn
+        .validators()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(_, vote)| *vote)
+        .filter_map(|(k, _)| Address::from_str(&k).ok())
+        .collect();
+    addresses.sort();
+    addresses.dedup();
+
+    if addresses.is_empty() {
+        return (set, fees);
+    }
+
+    let validator_share = fees / (addresses.len() as u128);
+    for address in addresses {
+        set.insert(StateUpdate {
+            address,
+            token: None,
+            amount: validator_share,
+            nonce: None,
+            storage: None,
+            package_address: None,
+            digest: TransactionDigest::default(),
+            update_account: UpdateAccount::Fee,
+        });
+    }
+
+    let paid = validator_share * (set.len() as u128);
+
+    (set, fees - paid)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use vrrb_core::{keypair::KeyPair, transactions::Transfer};
+
+    use super::*;
+
+    fn transfer(fee: u128, validators: HashMap<String, bool>) -> TransactionKind {
+        let mut txn = Transfer::default();
+        txn.fee = fee;
+        txn.validators = Some(validators);
+
+        TransactionKind::Transfer(txn)
+    }
+
+    fn validator_address() -> String {
+        Address::new(KeyPair::random().miner_kp.1).to_string()
+    }
+
+    #[test]
+    fn validator_fees_are_split_among_approving_validators() {
+        let approving = validator_address();
+        let rejecting = validator_address();
+        let txn = transfer(
+            100,
+            HashMap::from([(approving.clone(), true), (rejecting, false)]),
+        );
+
+        let (updates, unclaimed) = validator_fee_updates(&txn);
+
+        assert_eq!(unclaimed, 0);
+        assert_eq!(updates.len(), 1);
+        let update = updates.into_iter().next().unwrap();
+        assert_eq!(update.address.to_string(), approving);
+        assert_eq!(update.amount, 50);
+    }
+
+    #[test]
+    fn undistributed_validator_fees_are_returned() {
+        let (updates, unclaimed) = validator_fee_updates(&transfer(100, HashMap::new()));
+        assert!(updates.is_empty());
+        assert_eq!(unclaimed, 50);
+
+        let validators = (0..3).map(|_| (validator_address(), true)).collect();
+        let (updates, unclaimed) = validator_fee_updates(&transfer(100, validators));
+        assert_eq!(updates.len(), 3);
+        assert_eq!(unclaimed, 2);
     }
 }
@@ -1,23 +1,48 @@
-use std::path::PathBuf;
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::PathBuf,
+    sync::Arc,
+};
 
-use block::{Block, ConvergenceBlock, GenesisBlock, GenesisRewards, ProposalBlock};
+use block::{
+    header::BlockHeader, Block, ConvergenceBlock, GenesisBlock, GenesisRewards, ProposalBlock,
+};
 use ethereum_types::U256;
 use patriecia::RootHash;
 use primitives::Address;
 
 use storage_utils::{Result, StorageError};
-use vrrb_core::transactions::{Transaction, TransactionKind, Transfer};
+use vrrb_core::transactions::{
+    ContractAction, ContractTransaction, ReceiptStatus, Token, Transaction, TransactionDigest,
+    TransactionKind, TransactionReceipt, Transfer,
+};
 use vrrb_core::{
     account::{Account, UpdateArgs},
     claim::Claim,
+    staking::Stake,
+};
+use wasm_runtime::{
+    metering::GasSchedule,
+    module_cache::{ModuleCache, DEFAULT_MODULE_CACHE_SIZE},
 };
 
 use crate::{
-    ClaimStore, ClaimStoreReadHandleFactory, FromTxn, IntoUpdates, StateStore,
+    execute_contract, validator_fee_updates, ClaimStore, ClaimStoreReadHandleFactory,
+    ContractAccountState, ContractInput, ContractOutput, FromTxn, IntoUpdates, MempoolStore,
+    PackagePolicy, PackageResolver, ReceiptStore, StateCheckpoint, StateCheckpoints, StateStore,
     StateStoreReadHandleFactory, StateUpdate, TransactionStore, TransactionStoreReadHandleFactory,
-    VrrbDbReadHandle,
+    UpdateAccount, VrrbDbReadHandle, CONTRACT_INPUT_VERSION,
 };
 
+/// Version of the format the stores are written with, bumped whenever data
+/// written by earlier versions can no longer be read. Version 1 changed stake
+/// certificates to lists of quorum member signatures, so claims and stake
+/// transactions written before it cannot be decoded.
+pub const STORAGE_VERSION: u32 = 1;
+
+/// Name of the chain parameter the storage version is stored under
+const STORAGE_VERSION_PARAM: &str = "storage_version";
+
 #[derive(Debug, Clone)]
 pub struct VrrbDbConfig {
     pub path: PathBuf,
@@ -25,6 +50,11 @@ pub struct VrrbDbConfig {
     pub transaction_store_path: Option<String>,
     pub event_store_path: Option<String>,
     pub claim_store_path: Option<String>,
+    /// Keeps a copy of the mempool on disk so it survives restarts
+    pub persist_mempool: bool,
+    /// Number of versions stale trie nodes are kept for before being pruned.
+    /// Nothing is pruned when `None`
+    pub stale_node_retention: Option<u64>,
 }
 
 impl VrrbDbConfig {
@@ -71,6 +101,8 @@ impl Default for VrrbDbConfig {
             transaction_store_path: None,
             event_store_path: None,
             claim_store_path: None,
+            persist_mempool: false,
+            stale_node_retention: None,
         }
     }
 }
@@ -80,19 +112,62 @@ pub struct VrrbDb {
     state_store: StateStore,
     transaction_store: TransactionStore,
     claim_store: ClaimStore,
+    mempool_store: Option<MempoolStore>,
+    receipt_store: Option<ReceiptStore>,
+    checkpoints: StateCheckpoints,
+    package_policy: PackagePolicy,
+    package_resolver: Option<Arc<dyn PackageResolver>>,
+    module_cache: Option<ModuleCache>,
 }
 
 impl VrrbDb {
     pub fn new(config: VrrbDbConfig) -> Self {
-        let state_store = StateStore::new(&config.path);
-        let transaction_store = TransactionStore::new(&config.path);
-        let claim_store = ClaimStore::new(&config.path);
+        let state_store = StateStore::with_retention(&config.path, config.stale_node_retention);
+        let transaction_store =
+            TransactionStore::with_retention(&config.path, config.stale_node_retention);
+        let claim_store = ClaimStore::with_retention(&config.path, config.stale_node_retention);
+
+        let mempool_store = if config.persist_mempool {
+            MempoolStore::new(&config.path)
+                .map_err(|err| telemetry::error!("could not open mempool store: {err}"))
+                .ok()
+        } else {
+            None
+        };
 
-        Self {
+        let receipt_store = ReceiptStore::new(&config.path)
+            .map_err(|err| telemetry::error!("could not open receipt store: {err}"))
+            .ok();
+
+        check_storage_version(&state_store);
+
+        let checkpoints = open_checkpoints(&state_store);
+        let package_policy = open_package_policy(&state_store);
+
+        let module_cache = ModuleCache::new(config.path.join("modules"), DEFAULT_MODULE_CACHE_SIZE)
+            .map_err(|err| telemetry::error!("could not open module cache: {err}"))
+            .ok();
+
+        let db = Self {
             state_store,
             transaction_store,
             claim_store,
-        }
+            mempool_store,
+            receipt_store,
+            checkpoints,
+            package_policy,
+            package_resolver: None,
+            module_cache,
+        };
+        db.pin_checkpoints();
+
+        db
+    }
+
+    /// Returns the storage version the database was written with, if it was
+    /// recorded
+    pub fn storage_version(&self) -> Option<u32> {
+        read_storage_version(&self.state_store)
     }
 
     pub fn export_state(&self) {
@@ -116,6 +191,8 @@ impl VrrbDb {
             self.state_store.factory(),
             self.transaction_store_factory(),
             self.claim_store_factory(),
+            self.checkpoints.clone(),
+            self.receipt_store.clone(),
         )
     }
 
@@ -124,10 +201,77 @@ impl VrrbDb {
         transaction_store: TransactionStore,
         claim_store: ClaimStore,
     ) -> Self {
-        Self {
+        check_storage_version(&state_store);
+
+        let checkpoints = open_checkpoints(&state_store);
+        let package_policy = open_package_policy(&state_store);
+
+        let db = Self {
             state_store,
             transaction_store,
             claim_store,
+            mempool_store: None,
+            receipt_store: None,
+            checkpoints,
+            package_policy,
+            package_resolver: None,
+            module_cache: None,
+        };
+        db.pin_checkpoints();
+
+        db
+    }
+
+    /// Returns the on-disk copy of the mempool, if mempool persistence is
+    /// enabled.
+    pub fn mempool_store(&self) -> Option<&MempoolStore> {
+        self.mempool_store.as_ref()
+    }
+
+    /// Sets where the WASM modules of deployed contracts are fetched from.
+    /// Contract calls fail until a resolver is set.
+    pub fn set_package_resolver(&mut self, package_resolver: Arc<dyn PackageResolver>) {
+        self.package_resolver = Some(package_resolver);
+    }
+
+    /// Returns which publishers contracts may be deployed from and called,
+    /// as set by the genesis block
+    pub fn package_policy(&self) -> &PackagePolicy {
+        &self.package_policy
+    }
+
+    /// Returns the store versions recorded for every block applied so far.
+    pub fn checkpoints(&self) -> &StateCheckpoints {
+        &self.checkpoints
+    }
+
+    /// Records the versions the stores are at once a block has been applied,
+    /// so the state can later be read as it was at that block.
+    fn record_checkpoint(&self, block_height: u128, state_root_hash: &RootHash) -> Result<()> {
+        let checkpoint = StateCheckpoint {
+            block_height,
+            state_root_hash: hex::encode(state_root_hash.0),
+            state_version: self.state_store_factory().handle().version(),
+            transactions_version: self.transaction_store_factory().handle().version(),
+            claims_version: self.claim_store_factory().handle().version(),
+        };
+        self.pin_checkpoint(&checkpoint);
+
+        self.checkpoints.insert(checkpoint)
+    }
+
+    /// Keeps the store versions a checkpoint refers to from being pruned
+    fn pin_checkpoint(&self, checkpoint: &StateCheckpoint) {
+        self.state_store.db().pin_version(checkpoint.state_version);
+        self.transaction_store
+            .db()
+            .pin_version(checkpoint.transactions_version);
+        self.claim_store.db().pin_version(checkpoint.claims_version);
+    }
+
+    fn pin_checkpoints(&self) {
+        for checkpoint in self.checkpoints.values() {
+            self.pin_checkpoint(&checkpoint);
         }
     }
 
@@ -230,6 +374,11 @@ impl VrrbDb {
         todo!()
     }
 
+    /// Applies a transfer, creating the receiver's account if it does not
+    /// exist yet. Transfers carrying a multisig policy create the receiver as
+    /// a multisig account governed by it. If the receiver already exists,
+    /// e.g. because an earlier transfer of the same block created it, the
+    /// transfer only credits it.
     fn apply_transfer(&mut self, read_handle: VrrbDbReadHandle, txn: Transfer) -> Result<()> {
         let txn = TransactionKind::Transfer(txn);
 
@@ -241,7 +390,12 @@ impl VrrbDb {
         if let Err(StorageError::Other(_err)) =
             read_handle.get_account_by_address(&receiver_address)
         {
-            let account = Account::new(receiver_address.clone());
+            let account = match txn.multisig_policy() {
+                Some(policy) if policy.address() == receiver_address => {
+                    Account::new_multisig(policy.clone())
+                }
+                _ => Account::new(receiver_address.clone()),
+            };
             self.insert_account(receiver_address.clone(), account)?;
         };
 
@@ -261,6 +415,320 @@ impl VrrbDb {
         Ok(())
     }
 
+    /// Applies a staking, unstaking, delegation or slashing transaction by
+    /// updating the stake of the receiving claim and the balances of the
+    /// accounts involved. When the stake cannot be applied to the claim, the
+    /// sender is only charged the transaction fee.
+    fn apply_stake(
+        &mut self,
+        read_handle: VrrbDbReadHandle,
+        txn: TransactionKind,
+        stake: Stake,
+    ) -> Result<()> {
+        let sender_address = txn.sender_address();
+        let receiver_address = txn.receiver_address();
+
+        read_handle.get_account_by_address(&sender_address)?;
+
+        let mut updates = IntoUpdates::from_txn(txn.clone());
+
+        match Self::staked_claim(&read_handle, &txn, stake) {
+            Ok(claim) => {
+                if let Err(StorageError::Other(_err)) =
+                    read_handle.get_account_by_address(&receiver_address)
+                {
+                    let account = Account::new(receiver_address.clone());
+                    self.insert_account(receiver_address.clone(), account)?;
+                };
+
+                self.state_store
+                    .update_uncommited(sender_address, updates.sender_update.into())?;
+
+                self.state_store.update_uncommited(
+                    updates.receiver_update.address.clone(),
+                    updates.receiver_update.into(),
+                )?;
+
+                self.state_store.commit();
+
+                self.claim_store.insert(claim)?;
+            }
+            Err(_err) => {
+                updates.sender_update.amount = txn.fee();
+
+                self.state_store
+                    .update_uncommited(sender_address, updates.sender_update.into())?;
+
+                self.state_store.commit();
+            }
+        }
+
+        self.transaction_store.insert(txn)?;
+
+        Ok(())
+    }
+
+    /// Applies `stake` to the claim the transaction targets, failing when no
+    /// claim exists at its receiver address or when a withdrawal exceeds the
+    /// sender's share of the claim's stake
+    fn staked_claim(
+        read_handle: &VrrbDbReadHandle,
+        txn: &TransactionKind,
+        stake: Stake,
+    ) -> Result<Claim> {
+        let mut claim = read_handle.get_claim_by_address(&txn.receiver_address())?;
+        let prev_stake = claim.get_stake();
+
+        claim
+            .update_stake(stake)
+            .map_err(|err| StorageError::Other(err.to_string()))?;
+
+        if matches!(txn, TransactionKind::Unstake(_)) && claim.get_stake() == prev_stake {
+            return Err(StorageError::Other(
+                "withdrawal exceeds the sender's stake in the claim".to_string(),
+            ));
+        }
+
+        Ok(claim)
+    }
+
+    /// Applies a contract deployment or call. Deployments bind the package to
+    /// a new contract account and calls run the contract's module, storing
+    /// the state it returns on the contract account. When either fails, the
+    /// sender is only charged the transaction fee. A receipt of the outcome
+    /// is recorded in both cases.
+    fn apply_contract(
+        &mut self,
+        read_handle: VrrbDbReadHandle,
+        txn: ContractTransaction,
+        header: &BlockHeader,
+    ) -> Result<()> {
+        let sender_address = txn.sender_address();
+        let contract_address = txn.contract_address();
+
+        read_handle.get_account_by_address(&sender_address)?;
+        let contract_account = read_handle.get_account_by_address(&contract_address).ok();
+
+        let (outcome, points_used, transfers) = match txn.action() {
+            ContractAction::Deploy { package_cid } => match contract_account {
+                Some(_) => (
+                    Err(StorageError::Other(format!(
+                        "account {contract_address} already exists"
+                    ))),
+                    0,
+                    vec![],
+                ),
+                None => (
+                    self.contract_package(package_cid).map(|_| ContractOutput {
+                        state: None,
+                        result: None,
+                    }),
+                    0,
+                    vec![],
+                ),
+            },
+            ContractAction::Call { input, .. } => {
+                match self.contract_module(&contract_address, contract_account.as_ref()) {
+                    Ok(wasm_bytes) => {
+                        let storage = contract_account
+                            .as_ref()
+                            .and_then(|account| account.storage().clone());
+
+                        // The amount sent along with the call is spendable by
+                        // the contract while it runs
+                        let balance = contract_account
+                            .as_ref()
+                            .map(|account| account.credits().saturating_sub(account.debits()))
+                            .unwrap_or_default()
+                            .saturating_add(txn.amount());
+
+                        let balance_handle = read_handle.clone();
+                        let state = ContractAccountState::new(
+                            contract_address.clone(),
+                            balance,
+                            storage.as_deref(),
+                            Box::new(move |address| {
+                                balance_handle
+                                    .get_account_by_address(address)
+                                    .map(|account| {
+                                        account.credits().saturating_sub(account.debits())
+                                    })
+                                    .unwrap_or_default()
+                            }),
+                        );
+
+                        let input = ContractInput {
+                            version: CONTRACT_INPUT_VERSION,
+                            tx_id: txn.id().digest_string(),
+                            last_block_time: header.timestamp,
+                            block_height: header.block_height,
+                            sender: sender_address.clone(),
+                            contract_address: contract_address.clone(),
+                            amount: txn.amount(),
+                            state: storage,
+                            input: input.clone(),
+                        };
+
+                        // The schedule is part of the signed transaction, so
+                        // every node meters the call the same way
+                        match GasSchedule::from_version(txn.gas_schedule_version) {
+                            Some(schedule) => {
+                                let execution = execute_contract(
+                                    &wasm_bytes,
+                                    &input,
+                                    state,
+                                    txn.meter_limit,
+                                    schedule,
+                                    self.package_resolver.as_deref(),
+                                    self.module_cache.as_ref(),
+                                );
+
+                                (execution.output, execution.points_used, execution.transfers)
+                            }
+                            None => (
+                                Err(StorageError::Other(format!(
+                                    "unknown gas schedule version {}",
+                                    txn.gas_schedule_version
+                                ))),
+                                0,
+                                vec![],
+                            ),
+                        }
+                    }
+                    Err(err) => (Err(err), 0, vec![]),
+                }
+            }
+        };
+
+        // Recording the call as failed would only be this node's view, so
+        // the block is not applied until the package can be fetched
+        if let Err(StorageError::NotFound(package)) = &outcome {
+            return Err(StorageError::Other(format!(
+                "{package} is unavailable, cannot call contract {contract_address}"
+            )));
+        }
+
+        let txn_kind = TransactionKind::from(txn.clone());
+        let mut updates = IntoUpdates::from_txn(txn_kind.clone());
+
+        let (status, output) = match outcome {
+            Ok(output) => {
+                if let ContractAction::Deploy { package_cid } = txn.action() {
+                    let account = Account::new(contract_address.clone());
+                    self.insert_account(contract_address.clone(), account)?;
+                    updates.receiver_update.package_address = Some(package_cid.clone());
+                }
+                updates.receiver_update.storage = output.state;
+
+                self.state_store
+                    .update_uncommited(contract_address.clone(), updates.receiver_update.into())?;
+
+                (ReceiptStatus::Success, output.result)
+            }
+            Err(err) => {
+                updates.sender_update.amount = txn.fee();
+
+                (ReceiptStatus::Failed(err.to_string()), None)
+            }
+        };
+
+        self.state_store
+            .update_uncommited(sender_address, updates.sender_update.into())?;
+
+        self.state_store.commit();
+
+        if matches!(status, ReceiptStatus::Success) {
+            self.apply_contract_transfers(&read_handle, &contract_address, transfers)?;
+        }
+
+        self.transaction_store.insert(txn_kind)?;
+
+        if let Some(receipt_store) = &self.receipt_store {
+            receipt_store.insert(&TransactionReceipt {
+                txn_id: txn.id(),
+                block_height: header.block_height,
+                status,
+                contract_address,
+                output,
+                points_used,
+                fee: txn.fee(),
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Moves the tokens a contract transferred through the host ABI out of its
+    /// account, creating the accounts of receivers that do not exist yet
+    fn apply_contract_transfers(
+        &mut self,
+        read_handle: &VrrbDbReadHandle,
+        contract_address: &Address,
+        transfers: Vec<(Address, u128)>,
+    ) -> Result<()> {
+        for (receiver_address, amount) in transfers {
+            if read_handle
+                .get_account_by_address(&receiver_address)
+                .is_err()
+            {
+                let account = Account::new(receiver_address.clone());
+                self.insert_account(receiver_address.clone(), account)?;
+            }
+
+            self.state_store.update(UpdateArgs {
+                address: receiver_address.clone(),
+                nonce: None,
+                credits: Some(amount),
+                debits: None,
+                storage: None,
+                package_address: None,
+                digests: None,
+            })?;
+
+            self.state_store.update(UpdateArgs {
+                address: contract_address.clone(),
+                nonce: None,
+                credits: None,
+                debits: Some(amount),
+                storage: None,
+                package_address: None,
+                digests: None,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Fetches the WASM module of the contract deployed at `contract_address`
+    fn contract_module(
+        &self,
+        contract_address: &Address,
+        contract_account: Option<&Account>,
+    ) -> Result<Vec<u8>> {
+        let package_cid = contract_account
+            .and_then(|account| account.package_address().clone())
+            .ok_or_else(|| {
+                StorageError::Other(format!("no contract deployed at {contract_address}"))
+            })?;
+
+        self.contract_package(&package_cid)
+    }
+
+    /// Fetches the WASM module of the contract package `package_cid`,
+    /// failing unless the chain's package policy allows its publisher
+    fn contract_package(&self, package_cid: &str) -> Result<Vec<u8>> {
+        let package_resolver = self
+            .package_resolver
+            .as_ref()
+            .ok_or_else(|| StorageError::Other("no package resolver configured".to_string()))?;
+
+        let wasm_bytes = package_resolver.resolve(package_cid)?;
+        let publisher = package_resolver.publisher(package_cid)?;
+        self.package_policy.check_publisher(publisher.as_deref())?;
+
+        Ok(wasm_bytes)
+    }
+
     fn apply_genesis_rewards(
         &mut self,
         read_handle: VrrbDbReadHandle,
@@ -283,21 +751,153 @@ impl VrrbDb {
         Ok(())
     }
 
+    /// Credits the fees collected from a proposal block's transactions to
+    /// its proposer and to the validators that voted for each transaction,
+    /// according to each transaction's fee shares. The validator share of a
+    /// transaction no validator voted for goes to the proposer.
+    fn apply_fees(
+        &mut self,
+        read_handle: VrrbDbReadHandle,
+        proposer: &Address,
+        txns: &[TransactionKind],
+    ) -> Result<()> {
+        let mut fees: HashMap<Address, u128> = HashMap::new();
+        for txn in txns {
+            let (validator_fees, unclaimed) = validator_fee_updates(txn);
+            *fees.entry(proposer.clone()).or_default() += txn.proposer_fee_share() + unclaimed;
+
+            for update in validator_fees {
+                *fees.entry(update.address).or_default() += update.amount;
+            }
+        }
+
+        for (address, amount) in fees {
+            if amount == 0 {
+                continue;
+            }
+
+            if let Err(StorageError::Other(_err)) = read_handle.get_account_by_address(&address) {
+                let account = Account::new(address.clone());
+                self.insert_account(address.clone(), account)?;
+            };
+
+            let update = StateUpdate {
+                address: address.clone(),
+                token: Some(Token::default()),
+                amount,
+                nonce: None,
+                storage: None,
+                package_address: None,
+                digest: TransactionDigest::default(),
+                update_account: UpdateAccount::Fee,
+            };
+
+            self.state_store.update_uncommited(address, update.into())?;
+        }
+
+        self.state_store.commit();
+
+        Ok(())
+    }
+
     fn apply_txn(
         &mut self,
         read_handle: VrrbDbReadHandle,
         txn_kind: TransactionKind,
+        header: &BlockHeader,
     ) -> Result<()> {
         match txn_kind {
             TransactionKind::Transfer(txn) => self.apply_transfer(read_handle, txn),
+            TransactionKind::Stake(ref txn)
+            | TransactionKind::Unstake(ref txn)
+            | TransactionKind::Delegate(ref txn)
+            | TransactionKind::Slash(ref txn) => {
+                let stake = txn.stake().clone();
+                self.apply_stake(read_handle, txn_kind, stake)
+            }
+            TransactionKind::Deploy(txn) | TransactionKind::Call(txn) => {
+                self.apply_contract(read_handle, txn, header)
+            }
         }
     }
 
+    /// Returns the CIDs of the packages of the contracts deployed and called
+    /// by the transactions `convergence` includes, so they can be fetched
+    /// before the block is applied
+    pub fn contract_packages(
+        &self,
+        convergence: &ConvergenceBlock,
+        proposals: &[ProposalBlock],
+    ) -> BTreeSet<String> {
+        let read_handle = self.read_handle();
+        let contract_txns = convergence
+            .txns
+            .iter()
+            .filter_map(|(proposal, txn_set)| {
+                proposals
+                    .iter()
+                    .find(|pblock| &pblock.hash == proposal)
+                    .map(|block| (block, txn_set))
+            })
+            .flat_map(|(block, txn_set)| {
+                block
+                    .txns
+                    .iter()
+                    .filter(|(digest, _)| txn_set.contains(*digest))
+                    .filter_map(|(_, txn_kind)| match txn_kind {
+                        TransactionKind::Deploy(txn) | TransactionKind::Call(txn) => Some(txn),
+                        _ => None,
+                    })
+            });
+
+        let mut deployed = HashMap::new();
+        let mut called = BTreeSet::new();
+        for txn in contract_txns {
+            match txn.action() {
+                ContractAction::Deploy { package_cid } => {
+                    deployed.insert(txn.contract_address(), package_cid.clone());
+                }
+                ContractAction::Call { .. } => {
+                    called.insert(txn.contract_address());
+                }
+            }
+        }
+
+        let called = called
+            .into_iter()
+            .filter_map(|contract_address| {
+                deployed.get(&contract_address).cloned().or_else(|| {
+                    read_handle
+                        .get_account_by_address(&contract_address)
+                        .ok()
+                        .and_then(|account| account.package_address().clone())
+                })
+            })
+            .collect::<Vec<_>>();
+
+        deployed.into_values().chain(called).collect()
+    }
+
     pub fn apply_convergence_block(
         &mut self,
         convergence: &ConvergenceBlock,
         proposals: &[ProposalBlock],
     ) -> Result<ApplyBlockResult> {
+        if let Some(package_resolver) = &self.package_resolver {
+            let unavailable = self
+                .contract_packages(convergence, proposals)
+                .into_iter()
+                .filter(|package_cid| !package_resolver.is_available(package_cid))
+                .collect::<Vec<_>>();
+            if !unavailable.is_empty() {
+                return Err(StorageError::Other(format!(
+                    "packages {} are unavailable, cannot apply block {}",
+                    unavailable.join(", "),
+                    convergence.hash
+                )));
+            }
+        }
+
         let read_handle = self.read_handle();
         for (proposal, txn_set) in &convergence.txns {
             let block = proposals
@@ -310,9 +910,14 @@ impl VrrbDb {
 
             let mut txns = block.txns.clone();
             txns.retain(|digest, _| txn_set.contains(digest));
+
+            let mut applied = Vec::with_capacity(txns.len());
             for (_digest, txn_kind) in txns {
-                self.apply_txn(read_handle.clone(), txn_kind)?;
+                self.apply_txn(read_handle.clone(), txn_kind.clone(), &convergence.header)?;
+                applied.push(txn_kind);
             }
+
+            self.apply_fees(read_handle.clone(), &block.from.address, &applied)?;
         }
 
         self.transaction_store.commit();
@@ -321,6 +926,8 @@ impl VrrbDb {
         let state_root_hash = self.state_store.root_hash()?;
         let transactions_root_hash = self.transaction_store.root_hash()?;
 
+        self.record_checkpoint(convergence.header.block_height, &state_root_hash)?;
+
         Ok(ApplyBlockResult {
             state_root_hash,
             transactions_root_hash,
@@ -336,6 +943,8 @@ impl VrrbDb {
             ));
         }
         self.apply_genesis_rewards(read_handle.clone(), &block.genesis_rewards)?;
+        self.package_policy
+            .set_trusted_publishers(block.trusted_package_publishers.as_deref())?;
 
         self.transaction_store.commit();
         self.state_store.commit();
@@ -343,6 +952,8 @@ impl VrrbDb {
         let state_root_hash = self.state_store.root_hash()?;
         let transactions_root_hash = RootHash(Default::default());
 
+        self.record_checkpoint(block.header.block_height, &state_root_hash)?;
+
         Ok(ApplyBlockResult {
             state_root_hash,
             transactions_root_hash,
@@ -364,12 +975,75 @@ impl VrrbDb {
     }
 }
 
+/// Opens the checkpoints stored next to the state trie, falling back to
+/// in-memory checkpoints if they cannot be read
+fn open_checkpoints(state_store: &StateStore) -> StateCheckpoints {
+    StateCheckpoints::open(state_store.db())
+        .map_err(|err| telemetry::error!("could not open state checkpoints: {err}"))
+        .unwrap_or_default()
+}
+
+/// Reads the storage version recorded next to the state trie
+fn read_storage_version(state_store: &StateStore) -> Option<u32> {
+    state_store
+        .db()
+        .chain_param(STORAGE_VERSION_PARAM)
+        .ok()
+        .flatten()
+        .and_then(|bytes| bincode::deserialize::<u32>(&bytes).ok())
+}
+
+/// Records the storage version of new databases, and reports databases
+/// written with another version, whose contents may not be readable
+fn check_storage_version(state_store: &StateStore) {
+    match read_storage_version(state_store) {
+        Some(STORAGE_VERSION) => return,
+        None if state_store.is_empty().unwrap_or(false) => {}
+        version => {
+            telemetry::error!(
+                "database was written with storage version {}, this node uses {STORAGE_VERSION}: \
+                 claims and stake transactions stored in it may be unreadable, resync the node \
+                 from an empty data directory",
+                version.unwrap_or_default()
+            );
+            return;
+        }
+    }
+
+    let written = bincode::serialize(&STORAGE_VERSION)
+        .map_err(|err| err.to_string())
+        .and_then(|bytes| {
+            state_store
+                .db()
+                .put_chain_param(STORAGE_VERSION_PARAM, &bytes)
+                .map_err(|err| err.to_string())
+        });
+
+    if let Err(err) = written {
+        telemetry::error!("could not record the storage version: {err}");
+    }
+}
+
+/// Opens the package policy stored next to the state trie, falling back to
+/// an in-memory policy if it cannot be read
+fn open_package_policy(state_store: &StateStore) -> PackagePolicy {
+    PackagePolicy::open(state_store.db())
+        .map_err(|err| telemetry::error!("could not open package policy: {err}"))
+        .unwrap_or_default()
+}
+
 impl Clone for VrrbDb {
     fn clone(&self) -> VrrbDb {
         Self {
             state_store: self.state_store.clone(),
             transaction_store: self.transaction_store.clone(),
             claim_store: self.claim_store.clone(),
+            mempool_store: self.mempool_store.clone(),
+            receipt_store: self.receipt_store.clone(),
+            checkpoints: self.checkpoints.clone(),
+            package_policy: self.package_policy.clone(),
+            package_resolver: self.package_resolver.clone(),
+            module_cache: self.module_cache.clone(),
         }
     }
 }
@@ -1,13 +1,15 @@
 use std::collections::HashMap;
 
+use ethereum_types::U256;
 use primitives::{Address, NodeId};
 use storage_utils::StorageError;
-use vrrb_core::transactions::{TransactionDigest, TransactionKind};
+use vrrb_core::transactions::{TransactionDigest, TransactionKind, TransactionReceipt};
 use vrrb_core::{account::Account, claim::Claim};
 
 use crate::result::Result;
 use crate::{
-    ClaimStoreReadHandleFactory, StateStoreReadHandleFactory, TransactionStoreReadHandleFactory,
+    ClaimStoreReadHandleFactory, MerkleProof, ReceiptStore, StateAt, StateCheckpoint,
+    StateCheckpoints, StateStoreReadHandleFactory, TransactionStoreReadHandleFactory,
 };
 
 #[derive(Debug, Clone)]
@@ -15,6 +17,8 @@ pub struct VrrbDbReadHandle {
     state_store_handle_factory: StateStoreReadHandleFactory,
     transaction_store_handle_factory: TransactionStoreReadHandleFactory,
     claim_store_handle_factory: ClaimStoreReadHandleFactory,
+    checkpoints: StateCheckpoints,
+    receipt_store: Option<ReceiptStore>,
 }
 
 impl VrrbDbReadHandle {
@@ -22,11 +26,15 @@ impl VrrbDbReadHandle {
         state_store_handle_factory: StateStoreReadHandleFactory,
         transaction_store_handle_factory: TransactionStoreReadHandleFactory,
         claim_store_handle_factory: ClaimStoreReadHandleFactory,
+        checkpoints: StateCheckpoints,
+        receipt_store: Option<ReceiptStore>,
     ) -> Self {
         Self {
             state_store_handle_factory,
             transaction_store_handle_factory,
             claim_store_handle_factory,
+            checkpoints,
+            receipt_store,
         }
     }
 
@@ -56,4 +64,124 @@ impl VrrbDbReadHandle {
                 StorageError::Other(format!("Failed to get account by address: {:?}", err))
             })
     }
+
+    /// Returns the claim registered for the given address
+    pub fn get_claim_by_address(&self, address: &Address) -> Result<Claim> {
+        self.claim_store_values()?
+            .into_values()
+            .find(|claim| &claim.address == address)
+            .ok_or_else(|| StorageError::Other(format!("no claim found for address {address}")))
+    }
+
+    /// Returns the receipt recorded when the contract transaction with the
+    /// given digest was applied
+    pub fn get_receipt(&self, txn_id: &TransactionDigest) -> Result<TransactionReceipt> {
+        self.receipt_store
+            .as_ref()
+            .ok_or_else(|| StorageError::Other("receipt store is unavailable".to_string()))?
+            .get(txn_id)
+    }
+
+    /// Returns the checkpoint recorded for the block selected by `at`
+    pub fn checkpoint(&self, at: &StateAt) -> Result<StateCheckpoint> {
+        self.checkpoints.get(at)
+    }
+
+    /// Returns a copy of all values stored within the state trie as it was
+    /// at the block selected by `at`
+    pub fn state_store_values_at(&self, at: &StateAt) -> Result<HashMap<Address, Account>> {
+        let checkpoint = self.checkpoint(at)?;
+
+        self.state_store_handle_factory
+            .handle()
+            .entries_at_version(checkpoint.state_version)
+    }
+
+    /// Returns the account stored under `address` as it was at the block
+    /// selected by `at`
+    pub fn get_account_by_address_at(&self, address: &Address, at: &StateAt) -> Result<Account> {
+        let checkpoint = self.checkpoint(at)?;
+
+        self.state_store_handle_factory
+            .handle()
+            .get_at_version(address, checkpoint.state_version)
+            .map_err(|err| {
+                StorageError::Other(format!(
+                    "Failed to get account by address at {at:?}: {:?}",
+                    err
+                ))
+            })
+    }
+
+    /// Returns the transaction with the given digest if it had been confirmed
+    /// by the block selected by `at`
+    pub fn get_transaction_at(
+        &self,
+        digest: &TransactionDigest,
+        at: &StateAt,
+    ) -> Result<TransactionKind> {
+        let checkpoint = self.checkpoint(at)?;
+
+        self.transaction_store_handle_factory
+            .handle()
+            .get(digest, checkpoint.transactions_version)
+    }
+
+    /// Returns the claim registered for the given address as it was at the
+    /// block selected by `at`
+    pub fn get_claim_by_address_at(&self, address: &Address, at: &StateAt) -> Result<Claim> {
+        let checkpoint = self.checkpoint(at)?;
+
+        self.claim_store_handle_factory
+            .handle()
+            .entries_at_version(checkpoint.claims_version)?
+            .into_values()
+            .find(|claim| &claim.address == address)
+            .ok_or_else(|| {
+                StorageError::Other(format!("no claim found for address {address} at {at:?}"))
+            })
+    }
+
+    /// Returns a proof for the account stored under `address`, taken from the
+    /// latest state or from the block selected by `at`
+    pub fn get_account_proof(
+        &self,
+        address: &Address,
+        at: Option<&StateAt>,
+    ) -> Result<MerkleProof> {
+        let version = match at {
+            Some(at) => self.checkpoint(at)?.state_version,
+            None => self.state_store_handle_factory.handle().version(),
+        };
+
+        self.state_store_handle_factory.get_proof(address, version)
+    }
+
+    /// Returns a proof for the transaction with the given digest, taken from
+    /// the latest state or from the block selected by `at`
+    pub fn get_transaction_proof(
+        &self,
+        digest: &TransactionDigest,
+        at: Option<&StateAt>,
+    ) -> Result<MerkleProof> {
+        let version = match at {
+            Some(at) => self.checkpoint(at)?.transactions_version,
+            None => self.transaction_store_handle_factory.handle().version(),
+        };
+
+        self.transaction_store_handle_factory
+            .get_proof(digest, version)
+    }
+
+    /// Returns a proof for the claim with the given hash, taken from the
+    /// latest state or from the block selected by `at`
+    pub fn get_claim_proof(&self, claim_hash: &U256, at: Option<&StateAt>) -> Result<MerkleProof> {
+        let version = match at {
+            Some(at) => self.checkpoint(at)?.claims_version,
+            None => self.claim_store_handle_factory.handle().version(),
+        };
+
+        self.claim_store_handle_factory
+            .get_proof(claim_hash, version)
+    }
 }
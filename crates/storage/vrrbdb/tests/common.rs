@@ -42,6 +42,7 @@ pub fn _generate_random_transaction(
         signature,
         validators: None,
         nonce: 10,
+        fee: None,
     }))
 }
 
@@ -65,6 +66,7 @@ pub fn _generate_random_valid_transaction() -> TransactionKind {
         signature,
         validators: None,
         nonce: 10,
+        fee: None,
     }))
 }
 
@@ -0,0 +1,50 @@
+use std::env;
+
+use serial_test::serial;
+use vrrb_core::transactions::Transaction;
+use vrrbdb::{VrrbDb, VrrbDbConfig};
+mod common;
+
+use common::{_generate_random_string, _generate_random_valid_transaction};
+
+#[test]
+#[serial]
+fn persisted_mempool_survives_reopening_the_database() {
+    let temp_dir_path = env::temp_dir();
+    let db_path = temp_dir_path.join(_generate_random_string());
+
+    let config = VrrbDbConfig {
+        path: db_path,
+        persist_mempool: true,
+        ..Default::default()
+    };
+
+    let txn1 = _generate_random_valid_transaction();
+    let txn2 = _generate_random_valid_transaction();
+
+    {
+        let db = VrrbDb::new(config.clone());
+        let store = db.mempool_store().unwrap();
+
+        store.insert(&txn1).unwrap();
+        store.insert(&txn2).unwrap();
+        store.remove(&txn1.id()).unwrap();
+    }
+
+    let db = VrrbDb::new(config);
+    let store = db.mempool_store().unwrap();
+
+    assert_eq!(store.values().unwrap(), vec![txn2]);
+
+    store.clear().unwrap();
+
+    assert!(store.values().unwrap().is_empty());
+}
+
+#[test]
+#[serial]
+fn mempool_is_not_persisted_by_default() {
+    let db = VrrbDb::new(VrrbDbConfig::default());
+
+    assert!(db.mempool_store().is_none());
+}
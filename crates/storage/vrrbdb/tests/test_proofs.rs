@@ -0,0 +1,126 @@
+use vrrb_core::{
+    account::Account,
+    claim::Claim,
+    proofs::MerkleProof,
+    transactions::{Transaction, TransactionKind},
+};
+use vrrbdb::{VrrbDb, VrrbDbConfig};
+
+mod common;
+use common::{
+    _generate_random_address, _generate_random_claim, _generate_random_valid_transaction,
+};
+use serial_test::serial;
+
+/// Returns a copy of `proof` whose proven value has been altered
+fn tampered(proof: &MerkleProof) -> MerkleProof {
+    let mut proof = proof.clone();
+    if let Some(value) = proof.value.as_mut() {
+        value[0] ^= 1;
+    }
+
+    proof
+}
+
+#[test]
+#[serial]
+fn account_proofs_verify_against_the_state_root() {
+    let mut db = VrrbDb::new(VrrbDbConfig::default());
+
+    let (_, addr1) = _generate_random_address();
+    let (_, addr2) = _generate_random_address();
+    let (_, missing) = _generate_random_address();
+
+    db.insert_account(addr1.clone(), Account::new(addr1.clone()))
+        .unwrap();
+    db.insert_account(addr2.clone(), Account::new(addr2.clone()))
+        .unwrap();
+
+    let state_root_hash = hex::encode(db.state_root_hash().unwrap().0);
+    let read_handle = db.read_handle();
+
+    let inclusion = read_handle.get_account_proof(&addr1, None).unwrap();
+    assert!(inclusion.is_inclusion());
+    assert_eq!(inclusion.root_hash, state_root_hash);
+    inclusion.verify(&addr1, &state_root_hash).unwrap();
+    assert_eq!(
+        inclusion.value::<Account>().unwrap(),
+        Some(Account::new(addr1.clone()))
+    );
+
+    let exclusion = read_handle.get_account_proof(&missing, None).unwrap();
+    assert!(!exclusion.is_inclusion());
+    exclusion.verify(&missing, &state_root_hash).unwrap();
+
+    assert!(inclusion.verify(&addr2, &state_root_hash).is_err());
+    assert!(inclusion.verify(&addr1, &hex::encode([0u8; 32])).is_err());
+}
+
+#[test]
+#[serial]
+fn transaction_proofs_verify_against_the_transactions_root() {
+    let mut db = VrrbDb::new(VrrbDbConfig::default());
+
+    let txn = _generate_random_valid_transaction();
+    let missing = _generate_random_valid_transaction();
+
+    db.insert_transaction_unchecked(txn.clone()).unwrap();
+
+    let transactions_root_hash = hex::encode(db.transactions_root_hash().unwrap().0);
+    let read_handle = db.read_handle();
+
+    let inclusion = read_handle.get_transaction_proof(&txn.id(), None).unwrap();
+    assert!(inclusion.is_inclusion());
+    inclusion
+        .verify(&txn.id(), &transactions_root_hash)
+        .unwrap();
+    assert_eq!(
+        inclusion.value::<TransactionKind>().unwrap(),
+        Some(txn.clone())
+    );
+
+    let exclusion = read_handle
+        .get_transaction_proof(&missing.id(), None)
+        .unwrap();
+    assert!(!exclusion.is_inclusion());
+    exclusion
+        .verify(&missing.id(), &transactions_root_hash)
+        .unwrap();
+
+    assert!(tampered(&inclusion)
+        .verify(&txn.id(), &transactions_root_hash)
+        .is_err());
+    assert!(inclusion
+        .verify(&missing.id(), &transactions_root_hash)
+        .is_err());
+}
+
+#[test]
+#[serial]
+fn claim_proofs_verify_against_the_claims_root() {
+    let mut db = VrrbDb::new(VrrbDbConfig::default());
+
+    let claim = _generate_random_claim();
+    let missing = _generate_random_claim();
+
+    db.insert_claim(claim.clone()).unwrap();
+
+    let claims_root_hash = hex::encode(db.claims_root_hash().unwrap().0);
+    let read_handle = db.read_handle();
+
+    let inclusion = read_handle.get_claim_proof(&claim.hash, None).unwrap();
+    assert!(inclusion.is_inclusion());
+    inclusion.verify(&claim.hash, &claims_root_hash).unwrap();
+    assert_eq!(inclusion.value::<Claim>().unwrap(), Some(claim.clone()));
+
+    let exclusion = read_handle.get_claim_proof(&missing.hash, None).unwrap();
+    assert!(!exclusion.is_inclusion());
+    exclusion.verify(&missing.hash, &claims_root_hash).unwrap();
+
+    assert!(tampered(&inclusion)
+        .verify(&claim.hash, &claims_root_hash)
+        .is_err());
+    assert!(inclusion
+        .verify(&claim.hash, &hex::encode([0u8; 32]))
+        .is_err());
+}
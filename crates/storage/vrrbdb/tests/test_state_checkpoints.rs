@@ -0,0 +1,185 @@
+use std::env;
+
+use block::{header::BlockHeader, GenesisBlock, GenesisReceiver, GenesisRewards};
+use primitives::Address;
+use ritelinked::LinkedHashMap;
+use serial_test::serial;
+use vrrb_core::{account::Account, keypair::Keypair};
+use vrrbdb::{StateAt, VrrbDb, VrrbDbConfig, STORAGE_VERSION};
+
+mod common;
+use common::{_generate_random_address, _generate_random_claim, _generate_random_string};
+
+fn genesis_block(receiver: &Address, reward: u128) -> GenesisBlock {
+    let keypair = Keypair::random();
+    let header = BlockHeader::genesis(
+        0,
+        0,
+        0,
+        _generate_random_claim(),
+        *keypair.get_miner_secret_key(),
+        String::new(),
+    );
+
+    let mut rewards = LinkedHashMap::new();
+    rewards.insert(GenesisReceiver::new(receiver.clone()), reward);
+
+    GenesisBlock {
+        header,
+        genesis_rewards: GenesisRewards(rewards),
+        trusted_package_publishers: None,
+        claims: LinkedHashMap::new(),
+        hash: _generate_random_string(),
+        certificate: None,
+    }
+}
+
+#[test]
+#[serial]
+fn state_can_be_read_as_it_was_at_a_past_block() {
+    let config = VrrbDbConfig::default().with_path(env::temp_dir().join(_generate_random_string()));
+    let mut db = VrrbDb::new(config);
+
+    let (_, genesis_receiver) = _generate_random_address();
+    let (_, late_account) = _generate_random_address();
+
+    let result = db
+        .apply_genesis_block(genesis_block(&genesis_receiver, 100))
+        .unwrap();
+
+    db.insert_account(late_account.clone(), Account::new(late_account.clone()))
+        .unwrap();
+
+    let read_handle = db.read_handle();
+    let at_genesis = StateAt::Height(0);
+    let at_genesis_root = StateAt::StateRoot(result.state_root_hash_str());
+
+    let checkpoint = read_handle.checkpoint(&at_genesis).unwrap();
+    assert_eq!(checkpoint.state_root_hash, result.state_root_hash_str());
+    assert_eq!(
+        read_handle.checkpoint(&at_genesis_root).unwrap(),
+        checkpoint
+    );
+
+    assert!(read_handle
+        .get_account_by_address_at(&genesis_receiver, &at_genesis_root)
+        .is_ok());
+    assert!(read_handle
+        .get_account_by_address_at(&late_account, &at_genesis)
+        .is_err());
+    assert!(read_handle.get_account_by_address(&late_account).is_ok());
+
+    assert_eq!(
+        read_handle
+            .state_store_values_at(&at_genesis)
+            .unwrap()
+            .len(),
+        1
+    );
+    assert!(read_handle
+        .checkpoint(&StateAt::StateRoot("unknown".to_string()))
+        .is_err());
+}
+
+#[test]
+#[serial]
+fn checkpoints_survive_reopening_the_database() {
+    let config = VrrbDbConfig::default().with_path(env::temp_dir().join(_generate_random_string()));
+    let (_, genesis_receiver) = _generate_random_address();
+
+    let state_root_hash = {
+        let mut db = VrrbDb::new(config.clone());
+
+        db.apply_genesis_block(genesis_block(&genesis_receiver, 100))
+            .unwrap()
+            .state_root_hash_str()
+    };
+
+    let db = VrrbDb::new(config);
+    let read_handle = db.read_handle();
+
+    let checkpoint = read_handle
+        .checkpoint(&StateAt::StateRoot(state_root_hash.clone()))
+        .unwrap();
+    assert_eq!(checkpoint.block_height, 0);
+    assert_eq!(
+        read_handle.checkpoint(&StateAt::Height(0)).unwrap(),
+        checkpoint
+    );
+    assert!(read_handle
+        .get_account_by_address_at(&genesis_receiver, &StateAt::StateRoot(state_root_hash))
+        .is_ok());
+}
+
+#[test]
+#[serial]
+fn pruning_keeps_checkpointed_state_readable() {
+    let config = VrrbDbConfig {
+        stale_node_retention: Some(1),
+        ..VrrbDbConfig::default().with_path(env::temp_dir().join(_generate_random_string()))
+    };
+    let mut db = VrrbDb::new(config);
+
+    let (_, genesis_receiver) = _generate_random_address();
+    db.apply_genesis_block(genesis_block(&genesis_receiver, 100))
+        .unwrap();
+
+    let (_, first_account) = _generate_random_address();
+    db.insert_account(first_account.clone(), Account::new(first_account.clone()))
+        .unwrap();
+    let unpinned_version = db.state_store_factory().handle().version();
+
+    for _ in 0..3 {
+        let (_, address) = _generate_random_address();
+        db.insert_account(address.clone(), Account::new(address))
+            .unwrap();
+    }
+
+    let read_handle = db.read_handle();
+    assert!(read_handle
+        .get_account_by_address_at(&genesis_receiver, &StateAt::Height(0))
+        .is_ok());
+    assert!(db
+        .state_store_factory()
+        .get_proof(&first_account, unpinned_version)
+        .is_err());
+    assert!(read_handle
+        .get_account_proof(&genesis_receiver, Some(&StateAt::Height(0)))
+        .is_ok());
+    assert!(read_handle.get_account_by_address(&first_account).is_ok());
+}
+
+#[test]
+#[serial]
+fn package_policy_is_set_by_the_genesis_block() {
+    let config = VrrbDbConfig::default().with_path(env::temp_dir().join(_generate_random_string()));
+    let (_, genesis_receiver) = _generate_random_address();
+
+    {
+        let mut db = VrrbDb::new(config.clone());
+        assert!(db.package_policy().check_publisher(None).is_ok());
+
+        let mut genesis = genesis_block(&genesis_receiver, 100);
+        genesis.trusted_package_publishers = Some(vec!["02AB".to_string()]);
+        db.apply_genesis_block(genesis).unwrap();
+    }
+
+    let db = VrrbDb::new(config);
+    let policy = db.package_policy();
+    assert!(policy.check_publisher(Some("02ab")).is_ok());
+    assert!(policy.check_publisher(Some("03cd")).is_err());
+    assert!(policy.check_publisher(None).is_err());
+}
+
+#[test]
+#[serial]
+fn storage_version_is_recorded_for_new_databases() {
+    let config = VrrbDbConfig::default().with_path(env::temp_dir().join(_generate_random_string()));
+
+    let db = VrrbDb::new(config.clone());
+    assert_eq!(db.storage_version(), Some(STORAGE_VERSION));
+    drop(db);
+
+    let db = VrrbDb::new(config);
+    assert_eq!(db.storage_version(), Some(STORAGE_VERSION));
+}
@@ -1,8 +1,10 @@
+use std::env;
+
 use vrrb_core::account::Account;
 use vrrbdb::{VrrbDb, VrrbDbConfig};
 
 mod common;
-use common::_generate_random_address;
+use common::{_generate_random_address, _generate_random_string};
 use serial_test::serial;
 
 #[test]
@@ -36,3 +38,28 @@ fn accounts_can_be_added() {
 
     assert_eq!(entries.len(), 5);
 }
+
+#[test]
+#[serial]
+fn account_history_survives_reopening_the_database() {
+    let config = VrrbDbConfig::default().with_path(env::temp_dir().join(_generate_random_string()));
+
+    let (_, addr) = _generate_random_address();
+
+    let version = {
+        let mut db = VrrbDb::new(config.clone());
+        db.insert_account(addr.clone(), Account::new(addr.clone()))
+            .unwrap();
+
+        db.state_store_factory().handle().version()
+    };
+
+    let db = VrrbDb::new(config);
+    let account = db
+        .state_store_factory()
+        .handle()
+        .get_at_version(&addr, version)
+        .unwrap();
+
+    assert_eq!(account, Account::new(addr));
+}
@@ -18,6 +18,8 @@ fn transactions_can_be_added() {
         transaction_store_path: None,
         event_store_path: None,
         claim_store_path: None,
+        persist_mempool: false,
+        stale_node_retention: None,
     });
 
     let txn1 = _generate_random_valid_transaction();
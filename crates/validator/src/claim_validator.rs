@@ -1,10 +1,9 @@
 use std::result::Result as StdResult;
 
-use hbbft::crypto::{PublicKey, Signature};
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use vrrb_core::{
     claim::{Claim, Eligibility},
-    staking::{Stake, StakeUpdate, MIN_STAKE_VALIDATOR},
+    staking::{CertifyingQuorum, Stake, StakeUpdate, MIN_STAKE_VALIDATOR},
 };
 
 pub type Result<T> = StdResult<T, ClaimValidatorError>;
@@ -29,7 +28,7 @@ pub enum ClaimValidatorError {
     #[error("value {0} is outside of the permitted range [{1}, {2}]")]
     OutOfBounds(String, String, String),
 
-    #[error("Quorum Key missing")]
+    #[error("Quorum certifying stake updates is unknown")]
     QuorumKeyMissing,
 
     #[error("Invalid Quorum Key ")]
@@ -42,10 +41,24 @@ pub enum ClaimValidatorError {
     Jailed,
 }
 
-#[derive(Debug, Clone)]
-pub struct ClaimValidator;
+#[derive(Debug, Clone, Default)]
+pub struct ClaimValidator {
+    certifying_quorum: Option<CertifyingQuorum>,
+}
 
 impl ClaimValidator {
+    /// Creates a claim validator that rejects every staked claim until it is
+    /// given the quorum certifying stake updates
+    pub fn new() -> ClaimValidator {
+        ClaimValidator::default()
+    }
+
+    /// Checks stake certificates against the signatures of `quorum`
+    pub fn with_certifying_quorum(mut self, quorum: Option<CertifyingQuorum>) -> ClaimValidator {
+        self.certifying_quorum = quorum;
+        self
+    }
+
     /// The function validates a claim by checking if it is eligible, has enough
     /// stake, and verifying the stake transactions and certificates.
     ///
@@ -91,29 +104,16 @@ impl ClaimValidator {
                     .verify()
                     .map_err(|_| ClaimValidatorError::InvalidStakeTxn)?;
                 self.validate_timestamp(stake)?;
-                match stake.get_certificate() {
-                    None => return Err(ClaimValidatorError::NonCertifiedStake),
-                    Some(certificate) => {
-                        if stake.get_quorum_key().is_empty() {
-                            return Err(ClaimValidatorError::QuorumKeyMissing);
-                        }
-                        let public_key_arr = TryInto::<[u8; 48]>::try_into(stake.get_quorum_key())
-                            .map_err(|_| ClaimValidatorError::InvalidQuorumKey)?;
-                        let public_key = PublicKey::from_bytes(public_key_arr)
-                            .map_err(|_| ClaimValidatorError::InvalidQuorumKey)?;
-
-                        let signature_arr = TryInto::<[u8; 96]>::try_into(certificate.0.as_slice())
-                            .map_err(|_| ClaimValidatorError::InvalidStakeCertificate)?;
-
-                        let signature = Signature::from_bytes(signature_arr)
-                            .map_err(|_| ClaimValidatorError::InvalidStakeCertificate)?;
-
-                        let status = public_key.verify(&signature, certificate.1);
-                        if !status {
-                            return Err(ClaimValidatorError::InvalidStakeCertificate);
-                        }
-                    }
+                if stake.get_certificate().is_none() {
+                    return Err(ClaimValidatorError::NonCertifiedStake);
                 }
+                let quorum = self
+                    .certifying_quorum
+                    .as_ref()
+                    .ok_or(ClaimValidatorError::QuorumKeyMissing)?;
+                stake
+                    .verify_certificate(quorum)
+                    .map_err(|_| ClaimValidatorError::InvalidStakeCertificate)?;
 
                 Ok(())
             })?;
@@ -8,13 +8,23 @@ pub mod validator_core_manager;
 #[cfg(test)]
 mod tests {
 
-    use std::collections::HashMap;
+    use std::{collections::HashMap, net::SocketAddr};
 
-    use primitives::{Address, Signature};
+    use primitives::{Address, NodeId, Signature};
     use rand::{rngs::StdRng, Rng};
     use secp256k1::ecdsa;
+    use vrrb_core::account::Account;
+    use vrrb_core::claim::Claim;
     use vrrb_core::keypair::KeyPair;
-    use vrrb_core::transactions::{NewTransferArgs, TransactionKind, Transfer};
+    use vrrb_core::multisig::{MultisigPolicy, MultisigSignature};
+    use vrrb_core::staking::{CertifyingQuorum, Stake, StakeUpdate};
+    use vrrb_core::transactions::{
+        ContractAction, ContractTransaction, NewContractTransactionArgs, NewStakeTransactionArgs,
+        NewTransferArgs, StakeTransaction, Transaction, TransactionKind, Transfer, MIN_FEE,
+    };
+    use wasm_runtime::metering::GAS_SCHEDULE_VERSION;
+
+    use crate::txn_validator::{TxnValidator, TxnValidatorError};
 
     // TODO: Use proper txns when there will be proper txn validation
     // implemented
@@ -50,6 +60,7 @@ mod tests {
             signature: _mock_txn_signature(),
             validators: Some(HashMap::<String, bool>::new()),
             nonce: 0,
+            fee: None,
         }))
     }
 
@@ -78,4 +89,504 @@ mod tests {
         // let validated = valcore_manager.validate(&account_state, batch);
         // assert_eq!(validated, target);
     }
+
+    /// Harvester quorum members certifying stake updates
+    fn certifiers() -> Vec<(NodeId, KeyPair)> {
+        (0..3)
+            .map(|idx| (format!("harvester-{idx}"), KeyPair::random()))
+            .collect()
+    }
+
+    fn certifying_quorum(certifiers: &[(NodeId, KeyPair)]) -> CertifyingQuorum {
+        CertifyingQuorum {
+            members: certifiers
+                .iter()
+                .map(|(node_id, kp)| (node_id.clone(), kp.miner_kp.1))
+                .collect(),
+            threshold: 2,
+        }
+    }
+
+    fn stake_txn(
+        amount: StakeUpdate,
+        to: Option<Address>,
+        certifiers: &[(NodeId, KeyPair)],
+    ) -> StakeTransaction {
+        stake_txn_from(&KeyPair::random(), amount, to, certifiers)
+    }
+
+    fn stake_txn_from(
+        kp: &KeyPair,
+        amount: StakeUpdate,
+        to: Option<Address>,
+        certifiers: &[(NodeId, KeyPair)],
+    ) -> StakeTransaction {
+        let (sk, pk) = kp.miner_kp;
+        let address = Address::new(pk);
+
+        let mut stake = Stake::new(amount, sk, pk, address.clone(), to).unwrap();
+        if !certifiers.is_empty() {
+            let message = secp256k1::Message::from_slice(&stake.get_payload()).unwrap();
+            let certificate = certifiers
+                .iter()
+                .map(|(node_id, kp)| (node_id.clone(), kp.miner_kp.0.sign_ecdsa(message)))
+                .collect();
+            stake.certify(certificate).unwrap();
+        }
+
+        let mut txn = StakeTransaction::new(NewStakeTransactionArgs {
+            timestamp: chrono::Utc::now().timestamp(),
+            sender_address: address,
+            sender_public_key: pk,
+            stake,
+            signature: _mock_txn_signature(),
+            validators: None,
+            nonce: 1,
+            fee: None,
+        });
+        txn.sign(&sk);
+
+        txn
+    }
+
+    #[test]
+    fn should_validate_certified_stake_transaction() {
+        let certifiers = certifiers();
+        let validator =
+            TxnValidator::new().with_certifying_quorum(Some(certifying_quorum(&certifiers)));
+        let txn = TransactionKind::from(stake_txn(StakeUpdate::Add(10_000), None, &certifiers));
+
+        assert!(validator.validate_signature(&txn).is_ok());
+        assert!(validator.validate_stake(&txn).is_ok());
+    }
+
+    #[test]
+    fn should_reject_uncertified_stake_transaction() {
+        let certifiers = certifiers();
+        let validator =
+            TxnValidator::new().with_certifying_quorum(Some(certifying_quorum(&certifiers)));
+        let txn = TransactionKind::from(stake_txn(StakeUpdate::Add(10_000), None, &[]));
+
+        assert!(matches!(
+            validator.validate_stake(&txn),
+            Err(TxnValidatorError::InvalidStake(_))
+        ));
+    }
+
+    #[test]
+    fn should_reject_stake_certified_outside_the_quorum() {
+        let certifiers = certifiers();
+        let validator =
+            TxnValidator::new().with_certifying_quorum(Some(certifying_quorum(&certifiers)));
+        let txn = TransactionKind::from(stake_txn(
+            StakeUpdate::Add(10_000),
+            None,
+            &self::certifiers(),
+        ));
+
+        assert!(matches!(
+            validator.validate_stake(&txn),
+            Err(TxnValidatorError::InvalidStake(_))
+        ));
+
+        // Without knowing the certifying quorum no certificate can be trusted
+        let txn = TransactionKind::from(stake_txn(StakeUpdate::Add(10_000), None, &certifiers));
+        assert!(matches!(
+            TxnValidator::new().validate_stake(&txn),
+            Err(TxnValidatorError::InvalidStake(_))
+        ));
+    }
+
+    #[test]
+    fn should_accept_unstake_from_delegated_claim() {
+        let certifiers = certifiers();
+        let validator =
+            TxnValidator::new().with_certifying_quorum(Some(certifying_quorum(&certifiers)));
+        let delegate = Address::new(KeyPair::random().miner_kp.1);
+
+        let own =
+            TransactionKind::from(stake_txn(StakeUpdate::Withdrawal(5_000), None, &certifiers));
+        assert!(matches!(own, TransactionKind::Unstake(_)));
+        assert!(validator.validate_stake(&own).is_ok());
+
+        // Delegators withdraw their share from the claim they delegated to
+        let delegated = TransactionKind::from(stake_txn(
+            StakeUpdate::Withdrawal(5_000),
+            Some(delegate),
+            &certifiers,
+        ));
+        assert!(matches!(delegated, TransactionKind::Unstake(_)));
+        assert!(validator.validate_stake(&delegated).is_ok());
+    }
+
+    #[test]
+    fn should_skip_stake_certificate_checks_when_asked_to() {
+        let certifiers = certifiers();
+        let validator = TxnValidator::new().without_stake_certificates();
+
+        let certified =
+            TransactionKind::from(stake_txn(StakeUpdate::Add(10_000), None, &certifiers));
+        assert!(validator.validate_stake(&certified).is_ok());
+
+        let uncertified = TransactionKind::from(stake_txn(StakeUpdate::Add(10_000), None, &[]));
+        assert!(matches!(
+            validator.validate_stake(&uncertified),
+            Err(TxnValidatorError::InvalidStake(_))
+        ));
+    }
+
+    #[test]
+    fn should_reject_stake_without_target_claim_or_share() {
+        let certifiers = certifiers();
+        let validator =
+            TxnValidator::new().with_certifying_quorum(Some(certifying_quorum(&certifiers)));
+
+        let owner = KeyPair::random();
+        let owner_address = Address::new(owner.miner_kp.1);
+        let ip_address = "127.0.0.1:8080".parse::<SocketAddr>().unwrap();
+        let signature = Claim::signature_for_valid_claim(
+            owner.miner_kp.1,
+            ip_address,
+            owner.get_miner_secret_key().secret_bytes().to_vec(),
+        )
+        .unwrap();
+        let mut claim = Claim::new(
+            owner.miner_kp.1,
+            owner_address.clone(),
+            ip_address,
+            signature,
+            NodeId::default(),
+        )
+        .unwrap();
+
+        let delegator = KeyPair::random();
+        let delegation = TransactionKind::from(stake_txn_from(
+            &delegator,
+            StakeUpdate::Add(10_000),
+            Some(owner_address.clone()),
+            &certifiers,
+        ));
+        assert!(matches!(
+            validator.validate_stake_target(None, &delegation),
+            Err(TxnValidatorError::InvalidStake(_))
+        ));
+        assert!(validator
+            .validate_stake_target(Some(&claim), &delegation)
+            .is_ok());
+        claim
+            .update_stake(delegation.as_stake().unwrap().stake().clone())
+            .unwrap();
+
+        let withdrawal = TransactionKind::from(stake_txn_from(
+            &delegator,
+            StakeUpdate::Withdrawal(10_000),
+            Some(owner_address.clone()),
+            &certifiers,
+        ));
+        assert!(validator
+            .validate_stake_target(Some(&claim), &withdrawal)
+            .is_ok());
+
+        let excessive = TransactionKind::from(stake_txn_from(
+            &delegator,
+            StakeUpdate::Withdrawal(10_001),
+            Some(owner_address.clone()),
+            &certifiers,
+        ));
+        assert!(matches!(
+            validator.validate_stake_target(Some(&claim), &excessive),
+            Err(TxnValidatorError::InvalidStake(_))
+        ));
+
+        // The owner holds no share of the stake delegated to its claim
+        let theft = TransactionKind::from(stake_txn_from(
+            &owner,
+            StakeUpdate::Withdrawal(5_000),
+            None,
+            &certifiers,
+        ));
+        assert!(matches!(
+            validator.validate_stake_target(Some(&claim), &theft),
+            Err(TxnValidatorError::InvalidStake(_))
+        ));
+
+        // Stake transactions are rejected while the claims are unknown
+        assert!(matches!(
+            validator.validate_stake_claim(&delegation),
+            Err(TxnValidatorError::InvalidStake(_))
+        ));
+    }
+
+    #[test]
+    fn should_reject_slash_without_quorum_certificate() {
+        let certifiers = certifiers();
+        let validator =
+            TxnValidator::new().with_certifying_quorum(Some(certifying_quorum(&certifiers)));
+        let victim = Address::new(KeyPair::random().miner_kp.1);
+
+        let uncertified =
+            TransactionKind::from(stake_txn(StakeUpdate::Slash(50), Some(victim.clone()), &[]));
+        assert!(matches!(
+            validator.validate_stake(&uncertified),
+            Err(TxnValidatorError::InvalidStake(_))
+        ));
+
+        let self_certified = TransactionKind::from(stake_txn(
+            StakeUpdate::Slash(50),
+            Some(victim.clone()),
+            &self::certifiers(),
+        ));
+        assert!(matches!(
+            validator.validate_stake(&self_certified),
+            Err(TxnValidatorError::InvalidStake(_))
+        ));
+
+        let below_threshold = TransactionKind::from(stake_txn(
+            StakeUpdate::Slash(50),
+            Some(victim.clone()),
+            &certifiers[..1],
+        ));
+        assert!(matches!(
+            validator.validate_stake(&below_threshold),
+            Err(TxnValidatorError::InvalidStake(_))
+        ));
+
+        let certified =
+            TransactionKind::from(stake_txn(StakeUpdate::Slash(50), Some(victim), &certifiers));
+        assert!(matches!(certified, TransactionKind::Slash(_)));
+        assert!(validator.validate_stake(&certified).is_ok());
+    }
+
+    #[test]
+    fn should_reject_stake_transaction_submitted_as_another_kind() {
+        let txn = TransactionKind::Slash(stake_txn(StakeUpdate::Add(10_000), None, &certifiers()));
+
+        assert_eq!(
+            TxnValidator::new().validate_stake(&txn),
+            Err(TxnValidatorError::StakeKindMismatch)
+        );
+    }
+
+    #[test]
+    fn should_reject_transaction_paying_less_than_min_fee() {
+        let mut txn = _random_txn();
+        if let TransactionKind::Transfer(transfer) = &mut txn {
+            transfer.fee = 1;
+        }
+
+        assert_eq!(
+            TxnValidator::new().validate_fee(&txn),
+            Err(TxnValidatorError::TxnFeeTooLow(1, MIN_FEE))
+        );
+        assert!(TxnValidator::with_min_fee(1).validate_fee(&txn).is_ok());
+    }
+
+    fn contract_txn(action: ContractAction, meter_limit: u64) -> TransactionKind {
+        scheduled_contract_txn(action, meter_limit, GAS_SCHEDULE_VERSION)
+    }
+
+    fn scheduled_contract_txn(
+        action: ContractAction,
+        meter_limit: u64,
+        gas_schedule_version: u32,
+    ) -> TransactionKind {
+        let kp = KeyPair::random();
+        let (sk, pk) = kp.miner_kp;
+
+        let mut txn = ContractTransaction::new(NewContractTransactionArgs {
+            timestamp: chrono::Utc::now().timestamp(),
+            sender_address: Address::new(pk),
+            sender_public_key: pk,
+            action,
+            amount: 0,
+            meter_limit,
+            gas_schedule_version,
+            signature: _mock_txn_signature(),
+            validators: None,
+            nonce: 1,
+            fee: None,
+        });
+        txn.sign(&sk);
+
+        TransactionKind::from(txn)
+    }
+
+    #[test]
+    fn should_validate_contract_transactions() {
+        let deploy = contract_txn(
+            ContractAction::Deploy {
+                package_cid: "bafyreihk6fv2kh2fq4y3tnahwvsxmz3n3zdugkfwvgldhxwm4f7jh5zbey"
+                    .to_string(),
+            },
+            1_000,
+        );
+
+        assert!(TxnValidator::new().validate_signature(&deploy).is_ok());
+        assert!(TxnValidator::new().validate_contract(&deploy).is_ok());
+        assert!(TxnValidator::new().validate_stake(&deploy).is_ok());
+    }
+
+    #[test]
+    fn should_reject_contract_transactions_without_package_or_meter_limit() {
+        let missing_package = contract_txn(
+            ContractAction::Deploy {
+                package_cid: String::new(),
+            },
+            1_000,
+        );
+        let unmetered = contract_txn(
+            ContractAction::Call {
+                contract_address: Address::default(),
+                input: "{}".to_string(),
+            },
+            0,
+        );
+
+        assert!(matches!(
+            TxnValidator::new().validate_contract(&missing_package),
+            Err(TxnValidatorError::InvalidContract(_))
+        ));
+        assert!(matches!(
+            TxnValidator::new().validate_contract(&unmetered),
+            Err(TxnValidatorError::InvalidContract(_))
+        ));
+    }
+
+    #[test]
+    fn should_reject_contract_transactions_with_unknown_gas_schedule() {
+        let call = |gas_schedule_version| {
+            scheduled_contract_txn(
+                ContractAction::Call {
+                    contract_address: Address::default(),
+                    input: "{}".to_string(),
+                },
+                1_000,
+                gas_schedule_version,
+            )
+        };
+
+        assert!(TxnValidator::new().validate_contract(&call(1)).is_ok());
+        assert!(matches!(
+            TxnValidator::new().validate_contract(&call(GAS_SCHEDULE_VERSION + 1)),
+            Err(TxnValidatorError::InvalidContract(_))
+        ));
+    }
+
+    fn multisig_transfer(policy: &MultisigPolicy, signers: &[&KeyPair]) -> TransactionKind {
+        let (sender, cosigners) = signers.split_first().unwrap();
+        let mut transfer = Transfer::new(NewTransferArgs {
+            timestamp: chrono::Utc::now().timestamp(),
+            sender_address: policy.address(),
+            sender_public_key: sender.miner_public_key_owned(),
+            receiver_address: Address::new(KeyPair::random().miner_public_key_owned()),
+            token: None,
+            amount: 10,
+            signature: _mock_txn_signature(),
+            validators: None,
+            nonce: 1,
+            fee: None,
+        });
+        transfer.sign(sender.get_miner_secret_key());
+
+        let payload = transfer.build_payload();
+        transfer.cosignatures = cosigners
+            .iter()
+            .map(|cosigner| MultisigSignature::sign(&payload, cosigner.get_miner_secret_key()))
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        TransactionKind::Transfer(transfer)
+    }
+
+    #[test]
+    fn should_validate_multisig_transfer_meeting_threshold() {
+        let keys: Vec<KeyPair> = (0..3).map(|_| KeyPair::random()).collect();
+        let public_keys = keys.iter().map(KeyPair::miner_public_key_owned).collect();
+        let policy = MultisigPolicy::new(public_keys, 2).unwrap();
+        let account = Account::new_multisig(policy.clone());
+
+        let txn = multisig_transfer(&policy, &[&keys[0], &keys[2]]);
+
+        assert!(TxnValidator::new().validate_signature(&txn).is_ok());
+        assert!(TxnValidator::new().validate_signers(&account, &txn).is_ok());
+    }
+
+    #[test]
+    fn should_reject_multisig_transfer_below_threshold_or_from_outsiders() {
+        let keys: Vec<KeyPair> = (0..3).map(|_| KeyPair::random()).collect();
+        let public_keys = keys.iter().map(KeyPair::miner_public_key_owned).collect();
+        let policy = MultisigPolicy::new(public_keys, 2).unwrap();
+        let account = Account::new_multisig(policy.clone());
+        let outsider = KeyPair::random();
+
+        for signers in [vec![&keys[0]], vec![&keys[0], &outsider]] {
+            let txn = multisig_transfer(&policy, &signers);
+
+            assert!(matches!(
+                TxnValidator::new().validate_signers(&account, &txn),
+                Err(TxnValidatorError::InvalidMultisig(_))
+            ));
+        }
+
+        let single_key_account = Account::new(Address::new(keys[0].miner_public_key_owned()));
+        let txn = multisig_transfer(&policy, &[&keys[0], &keys[1]]);
+        assert!(matches!(
+            TxnValidator::new().validate_signers(&single_key_account, &txn),
+            Err(TxnValidatorError::InvalidMultisig(_))
+        ));
+    }
+
+    fn multisig_creation(policy: MultisigPolicy, receiver: Address) -> TransactionKind {
+        let sender = KeyPair::random();
+        let mut transfer = Transfer::builder()
+            .timestamp(chrono::Utc::now().timestamp())
+            .sender_address(Address::new(sender.miner_public_key_owned()))
+            .sender_public_key(sender.miner_public_key_owned())
+            .receiver_address(receiver)
+            .amount(10)
+            .signature(_mock_txn_signature())
+            .nonce(1)
+            .multisig_policy(policy)
+            .build()
+            .unwrap();
+        transfer.sign(sender.get_miner_secret_key());
+
+        TransactionKind::Transfer(transfer)
+    }
+
+    #[test]
+    fn should_validate_transfer_creating_multisig_account() {
+        let public_keys = (0..3)
+            .map(|_| KeyPair::random().miner_public_key_owned())
+            .collect();
+        let policy = MultisigPolicy::new(public_keys, 2).unwrap();
+        let txn = multisig_creation(policy.clone(), policy.address());
+
+        assert!(TxnValidator::new().validate_signature(&txn).is_ok());
+        assert!(TxnValidator::new()
+            .validate_multisig_creation(None, &txn)
+            .is_ok());
+    }
+
+    #[test]
+    fn should_reject_multisig_creation_at_another_or_existing_address() {
+        let public_keys = (0..3)
+            .map(|_| KeyPair::random().miner_public_key_owned())
+            .collect();
+        let policy = MultisigPolicy::new(public_keys, 2).unwrap();
+
+        let elsewhere = Address::new(KeyPair::random().miner_public_key_owned());
+        let txn = multisig_creation(policy.clone(), elsewhere);
+        assert!(matches!(
+            TxnValidator::new().validate_multisig_creation(None, &txn),
+            Err(TxnValidatorError::InvalidMultisig(_))
+        ));
+
+        let existing = Account::new(policy.address());
+        let txn = multisig_creation(policy.clone(), policy.address());
+        assert!(matches!(
+            TxnValidator::new().validate_multisig_creation(Some(&existing), &txn),
+            Err(TxnValidatorError::InvalidMultisig(_))
+        ));
+    }
 }
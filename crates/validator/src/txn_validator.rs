@@ -1,8 +1,14 @@
 use std::result::Result as StdResult;
 
 use sha2::{Digest, Sha256};
-use storage::vrrbdb::StateStoreReadHandleFactory;
-use vrrb_core::transactions::{Transaction, TransactionKind};
+use storage::vrrbdb::{ClaimStoreReadHandleFactory, StateStoreReadHandleFactory};
+use vrrb_core::account::Account;
+use vrrb_core::claim::Claim;
+use vrrb_core::staking::{CertifyingQuorum, StakeUpdate};
+use vrrb_core::transactions::{
+    ContractAction, StakeTransactionKind, Transaction, TransactionKind, MIN_FEE,
+};
+use wasm_runtime::metering::GasSchedule;
 
 pub type Result<T> = StdResult<T, TxnValidatorError>;
 
@@ -43,12 +49,27 @@ pub enum TxnValidatorError {
     #[error("invalid amount")]
     TxnAmountIncorrect,
 
+    #[error("fee {0} is below the minimum fee of {1}")]
+    TxnFeeTooLow(u128, u128),
+
     #[error("invalid signature")]
     TxnSignatureIncorrect(String),
 
     #[error("invalid threshold signature")]
     TxnSignatureTresholdIncorrect,
 
+    #[error("invalid multisig transaction: {0}")]
+    InvalidMultisig(String),
+
+    #[error("stake transaction does not match its transaction kind")]
+    StakeKindMismatch,
+
+    #[error("invalid stake: {0}")]
+    InvalidStake(String),
+
+    #[error("invalid contract transaction: {0}")]
+    InvalidContract(String),
+
     #[error("value not found")]
     NotFound,
 
@@ -60,14 +81,66 @@ pub enum TxnValidatorError {
     Other(String),
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 // TODO: make validator configurable
-pub struct TxnValidator;
+pub struct TxnValidator {
+    min_fee: u128,
+    certifying_quorum: Option<CertifyingQuorum>,
+    check_stake_certificates: bool,
+    claim_reader: Option<ClaimStoreReadHandleFactory>,
+}
+
+impl Default for TxnValidator {
+    fn default() -> Self {
+        Self {
+            min_fee: MIN_FEE,
+            certifying_quorum: None,
+            check_stake_certificates: true,
+            claim_reader: None,
+        }
+    }
+}
 
 impl TxnValidator {
     /// Creates a new Txn validator
     pub fn new() -> TxnValidator {
-        TxnValidator
+        TxnValidator::default()
+    }
+
+    /// Creates a new Txn validator that rejects transactions paying less
+    /// than `min_fee`
+    pub fn with_min_fee(min_fee: u128) -> TxnValidator {
+        TxnValidator {
+            min_fee,
+            ..Default::default()
+        }
+    }
+
+    /// Checks stake certificates against the signatures of `quorum`. Stake
+    /// transactions are rejected while the certifying quorum is unknown.
+    pub fn with_certifying_quorum(mut self, quorum: Option<CertifyingQuorum>) -> TxnValidator {
+        self.certifying_quorum = quorum;
+        self
+    }
+
+    /// Skips checking stake certificates against the certifying quorum, for
+    /// transactions validated before the quorum is known that are validated
+    /// again before being included in a block. Stakes must still carry a
+    /// certificate.
+    pub fn without_stake_certificates(mut self) -> TxnValidator {
+        self.check_stake_certificates = false;
+        self
+    }
+
+    /// Checks stake transactions against the claims they target, read from
+    /// `claim_reader`. Stake transactions are rejected while the claims are
+    /// unknown.
+    pub fn with_claim_reader(
+        mut self,
+        claim_reader: Option<ClaimStoreReadHandleFactory>,
+    ) -> TxnValidator {
+        self.claim_reader = claim_reader;
+        self
     }
 
     /// An entire Txn validator
@@ -86,12 +159,18 @@ impl TxnValidator {
         state_reader: StateStoreReadHandleFactory,
         txn: &TransactionKind,
     ) -> Result<()> {
-        self.validate_amount(state_reader, txn)
+        self.validate_fee(txn)
+            .and_then(|_| self.validate_amount(state_reader.clone(), txn))
             .and_then(|_| self.validate_public_key(txn))
             //           .and_then(|_| self.validate_sender_address(txn))
             //           .and_then(|_| self.validate_receiver_address(txn))
             .and_then(|_| self.validate_signature(txn))
+            .and_then(|_| self.validate_multisig(state_reader.clone(), txn))
+            .and_then(|_| self.validate_new_multisig(state_reader, txn))
             .and_then(|_| self.validate_timestamp(txn))
+            .and_then(|_| self.validate_stake(txn))
+            .and_then(|_| self.validate_stake_claim(txn))
+            .and_then(|_| self.validate_contract(txn))
     }
 
     /// Txn signature validator.
@@ -106,6 +185,90 @@ impl TxnValidator {
             .map_err(|err| TxnValidatorError::TxnSignatureIncorrect(err.to_string()))
     }
 
+    /// Multisig txn validator. Reads the sender's account and checks the
+    /// transaction against its multisig policy, if it has one.
+    pub fn validate_multisig(
+        &self,
+        state_reader: StateStoreReadHandleFactory,
+        txn: &TransactionKind,
+    ) -> Result<()> {
+        let account = state_reader
+            .handle()
+            .get(&txn.sender_address())
+            .map_err(|_| TxnValidatorError::SenderAddressIncorrect)?;
+
+        self.validate_signers(&account, txn)
+    }
+
+    /// Checks that a transaction sent from a multisig account was signed by
+    /// enough keys of its policy, and that transactions sent from other
+    /// accounts carry no cosignatures.
+    pub fn validate_signers(&self, account: &Account, txn: &TransactionKind) -> Result<()> {
+        let policy = match account.multisig() {
+            Some(policy) => policy,
+            None if txn.cosignatures().is_empty() => return Ok(()),
+            None => {
+                return Err(TxnValidatorError::InvalidMultisig(
+                    "sender is not a multisig account".to_string(),
+                ))
+            }
+        };
+
+        if &policy.address() != account.address() {
+            return Err(TxnValidatorError::InvalidMultisig(
+                "policy does not match the sender address".to_string(),
+            ));
+        }
+
+        policy
+            .verify(&txn.build_payload(), &txn.signatures())
+            .map_err(|err| TxnValidatorError::InvalidMultisig(err.to_string()))
+    }
+
+    /// Multisig creation validator. Reads the receiver's account and checks
+    /// a transfer creating a multisig account against it.
+    pub fn validate_new_multisig(
+        &self,
+        state_reader: StateStoreReadHandleFactory,
+        txn: &TransactionKind,
+    ) -> Result<()> {
+        let receiver = state_reader.handle().get(&txn.receiver_address()).ok();
+
+        self.validate_multisig_creation(receiver.as_ref(), txn)
+    }
+
+    /// Checks that a transfer creating a multisig account carries a valid
+    /// policy whose address is the receiver's, and that no account exists at
+    /// that address yet.
+    pub fn validate_multisig_creation(
+        &self,
+        receiver: Option<&Account>,
+        txn: &TransactionKind,
+    ) -> Result<()> {
+        let policy = match txn.multisig_policy() {
+            Some(policy) => policy,
+            None => return Ok(()),
+        };
+
+        policy
+            .validate()
+            .map_err(|err| TxnValidatorError::InvalidMultisig(err.to_string()))?;
+
+        if policy.address() != txn.receiver_address() {
+            return Err(TxnValidatorError::InvalidMultisig(
+                "policy does not match the receiver address".to_string(),
+            ));
+        }
+
+        if receiver.is_some() {
+            return Err(TxnValidatorError::InvalidMultisig(
+                "an account already exists at the receiver address".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
     /// Txn public key validator
     pub fn validate_public_key(&self, txn: &TransactionKind) -> Result<()> {
         if !txn.sender_public_key().to_string().is_empty() {
@@ -167,13 +330,23 @@ impl TxnValidator {
         state_reader: StateStoreReadHandleFactory,
         txn: &TransactionKind,
     ) -> Result<()> {
+        // Unstaking credits the sender and slashing moves no tokens, so
+        // only the fee needs to be covered by the sender's balance
+        let debit = match txn {
+            TransactionKind::Unstake(_) | TransactionKind::Slash(_) => txn.fee(),
+            _ => txn
+                .amount()
+                .checked_add(txn.fee())
+                .ok_or(TxnValidatorError::TxnAmountIncorrect)?,
+        };
+
         let address = txn.sender_address();
         let account = state_reader
             .handle()
             .get(&address)
             .map_err(|_| TxnValidatorError::SenderAddressIncorrect)?;
         if (account.credits() - account.debits())
-            .checked_sub(txn.amount())
+            .checked_sub(debit)
             .is_none()
         {
             return Err(TxnValidatorError::TxnAmountIncorrect);
@@ -181,4 +354,154 @@ impl TxnValidator {
 
         Ok(())
     }
+
+    /// Txn fee validator
+    pub fn validate_fee(&self, txn: &TransactionKind) -> Result<()> {
+        if txn.fee() < self.min_fee {
+            return Err(TxnValidatorError::TxnFeeTooLow(txn.fee(), self.min_fee));
+        }
+
+        Ok(())
+    }
+
+    /// Stake txn validator. Checks that the wrapped stake agrees with the
+    /// transaction kind it was submitted as, that it was issued by the
+    /// transaction's sender, and that the stake was signed and certified by
+    /// the current certifying quorum.
+    pub fn validate_stake(&self, txn: &TransactionKind) -> Result<()> {
+        let (stake_txn, expected) = match txn {
+            TransactionKind::Transfer(_)
+            | TransactionKind::Deploy(_)
+            | TransactionKind::Call(_) => return Ok(()),
+            TransactionKind::Stake(stake_txn) => (stake_txn, StakeTransactionKind::Stake),
+            TransactionKind::Unstake(stake_txn) => (stake_txn, StakeTransactionKind::Unstake),
+            TransactionKind::Delegate(stake_txn) => (stake_txn, StakeTransactionKind::Delegate),
+            TransactionKind::Slash(stake_txn) => (stake_txn, StakeTransactionKind::Slash),
+        };
+
+        if stake_txn.kind() != expected {
+            return Err(TxnValidatorError::StakeKindMismatch);
+        }
+
+        let stake = stake_txn.stake();
+        if stake.get_sender() != txn.sender_address() {
+            return Err(TxnValidatorError::InvalidStake(
+                "stake was not issued by the transaction sender".to_string(),
+            ));
+        }
+
+        stake
+            .verify()
+            .map_err(|err| TxnValidatorError::InvalidStake(err.to_string()))?;
+
+        if stake.get_certificate().is_none() {
+            return Err(TxnValidatorError::InvalidStake(
+                "stake has not been certified".to_string(),
+            ));
+        }
+
+        if self.check_stake_certificates {
+            let quorum = self.certifying_quorum.as_ref().ok_or_else(|| {
+                TxnValidatorError::InvalidStake(
+                    "the quorum certifying stake updates is unknown".to_string(),
+                )
+            })?;
+            stake
+                .verify_certificate(quorum)
+                .map_err(|err| TxnValidatorError::InvalidStake(err.to_string()))?;
+        }
+
+        match stake.get_amount() {
+            StakeUpdate::Add(0) | StakeUpdate::Withdrawal(0) => Err(
+                TxnValidatorError::InvalidStake("stake amount must be non-zero".to_string()),
+            ),
+            StakeUpdate::Slash(pct) if pct == 0 || pct > 100 => {
+                Err(TxnValidatorError::OutOfBounds(
+                    "slash percentage".to_string(),
+                    1.to_string(),
+                    100.to_string(),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Stake claim validator. Reads the claim a stake transaction targets and
+    /// checks the transaction against it.
+    pub fn validate_stake_claim(&self, txn: &TransactionKind) -> Result<()> {
+        if txn.as_stake().is_none() {
+            return Ok(());
+        }
+
+        let claim_reader = self.claim_reader.as_ref().ok_or_else(|| {
+            TxnValidatorError::InvalidStake("the claims stake is posted to are unknown".to_string())
+        })?;
+
+        let receiver_address = txn.receiver_address();
+        let claim = claim_reader
+            .handle()
+            .entries()
+            .map_err(|err| TxnValidatorError::Other(err.to_string()))?
+            .into_values()
+            .find(|claim| claim.address == receiver_address);
+
+        self.validate_stake_target(claim.as_ref(), txn)
+    }
+
+    /// Checks that a claim exists at the address a stake transaction targets,
+    /// and that unstaking withdraws no more than the share of the claim's
+    /// stake the sender posted to or delegated to it.
+    pub fn validate_stake_target(
+        &self,
+        claim: Option<&Claim>,
+        txn: &TransactionKind,
+    ) -> Result<()> {
+        let stake = match txn.as_stake() {
+            Some(stake_txn) => stake_txn.stake(),
+            None => return Ok(()),
+        };
+
+        let claim = claim.ok_or_else(|| {
+            TxnValidatorError::InvalidStake(format!("no claim exists at {}", stake.get_receiver()))
+        })?;
+
+        match stake.get_amount() {
+            StakeUpdate::Withdrawal(amount) if amount > claim.get_stake_of(&stake.get_sender()) => {
+                Err(TxnValidatorError::InvalidStake(
+                    "withdrawal exceeds the sender's stake in the claim".to_string(),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Contract txn validator. Checks that deployments name a package and
+    /// that every contract transaction allows its contract some metering
+    /// points to run with, under a gas schedule this node knows.
+    pub fn validate_contract(&self, txn: &TransactionKind) -> Result<()> {
+        let contract_txn = match txn.as_contract() {
+            Some(contract_txn) => contract_txn,
+            None => return Ok(()),
+        };
+
+        if contract_txn.meter_limit == 0 {
+            return Err(TxnValidatorError::InvalidContract(
+                "meter limit must be non-zero".to_string(),
+            ));
+        }
+
+        if GasSchedule::from_version(contract_txn.gas_schedule_version).is_none() {
+            return Err(TxnValidatorError::InvalidContract(format!(
+                "unknown gas schedule version {}",
+                contract_txn.gas_schedule_version
+            )));
+        }
+
+        match contract_txn.action() {
+            ContractAction::Deploy { package_cid } if package_cid.trim().is_empty() => Err(
+                TxnValidatorError::InvalidContract("missing package CID".to_string()),
+            ),
+            _ => Ok(()),
+        }
+    }
 }
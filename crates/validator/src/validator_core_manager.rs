@@ -4,6 +4,7 @@ use mempool::MempoolReadHandleFactory;
 use rayon::ThreadPoolBuilder;
 use storage::vrrbdb::{ClaimStoreReadHandleFactory, StateStoreReadHandleFactory};
 use vrrb_core::claim::Claim;
+use vrrb_core::staking::CertifyingQuorum;
 use vrrb_core::transactions::{TransactionDigest, TransactionKind};
 
 use crate::{
@@ -23,6 +24,7 @@ pub struct ValidatorCoreManager {
     mempool_reader: MempoolReadHandleFactory,
     state_reader: StateStoreReadHandleFactory,
     claim_reader: ClaimStoreReadHandleFactory,
+    certifying_quorum: Option<CertifyingQuorum>,
 }
 
 impl Clone for ValidatorCoreManager {
@@ -34,12 +36,14 @@ impl Clone for ValidatorCoreManager {
         let mempool_reader = self.mempool_reader.clone();
         let state_reader = self.state_reader.clone();
         let claim_reader = self.claim_reader.clone();
+        let certifying_quorum = self.certifying_quorum.clone();
 
         Self {
             core_pool,
             mempool_reader,
             state_reader,
             claim_reader,
+            certifying_quorum,
         }
     }
 }
@@ -63,20 +67,38 @@ impl ValidatorCoreManager {
             mempool_reader,
             state_reader,
             claim_reader,
+            certifying_quorum: None,
         })
     }
 
+    /// Sets the quorum whose signatures certify stake updates, replacing the
+    /// previous one when quorums are reassigned
+    pub fn set_certifying_quorum(&mut self, quorum: CertifyingQuorum) {
+        self.certifying_quorum = Some(quorum);
+    }
+
+    fn txn_validator(&self) -> TxnValidator {
+        TxnValidator::new()
+            .with_certifying_quorum(self.certifying_quorum.clone())
+            .with_claim_reader(Some(self.claim_reader.clone()))
+    }
+
+    fn claim_validator(&self) -> ClaimValidator {
+        ClaimValidator::new().with_certifying_quorum(self.certifying_quorum.clone())
+    }
+
     pub fn validate_transaction_kind(
         &mut self,
         transaction: &TransactionDigest,
         mempool_reader: MempoolReadHandleFactory,
         state_reader: StateStoreReadHandleFactory,
     ) -> crate::txn_validator::Result<TransactionKind> {
+        let (txn_validator, claim_validator) = (self.txn_validator(), self.claim_validator());
         self.core_pool.install(|| {
             let valcore = Core::new(
                 self.core_pool.current_thread_index().unwrap_or(0) as CoreId,
-                TxnValidator::new(),
-                ClaimValidator,
+                txn_validator,
+                claim_validator,
             );
             valcore.process_transaction_kind(transaction, mempool_reader, state_reader)
         })
@@ -89,11 +111,12 @@ impl ValidatorCoreManager {
         state_reader: StateStoreReadHandleFactory,
     ) -> HashSet<(TransactionKind, crate::txn_validator::Result<()>)> {
         // ) -> HashSet<(Txn, bool)> {
+        let (txn_validator, claim_validator) = (self.txn_validator(), self.claim_validator());
         self.core_pool.install(|| {
             let valcore = Core::new(
                 self.core_pool.current_thread_index().unwrap_or(0) as CoreId,
-                TxnValidator::new(),
-                ClaimValidator,
+                txn_validator,
+                claim_validator,
             );
             valcore.process_transactions(batch, mempool_reader, state_reader)
         })
@@ -103,11 +126,12 @@ impl ValidatorCoreManager {
         &mut self,
         claims: Vec<Claim>,
     ) -> HashSet<(Claim, crate::claim_validator::Result<()>)> {
+        let (txn_validator, claim_validator) = (self.txn_validator(), self.claim_validator());
         self.core_pool.install(|| {
             let valcore = Core::new(
                 self.core_pool.current_thread_index().unwrap_or(0) as CoreId,
-                TxnValidator::new(),
-                ClaimValidator,
+                txn_validator,
+                claim_validator,
             );
             valcore.process_claims(claims)
         })
@@ -0,0 +1,62 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use vrrb_core::{
+    keypair::Keypair,
+    keystore::{self, KEYSTORE_PASSPHRASE_ENV},
+};
+
+use crate::ConfigError;
+
+/// Where the passphrase of a keystore is read from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq)]
+pub enum PassphraseSource {
+    /// An environment variable
+    Env(String),
+    /// A file holding the passphrase, without its trailing newline
+    File(PathBuf),
+}
+
+impl Default for PassphraseSource {
+    fn default() -> Self {
+        PassphraseSource::Env(KEYSTORE_PASSPHRASE_ENV.to_string())
+    }
+}
+
+/// An encrypted keystore holding the node's keypair
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq)]
+pub struct KeystoreConfig {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub passphrase: PassphraseSource,
+}
+
+impl KeystoreConfig {
+    pub fn read_passphrase(&self) -> crate::Result<Vec<u8>> {
+        let passphrase = match &self.passphrase {
+            PassphraseSource::Env(var) => keystore::read_passphrase_env(var),
+            PassphraseSource::File(path) => keystore::read_passphrase(Some(path.as_path())),
+        };
+
+        passphrase.map_err(|err| {
+            ConfigError::Other(format!(
+                "failed to read passphrase of keystore {}: {err}",
+                self.path.display()
+            ))
+        })
+    }
+
+    /// Decrypts the node's keypair
+    pub fn load_keypair(&self) -> crate::Result<Keypair> {
+        let passphrase = self.read_passphrase()?;
+
+        keystore::read_keystore_file(&self.path)
+            .and_then(|keystore| keystore.to_keypair(&passphrase))
+            .map_err(|err| {
+                ConfigError::Other(format!(
+                    "failed to load keypair from keystore {}: {err}",
+                    self.path.display()
+                ))
+            })
+    }
+}
@@ -1,5 +1,7 @@
 mod bootstrap;
 pub mod bootstrap_quorum;
+mod keystore_config;
+pub mod mempool_config;
 mod node_config;
 pub mod quorum;
 pub mod result;
@@ -8,6 +10,8 @@ pub mod threshold_config;
 
 pub use bootstrap::*;
 pub use bootstrap_quorum::*;
+pub use keystore_config::*;
+pub use mempool_config::*;
 pub use node_config::*;
 pub use quorum::*;
 pub use result::*;
@@ -69,4 +73,20 @@ mod tests {
         let valid_config = valid_threshold_config();
         valid_config.validate().unwrap();
     }
+
+    #[test]
+    fn successful_validate_default_mempool_config() {
+        MempoolConfig::default().validate().unwrap();
+    }
+
+    #[test]
+    fn sender_quota_cannot_exceed_mempool_capacity() {
+        let invalid_config = MempoolConfig {
+            max_txns: 10,
+            max_txns_per_sender: 11,
+            ..Default::default()
+        };
+
+        assert!(invalid_config.validate().is_err());
+    }
 }
@@ -0,0 +1,82 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ConfigError;
+
+/// Decides which pending transactions are dropped first once the mempool
+/// grows past its configured limits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, Eq, Hash)]
+pub enum EvictionPolicy {
+    /// Drop the transactions paying the lowest fee first
+    #[default]
+    LowestFee,
+    /// Drop the transactions that have been waiting the longest first
+    Oldest,
+}
+
+impl FromStr for EvictionPolicy {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "lowest-fee" => Ok(EvictionPolicy::LowestFee),
+            "oldest" => Ok(EvictionPolicy::Oldest),
+            _ => Err(ConfigError::Other(format!("invalid eviction policy {s}"))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Eq)]
+pub struct MempoolConfig {
+    /// Maximum number of pending transactions
+    pub max_txns: usize,
+    /// Maximum combined size of the pending transactions, in bytes
+    pub max_size_bytes: usize,
+    /// Number of seconds a transaction may wait in the mempool before it
+    /// expires
+    pub txn_ttl_secs: i64,
+    /// Maximum number of pending transactions a single address may have
+    pub max_txns_per_sender: usize,
+    pub eviction_policy: EvictionPolicy,
+    /// Whether pending transactions are also written to disk and reloaded
+    /// when the node restarts
+    #[serde(default)]
+    pub persist: bool,
+}
+
+impl Default for MempoolConfig {
+    fn default() -> Self {
+        MempoolConfig {
+            max_txns: 10_000,
+            max_size_bytes: 32 * 1024 * 1024,
+            txn_ttl_secs: 60 * 60 * 3,
+            max_txns_per_sender: 64,
+            eviction_policy: EvictionPolicy::default(),
+            persist: false,
+        }
+    }
+}
+
+impl MempoolConfig {
+    pub fn validate(&self) -> crate::Result<()> {
+        if self.max_txns == 0 || self.max_size_bytes == 0 {
+            return Err(ConfigError::Other(
+                "mempool max_txns and max_size_bytes must be greater than 0".to_string(),
+            ));
+        }
+        if self.max_txns_per_sender == 0 || self.max_txns_per_sender > self.max_txns {
+            return Err(ConfigError::Other(format!(
+                "mempool max_txns_per_sender {} == 0 || > {}",
+                self.max_txns_per_sender, self.max_txns
+            )));
+        }
+        if self.txn_ttl_secs <= 0 {
+            return Err(ConfigError::Other(format!(
+                "mempool txn_ttl_secs {} <= 0",
+                self.txn_ttl_secs
+            )));
+        }
+        Ok(())
+    }
+}
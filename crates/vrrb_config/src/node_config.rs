@@ -11,8 +11,8 @@ use uuid::Uuid;
 use vrrb_core::keypair::Keypair;
 
 use crate::{
-    bootstrap::BootstrapConfig, BootstrapPeerData, QuorumMember, QuorumMembershipConfig,
-    ThresholdConfig,
+    bootstrap::BootstrapConfig, BootstrapPeerData, KeystoreConfig, MempoolConfig, QuorumMember,
+    QuorumMembershipConfig, ThresholdConfig,
 };
 
 #[derive(Builder, Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
@@ -90,6 +90,11 @@ pub struct NodeConfig {
     // within this data structure
     pub keypair: Keypair,
 
+    #[builder(default)]
+    /// Encrypted keystore the keypair is loaded from. The keypair is read
+    /// from plaintext key files when unset
+    pub keystore: Option<KeystoreConfig>,
+
     #[builder(default = "false")]
     /// Enables the node's reporting and control UI
     // TODO: consider renaming to enable_ui instead
@@ -106,6 +111,34 @@ pub struct NodeConfig {
 
     pub threshold_config: ThresholdConfig,
 
+    #[builder(default)]
+    /// Capacity limits, expiry and eviction settings for the mempool
+    pub mempool_config: MempoolConfig,
+
+    #[builder(default)]
+    /// Number of versions of the state tries to keep around for historical
+    /// queries and proofs. Older trie nodes are pruned. Keeps every version
+    /// when unset
+    pub state_history_retention: Option<u64>,
+
+    #[builder(default)]
+    /// CID of the smart contract runtime package holding the QuickJS
+    /// provider that JavaScript contracts compiled by Javy link against.
+    /// Such contracts fail to execute when unset
+    pub javy_provider_package: Option<String>,
+
+    #[builder(default)]
+    /// Directory of a local content-addressed store to fetch contract
+    /// packages from, instead of the IPFS service on the default port
+    pub package_store_path: Option<PathBuf>,
+
+    #[builder(default)]
+    /// Hex encoded public keys of the publishers whose smart contract
+    /// packages the chain executes, written into the genesis block when
+    /// this node mines it. Nodes joining an existing chain follow the list
+    /// in its genesis block instead. Any package is executed when unset
+    pub trusted_package_publishers: Option<Vec<String>>,
+
     pub whitelisted_nodes: Vec<QuorumMember>,
 }
 
@@ -149,6 +182,7 @@ impl NodeConfig {
             preload_mock_state: self.preload_mock_state,
             bootstrap_config: self.bootstrap_config.clone(),
             keypair: self.keypair.clone(),
+            keystore: self.keystore.clone(),
             ..other
         }
     }
@@ -183,9 +217,15 @@ impl Default for NodeConfig {
             bootstrap_peer_data: None,
             quorum_config: None,
             keypair: Keypair::random(),
+            keystore: None,
             enable_ui: false,
             disable_networking: false,
             threshold_config: ThresholdConfig::default(),
+            mempool_config: MempoolConfig::default(),
+            state_history_retention: None,
+            javy_provider_package: None,
+            package_store_path: None,
+            trusted_package_publishers: None,
             enable_block_indexing: false,
             whitelisted_nodes: vec![],
         }
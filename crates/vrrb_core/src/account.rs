@@ -10,6 +10,7 @@ use primitives::Address;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
+use crate::multisig::MultisigPolicy;
 use crate::transactions::transaction::TransactionDigest;
 use crate::{Error, Result};
 
@@ -186,6 +187,10 @@ pub struct Account {
     storage: Option<String>,
     package_address: Option<String>,
     digests: AccountDigests,
+    /// Keys controlling the account when it is a multisig account, none for
+    /// accounts controlled by the key their address was derived from
+    #[serde(default)]
+    multisig: Option<MultisigPolicy>,
     // #[serde(skip_serializing)]
     // created_at: i64,
     // #[serde(skip_serializing)]
@@ -218,11 +223,22 @@ impl Account {
             storage,
             package_address,
             digests,
+            multisig: None,
             // created_at: Utc::now().timestamp(),
             // updated_at: None,
         }
     }
 
+    /// Returns new, empty multisig account governed by `policy`, at the
+    /// address derived from it.
+    pub fn new_multisig(policy: MultisigPolicy) -> Account {
+        let mut account = Account::new(policy.address());
+        account.multisig = Some(policy);
+        account.rehash();
+
+        account
+    }
+
     /// Modifies accounts hash, recalculating it using account's fields.
     fn rehash(&mut self) {
         let mut hasher = Sha256::new();
@@ -237,6 +253,13 @@ impl Account {
         if let Some(package_address) = &self.package_address {
             hasher.update(package_address.as_bytes());
         }
+
+        if let Some(multisig) = &self.multisig {
+            hasher.update([multisig.threshold()]);
+            for public_key in multisig.public_keys() {
+                hasher.update(public_key.serialize());
+            }
+        }
         self.hash = format!("{:x}", hasher.finalize());
     }
 
@@ -399,6 +422,9 @@ impl Account {
     pub fn digests(&self) -> &AccountDigests {
         &self.digests
     }
+    pub fn multisig(&self) -> Option<&MultisigPolicy> {
+        self.multisig.as_ref()
+    }
     // pub fn created_at(&self) -> i64 {
     //     self.created_at
     // }
@@ -429,4 +455,16 @@ mod tests {
 
         assert_eq!(account.nonce, 0);
     }
+
+    #[test]
+    fn should_create_multisig_account_at_policy_address() {
+        let public_keys = (0..3).map(|_| generate_account_keypair().1).collect();
+        let policy = MultisigPolicy::new(public_keys, 2).unwrap();
+
+        let account = Account::new_multisig(policy.clone());
+
+        assert_eq!(account.address(), &policy.address());
+        assert_eq!(account.multisig(), Some(&policy));
+        assert_ne!(account.hash(), Account::new(policy.address()).hash());
+    }
 }
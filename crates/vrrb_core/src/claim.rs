@@ -1,4 +1,4 @@
-use std::net::SocketAddr;
+use std::{collections::BTreeMap, net::SocketAddr};
 
 use ethereum_types::U256;
 use primitives::{Address, NodeId, PublicKey, SerializedSecretKey};
@@ -258,29 +258,54 @@ impl Claim {
     }
 
     fn depositing_claim(&self, stake_txn: &Stake) -> bool {
-        stake_txn.get_sender() == self.address
+        stake_txn.get_receiver() == self.address
     }
 
     /// Checks the cumulative value of a nodes stake by calculating
     /// the UTXO of the stake transactions.
     fn check_stake_utxo(&self) -> u128 {
-        self.stake_txns
-            .iter()
-            .fold(0u128, |mut acc, val| match val.get_amount() {
+        self.get_stakes()
+            .values()
+            .fold(0u128, |acc, stake| acc.saturating_add(*stake))
+    }
+
+    /// Returns the stake each address holds in the claim, keyed by the
+    /// address that added it: the claim owner's own stake along with the
+    /// stake delegated to the claim. Withdrawals only draw from the share
+    /// of the address making them, and are ignored when that share is too
+    /// small, while slashing reduces every share by the same percentage.
+    pub fn get_stakes(&self) -> BTreeMap<Address, u128> {
+        let mut stakes = BTreeMap::<Address, u128>::new();
+
+        for stake_txn in &self.stake_txns {
+            match stake_txn.get_amount() {
                 StakeUpdate::Add(value) => {
-                    if let Some(v) = acc.checked_add(value) {
-                        acc = v;
+                    let share = stakes.entry(stake_txn.get_sender()).or_default();
+                    if let Some(v) = share.checked_add(value) {
+                        *share = v;
                     }
-                    acc
                 }
                 StakeUpdate::Withdrawal(value) => {
-                    if let Some(v) = acc.checked_sub(value) {
-                        acc = v;
+                    if let Some(share) = stakes.get_mut(&stake_txn.get_sender()) {
+                        if let Some(v) = share.checked_sub(value) {
+                            *share = v;
+                        }
                     }
-                    acc
                 }
-                StakeUpdate::Slash(pct) => self.slash_calculator(pct, acc),
-            })
+                StakeUpdate::Slash(pct) => stakes
+                    .values_mut()
+                    .for_each(|share| *share = self.slash_calculator(pct, *share)),
+            }
+        }
+
+        stakes.retain(|_, share| *share > 0);
+        stakes
+    }
+
+    /// Returns the stake `staker` holds in the claim, which is all it can
+    /// withdraw from it
+    pub fn get_stake_of(&self, staker: &Address) -> u128 {
+        self.get_stakes().get(staker).copied().unwrap_or_default()
     }
 
     /// Returns the slashed value of a nodes stake after a slashing
@@ -667,7 +692,49 @@ mod tests {
         )
         .unwrap();
 
-        stake.certify((vec![0; 96], vec![0; 96])).unwrap();
+        let signature = stake.get_signature();
+        stake.certify(vec![(NodeId::default(), signature)]).unwrap();
+
+        assert!(claim.update_stake(stake).is_ok());
+        assert_eq!(claim.get_stake(), 10_000u128);
+    }
+
+    #[test]
+    fn should_add_delegated_stake_to_receiving_claim() {
+        let kp = KeyPair::random();
+        let public_key = kp.miner_kp.1;
+        let address = Address::new(public_key.clone());
+        let ip_address = "127.0.0.1:8080".parse::<SocketAddr>().unwrap();
+        let signature = Claim::signature_for_valid_claim(
+            public_key.clone(),
+            ip_address,
+            kp.get_miner_secret_key().secret_bytes().to_vec(),
+        )
+        .unwrap();
+        let mut claim = Claim::new(
+            public_key,
+            address.clone(),
+            ip_address,
+            signature,
+            NodeId::default(),
+        )
+        .unwrap();
+
+        let delegator_kp = KeyPair::random();
+        let delegator_address = Address::new(delegator_kp.miner_kp.1.clone());
+        let amount = StakeUpdate::Add(10_000u128);
+
+        let mut stake = Stake::new(
+            amount,
+            delegator_kp.miner_kp.0.clone(),
+            delegator_kp.miner_kp.1.clone(),
+            delegator_address,
+            Some(address),
+        )
+        .unwrap();
+
+        let signature = stake.get_signature();
+        stake.certify(vec![(NodeId::default(), signature)]).unwrap();
 
         assert!(claim.update_stake(stake).is_ok());
         assert_eq!(claim.get_stake(), 10_000u128);
@@ -708,7 +775,8 @@ mod tests {
         )
         .unwrap();
 
-        stake.certify((vec![0; 96], vec![0; 96])).unwrap();
+        let signature = stake.get_signature();
+        stake.certify(vec![(NodeId::default(), signature)]).unwrap();
 
         assert!(claim.update_stake(stake).is_ok());
         assert_eq!(claim.get_stake_txns().len(), 1);
@@ -747,7 +815,8 @@ mod tests {
         )
         .unwrap();
 
-        stake.certify((vec![0; 96], vec![0; 96])).unwrap();
+        let signature = stake.get_signature();
+        stake.certify(vec![(NodeId::default(), signature)]).unwrap();
 
         assert!(claim.update_stake(stake).is_ok());
         assert_eq!(claim.get_stake(), 10_000u128);
@@ -763,7 +832,8 @@ mod tests {
         )
         .unwrap();
 
-        stake.certify((vec![0; 96], vec![0; 96])).unwrap();
+        let signature = stake.get_signature();
+        stake.certify(vec![(NodeId::default(), signature)]).unwrap();
 
         assert!(claim.update_stake(stake).is_ok());
         assert_eq!(claim.get_stake(), 5_000u128);
@@ -804,7 +874,8 @@ mod tests {
         )
         .unwrap();
 
-        stake.certify((vec![0; 96], vec![0; 96])).unwrap();
+        let signature = stake.get_signature();
+        stake.certify(vec![(NodeId::default(), signature)]).unwrap();
 
         assert!(claim.update_stake(stake).is_ok());
         assert_eq!(claim.get_stake(), 0u128);
@@ -846,7 +917,8 @@ mod tests {
         )
         .unwrap();
 
-        stake.certify((vec![0; 96], vec![0; 96])).unwrap();
+        let signature = stake.get_signature();
+        stake.certify(vec![(NodeId::default(), signature)]).unwrap();
 
         assert!(claim.update_stake(stake).is_ok());
         assert_eq!(claim.get_stake(), 10_000u128);
@@ -862,7 +934,8 @@ mod tests {
         )
         .unwrap();
 
-        stake.certify((vec![0; 96], vec![0; 96])).unwrap();
+        let signature = stake.get_signature();
+        stake.certify(vec![(NodeId::default(), signature)]).unwrap();
 
         assert!(claim.update_stake(stake).is_ok());
         assert_eq!(claim.get_stake(), 7_500u128);
@@ -903,7 +976,8 @@ mod tests {
         )
         .unwrap();
 
-        stake.certify((vec![0; 96], vec![0; 96])).unwrap();
+        let signature = stake.get_signature();
+        stake.certify(vec![(NodeId::default(), signature)]).unwrap();
 
         assert!(claim.update_stake(stake).is_ok());
         assert_eq!(claim.get_stake(), 0u128);
@@ -945,7 +1019,8 @@ mod tests {
         )
         .unwrap();
 
-        stake.certify((vec![0; 96], vec![0; 96])).unwrap();
+        let signature = stake.get_signature();
+        stake.certify(vec![(NodeId::default(), signature)]).unwrap();
 
         assert!(claim.update_stake(stake).is_ok());
         assert_eq!(claim.get_stake(), 10_000u128);
@@ -961,10 +1036,88 @@ mod tests {
         )
         .unwrap();
 
-        stake.certify((vec![0; 96], vec![0; 96])).unwrap();
+        let signature = stake.get_signature();
+        stake.certify(vec![(NodeId::default(), signature)]).unwrap();
 
         assert!(claim.update_stake(stake).is_ok());
         assert_eq!(claim.get_stake(), 90_000u128);
         assert_eq!(claim.get_stake_txns().len(), 2);
     }
+
+    #[test]
+    fn should_only_withdraw_delegated_stake_to_its_delegator() {
+        let kp = KeyPair::random();
+        let public_key = kp.miner_kp.1;
+        let address = Address::new(public_key.clone());
+        let ip_address = "127.0.0.1:8080".parse::<SocketAddr>().unwrap();
+        let signature = Claim::signature_for_valid_claim(
+            public_key.clone(),
+            ip_address,
+            kp.get_miner_secret_key().secret_bytes().to_vec(),
+        )
+        .unwrap();
+        let mut claim = Claim::new(
+            public_key,
+            address.clone(),
+            ip_address,
+            signature,
+            NodeId::default(),
+        )
+        .unwrap();
+
+        let delegator_kp = KeyPair::random();
+        let delegator_address = Address::new(delegator_kp.miner_kp.1.clone());
+
+        let certified = |amount: StakeUpdate, kp: &KeyPair, to: Option<Address>| {
+            let mut stake = Stake::new(
+                amount,
+                kp.miner_kp.0.clone(),
+                kp.miner_kp.1.clone(),
+                Address::new(kp.miner_kp.1.clone()),
+                to,
+            )
+            .unwrap();
+            let signature = stake.get_signature();
+            stake.certify(vec![(NodeId::default(), signature)]).unwrap();
+            stake
+        };
+
+        claim
+            .update_stake(certified(StakeUpdate::Add(10_000u128), &kp, None))
+            .unwrap();
+        claim
+            .update_stake(certified(
+                StakeUpdate::Add(20_000u128),
+                &delegator_kp,
+                Some(address.clone()),
+            ))
+            .unwrap();
+        assert_eq!(claim.get_stake(), 30_000u128);
+        assert_eq!(claim.get_stake_of(&address), 10_000u128);
+        assert_eq!(claim.get_stake_of(&delegator_address), 20_000u128);
+
+        // The owner cannot withdraw more than its own share
+        claim
+            .update_stake(certified(StakeUpdate::Withdrawal(15_000u128), &kp, None))
+            .unwrap();
+        assert_eq!(claim.get_stake(), 30_000u128);
+
+        claim
+            .update_stake(certified(
+                StakeUpdate::Withdrawal(15_000u128),
+                &delegator_kp,
+                Some(address.clone()),
+            ))
+            .unwrap();
+        assert_eq!(claim.get_stake(), 15_000u128);
+        assert_eq!(claim.get_stake_of(&address), 10_000u128);
+        assert_eq!(claim.get_stake_of(&delegator_address), 5_000u128);
+
+        claim
+            .update_stake(certified(StakeUpdate::Slash(50u8), &kp, None))
+            .unwrap();
+        assert_eq!(claim.get_stake_of(&address), 5_000u128);
+        assert_eq!(claim.get_stake_of(&delegator_address), 2_500u128);
+        assert_eq!(claim.get_stake(), 7_500u128);
+    }
 }
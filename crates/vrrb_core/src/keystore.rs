@@ -0,0 +1,431 @@
+//! Password protected keystore files
+//!
+//! A keystore holds a single secret, a node keypair, a wallet secret key or
+//! a wallet mnemonic phrase, encrypted with AES-256-GCM under a key derived
+//! from a passphrase with scrypt. The format follows the Ethereum keystore:
+//! a versioned JSON document recording the KDF and cipher parameters next to
+//! the ciphertext, so files stay readable as the defaults change.
+
+use std::{
+    fs::OpenOptions,
+    io::{Cursor, Write},
+    path::Path,
+};
+
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::keypair::{read_keypair, write_keypair, KeyPair, KeyPairError};
+
+/// Version of the keystore format written by this module
+pub const KEYSTORE_VERSION: u32 = 1;
+
+/// Environment variable the passphrase of a keystore is read from, unless a
+/// passphrase file is given
+pub const KEYSTORE_PASSPHRASE_ENV: &str = "VRRB_KEYSTORE_PASSPHRASE";
+
+const KDF_SCRYPT: &str = "scrypt";
+const CIPHER_AES_256_GCM: &str = "aes-256-gcm";
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+
+#[derive(Error, Debug)]
+pub enum KeystoreError {
+    #[error("unsupported keystore version {0}")]
+    UnsupportedVersion(u32),
+    #[error("unsupported keystore {0}")]
+    Unsupported(String),
+    #[error("keystore holds a {0:?}, not a {1:?}")]
+    UnexpectedKind(KeystoreKind, KeystoreKind),
+    #[error("invalid keystore: {0}")]
+    Invalid(String),
+    #[error("incorrect passphrase or corrupted keystore")]
+    Decryption,
+    #[error("keypair error: {0}")]
+    KeyPair(#[from] KeyPairError),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("serde_json error: {0}")]
+    SerdeJson(#[from] serde_json::Error),
+}
+
+pub type Result<T> = std::result::Result<T, KeystoreError>;
+
+/// The kind of secret a keystore holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum KeystoreKind {
+    /// A node's validator and miner secret keys
+    Keypair,
+    /// A single secp256k1 secret key
+    SecretKey,
+    /// A BIP-39 mnemonic phrase
+    Mnemonic,
+}
+
+/// Cost parameters of the scrypt KDF
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScryptParams {
+    /// Base two logarithm of the CPU/memory cost
+    pub log_n: u8,
+    pub r: u32,
+    pub p: u32,
+}
+
+impl Default for ScryptParams {
+    /// The parameters of Ethereum keystores, n = 2^18
+    fn default() -> Self {
+        Self {
+            log_n: 18,
+            r: 8,
+            p: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeystoreCrypto {
+    pub kdf: String,
+    pub kdf_params: ScryptParams,
+    /// Hex encoded KDF salt
+    pub salt: String,
+    pub cipher: String,
+    /// Hex encoded cipher nonce
+    pub nonce: String,
+    /// Hex encoded ciphertext, including the authentication tag
+    pub ciphertext: String,
+}
+
+/// An encrypted secret, as stored on disk
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Keystore {
+    pub version: u32,
+    pub kind: KeystoreKind,
+    /// Hex encoded public key of the secret, if it has one, so the keystore
+    /// can be identified without decrypting it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    pub crypto: KeystoreCrypto,
+}
+
+impl Keystore {
+    /// Encrypts `secret` under `passphrase` with the default KDF parameters
+    pub fn encrypt(
+        kind: KeystoreKind,
+        secret: &[u8],
+        public_key: Option<String>,
+        passphrase: &[u8],
+    ) -> Result<Self> {
+        Self::encrypt_with_params(
+            kind,
+            secret,
+            public_key,
+            passphrase,
+            ScryptParams::default(),
+        )
+    }
+
+    pub fn encrypt_with_params(
+        kind: KeystoreKind,
+        secret: &[u8],
+        public_key: Option<String>,
+        passphrase: &[u8],
+        kdf_params: ScryptParams,
+    ) -> Result<Self> {
+        let mut rng = rand::thread_rng();
+        let mut salt = [0; SALT_LEN];
+        let mut nonce = [0; NONCE_LEN];
+        rng.fill_bytes(&mut salt);
+        rng.fill_bytes(&mut nonce);
+
+        let key = derive_key(passphrase, &salt, &kdf_params)?;
+        let cipher = Aes256Gcm::new_from_slice(&key)
+            .map_err(|err| KeystoreError::Invalid(err.to_string()))?;
+        let ciphertext = cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: secret,
+                    aad: &associated_data(kind),
+                },
+            )
+            .map_err(|_| KeystoreError::Invalid("failed to encrypt secret".to_string()))?;
+
+        Ok(Self {
+            version: KEYSTORE_VERSION,
+            kind,
+            public_key,
+            crypto: KeystoreCrypto {
+                kdf: KDF_SCRYPT.to_string(),
+                kdf_params,
+                salt: hex::encode(salt),
+                cipher: CIPHER_AES_256_GCM.to_string(),
+                nonce: hex::encode(nonce),
+                ciphertext: hex::encode(ciphertext),
+            },
+        })
+    }
+
+    /// Decrypts the secret, failing if the passphrase is wrong or the
+    /// keystore was tampered with
+    pub fn decrypt(&self, passphrase: &[u8]) -> Result<Vec<u8>> {
+        if self.version != KEYSTORE_VERSION {
+            return Err(KeystoreError::UnsupportedVersion(self.version));
+        }
+        if self.crypto.kdf != KDF_SCRYPT {
+            return Err(KeystoreError::Unsupported(format!(
+                "KDF {}",
+                self.crypto.kdf
+            )));
+        }
+        if self.crypto.cipher != CIPHER_AES_256_GCM {
+            return Err(KeystoreError::Unsupported(format!(
+                "cipher {}",
+                self.crypto.cipher
+            )));
+        }
+
+        let salt = decode_hex("salt", &self.crypto.salt)?;
+        let nonce = decode_hex("nonce", &self.crypto.nonce)?;
+        let ciphertext = decode_hex("ciphertext", &self.crypto.ciphertext)?;
+        if nonce.len() != NONCE_LEN {
+            return Err(KeystoreError::Invalid("invalid nonce length".to_string()));
+        }
+
+        let key = derive_key(passphrase, &salt, &self.crypto.kdf_params)?;
+        let cipher = Aes256Gcm::new_from_slice(&key)
+            .map_err(|err| KeystoreError::Invalid(err.to_string()))?;
+
+        cipher
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: &associated_data(self.kind),
+                },
+            )
+            .map_err(|_| KeystoreError::Decryption)
+    }
+
+    /// Decrypts the secret, checking that it is of the expected kind
+    pub fn decrypt_kind(&self, kind: KeystoreKind, passphrase: &[u8]) -> Result<Vec<u8>> {
+        if self.kind != kind {
+            return Err(KeystoreError::UnexpectedKind(self.kind, kind));
+        }
+
+        self.decrypt(passphrase)
+    }
+
+    /// Encrypts the secret again under a new passphrase, with fresh salt and
+    /// nonce
+    pub fn reencrypt(&self, passphrase: &[u8], new_passphrase: &[u8]) -> Result<Self> {
+        let secret = self.decrypt(passphrase)?;
+
+        Self::encrypt_with_params(
+            self.kind,
+            &secret,
+            self.public_key.clone(),
+            new_passphrase,
+            self.crypto.kdf_params,
+        )
+    }
+
+    /// Encrypts a node keypair
+    pub fn from_keypair(keypair: &KeyPair, passphrase: &[u8]) -> Result<Self> {
+        let mut secret = vec![];
+        write_keypair(keypair, &mut secret)?;
+        let public_key = hex::encode(keypair.get_miner_public_key().serialize());
+
+        Self::encrypt(KeystoreKind::Keypair, &secret, Some(public_key), passphrase)
+    }
+
+    /// Decrypts a node keypair
+    pub fn to_keypair(&self, passphrase: &[u8]) -> Result<KeyPair> {
+        let secret = self.decrypt_kind(KeystoreKind::Keypair, passphrase)?;
+
+        Ok(read_keypair(&mut Cursor::new(secret))?)
+    }
+}
+
+/// Binds the ciphertext to the kind of secret, so a keystore cannot be
+/// relabelled without failing to decrypt
+fn associated_data(kind: KeystoreKind) -> Vec<u8> {
+    format!("vrrb-keystore-v{KEYSTORE_VERSION}:{kind:?}").into_bytes()
+}
+
+fn derive_key(passphrase: &[u8], salt: &[u8], params: &ScryptParams) -> Result<[u8; KEY_LEN]> {
+    let scrypt_params = scrypt::Params::new(params.log_n, params.r, params.p, KEY_LEN)
+        .map_err(|err| KeystoreError::Invalid(format!("invalid scrypt parameters: {err}")))?;
+
+    let mut key = [0; KEY_LEN];
+    scrypt::scrypt(passphrase, salt, &scrypt_params, &mut key)
+        .map_err(|err| KeystoreError::Invalid(format!("failed to derive key: {err}")))?;
+
+    Ok(key)
+}
+
+fn decode_hex(field: &str, value: &str) -> Result<Vec<u8>> {
+    hex::decode(value).map_err(|_| KeystoreError::Invalid(format!("invalid {field} encoding")))
+}
+
+/// Reads a keystore from a file
+pub fn read_keystore_file<F: AsRef<Path>>(path: F) -> Result<Keystore> {
+    let contents = std::fs::read(path)?;
+
+    Ok(serde_json::from_slice(&contents)?)
+}
+
+/// Writes a keystore to a file, readable only by its owner
+pub fn write_keystore_file<F: AsRef<Path>>(keystore: &Keystore, path: F) -> Result<()> {
+    let path = path.as_ref();
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let mut file = {
+        #[cfg(not(unix))]
+        {
+            OpenOptions::new()
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            OpenOptions::new().mode(0o600)
+        }
+    }
+    .write(true)
+    .truncate(true)
+    .create(true)
+    .open(path)?;
+
+    file.write_all(serde_json::to_string_pretty(keystore)?.as_bytes())?;
+
+    Ok(())
+}
+
+/// Reads a keystore passphrase from `path`, without its trailing newline, or
+/// from [KEYSTORE_PASSPHRASE_ENV] when no path is given
+pub fn read_passphrase(path: Option<&Path>) -> Result<Vec<u8>> {
+    match path {
+        Some(path) => {
+            let passphrase = std::fs::read_to_string(path)?;
+            Ok(passphrase
+                .trim_end_matches(['\r', '\n'])
+                .as_bytes()
+                .to_vec())
+        }
+        None => read_passphrase_env(KEYSTORE_PASSPHRASE_ENV),
+    }
+}
+
+/// Reads a keystore passphrase from the environment variable `var`
+pub fn read_passphrase_env(var: &str) -> Result<Vec<u8>> {
+    std::env::var(var)
+        .map(String::into_bytes)
+        .map_err(|_| KeystoreError::Invalid(format!("no passphrase was set in {var}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cheap parameters, the defaults take around a second per derivation
+    const TEST_PARAMS: ScryptParams = ScryptParams {
+        log_n: 4,
+        r: 8,
+        p: 1,
+    };
+
+    #[test]
+    fn decrypts_with_the_passphrase_only() {
+        let keystore = Keystore::encrypt_with_params(
+            KeystoreKind::Mnemonic,
+            b"abandon about",
+            None,
+            b"correct horse",
+            TEST_PARAMS,
+        )
+        .unwrap();
+
+        assert_eq!(
+            keystore.decrypt(b"correct horse").unwrap(),
+            b"abandon about"
+        );
+        assert!(matches!(
+            keystore.decrypt(b"battery staple"),
+            Err(KeystoreError::Decryption)
+        ));
+        assert!(matches!(
+            keystore.decrypt_kind(KeystoreKind::SecretKey, b"correct horse"),
+            Err(KeystoreError::UnexpectedKind(..))
+        ));
+
+        let mut relabelled = keystore.clone();
+        relabelled.kind = KeystoreKind::SecretKey;
+        assert!(matches!(
+            relabelled.decrypt(b"correct horse"),
+            Err(KeystoreError::Decryption)
+        ));
+    }
+
+    #[test]
+    fn reencrypts_under_a_new_passphrase() {
+        let keystore = Keystore::encrypt_with_params(
+            KeystoreKind::SecretKey,
+            &[7; 32],
+            Some("02ab".to_string()),
+            b"old",
+            TEST_PARAMS,
+        )
+        .unwrap();
+
+        let reencrypted = keystore.reencrypt(b"old", b"new").unwrap();
+        assert_ne!(reencrypted.crypto.salt, keystore.crypto.salt);
+        assert_eq!(reencrypted.public_key, keystore.public_key);
+        assert_eq!(reencrypted.decrypt(b"new").unwrap(), vec![7; 32]);
+        assert!(reencrypted.decrypt(b"old").is_err());
+        assert!(keystore.reencrypt(b"wrong", b"new").is_err());
+    }
+
+    #[test]
+    fn round_trips_keypairs_through_files() {
+        let keypair = KeyPair::random();
+        let mut secret = vec![];
+        write_keypair(&keypair, &mut secret).unwrap();
+        let keystore = Keystore::encrypt_with_params(
+            KeystoreKind::Keypair,
+            &secret,
+            None,
+            b"passphrase",
+            TEST_PARAMS,
+        )
+        .unwrap();
+
+        let path = std::env::temp_dir().join(format!(
+            "vrrb-keystore-test-{}.json",
+            keypair.get_miner_public_key()
+        ));
+        write_keystore_file(&keystore, &path).unwrap();
+        let read = read_keystore_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(read, keystore);
+        assert_eq!(read.to_keypair(b"passphrase").unwrap(), keypair);
+
+        let mut future = read.clone();
+        future.version = KEYSTORE_VERSION + 1;
+        assert!(matches!(
+            future.to_keypair(b"passphrase"),
+            Err(KeystoreError::UnsupportedVersion(_))
+        ));
+    }
+}
@@ -7,9 +7,12 @@ pub mod component;
 pub mod handler;
 pub mod helpers;
 pub mod keypair;
+pub mod keystore;
+pub mod multisig;
 pub mod node_health_report;
 pub mod nonceable;
 pub mod ownable;
+pub mod proofs;
 pub mod result;
 pub mod serde_helpers;
 pub mod staking;
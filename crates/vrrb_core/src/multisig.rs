@@ -0,0 +1,266 @@
+//! Accounts controlled by several keys
+//!
+//! A multisig account is governed by a [MultisigPolicy]: a set of public keys
+//! and the number of them that must sign a transaction spending from the
+//! account. The account's address is derived from its policy, so a policy
+//! can always be checked against the address it claims to control.
+//!
+//! Every signer signs the same transaction payload. The signer submitting
+//! the transaction is its sender public key and carries the transaction's
+//! signature; the others are attached as [MultisigSignature]s.
+
+use std::collections::HashSet;
+
+use primitives::{Address, PublicKey, SecretKey, Signature};
+use secp256k1::Message;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+/// Maximum number of keys in a multisig policy
+pub const MAX_MULTISIG_KEYS: usize = 16;
+
+/// Prefix of the data hashed into multisig addresses, keeping them apart
+/// from addresses derived from a single public key
+const MULTISIG_ADDRESS_DOMAIN: &[u8] = b"vrrb-multisig";
+
+#[derive(Error, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MultisigError {
+    #[error("threshold {0} must be between 1 and the number of keys, {1}")]
+    InvalidThreshold(u8, usize),
+    #[error("a multisig policy holds at most {MAX_MULTISIG_KEYS} keys, got {0}")]
+    TooManyKeys(usize),
+    #[error("key {0} is listed more than once")]
+    DuplicateKey(PublicKey),
+    #[error("key {0} is not part of the multisig policy")]
+    UnknownKey(PublicKey),
+    #[error("invalid signature from key {0}")]
+    InvalidSignature(PublicKey),
+    #[error("{0} valid signatures, {1} required")]
+    BelowThreshold(usize, u8),
+    #[error("invalid payload: {0}")]
+    InvalidPayload(String),
+}
+
+/// The keys controlling a multisig account and how many of them must sign
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MultisigPolicy {
+    public_keys: Vec<PublicKey>,
+    threshold: u8,
+}
+
+impl MultisigPolicy {
+    /// Creates a policy requiring `threshold` of `public_keys` to sign. The
+    /// order of the keys does not matter.
+    pub fn new(mut public_keys: Vec<PublicKey>, threshold: u8) -> Result<Self, MultisigError> {
+        public_keys.sort();
+
+        let policy = Self {
+            public_keys,
+            threshold,
+        };
+        policy.validate()?;
+
+        Ok(policy)
+    }
+
+    pub fn public_keys(&self) -> &[PublicKey] {
+        &self.public_keys
+    }
+
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /// Checks the policy can be satisfied and lists no key twice. Policies
+    /// read back from storage or the network are only trusted once checked.
+    pub fn validate(&self) -> Result<(), MultisigError> {
+        if self.public_keys.len() > MAX_MULTISIG_KEYS {
+            return Err(MultisigError::TooManyKeys(self.public_keys.len()));
+        }
+        if self.threshold == 0 || self.threshold as usize > self.public_keys.len() {
+            return Err(MultisigError::InvalidThreshold(
+                self.threshold,
+                self.public_keys.len(),
+            ));
+        }
+
+        let mut seen = HashSet::new();
+        for public_key in &self.public_keys {
+            if !seen.insert(public_key) {
+                return Err(MultisigError::DuplicateKey(*public_key));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the address of the account governed by the policy: the lower
+    /// 20 bytes of the SHA-256 hash of its threshold and sorted keys
+    pub fn address(&self) -> Address {
+        let mut public_keys = self.public_keys.clone();
+        public_keys.sort();
+
+        let mut hasher = Sha256::new();
+        hasher.update(MULTISIG_ADDRESS_DOMAIN);
+        hasher.update([self.threshold]);
+        for public_key in &public_keys {
+            hasher.update(public_key.serialize());
+        }
+        let hash = hasher.finalize();
+
+        let mut address_bytes = [0u8; 20];
+        address_bytes.copy_from_slice(&hash[(hash.len() - 20)..]);
+
+        Address(address_bytes)
+    }
+
+    /// Checks that at least `threshold` distinct keys of the policy signed
+    /// `payload`
+    pub fn verify(
+        &self,
+        payload: &str,
+        signatures: &[MultisigSignature],
+    ) -> Result<(), MultisigError> {
+        self.validate()?;
+
+        let message = payload_message(payload)?;
+        let mut signers = HashSet::new();
+        for signature in signatures {
+            if !self.public_keys.contains(&signature.public_key) {
+                return Err(MultisigError::UnknownKey(signature.public_key));
+            }
+            if !signers.insert(signature.public_key) {
+                return Err(MultisigError::DuplicateKey(signature.public_key));
+            }
+
+            signature
+                .signature
+                .verify(&message, &signature.public_key)
+                .map_err(|_| MultisigError::InvalidSignature(signature.public_key))?;
+        }
+
+        if signers.len() < self.threshold as usize {
+            return Err(MultisigError::BelowThreshold(signers.len(), self.threshold));
+        }
+
+        Ok(())
+    }
+}
+
+/// A signature over a transaction payload by one of the keys of a multisig
+/// policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MultisigSignature {
+    pub public_key: PublicKey,
+    pub signature: Signature,
+}
+
+impl MultisigSignature {
+    /// Signs `payload` the way transactions sign their payload
+    pub fn sign(payload: &str, secret_key: &SecretKey) -> Result<Self, MultisigError> {
+        let message = payload_message(payload)?;
+
+        Ok(Self {
+            public_key: PublicKey::from_secret_key(&secp256k1::Secp256k1::new(), secret_key),
+            signature: secret_key.sign_ecdsa(message),
+        })
+    }
+}
+
+fn payload_message(payload: &str) -> Result<Message, MultisigError> {
+    let hash = Sha256::digest(payload.as_bytes());
+
+    Message::from_slice(&hash).map_err(|err| MultisigError::InvalidPayload(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keypair::KeyPair;
+
+    use super::*;
+
+    fn keys(count: usize) -> Vec<KeyPair> {
+        (0..count).map(|_| KeyPair::random()).collect()
+    }
+
+    fn policy(keys: &[KeyPair], threshold: u8) -> MultisigPolicy {
+        let public_keys = keys.iter().map(KeyPair::miner_public_key_owned).collect();
+
+        MultisigPolicy::new(public_keys, threshold).unwrap()
+    }
+
+    #[test]
+    fn addresses_do_not_depend_on_key_order() {
+        let keys = keys(3);
+        let mut public_keys: Vec<_> = keys.iter().map(KeyPair::miner_public_key_owned).collect();
+        let address = MultisigPolicy::new(public_keys.clone(), 2)
+            .unwrap()
+            .address();
+
+        public_keys.reverse();
+        assert_eq!(
+            MultisigPolicy::new(public_keys.clone(), 2)
+                .unwrap()
+                .address(),
+            address
+        );
+        assert_ne!(
+            MultisigPolicy::new(public_keys, 3).unwrap().address(),
+            address
+        );
+    }
+
+    #[test]
+    fn rejects_unsatisfiable_policies() {
+        let public_keys: Vec<_> = keys(2)
+            .iter()
+            .map(KeyPair::miner_public_key_owned)
+            .collect();
+
+        assert_eq!(
+            MultisigPolicy::new(public_keys.clone(), 0),
+            Err(MultisigError::InvalidThreshold(0, 2))
+        );
+        assert_eq!(
+            MultisigPolicy::new(public_keys.clone(), 3),
+            Err(MultisigError::InvalidThreshold(3, 2))
+        );
+        assert_eq!(
+            MultisigPolicy::new(vec![public_keys[0], public_keys[0]], 1),
+            Err(MultisigError::DuplicateKey(public_keys[0]))
+        );
+    }
+
+    #[test]
+    fn verifies_threshold_signatures() {
+        let keys = keys(3);
+        let policy = policy(&keys, 2);
+        let payload = "payload";
+
+        let sign = |key: &KeyPair| MultisigSignature::sign(payload, key.get_miner_secret_key());
+        let first = sign(&keys[0]).unwrap();
+        let second = sign(&keys[1]).unwrap();
+
+        assert!(policy.verify(payload, &[first, second]).is_ok());
+        assert_eq!(
+            policy.verify(payload, &[first]),
+            Err(MultisigError::BelowThreshold(1, 2))
+        );
+        assert_eq!(
+            policy.verify(payload, &[first, first]),
+            Err(MultisigError::DuplicateKey(first.public_key))
+        );
+        assert_eq!(
+            policy.verify("other payload", &[first, second]),
+            Err(MultisigError::InvalidSignature(first.public_key))
+        );
+
+        let outsider = sign(&KeyPair::random()).unwrap();
+        assert_eq!(
+            policy.verify(payload, &[first, outsider]),
+            Err(MultisigError::UnknownKey(outsider.public_key))
+        );
+    }
+}
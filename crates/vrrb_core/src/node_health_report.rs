@@ -1,4 +1,13 @@
+use primitives::Round;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
-pub struct NodeHealthReport {}
+pub struct NodeHealthReport {
+    /// Hash of the most recent genesis or convergence block known to the node
+    pub last_block_hash: Option<String>,
+    pub last_block_height: Option<u128>,
+    pub round: Round,
+    /// Number of blocks of any kind stored within the node's DAG
+    pub block_count: usize,
+    pub mempool_size: usize,
+}
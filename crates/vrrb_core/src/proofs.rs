@@ -0,0 +1,65 @@
+use patriecia::{KeyHash, RootHash, SparseMerkleProof, Version};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::{Error, Result};
+
+/// Sparse Merkle proof that a key is either bound to a value or absent from
+/// one of the store tries at a given version.
+///
+/// Proofs carry everything needed to check them, so light clients can verify
+/// balances, transactions and claims against a root hash they already trust
+/// (e.g. one taken from a certified block) without access to the database.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    /// Hex encoded root hash of the trie the proof was generated against
+    pub root_hash: String,
+    pub version: Version,
+    /// Serialized value stored under the key, `None` if the key is absent
+    pub value: Option<Vec<u8>>,
+    pub proof: SparseMerkleProof<Sha256>,
+}
+
+impl MerkleProof {
+    /// Returns true if the proof shows the key is present in the trie
+    pub fn is_inclusion(&self) -> bool {
+        self.value.is_some()
+    }
+
+    /// Decodes the value the proof commits to
+    pub fn value<V: DeserializeOwned>(&self) -> Result<Option<V>> {
+        self.value
+            .as_ref()
+            .map(|value| bincode::deserialize::<V>(value))
+            .transpose()
+            .map_err(|err| Error::Other(format!("failed to decode proven value: {err}")))
+    }
+
+    /// Checks that the proof binds `key` to its value, or to nothing, within
+    /// the trie whose root hash is `trusted_root_hash`.
+    pub fn verify<K: Serialize>(&self, key: &K, trusted_root_hash: &str) -> Result<()> {
+        let root_hash = decode_root_hash(trusted_root_hash)?;
+
+        self.proof
+            .verify(root_hash, key_hash(key)?, self.value.as_ref())
+            .map_err(|err| Error::Other(format!("invalid proof: {err}")))
+    }
+}
+
+/// Hashes a key the same way the store tries do before inserting it
+pub fn key_hash<K: Serialize>(key: &K) -> Result<KeyHash> {
+    let bytes = bincode::serialize(key).map_err(|err| Error::Other(err.to_string()))?;
+
+    Ok(KeyHash::with::<Sha256>(bytes))
+}
+
+fn decode_root_hash(root_hash: &str) -> Result<RootHash> {
+    let bytes = hex::decode(root_hash)
+        .map_err(|err| Error::Other(format!("invalid root hash {root_hash}: {err}")))?;
+
+    let bytes: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| Error::Other(format!("invalid root hash length: {root_hash}")))?;
+
+    Ok(RootHash(bytes))
+}
@@ -1,14 +1,23 @@
-use primitives::{Address, PayloadHash, QuorumPublicKey, Signature};
+use std::collections::{HashMap, HashSet};
+
+use primitives::{Address, NodeId, PayloadHash, PublicKey, QuorumPublicKey, Signature};
 use secp256k1::Message;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use thiserror::Error;
 use utils::hash_data;
 
 use crate::keypair::{MinerPublicKey, MinerSecretKey};
 
-/// Represents a byte array that can be converted into a
-/// ThresholdSignature
-pub type Certificate = (Vec<u8>, PayloadHash);
+/// Signatures of quorum members over a stake's payload, attesting that the
+/// quorum approved the stake update.
+///
+/// Certificates used to be a single signature paired with the hash of the
+/// payload it signed. Stakes carrying one are still read from self-describing
+/// formats, such as the JSON gossiped between nodes and kept in the mempool
+/// store, but as uncertified since that signature was never tied to a quorum.
+/// Binary encodings of the old format cannot be read, which is why the
+/// storage version of `vrrbdb` was bumped along with this change.
+pub type Certificate = Vec<(NodeId, Signature)>;
 pub const MIN_STAKE_FARMER: u128 = 10_000;
 pub const MIN_STAKE_VALIDATOR: u128 = 50_000;
 
@@ -24,10 +33,20 @@ pub enum StakeError {
     UncertifiedStake,
     #[error("StakError: The certificate is invalid")]
     InvalidCertificate,
+    #[error("StakeError: The certificate has {0} valid signatures, {1} are required")]
+    CertificateBelowThreshold(usize, usize),
     #[error("StakeError: {0}")]
     Other(String),
 }
 
+/// The quorum whose members certify stake updates, and how many of its
+/// members must sign a certificate for it to be valid
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CertifyingQuorum {
+    pub members: HashMap<NodeId, PublicKey>,
+    pub threshold: usize,
+}
+
 /// Provides an enum with the 3 different types of StakeUpdates that
 /// are possible, and a inner value which is the amount (for Add and
 /// Withdrawal variants) and the percent to slash (for Slash) variant.
@@ -87,9 +106,36 @@ pub struct Stake {
     timestamp: i64,
     signature: Signature,
     validator_quorum_key: QuorumPublicKey,
+    #[serde(default, deserialize_with = "deserialize_certificate")]
     certificate: Option<Certificate>,
 }
 
+/// The certificate formats a stake may have been serialized with
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CertificateFormat {
+    Current(Certificate),
+    Legacy((Vec<u8>, PayloadHash)),
+}
+
+/// Reads a stake's certificate, dropping certificates of the legacy format
+/// from formats able to tell them apart
+fn deserialize_certificate<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<Certificate>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    if !deserializer.is_human_readable() {
+        return Option::<Certificate>::deserialize(deserializer);
+    }
+
+    match Option::<CertificateFormat>::deserialize(deserializer)? {
+        Some(CertificateFormat::Current(certificate)) => Ok(Some(certificate)),
+        Some(CertificateFormat::Legacy(_)) | None => Ok(None),
+    }
+}
+
 impl Stake {
     pub const MAX: u128 = 100_000;
     pub const MIN: u128 = 10_000;
@@ -203,9 +249,8 @@ impl Stake {
     }
 
     /// Returns the instances certificate, if there is one.
-    /// The certificate is a Threshold Signature that Farmer
-    /// nodes use to ensure a given threshold of validators have
-    /// agreed upon the validity of a given transaction, in this
+    /// The certificate holds the signatures of the quorum members
+    /// that agreed upon the validity of a given transaction, in this
     /// case, the Stake transaction instance
     pub fn get_certificate(&self) -> Option<Certificate> {
         self.certificate.clone()
@@ -213,8 +258,7 @@ impl Stake {
 
     /// Adds a certificate to the instance.
     pub fn certify(&mut self, certificate: Certificate) -> Result<()> {
-        const VALID_CERTIFICATE_LENGTH: usize = 96;
-        if certificate.0.len() != VALID_CERTIFICATE_LENGTH {
+        if certificate.is_empty() {
             return Err(StakeError::InvalidCertificate);
         }
 
@@ -223,6 +267,44 @@ impl Stake {
         Ok(())
     }
 
+    /// Verifies that the certificate holds valid signatures over the stake's
+    /// payload from at least `threshold` distinct members of `quorum`.
+    /// Holding a certificate proves nothing until it is verified against the
+    /// quorum currently certifying stake updates.
+    pub fn verify_certificate(&self, quorum: &CertifyingQuorum) -> Result<()> {
+        let certificate = self
+            .certificate
+            .as_ref()
+            .ok_or(StakeError::UncertifiedStake)?;
+        let message =
+            Message::from_slice(&self.get_payload()).map_err(|_| StakeError::InvalidPayload)?;
+
+        let mut signers = HashSet::new();
+        for (node_id, signature) in certificate {
+            let public_key = quorum
+                .members
+                .get(node_id)
+                .ok_or(StakeError::InvalidCertificate)?;
+            if !signers.insert(node_id) {
+                return Err(StakeError::InvalidCertificate);
+            }
+
+            signature
+                .verify(&message, public_key)
+                .map_err(|_| StakeError::InvalidCertificate)?;
+        }
+
+        let required = quorum.threshold.max(1);
+        if signers.len() < required {
+            return Err(StakeError::CertificateBelowThreshold(
+                signers.len(),
+                required,
+            ));
+        }
+
+        Ok(())
+    }
+
     /// Verifies the signature of the StakeTransaction
     pub fn verify(&self) -> Result<()> {
         let payload = self.get_payload();
@@ -268,7 +350,8 @@ mod tests {
 
         let mut stake = Stake::new(amount, sk, pk, from, None).unwrap();
 
-        stake.certify((vec![0u8; 96], vec![0u8; 32])).unwrap();
+        let signature = stake.get_signature();
+        stake.certify(vec![(NodeId::default(), signature)]).unwrap();
 
         assert!(stake.get_certificate().is_some());
     }
@@ -324,9 +407,125 @@ mod tests {
 
         let mut stake = Stake::new(amount, sk, pk, from, None).unwrap();
 
-        let result = stake.certify((vec![0u8; 32], vec![0u8; 32]));
+        let result = stake.certify(vec![]);
 
         assert!(result.is_err());
         assert!(stake.get_certificate().is_none());
     }
+
+    fn certifying_quorum(
+        size: usize,
+        threshold: usize,
+    ) -> (Vec<(NodeId, KeyPair)>, CertifyingQuorum) {
+        let members: Vec<(NodeId, KeyPair)> = (0..size)
+            .map(|idx| (format!("node-{idx}"), KeyPair::random()))
+            .collect();
+        let quorum = CertifyingQuorum {
+            members: members
+                .iter()
+                .map(|(node_id, kp)| (node_id.clone(), kp.miner_kp.1))
+                .collect(),
+            threshold,
+        };
+
+        (members, quorum)
+    }
+
+    fn sign_stake(stake: &Stake, node_id: &NodeId, kp: &KeyPair) -> (NodeId, Signature) {
+        let message = Message::from_slice(&stake.get_payload()).unwrap();
+
+        (node_id.clone(), kp.miner_kp.0.sign_ecdsa(message))
+    }
+
+    #[test]
+    fn should_verify_certificate_against_quorum() {
+        let keypair = KeyPair::random();
+        let from = Address::new(keypair.miner_kp.1);
+        let mut stake = Stake::new(
+            StakeUpdate::Add(10_000u128),
+            keypair.miner_kp.0,
+            keypair.miner_kp.1,
+            from.clone(),
+            None,
+        )
+        .unwrap();
+        let (members, quorum) = certifying_quorum(3, 2);
+
+        assert_eq!(
+            stake.verify_certificate(&quorum),
+            Err(StakeError::UncertifiedStake)
+        );
+
+        let first = sign_stake(&stake, &members[0].0, &members[0].1);
+        let second = sign_stake(&stake, &members[1].0, &members[1].1);
+
+        stake.certify(vec![first.clone()]).unwrap();
+        assert_eq!(
+            stake.verify_certificate(&quorum),
+            Err(StakeError::CertificateBelowThreshold(1, 2))
+        );
+
+        stake.certify(vec![first.clone(), first.clone()]).unwrap();
+        assert_eq!(
+            stake.verify_certificate(&quorum),
+            Err(StakeError::InvalidCertificate)
+        );
+
+        let outsider = sign_stake(&stake, &"outsider".to_string(), &KeyPair::random());
+        stake.certify(vec![first.clone(), outsider]).unwrap();
+        assert_eq!(
+            stake.verify_certificate(&quorum),
+            Err(StakeError::InvalidCertificate)
+        );
+
+        stake.certify(vec![first.clone(), second.clone()]).unwrap();
+        assert!(stake.verify_certificate(&quorum).is_ok());
+
+        // A certificate only vouches for the stake it was issued for
+        let mut other = Stake::new(
+            StakeUpdate::Withdrawal(10_000u128),
+            keypair.miner_kp.0,
+            keypair.miner_kp.1,
+            from,
+            None,
+        )
+        .unwrap();
+        other.certify(vec![first, second]).unwrap();
+        assert_eq!(
+            other.verify_certificate(&quorum),
+            Err(StakeError::InvalidCertificate)
+        );
+    }
+
+    #[test]
+    fn should_read_stakes_with_legacy_certificates_as_uncertified() {
+        let keypair = KeyPair::random();
+        let mut stake = Stake::new(
+            StakeUpdate::Add(10_000u128),
+            keypair.miner_kp.0,
+            keypair.miner_kp.1,
+            Address::new(keypair.miner_kp.1),
+            None,
+        )
+        .unwrap();
+        let signature = stake.get_signature();
+        stake
+            .certify(vec![("harvester-0".to_string(), signature)])
+            .unwrap();
+
+        let json = serde_json::to_value(&stake).unwrap();
+        assert_eq!(
+            serde_json::from_value::<Stake>(json.clone()).unwrap(),
+            stake
+        );
+
+        let bytes = bincode::serialize(&stake).unwrap();
+        assert_eq!(bincode::deserialize::<Stake>(&bytes).unwrap(), stake);
+
+        let mut legacy = json;
+        legacy["certificate"] = serde_json::json!([[1, 2, 3], [4, 5, 6]]);
+        let legacy = serde_json::from_value::<Stake>(legacy).unwrap();
+        assert!(legacy.get_certificate().is_none());
+        assert_eq!(legacy.get_signature(), stake.get_signature());
+    }
 }
@@ -0,0 +1,366 @@
+use std::{
+    collections::HashMap,
+    hash::{Hash, Hasher},
+};
+
+use primitives::{Address, ByteVec, PublicKey, SecretKey};
+use secp256k1::{ecdsa::Signature, Message};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utils::hash_data;
+
+use crate::transactions::transaction::Transaction;
+use crate::transactions::transfer::default_fee;
+use crate::transactions::{
+    Token, TransactionDigest, TransactionKind, TxAmount, TxFee, TxNonce, TxTimestamp, BASE_FEE,
+};
+
+/// What a `ContractTransaction` does once it is applied to state
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum ContractAction {
+    /// Deploys the smart contract packaged as the `web3_pkg` package with
+    /// the given CID to a new contract account
+    Deploy { package_cid: String },
+    /// Invokes a deployed contract, passing `input` to it as JSON
+    Call {
+        contract_address: Address,
+        input: String,
+    },
+}
+
+/// Version of the gas schedule contract transactions that predate schedule
+/// versioning are metered with
+pub const LEGACY_GAS_SCHEDULE_VERSION: u32 = 1;
+
+fn legacy_gas_schedule_version() -> u32 {
+    LEGACY_GAS_SCHEDULE_VERSION
+}
+
+pub fn generate_contract_digest_vec(
+    timestamp: TxTimestamp,
+    sender_address: String,
+    sender_public_key: PublicKey,
+    action: &ContractAction,
+    amount: TxAmount,
+    meter_limit: u64,
+    gas_schedule_version: u32,
+    nonce: TxNonce,
+    fee: TxFee,
+) -> ByteVec {
+    let payload_string = format!(
+        "{},{},{},{:?},{},{},{},{},{}",
+        &timestamp,
+        &sender_address,
+        &sender_public_key,
+        action,
+        &amount,
+        &meter_limit,
+        &gas_schedule_version,
+        &nonce,
+        &fee
+    );
+
+    let mut hasher = Sha256::new();
+    hasher.update(payload_string);
+    let hash = hasher.finalize();
+
+    hash.to_vec()
+}
+
+/// Derives the address a contract deployed by `sender_address` with the
+/// given nonce lives at
+pub fn contract_address(sender_address: &Address, nonce: TxNonce) -> Address {
+    let mut hasher = Sha256::new();
+    hasher.update(sender_address.raw_address());
+    hasher.update(nonce.to_be_bytes());
+    let hash = hasher.finalize();
+
+    let mut address_bytes = [0u8; 20];
+    address_bytes.copy_from_slice(&hash[(hash.len() - 20)..]);
+
+    Address(address_bytes)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewContractTransactionArgs {
+    pub timestamp: TxTimestamp,
+    pub sender_address: Address,
+    pub sender_public_key: PublicKey,
+    pub action: ContractAction,
+    pub amount: TxAmount,
+    pub meter_limit: u64,
+    #[serde(default = "legacy_gas_schedule_version")]
+    pub gas_schedule_version: u32,
+    pub signature: Signature,
+    pub validators: Option<HashMap<String, bool>>,
+    pub nonce: TxNonce,
+    #[serde(default)]
+    pub fee: Option<TxFee>,
+}
+
+/// Deploys or calls a WASM smart contract. `amount` is transferred from the
+/// sender to the contract account and `meter_limit` caps the metering points
+/// the contract may consume while it runs, charged according to the gas
+/// schedule published under `gas_schedule_version`.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq)]
+pub struct ContractTransaction {
+    pub id: TransactionDigest,
+    pub timestamp: TxTimestamp,
+    pub sender_address: Address,
+    pub sender_public_key: PublicKey,
+    pub action: ContractAction,
+    pub amount: TxAmount,
+    pub meter_limit: u64,
+    #[serde(default = "legacy_gas_schedule_version")]
+    pub gas_schedule_version: u32,
+    pub signature: Signature,
+    pub validators: Option<HashMap<String, bool>>,
+    pub nonce: TxNonce,
+    #[serde(default = "default_fee")]
+    pub fee: TxFee,
+}
+
+impl ContractTransaction {
+    pub fn new(args: NewContractTransactionArgs) -> Self {
+        let digest_vec = generate_contract_digest_vec(
+            args.timestamp,
+            args.sender_address.to_string(),
+            args.sender_public_key,
+            &args.action,
+            args.amount,
+            args.meter_limit,
+            args.gas_schedule_version,
+            args.nonce,
+            args.fee.unwrap_or(BASE_FEE),
+        );
+
+        Self {
+            id: TransactionDigest::from(digest_vec),
+            timestamp: args.timestamp,
+            sender_address: args.sender_address,
+            sender_public_key: args.sender_public_key,
+            action: args.action,
+            amount: args.amount,
+            meter_limit: args.meter_limit,
+            gas_schedule_version: args.gas_schedule_version,
+            signature: args.signature,
+            validators: args.validators,
+            nonce: args.nonce,
+            fee: args.fee.unwrap_or(BASE_FEE),
+        }
+    }
+
+    pub fn action(&self) -> &ContractAction {
+        &self.action
+    }
+
+    /// Returns the address of the contract this transaction deploys or calls
+    pub fn contract_address(&self) -> Address {
+        match &self.action {
+            ContractAction::Deploy { .. } => contract_address(&self.sender_address, self.nonce),
+            ContractAction::Call {
+                contract_address, ..
+            } => contract_address.clone(),
+        }
+    }
+
+    pub fn generate_txn_digest_vec(&self) -> ByteVec {
+        generate_contract_digest_vec(
+            self.timestamp,
+            self.sender_address.to_string(),
+            self.sender_public_key,
+            &self.action,
+            self.amount,
+            self.meter_limit,
+            self.gas_schedule_version,
+            self.nonce,
+            self.fee,
+        )
+    }
+}
+
+impl From<ContractTransaction> for TransactionKind {
+    fn from(txn: ContractTransaction) -> Self {
+        match txn.action {
+            ContractAction::Deploy { .. } => TransactionKind::Deploy(txn),
+            ContractAction::Call { .. } => TransactionKind::Call(txn),
+        }
+    }
+}
+
+impl Transaction for ContractTransaction {
+    fn id(&self) -> TransactionDigest {
+        self.id.clone()
+    }
+
+    fn timestamp(&self) -> TxTimestamp {
+        self.timestamp
+    }
+
+    fn sender_address(&self) -> Address {
+        self.sender_address.clone()
+    }
+
+    fn sender_public_key(&self) -> PublicKey {
+        self.sender_public_key
+    }
+
+    /// The address of the contract being deployed or called
+    fn receiver_address(&self) -> Address {
+        self.contract_address()
+    }
+
+    fn token(&self) -> Token {
+        Token::default()
+    }
+
+    fn amount(&self) -> TxAmount {
+        self.amount
+    }
+
+    fn signature(&self) -> Signature {
+        self.signature
+    }
+
+    fn validators(&self) -> Option<HashMap<String, bool>> {
+        self.validators.clone()
+    }
+
+    fn nonce(&self) -> TxNonce {
+        self.nonce
+    }
+
+    fn fee(&self) -> u128 {
+        self.fee
+    }
+
+    fn validator_fee_share(&self) -> u128 {
+        self.fee / 2u128
+    }
+
+    fn proposer_fee_share(&self) -> u128 {
+        self.fee - self.validator_fee_share()
+    }
+
+    fn build_payload(&self) -> String {
+        format!(
+            "{:x}",
+            hash_data!(
+                self.sender_address.clone(),
+                self.sender_public_key.clone(),
+                self.action.clone(),
+                self.amount,
+                self.meter_limit,
+                self.gas_schedule_version,
+                self.nonce.clone(),
+                self.fee
+            )
+        )
+    }
+
+    fn digest(&self) -> TransactionDigest {
+        self.id()
+    }
+
+    fn sign(&mut self, sk: &SecretKey) {
+        let mut hasher = sha2::Sha256::new();
+        hasher.update(self.build_payload().as_bytes());
+        let result = hasher.finalize().to_vec();
+        let message = Message::from_slice(&result);
+        if let Ok(msg) = message {
+            let sig = sk.sign_ecdsa(msg);
+            self.signature = sig;
+        }
+    }
+}
+
+/// Hashes the fields the transaction digest covers, so that contract
+/// transactions that compare equal hash the same
+impl Hash for ContractTransaction {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.timestamp.hash(state);
+        self.sender_address.hash(state);
+        self.sender_public_key.hash(state);
+        self.action.hash(state);
+        self.amount.hash(state);
+        self.meter_limit.hash(state);
+        self.gas_schedule_version.hash(state);
+        self.nonce.hash(state);
+        self.fee.hash(state);
+    }
+}
+
+impl PartialEq for ContractTransaction {
+    fn eq(&self, other: &Self) -> bool {
+        self.generate_txn_digest_vec() == other.generate_txn_digest_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keypair::KeyPair;
+
+    use super::*;
+
+    fn contract_txn(action: ContractAction, nonce: TxNonce) -> ContractTransaction {
+        let keypair = KeyPair::random();
+        let (sk, pk) = keypair.miner_kp;
+        let address = Address::new(pk);
+
+        let mut txn = ContractTransaction::new(NewContractTransactionArgs {
+            timestamp: chrono::Utc::now().timestamp(),
+            sender_address: address,
+            sender_public_key: pk,
+            action,
+            amount: 0,
+            meter_limit: 1_000_000,
+            gas_schedule_version: LEGACY_GAS_SCHEDULE_VERSION,
+            signature: sk
+                .sign_ecdsa(Message::from_hashed_data::<secp256k1::hashes::sha256::Hash>(b"vrrb")),
+            validators: None,
+            nonce,
+            fee: None,
+        });
+        txn.sign(&sk);
+
+        txn
+    }
+
+    #[test]
+    fn contract_transactions_map_to_their_transaction_kind() {
+        let deploy = contract_txn(
+            ContractAction::Deploy {
+                package_cid: "bafyreihk6fv2kh2fq4y3tnahwvsxmz3n3zdugkfwvgldhxwm4f7jh5zbey"
+                    .to_string(),
+            },
+            1,
+        );
+        let call = contract_txn(
+            ContractAction::Call {
+                contract_address: deploy.contract_address(),
+                input: "{}".to_string(),
+            },
+            1,
+        );
+
+        assert!(matches!(
+            TransactionKind::from(deploy.clone()),
+            TransactionKind::Deploy(_)
+        ));
+        assert!(matches!(
+            TransactionKind::from(call.clone()),
+            TransactionKind::Call(_)
+        ));
+        assert_eq!(call.receiver_address(), deploy.contract_address());
+    }
+
+    #[test]
+    fn deployments_get_a_new_address_per_nonce() {
+        let sender = Address::new(KeyPair::random().miner_kp.1);
+
+        assert_ne!(contract_address(&sender, 1), contract_address(&sender, 2));
+        assert_ne!(contract_address(&sender, 1), sender);
+        assert_eq!(contract_address(&sender, 1), contract_address(&sender, 1));
+    }
+}
@@ -1,7 +1,15 @@
+pub mod contract;
+pub mod receipt;
+pub mod stake;
 pub mod transaction;
 pub mod transaction_kind;
 pub mod transfer;
+pub mod unsigned;
 
+pub use contract::*;
+pub use receipt::*;
+pub use stake::*;
 pub use transaction::*;
 pub use transaction_kind::*;
 pub use transfer::*;
+pub use unsigned::*;
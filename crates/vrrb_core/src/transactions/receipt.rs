@@ -0,0 +1,35 @@
+use primitives::Address;
+use serde::{Deserialize, Serialize};
+
+use crate::transactions::TransactionDigest;
+
+/// Outcome of applying a contract transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReceiptStatus {
+    Success,
+    /// The transaction was included but reverted, only its fee was charged
+    Failed(String),
+}
+
+/// Record of what a contract deployment or call did once it was applied,
+/// kept so clients can look it up by the transaction's digest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionReceipt {
+    pub txn_id: TransactionDigest,
+    pub block_height: u128,
+    pub status: ReceiptStatus,
+    pub contract_address: Address,
+    /// Whatever the contract returned as its result, if anything
+    pub output: Option<String>,
+    /// Metering points consumed while running the contract
+    pub points_used: u64,
+    pub fee: u128,
+}
+
+impl TransactionReceipt {
+    pub fn is_success(&self) -> bool {
+        self.status == ReceiptStatus::Success
+    }
+}
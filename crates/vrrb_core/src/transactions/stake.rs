@@ -0,0 +1,311 @@
+use std::{
+    collections::HashMap,
+    hash::{Hash, Hasher},
+};
+
+use primitives::{Address, ByteVec, PublicKey, SecretKey};
+use secp256k1::{ecdsa::Signature, Message};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utils::hash_data;
+
+use crate::staking::{Stake, StakeUpdate};
+use crate::transactions::transaction::Transaction;
+use crate::transactions::transfer::default_fee;
+use crate::transactions::{
+    Token, TransactionDigest, TransactionKind, TxAmount, TxFee, TxNonce, TxTimestamp, BASE_FEE,
+};
+
+/// The role a `StakeTransaction` plays on chain. It is derived from the
+/// wrapped `Stake` so a transaction can never claim to be something its
+/// stake update is not.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum StakeTransactionKind {
+    /// Posts stake to the sender's own claim
+    Stake,
+    /// Withdraws the stake the sender posted to or delegated to a claim
+    Unstake,
+    /// Posts stake to a claim owned by another node
+    Delegate,
+    /// Slashes a percentage of a claim's stake
+    Slash,
+}
+
+pub fn generate_stake_digest_vec(
+    timestamp: TxTimestamp,
+    sender_address: String,
+    sender_public_key: PublicKey,
+    stake_payload: ByteVec,
+    nonce: TxNonce,
+    fee: TxFee,
+) -> ByteVec {
+    let payload_string = format!(
+        "{},{},{},{},{},{}",
+        &timestamp,
+        &sender_address,
+        &sender_public_key,
+        hex::encode(stake_payload),
+        &nonce,
+        &fee
+    );
+
+    let mut hasher = Sha256::new();
+    hasher.update(payload_string);
+    let hash = hasher.finalize();
+
+    hash.to_vec()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewStakeTransactionArgs {
+    pub timestamp: TxTimestamp,
+    pub sender_address: Address,
+    pub sender_public_key: PublicKey,
+    pub stake: Stake,
+    pub signature: Signature,
+    pub validators: Option<HashMap<String, bool>>,
+    pub nonce: TxNonce,
+    #[serde(default)]
+    pub fee: Option<TxFee>,
+}
+
+/// Wraps a `Stake` so that staking, unstaking, delegation and slashing
+/// travel through the mempool, validators and blocks like any other
+/// transaction.
+#[derive(Clone, Debug, Serialize, Deserialize, Eq)]
+pub struct StakeTransaction {
+    pub id: TransactionDigest,
+    pub timestamp: TxTimestamp,
+    pub sender_address: Address,
+    pub sender_public_key: PublicKey,
+    pub stake: Stake,
+    pub signature: Signature,
+    pub validators: Option<HashMap<String, bool>>,
+    pub nonce: TxNonce,
+    #[serde(default = "default_fee")]
+    pub fee: TxFee,
+}
+
+impl StakeTransaction {
+    pub fn new(args: NewStakeTransactionArgs) -> Self {
+        let digest_vec = generate_stake_digest_vec(
+            args.timestamp,
+            args.sender_address.to_string(),
+            args.sender_public_key,
+            args.stake.get_payload(),
+            args.nonce,
+            args.fee.unwrap_or(BASE_FEE),
+        );
+
+        Self {
+            id: TransactionDigest::from(digest_vec),
+            timestamp: args.timestamp,
+            sender_address: args.sender_address,
+            sender_public_key: args.sender_public_key,
+            stake: args.stake,
+            signature: args.signature,
+            validators: args.validators,
+            nonce: args.nonce,
+            fee: args.fee.unwrap_or(BASE_FEE),
+        }
+    }
+
+    /// Returns the wrapped stake
+    pub fn stake(&self) -> &Stake {
+        &self.stake
+    }
+
+    /// Returns the kind of stake transaction this is, based on the wrapped
+    /// `StakeUpdate` and whether the stake targets another node's claim.
+    pub fn kind(&self) -> StakeTransactionKind {
+        match self.stake.get_amount() {
+            StakeUpdate::Add(_) if self.stake.get_receiver() != self.stake.get_sender() => {
+                StakeTransactionKind::Delegate
+            }
+            StakeUpdate::Add(_) => StakeTransactionKind::Stake,
+            StakeUpdate::Withdrawal(_) => StakeTransactionKind::Unstake,
+            StakeUpdate::Slash(_) => StakeTransactionKind::Slash,
+        }
+    }
+
+    pub fn generate_txn_digest_vec(&self) -> ByteVec {
+        generate_stake_digest_vec(
+            self.timestamp,
+            self.sender_address.to_string(),
+            self.sender_public_key,
+            self.stake.get_payload(),
+            self.nonce,
+            self.fee,
+        )
+    }
+}
+
+impl From<StakeTransaction> for TransactionKind {
+    fn from(txn: StakeTransaction) -> Self {
+        match txn.kind() {
+            StakeTransactionKind::Stake => TransactionKind::Stake(txn),
+            StakeTransactionKind::Unstake => TransactionKind::Unstake(txn),
+            StakeTransactionKind::Delegate => TransactionKind::Delegate(txn),
+            StakeTransactionKind::Slash => TransactionKind::Slash(txn),
+        }
+    }
+}
+
+impl Transaction for StakeTransaction {
+    fn id(&self) -> TransactionDigest {
+        self.id.clone()
+    }
+
+    fn timestamp(&self) -> TxTimestamp {
+        self.timestamp
+    }
+
+    fn sender_address(&self) -> Address {
+        self.sender_address.clone()
+    }
+
+    fn sender_public_key(&self) -> PublicKey {
+        self.sender_public_key
+    }
+
+    /// The address of the claim whose stake is being updated
+    fn receiver_address(&self) -> Address {
+        self.stake.get_receiver()
+    }
+
+    fn token(&self) -> Token {
+        Token::default()
+    }
+
+    /// The amount of tokens being staked or withdrawn. Slashing is expressed
+    /// as a percentage of the claim's stake so it never moves tokens directly.
+    fn amount(&self) -> TxAmount {
+        match self.stake.get_amount() {
+            StakeUpdate::Add(amount) | StakeUpdate::Withdrawal(amount) => amount,
+            StakeUpdate::Slash(_) => 0,
+        }
+    }
+
+    fn signature(&self) -> Signature {
+        self.signature
+    }
+
+    fn validators(&self) -> Option<HashMap<String, bool>> {
+        self.validators.clone()
+    }
+
+    fn nonce(&self) -> TxNonce {
+        self.nonce
+    }
+
+    fn fee(&self) -> u128 {
+        self.fee
+    }
+
+    fn validator_fee_share(&self) -> u128 {
+        self.fee / 2u128
+    }
+
+    fn proposer_fee_share(&self) -> u128 {
+        self.fee - self.validator_fee_share()
+    }
+
+    fn build_payload(&self) -> String {
+        format!(
+            "{:x}",
+            hash_data!(
+                self.sender_address.clone(),
+                self.sender_public_key.clone(),
+                self.stake.get_payload(),
+                self.nonce.clone(),
+                self.fee
+            )
+        )
+    }
+
+    fn digest(&self) -> TransactionDigest {
+        self.id()
+    }
+
+    fn sign(&mut self, sk: &SecretKey) {
+        let mut hasher = sha2::Sha256::new();
+        hasher.update(self.build_payload().as_bytes());
+        let result = hasher.finalize().to_vec();
+        let message = Message::from_slice(&result);
+        if let Ok(msg) = message {
+            let sig = sk.sign_ecdsa(msg);
+            self.signature = sig;
+        }
+    }
+}
+
+/// Hashes the fields the transaction digest covers, so that stake
+/// transactions that compare equal hash the same
+impl Hash for StakeTransaction {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.timestamp.hash(state);
+        self.sender_address.hash(state);
+        self.sender_public_key.hash(state);
+        self.stake.get_payload().hash(state);
+        self.nonce.hash(state);
+        self.fee.hash(state);
+    }
+}
+
+impl PartialEq for StakeTransaction {
+    fn eq(&self, other: &Self) -> bool {
+        self.generate_txn_digest_vec() == other.generate_txn_digest_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::keypair::KeyPair;
+
+    use super::*;
+
+    fn stake_txn(amount: StakeUpdate, to: Option<Address>) -> StakeTransaction {
+        let keypair = KeyPair::random();
+        let (sk, pk) = keypair.miner_kp;
+        let address = Address::new(pk);
+        let stake = Stake::new(amount, sk, pk, address.clone(), to).unwrap();
+
+        let mut txn = StakeTransaction::new(NewStakeTransactionArgs {
+            timestamp: chrono::Utc::now().timestamp(),
+            sender_address: address,
+            sender_public_key: pk,
+            stake: stake.clone(),
+            signature: stake.get_signature(),
+            validators: None,
+            nonce: 1,
+            fee: None,
+        });
+        txn.sign(&sk);
+
+        txn
+    }
+
+    #[test]
+    fn stake_transactions_map_to_their_transaction_kind() {
+        let delegate_to = Address::new(KeyPair::random().miner_kp.1);
+
+        let stake = TransactionKind::from(stake_txn(StakeUpdate::Add(10_000), None));
+        let unstake = TransactionKind::from(stake_txn(StakeUpdate::Withdrawal(5_000), None));
+        let delegate =
+            TransactionKind::from(stake_txn(StakeUpdate::Add(10_000), Some(delegate_to)));
+        let slash = TransactionKind::from(stake_txn(StakeUpdate::Slash(25), None));
+
+        assert!(matches!(stake, TransactionKind::Stake(_)));
+        assert!(matches!(unstake, TransactionKind::Unstake(_)));
+        assert!(matches!(delegate, TransactionKind::Delegate(_)));
+        assert!(matches!(slash, TransactionKind::Slash(_)));
+    }
+
+    #[test]
+    fn slashing_does_not_move_tokens() {
+        let txn = stake_txn(StakeUpdate::Slash(25), None);
+
+        assert_eq!(txn.amount(), 0);
+        assert_eq!(txn.receiver_address(), txn.sender_address());
+    }
+}
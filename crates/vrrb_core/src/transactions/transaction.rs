@@ -13,6 +13,8 @@ use std::hash::Hash;
 use std::str::FromStr;
 
 pub const BASE_FEE: u128 = 0x2D79883D2000;
+/// The lowest fee a transaction can pay and still be accepted by validators
+pub const MIN_FEE: u128 = BASE_FEE;
 
 pub trait Transaction {
     fn id(&self) -> TransactionDigest;
@@ -211,9 +213,20 @@ pub const TRANSACTION_DIGEST_LENGTH: usize = DIGEST_LENGTH;
 
 #[cfg(test)]
 mod tests {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
     use super::*;
+    use crate::keypair::KeyPair;
+    use crate::multisig::MultisigSignature;
     use crate::transactions::{TransactionDigest, Transfer};
 
+    fn hash_of(txn: &Transfer) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        txn.hash(&mut hasher);
+        hasher.finish()
+    }
+
     #[test]
     fn test_txn_digest_serde() {
         let txn = Transfer::default();
@@ -225,4 +238,33 @@ mod tests {
 
         assert_eq!(txn_digest, txn_digest_recovered);
     }
+
+    #[test]
+    fn transactions_differing_only_in_fee_are_distinct() {
+        let txn = Transfer::default();
+        let mut bumped = txn.clone();
+        bumped.fee += 1;
+
+        assert_ne!(
+            txn.generate_txn_digest_vec(),
+            bumped.generate_txn_digest_vec()
+        );
+        assert_ne!(txn, bumped);
+    }
+
+    #[test]
+    fn cosignatures_do_not_change_transaction_identity() {
+        let txn = Transfer::default();
+        let mut cosigned = txn.clone();
+        let cosigner = KeyPair::random();
+        cosigned.cosignatures = vec![MultisigSignature::sign(
+            &cosigned.build_payload(),
+            cosigner.get_miner_secret_key(),
+        )
+        .unwrap()];
+
+        assert_eq!(txn.id(), cosigned.id());
+        assert_eq!(txn, cosigned);
+        assert_eq!(hash_of(&txn), hash_of(&cosigned));
+    }
 }
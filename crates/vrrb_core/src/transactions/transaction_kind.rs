@@ -1,6 +1,7 @@
+use crate::multisig::{MultisigPolicy, MultisigSignature};
 use crate::transactions::{
-    Token, Transaction, TransactionDigest, Transfer, TransferBuilder, TxAmount, TxNonce,
-    TxTimestamp,
+    ContractTransaction, StakeTransaction, Token, Transaction, TransactionDigest, Transfer,
+    TransferBuilder, TxAmount, TxNonce, TxTimestamp,
 };
 use primitives::{Address, PublicKey, SecretKey, Signature};
 use serde::{Deserialize, Serialize};
@@ -9,12 +10,71 @@ use std::collections::HashMap;
 #[derive(Hash, Debug, Deserialize, Clone, Serialize, Eq, PartialEq)]
 pub enum TransactionKind {
     Transfer(Transfer),
+    Stake(StakeTransaction),
+    Unstake(StakeTransaction),
+    Delegate(StakeTransaction),
+    Slash(StakeTransaction),
+    Deploy(ContractTransaction),
+    Call(ContractTransaction),
 }
 
 impl TransactionKind {
     pub fn transfer_builder() -> TransferBuilder {
         Transfer::builder()
     }
+
+    /// Returns the wrapped stake transaction if this is a staking,
+    /// unstaking, delegation or slashing transaction.
+    pub fn as_stake(&self) -> Option<&StakeTransaction> {
+        match self {
+            TransactionKind::Transfer(_)
+            | TransactionKind::Deploy(_)
+            | TransactionKind::Call(_) => None,
+            TransactionKind::Stake(txn)
+            | TransactionKind::Unstake(txn)
+            | TransactionKind::Delegate(txn)
+            | TransactionKind::Slash(txn) => Some(txn),
+        }
+    }
+
+    /// Returns the wrapped contract transaction if this deploys or calls a
+    /// smart contract.
+    pub fn as_contract(&self) -> Option<&ContractTransaction> {
+        match self {
+            TransactionKind::Deploy(txn) | TransactionKind::Call(txn) => Some(txn),
+            _ => None,
+        }
+    }
+
+    /// Returns the signatures of the other signers of a transfer sent from a
+    /// multisig account. Other transactions have a single signer.
+    pub fn cosignatures(&self) -> &[MultisigSignature] {
+        match self {
+            TransactionKind::Transfer(transfer) => &transfer.cosignatures,
+            _ => &[],
+        }
+    }
+
+    /// Returns the policy of the multisig account a transfer creates at its
+    /// receiver address, if it creates one
+    pub fn multisig_policy(&self) -> Option<&MultisigPolicy> {
+        match self {
+            TransactionKind::Transfer(transfer) => transfer.multisig_policy.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Returns the sender's signature along with the cosignatures, as checked
+    /// against the policy of a multisig sender.
+    pub fn signatures(&self) -> Vec<MultisigSignature> {
+        let mut signatures = vec![MultisigSignature {
+            public_key: self.sender_public_key(),
+            signature: self.signature(),
+        }];
+        signatures.extend_from_slice(self.cosignatures());
+
+        signatures
+    }
 }
 
 impl Default for TransactionKind {
@@ -27,96 +87,176 @@ impl Transaction for TransactionKind {
     fn id(&self) -> TransactionDigest {
         match self {
             TransactionKind::Transfer(transfer) => transfer.id(),
+            TransactionKind::Stake(txn)
+            | TransactionKind::Unstake(txn)
+            | TransactionKind::Delegate(txn)
+            | TransactionKind::Slash(txn) => txn.id(),
+            TransactionKind::Deploy(txn) | TransactionKind::Call(txn) => txn.id(),
         }
     }
 
     fn timestamp(&self) -> TxTimestamp {
         match self {
             TransactionKind::Transfer(transfer) => transfer.timestamp(),
+            TransactionKind::Stake(txn)
+            | TransactionKind::Unstake(txn)
+            | TransactionKind::Delegate(txn)
+            | TransactionKind::Slash(txn) => txn.timestamp(),
+            TransactionKind::Deploy(txn) | TransactionKind::Call(txn) => txn.timestamp(),
         }
     }
 
     fn sender_address(&self) -> Address {
         match self {
             TransactionKind::Transfer(transfer) => transfer.sender_address(),
+            TransactionKind::Stake(txn)
+            | TransactionKind::Unstake(txn)
+            | TransactionKind::Delegate(txn)
+            | TransactionKind::Slash(txn) => txn.sender_address(),
+            TransactionKind::Deploy(txn) | TransactionKind::Call(txn) => txn.sender_address(),
         }
     }
 
     fn sender_public_key(&self) -> PublicKey {
         match self {
             TransactionKind::Transfer(transfer) => transfer.sender_public_key(),
+            TransactionKind::Stake(txn)
+            | TransactionKind::Unstake(txn)
+            | TransactionKind::Delegate(txn)
+            | TransactionKind::Slash(txn) => txn.sender_public_key(),
+            TransactionKind::Deploy(txn) | TransactionKind::Call(txn) => txn.sender_public_key(),
         }
     }
 
     fn receiver_address(&self) -> Address {
         match self {
             TransactionKind::Transfer(transfer) => transfer.receiver_address(),
+            TransactionKind::Stake(txn)
+            | TransactionKind::Unstake(txn)
+            | TransactionKind::Delegate(txn)
+            | TransactionKind::Slash(txn) => txn.receiver_address(),
+            TransactionKind::Deploy(txn) | TransactionKind::Call(txn) => txn.receiver_address(),
         }
     }
 
     fn token(&self) -> Token {
         match self {
             TransactionKind::Transfer(transfer) => transfer.token(),
+            TransactionKind::Stake(txn)
+            | TransactionKind::Unstake(txn)
+            | TransactionKind::Delegate(txn)
+            | TransactionKind::Slash(txn) => txn.token(),
+            TransactionKind::Deploy(txn) | TransactionKind::Call(txn) => txn.token(),
         }
     }
 
     fn amount(&self) -> TxAmount {
         match self {
             TransactionKind::Transfer(transfer) => transfer.amount(),
+            TransactionKind::Stake(txn)
+            | TransactionKind::Unstake(txn)
+            | TransactionKind::Delegate(txn)
+            | TransactionKind::Slash(txn) => txn.amount(),
+            TransactionKind::Deploy(txn) | TransactionKind::Call(txn) => txn.amount(),
         }
     }
 
     fn signature(&self) -> Signature {
         match self {
             TransactionKind::Transfer(transfer) => transfer.signature(),
+            TransactionKind::Stake(txn)
+            | TransactionKind::Unstake(txn)
+            | TransactionKind::Delegate(txn)
+            | TransactionKind::Slash(txn) => txn.signature(),
+            TransactionKind::Deploy(txn) | TransactionKind::Call(txn) => txn.signature(),
         }
     }
 
     fn validators(&self) -> Option<HashMap<String, bool>> {
         match self {
             TransactionKind::Transfer(transfer) => transfer.validators(),
+            TransactionKind::Stake(txn)
+            | TransactionKind::Unstake(txn)
+            | TransactionKind::Delegate(txn)
+            | TransactionKind::Slash(txn) => txn.validators(),
+            TransactionKind::Deploy(txn) | TransactionKind::Call(txn) => txn.validators(),
         }
     }
 
     fn nonce(&self) -> TxNonce {
         match self {
             TransactionKind::Transfer(transfer) => transfer.nonce(),
+            TransactionKind::Stake(txn)
+            | TransactionKind::Unstake(txn)
+            | TransactionKind::Delegate(txn)
+            | TransactionKind::Slash(txn) => txn.nonce(),
+            TransactionKind::Deploy(txn) | TransactionKind::Call(txn) => txn.nonce(),
         }
     }
 
     fn fee(&self) -> u128 {
         match self {
             TransactionKind::Transfer(transfer) => transfer.fee(),
+            TransactionKind::Stake(txn)
+            | TransactionKind::Unstake(txn)
+            | TransactionKind::Delegate(txn)
+            | TransactionKind::Slash(txn) => txn.fee(),
+            TransactionKind::Deploy(txn) | TransactionKind::Call(txn) => txn.fee(),
         }
     }
 
     fn validator_fee_share(&self) -> u128 {
         match self {
             TransactionKind::Transfer(transfer) => transfer.validator_fee_share(),
+            TransactionKind::Stake(txn)
+            | TransactionKind::Unstake(txn)
+            | TransactionKind::Delegate(txn)
+            | TransactionKind::Slash(txn) => txn.validator_fee_share(),
+            TransactionKind::Deploy(txn) | TransactionKind::Call(txn) => txn.validator_fee_share(),
         }
     }
 
     fn proposer_fee_share(&self) -> u128 {
         match self {
             TransactionKind::Transfer(transfer) => transfer.proposer_fee_share(),
+            TransactionKind::Stake(txn)
+            | TransactionKind::Unstake(txn)
+            | TransactionKind::Delegate(txn)
+            | TransactionKind::Slash(txn) => txn.proposer_fee_share(),
+            TransactionKind::Deploy(txn) | TransactionKind::Call(txn) => txn.proposer_fee_share(),
         }
     }
 
     fn build_payload(&self) -> String {
         match self {
             TransactionKind::Transfer(transfer) => transfer.build_payload(),
+            TransactionKind::Stake(txn)
+            | TransactionKind::Unstake(txn)
+            | TransactionKind::Delegate(txn)
+            | TransactionKind::Slash(txn) => txn.build_payload(),
+            TransactionKind::Deploy(txn) | TransactionKind::Call(txn) => txn.build_payload(),
         }
     }
 
     fn digest(&self) -> TransactionDigest {
         match self {
             TransactionKind::Transfer(transfer) => transfer.id(),
+            TransactionKind::Stake(txn)
+            | TransactionKind::Unstake(txn)
+            | TransactionKind::Delegate(txn)
+            | TransactionKind::Slash(txn) => txn.id(),
+            TransactionKind::Deploy(txn) | TransactionKind::Call(txn) => txn.id(),
         }
     }
 
     fn sign(&mut self, sk: &SecretKey) {
         match self {
             TransactionKind::Transfer(transfer) => transfer.sign(sk),
+            TransactionKind::Stake(txn)
+            | TransactionKind::Unstake(txn)
+            | TransactionKind::Delegate(txn)
+            | TransactionKind::Slash(txn) => txn.sign(sk),
+            TransactionKind::Deploy(txn) | TransactionKind::Call(txn) => txn.sign(sk),
         }
     }
 }
@@ -11,6 +11,7 @@ use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use utils::hash_data;
 
+use crate::multisig::{MultisigPolicy, MultisigSignature};
 use crate::transactions::transaction::Transaction;
 use crate::transactions::{Token, TransactionDigest, TransactionKind, BASE_FEE};
 use crate::{
@@ -37,11 +38,24 @@ pub fn generate_transfer_digest_vec(
     token: Token,
     amount: TxAmount,
     nonce: TxNonce,
+    fee: TxFee,
+    multisig_policy: Option<&MultisigPolicy>,
 ) -> ByteVec {
-    let payload_string = format!(
-        "{},{},{},{},{},{:?},{}",
-        &timestamp, &sender_address, &sender_public_key, &receiver_address, &amount, &token, &nonce
+    let mut payload_string = format!(
+        "{},{},{},{},{},{:?},{},{}",
+        &timestamp,
+        &sender_address,
+        &sender_public_key,
+        &receiver_address,
+        &amount,
+        &token,
+        &nonce,
+        &fee
     );
+    // The address of a multisig policy commits to its keys and threshold
+    if let Some(policy) = multisig_policy {
+        payload_string.push_str(&format!(",{}", policy.address()));
+    }
 
     let mut hasher = Sha256::new();
     hasher.update(payload_string);
@@ -54,6 +68,11 @@ pub type TxNonce = u128;
 pub type TxTimestamp = i64;
 pub type TxAmount = u128;
 pub type TxSignature = Vec<u8>;
+pub type TxFee = u128;
+
+pub(crate) fn default_fee() -> TxFee {
+    BASE_FEE
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize, Eq)]
 pub struct Transfer {
@@ -67,6 +86,16 @@ pub struct Transfer {
     pub signature: Signature,
     pub validators: Option<HashMap<String, bool>>,
     pub nonce: TxNonce,
+    #[serde(default = "default_fee")]
+    pub fee: TxFee,
+    /// Signatures of the other signers of a transfer sent from a multisig
+    /// account. The sender public key's signature is the one above.
+    #[serde(default)]
+    pub cosignatures: Vec<MultisigSignature>,
+    /// Policy of the multisig account the transfer creates at its receiver
+    /// address, which must be the address of the policy
+    #[serde(default)]
+    pub multisig_policy: Option<MultisigPolicy>,
 }
 
 #[derive(Clone, Default)]
@@ -80,6 +109,9 @@ pub struct TransferBuilder {
     signature: Option<Signature>,
     validators: Option<HashMap<String, bool>>,
     nonce: Option<TxNonce>,
+    fee: Option<TxFee>,
+    cosignatures: Vec<MultisigSignature>,
+    multisig_policy: Option<MultisigPolicy>,
 }
 
 impl TransferBuilder {
@@ -126,7 +158,9 @@ impl TransferBuilder {
                 self.receiver_address.clone(),
                 self.token.clone(),
                 self.amount.clone(),
-                self.nonce.clone()
+                self.nonce.clone(),
+                self.fee.unwrap_or(BASE_FEE),
+                self.multisig_policy.clone()
             )
         )
     }
@@ -141,6 +175,27 @@ impl TransferBuilder {
         self
     }
 
+    /// Sets the fee paid to the proposer and validators. Defaults to
+    /// `BASE_FEE` when not set.
+    pub fn fee(mut self, fee: TxFee) -> Self {
+        self.fee = Some(fee);
+        self
+    }
+
+    /// Sets the signatures of the other signers of a transfer sent from a
+    /// multisig account
+    pub fn cosignatures(mut self, cosignatures: Vec<MultisigSignature>) -> Self {
+        self.cosignatures = cosignatures;
+        self
+    }
+
+    /// Creates the multisig account governed by `policy` at the receiver
+    /// address when the transfer is applied
+    pub fn multisig_policy(mut self, policy: MultisigPolicy) -> Self {
+        self.multisig_policy = Some(policy);
+        self
+    }
+
     pub fn build(self) -> Result<Transfer, &'static str> {
         let id = generate_transfer_digest_vec(
             self.timestamp.ok_or("timestamp is missing")?,
@@ -157,6 +212,8 @@ impl TransferBuilder {
             self.token.clone().unwrap_or_default(),
             self.amount.ok_or("amount is missing")?,
             self.nonce.ok_or("nonce is missing")?,
+            self.fee.unwrap_or(BASE_FEE),
+            self.multisig_policy.as_ref(),
         );
 
         Ok(Transfer {
@@ -170,6 +227,9 @@ impl TransferBuilder {
             signature: self.signature.ok_or("signature is missing")?,
             validators: self.validators,
             nonce: self.nonce.unwrap(),
+            fee: self.fee.unwrap_or(BASE_FEE),
+            cosignatures: self.cosignatures,
+            multisig_policy: self.multisig_policy,
         })
     }
 
@@ -191,6 +251,8 @@ pub struct NewTransferArgs {
     pub signature: Signature,
     pub validators: Option<HashMap<String, bool>>,
     pub nonce: TxNonce,
+    #[serde(default)]
+    pub fee: Option<TxFee>,
 }
 
 impl Default for Transfer {
@@ -214,6 +276,8 @@ impl Transfer {
             token.clone(),
             args.amount,
             args.nonce,
+            args.fee.unwrap_or(BASE_FEE),
+            None,
         );
 
         let digest = TransactionDigest::from(digest_vec);
@@ -230,6 +294,9 @@ impl Transfer {
             signature: args.signature,
             validators: args.validators,
             nonce: args.nonce,
+            fee: args.fee.unwrap_or(BASE_FEE),
+            cosignatures: Vec::new(),
+            multisig_policy: None,
         }
     }
 
@@ -247,6 +314,8 @@ impl Transfer {
             Token::default(),
             0,
             0,
+            BASE_FEE,
+            None,
         );
 
         let digest = TransactionDigest::from(digest_vec);
@@ -277,6 +346,9 @@ impl Transfer {
             signature,
             validators: None,
             nonce: 0,
+            fee: BASE_FEE,
+            cosignatures: Vec::new(),
+            multisig_policy: None,
         }
     }
 
@@ -289,6 +361,8 @@ impl Transfer {
             self.token(),
             self.amount(),
             self.nonce(),
+            self.fee,
+            self.multisig_policy.as_ref(),
         );
 
         digest.into()
@@ -322,6 +396,8 @@ impl Transfer {
             self.token(),
             self.amount(),
             self.nonce(),
+            self.fee,
+            self.multisig_policy.as_ref(),
         )
     }
 
@@ -388,15 +464,15 @@ impl Transaction for Transfer {
     }
 
     fn fee(&self) -> u128 {
-        BASE_FEE
+        self.fee
     }
 
     fn validator_fee_share(&self) -> u128 {
-        BASE_FEE / 2u128
+        self.fee / 2u128
     }
 
     fn proposer_fee_share(&self) -> u128 {
-        BASE_FEE / 2u128
+        self.fee - self.validator_fee_share()
     }
 
     fn build_payload(&self) -> String {
@@ -408,7 +484,9 @@ impl Transaction for Transfer {
                 self.receiver_address.clone(),
                 self.token.clone(),
                 self.amount.clone(),
-                self.nonce.clone()
+                self.nonce.clone(),
+                self.fee,
+                self.multisig_policy.clone()
             )
         )
     }
@@ -481,6 +559,9 @@ impl From<Transfer> for TransactionDigest {
     }
 }
 
+/// Hashes the fields the transaction digest covers, so that transfers that
+/// compare equal hash the same. Signatures and cosignatures are left out like
+/// they are from the digest.
 impl Hash for Transfer {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.timestamp.hash(state);
@@ -489,8 +570,9 @@ impl Hash for Transfer {
         self.receiver_address.hash(state);
         self.token.hash(state);
         self.amount.hash(state);
-        self.signature.hash(state);
         self.nonce.hash(state);
+        self.fee.hash(state);
+        self.multisig_policy.hash(state);
     }
 
     fn hash_slice<H: Hasher>(data: &[Self], state: &mut H)
@@ -0,0 +1,282 @@
+//! Transactions signed away from the machine that builds them
+//!
+//! A machine that can reach a node builds an [UnsignedTransaction] and writes
+//! it to a file. The file is carried to an air-gapped machine holding the
+//! sender's secret key, which signs it, and the signed [TransactionKind] is
+//! carried back to be submitted through the `createTxn` RPC method.
+//!
+//! Transfers sent from a multisig account carry the account's policy
+//! instead, and are passed from signer to signer, each adding a partial
+//! signature, until enough keys have signed for them to be assembled.
+
+use primitives::{PublicKey, SecretKey, Signature};
+use secp256k1::Secp256k1;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    keypair::{KeyPair, KeyPairError},
+    multisig::{MultisigError, MultisigPolicy, MultisigSignature},
+    transactions::{Transaction, TransactionKind},
+};
+
+/// Version of the unsigned transaction format written by this module
+pub const UNSIGNED_TRANSACTION_VERSION: u32 = 1;
+
+#[derive(thiserror::Error, Debug)]
+pub enum SigningError {
+    #[error("unsupported unsigned transaction version {0}")]
+    UnsupportedVersion(u32),
+    #[error("signing payload does not match the transaction")]
+    PayloadMismatch,
+    #[error("transaction is sent by {0}, not by the signing key")]
+    WrongKey(PublicKey),
+    #[error("signature verification failed: {0}")]
+    Verification(#[from] KeyPairError),
+    #[error("transaction is sent from a multisig account, it must be cosigned")]
+    RequiresCosigning,
+    #[error("transaction is not sent from a multisig account")]
+    NotMultisig,
+    #[error("only transfers can be sent from multisig accounts")]
+    UnsupportedMultisigTransaction,
+    #[error("transaction has not been signed by its sender public key {0}")]
+    MissingSignature(PublicKey),
+    #[error("multisig error: {0}")]
+    Multisig(#[from] MultisigError),
+}
+
+/// Returns the signature unsigned transactions carry until they are signed.
+/// It does not verify against any key.
+pub fn placeholder_signature() -> Signature {
+    Signature::from_compact(&[0; 64]).expect("zero is a valid compact signature")
+}
+
+/// A transaction waiting to be signed by its sender
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct UnsignedTransaction {
+    pub version: u32,
+    /// The payload the sender signs, so the signer can tell what they are
+    /// signing without recomputing it
+    pub payload: String,
+    /// The transaction, carrying a [placeholder_signature]
+    pub txn: TransactionKind,
+    /// Policy of the multisig account sending the transaction, none when it
+    /// is signed by its sender alone
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multisig: Option<MultisigPolicy>,
+    /// Partial signatures collected from the keys of the multisig policy
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub signatures: Vec<MultisigSignature>,
+}
+
+impl UnsignedTransaction {
+    pub fn new(txn: TransactionKind) -> Self {
+        Self {
+            version: UNSIGNED_TRANSACTION_VERSION,
+            payload: txn.build_payload(),
+            txn,
+            multisig: None,
+            signatures: Vec::new(),
+        }
+    }
+
+    /// Wraps a transfer sent from the multisig account governed by `policy`
+    pub fn new_multisig(txn: TransactionKind, policy: MultisigPolicy) -> Self {
+        Self {
+            multisig: Some(policy),
+            ..Self::new(txn)
+        }
+    }
+
+    /// Signs the transaction with the sender's secret key, returning it
+    /// ready to be submitted
+    pub fn sign(&self, secret_key: &SecretKey) -> Result<TransactionKind, SigningError> {
+        self.check_payload()?;
+        if self.multisig.is_some() {
+            return Err(SigningError::RequiresCosigning);
+        }
+
+        let sender_public_key = self.txn.sender_public_key();
+        if PublicKey::from_secret_key(&Secp256k1::new(), secret_key) != sender_public_key {
+            return Err(SigningError::WrongKey(sender_public_key));
+        }
+
+        let mut txn = self.txn.clone();
+        txn.sign(secret_key);
+
+        KeyPair::verify_ecdsa_sign(
+            txn.signature().to_string(),
+            txn.build_payload().as_bytes(),
+            sender_public_key.serialize().to_vec(),
+        )?;
+
+        Ok(txn)
+    }
+
+    /// Adds the partial signature of one of the keys of the multisig policy,
+    /// replacing any earlier signature of the same key
+    pub fn cosign(&mut self, secret_key: &SecretKey) -> Result<MultisigSignature, SigningError> {
+        self.check_payload()?;
+        let policy = self.multisig.as_ref().ok_or(SigningError::NotMultisig)?;
+
+        let signature = MultisigSignature::sign(&self.payload, secret_key)?;
+        if !policy.public_keys().contains(&signature.public_key) {
+            return Err(MultisigError::UnknownKey(signature.public_key).into());
+        }
+
+        self.signatures
+            .retain(|existing| existing.public_key != signature.public_key);
+        self.signatures.push(signature);
+
+        Ok(signature)
+    }
+
+    /// Returns whether enough keys of the multisig policy have signed
+    pub fn is_fully_signed(&self) -> bool {
+        match &self.multisig {
+            Some(policy) => policy.verify(&self.payload, &self.signatures).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Builds the transfer out of the partial signatures collected so far,
+    /// ready to be submitted. The sender public key must be among the
+    /// signers, its signature becoming the transfer's signature.
+    pub fn assemble(&self) -> Result<TransactionKind, SigningError> {
+        self.check_payload()?;
+        let policy = self.multisig.as_ref().ok_or(SigningError::NotMultisig)?;
+        policy.verify(&self.payload, &self.signatures)?;
+
+        let sender_public_key = self.txn.sender_public_key();
+        let (sender, cosigners): (Vec<_>, Vec<_>) = self
+            .signatures
+            .iter()
+            .copied()
+            .partition(|signature| signature.public_key == sender_public_key);
+        let sender = sender
+            .first()
+            .ok_or(SigningError::MissingSignature(sender_public_key))?;
+
+        let mut txn = self.txn.clone();
+        match &mut txn {
+            TransactionKind::Transfer(transfer) => {
+                transfer.signature = sender.signature;
+                transfer.cosignatures = cosigners;
+            }
+            _ => return Err(SigningError::UnsupportedMultisigTransaction),
+        }
+
+        Ok(txn)
+    }
+
+    fn check_payload(&self) -> Result<(), SigningError> {
+        if self.version != UNSIGNED_TRANSACTION_VERSION {
+            return Err(SigningError::UnsupportedVersion(self.version));
+        }
+        if self.payload != self.txn.build_payload() {
+            return Err(SigningError::PayloadMismatch);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use primitives::Address;
+
+    use super::*;
+
+    fn unsigned_transfer(keypair: &KeyPair) -> UnsignedTransaction {
+        UnsignedTransaction::new(transfer(
+            keypair,
+            Address::new(*keypair.get_miner_public_key()),
+        ))
+    }
+
+    fn transfer(keypair: &KeyPair, sender_address: Address) -> TransactionKind {
+        let public_key = *keypair.get_miner_public_key();
+        TransactionKind::transfer_builder()
+            .timestamp(chrono::Utc::now().timestamp())
+            .sender_address(sender_address)
+            .sender_public_key(public_key)
+            .receiver_address(Address::new(KeyPair::random().miner_public_key_owned()))
+            .amount(10)
+            .validators(HashMap::new())
+            .nonce(1)
+            .signature(placeholder_signature())
+            .build_kind()
+            .unwrap()
+    }
+
+    #[test]
+    fn signs_transactions_read_back_from_json() {
+        let keypair = KeyPair::random();
+        let unsigned = unsigned_transfer(&keypair);
+
+        let json = serde_json::to_string(&unsigned).unwrap();
+        let unsigned: UnsignedTransaction = serde_json::from_str(&json).unwrap();
+        let txn = unsigned.sign(keypair.get_miner_secret_key()).unwrap();
+
+        assert_ne!(txn.signature(), placeholder_signature());
+        assert_eq!(txn.id(), unsigned.txn.id());
+        assert!(KeyPair::verify_ecdsa_sign(
+            txn.signature().to_string(),
+            unsigned.payload.as_bytes(),
+            txn.sender_public_key().serialize().to_vec(),
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn refuses_other_keys_and_altered_payloads() {
+        let keypair = KeyPair::random();
+        let unsigned = unsigned_transfer(&keypair);
+
+        assert!(matches!(
+            unsigned.sign(KeyPair::random().get_miner_secret_key()),
+            Err(SigningError::WrongKey(_))
+        ));
+
+        let mut altered = unsigned.clone();
+        altered.payload = unsigned_transfer(&keypair).payload;
+        assert!(matches!(
+            altered.sign(keypair.get_miner_secret_key()),
+            Err(SigningError::PayloadMismatch)
+        ));
+    }
+
+    #[test]
+    fn assembles_multisig_transfers_once_enough_keys_cosigned() {
+        let keys: Vec<KeyPair> = (0..3).map(|_| KeyPair::random()).collect();
+        let public_keys = keys.iter().map(KeyPair::miner_public_key_owned).collect();
+        let policy = MultisigPolicy::new(public_keys, 2).unwrap();
+        let mut unsigned =
+            UnsignedTransaction::new_multisig(transfer(&keys[0], policy.address()), policy.clone());
+
+        assert!(matches!(
+            unsigned.sign(keys[0].get_miner_secret_key()),
+            Err(SigningError::RequiresCosigning)
+        ));
+
+        unsigned.cosign(keys[2].get_miner_secret_key()).unwrap();
+        assert!(!unsigned.is_fully_signed());
+        assert!(matches!(
+            unsigned.assemble(),
+            Err(SigningError::Multisig(MultisigError::BelowThreshold(1, 2)))
+        ));
+        assert!(matches!(
+            unsigned.cosign(KeyPair::random().get_miner_secret_key()),
+            Err(SigningError::Multisig(MultisigError::UnknownKey(_)))
+        ));
+
+        unsigned.cosign(keys[0].get_miner_secret_key()).unwrap();
+        assert!(unsigned.is_fully_signed());
+
+        let txn = unsigned.assemble().unwrap();
+        assert_eq!(txn.cosignatures().len(), 1);
+        assert!(policy.verify(&unsigned.payload, &txn.signatures()).is_ok());
+    }
+}
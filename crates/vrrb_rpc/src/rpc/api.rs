@@ -6,20 +6,29 @@ use jsonrpsee::{core::Error as RpseeError, proc_macros::rpc};
 use primitives::{Address, NodeType, Round};
 use secp256k1::PublicKey;
 use serde::{Deserialize, Serialize};
-use storage::vrrbdb::Claims;
+use storage::vrrbdb::{Claims, MerkleProof, StateAt, StateCheckpoint};
 use vrrb_config::QuorumMembershipConfig;
 use vrrb_core::account::Account;
+use vrrb_core::claim::Claim;
 use vrrb_core::node_health_report::NodeHealthReport;
 use vrrb_core::transactions::{
-    RpcTransactionDigest, Token, Transaction, TransactionKind, TxAmount, TxNonce, TxTimestamp,
+    ContractTransaction, RpcTransactionDigest, Token, Transaction, TransactionKind,
+    TransactionReceipt, TxAmount, TxNonce, TxTimestamp,
 };
 
-use crate::rpc::SignOpts;
+use crate::rpc::{
+    subscriptions::{
+        BlockNotification, BlockSubscriptionFilter, ClaimSubscriptionFilter,
+        TransactionNotification, TransactionSubscriptionFilter,
+    },
+    SignOpts,
+};
 
 pub type ExampleHash = [u8; 32];
 pub type ExampleStorageKey = Vec<u8>;
 pub type FullStateSnapshot = HashMap<Address, Account>;
 pub type FullMempoolSnapshot = Vec<RpcTransactionRecord>;
+pub type MempoolSnapshot = Vec<TransactionKind>;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransactionRecord;
@@ -41,6 +50,7 @@ pub struct RpcTransactionRecord {
     pub signature: String,
     pub validators: HashMap<String, bool>,
     pub nonce: TxNonce,
+    pub fee: u128,
 }
 
 impl From<TransactionKind> for RpcTransactionRecord {
@@ -56,10 +66,44 @@ impl From<TransactionKind> for RpcTransactionRecord {
             signature: txn.signature().to_string(),
             validators: txn.validators().unwrap_or_default(),
             nonce: txn.nonce(),
+            fee: txn.fee(),
         }
     }
 }
 
+/// Where an account's nonce stands, for senders to pick the nonce of their
+/// next transaction
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcAccountNonce {
+    /// Whether the account exists in the current state
+    pub exists: bool,
+    /// Nonce of the account in the current state, zero for accounts that do
+    /// not exist yet
+    pub account_nonce: TxNonce,
+    /// Number of the account's transactions pending in the mempool
+    pub pending_txns: usize,
+}
+
+impl RpcAccountNonce {
+    /// Returns the nonce the account's next transaction should use
+    pub fn next_nonce(&self) -> TxNonce {
+        self.account_nonce + self.pending_txns as TxNonce
+    }
+}
+
+/// A smart contract deployed to the ledger
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcProgram {
+    pub contract_address: Address,
+    /// CID of the `web3_pkg` package the contract was deployed from
+    pub package_cid: String,
+    /// Storage left on the contract account by its last call
+    pub state: Option<String>,
+    pub balance: u128,
+}
+
 #[rpc(server, client, namespace = "state")]
 #[async_trait]
 pub trait RpcApi {
@@ -71,6 +115,16 @@ pub trait RpcApi {
     #[method(name = "getFullMempool")]
     async fn get_full_mempool(&self) -> Result<FullMempoolSnapshot, RpseeError>;
 
+    /// Returns every transaction pending within the mempool, in a form that
+    /// can be fed back to `importMempool`
+    #[method(name = "exportMempool")]
+    async fn export_mempool(&self) -> Result<MempoolSnapshot, RpseeError>;
+
+    /// Submits every transaction within a snapshot produced by
+    /// `exportMempool`. Returns the number of transactions submitted
+    #[method(name = "importMempool")]
+    async fn import_mempool(&self, snapshot: MempoolSnapshot) -> Result<usize, RpseeError>;
+
     /// Returns the node type this client is connected to
     #[method(name = "getNodeType")]
     async fn get_node_type(&self) -> Result<NodeType, RpseeError>;
@@ -93,6 +147,9 @@ pub trait RpcApi {
         digests: Vec<RpcTransactionDigest>,
     ) -> Result<HashMap<RpcTransactionDigest, RpcTransactionRecord>, RpseeError>;
 
+    /// Requests the creation of an account at an address that has none.
+    /// Multisig accounts are created on chain by a transfer carrying their
+    /// policy instead
     #[method(name = "createAccount")]
     async fn create_account(&self, address: Address, account: Account) -> Result<(), RpseeError>;
 
@@ -102,23 +159,110 @@ pub trait RpcApi {
     #[method(name = "getAccount")]
     async fn get_account(&self, address: Address) -> Result<Account, RpseeError>;
 
+    /// Returns the block height, state root hash and store versions of the
+    /// block selected by `at`
+    #[method(name = "getStateCheckpoint")]
+    async fn get_state_checkpoint(&self, at: StateAt) -> Result<StateCheckpoint, RpseeError>;
+
+    /// Returns an account as it was at the block selected by `at`
+    #[method(name = "getAccountAt")]
+    async fn get_account_at(&self, address: Address, at: StateAt) -> Result<Account, RpseeError>;
+
+    /// Returns a transaction if it had been confirmed by the block selected by
+    /// `at`
+    #[method(name = "getTransactionAt")]
+    async fn get_transaction_at(
+        &self,
+        transaction_digest: RpcTransactionDigest,
+        at: StateAt,
+    ) -> Result<RpcTransactionRecord, RpseeError>;
+
+    /// Returns the claim registered for an address as it was at the block
+    /// selected by `at`
+    #[method(name = "getClaimAt")]
+    async fn get_claim_at(&self, address: Address, at: StateAt) -> Result<Claim, RpseeError>;
+
+    /// Returns a Merkle proof that an account is present in, or absent from,
+    /// the state trie. Defaults to the latest state when `at` is omitted
+    #[method(name = "getAccountProof")]
+    async fn get_account_proof(
+        &self,
+        address: Address,
+        at: Option<StateAt>,
+    ) -> Result<MerkleProof, RpseeError>;
+
+    /// Returns a Merkle proof that a transaction is present in, or absent
+    /// from, the transaction trie. Defaults to the latest state when `at` is
+    /// omitted
+    #[method(name = "getTransactionProof")]
+    async fn get_transaction_proof(
+        &self,
+        transaction_digest: RpcTransactionDigest,
+        at: Option<StateAt>,
+    ) -> Result<MerkleProof, RpseeError>;
+
+    /// Returns a Merkle proof that a claim is present in, or absent from, the
+    /// claim trie. Defaults to the latest state when `at` is omitted
+    #[method(name = "getClaimProof")]
+    async fn get_claim_proof(
+        &self,
+        claim_hash: ClaimHash,
+        at: Option<StateAt>,
+    ) -> Result<MerkleProof, RpseeError>;
+
     #[method(name = "faucetDrip")]
     async fn faucet_drip(&self, address: Address) -> Result<(), RpseeError>;
 
+    /// Deprecated: sends the sender's secret key to the node. Build the
+    /// transaction, sign it offline with `vrrb_core::transactions::UnsignedTransaction`
+    /// and submit the signed transaction through `createTxn` instead
     #[method(name = "signTransaction")]
     async fn sign_transaction(&self, sign_opts: SignOpts) -> Result<String, RpseeError>;
 
     #[method(name = "getRound")]
     async fn get_round(&self) -> Result<Round, RpseeError>;
 
+    /// Returns every block stored within the node's DAG, ordered by round
     #[method(name = "getBlocks")]
     async fn get_blocks(&self) -> Result<Vec<Block>, RpseeError>;
 
+    /// Returns the block identified by the given hash, if the node knows about it
+    #[method(name = "getBlockByHash")]
+    async fn get_block_by_hash(&self, block_hash: String) -> Result<Option<Block>, RpseeError>;
+
+    /// Returns the genesis and convergence blocks whose height lies within
+    /// `start..=end`. At most `MAX_BLOCKS_PER_PAGE` blocks can be requested at once
+    #[method(name = "getBlocksByHeightRange")]
+    async fn get_blocks_by_height_range(
+        &self,
+        start: u128,
+        end: u128,
+    ) -> Result<Vec<Block>, RpseeError>;
+
+    /// Returns the contract deployed at the given address
     #[method(name = "getProgram")]
-    async fn get_program(&self) -> Result<(), RpseeError>;
+    async fn get_program(&self, contract_address: Address) -> Result<RpcProgram, RpseeError>;
 
+    /// Submits a transaction calling a deployed contract. Contracts are
+    /// deployed by submitting a deploy transaction through `createTxn`
     #[method(name = "callProgram")]
-    async fn call_program(&self) -> Result<(), RpseeError>;
+    async fn call_program(
+        &self,
+        txn: ContractTransaction,
+    ) -> Result<RpcTransactionRecord, RpseeError>;
+
+    /// Returns the receipt recorded when a contract deployment or call was
+    /// applied
+    #[method(name = "getTransactionReceipt")]
+    async fn get_transaction_receipt(
+        &self,
+        transaction_digest: RpcTransactionDigest,
+    ) -> Result<TransactionReceipt, RpseeError>;
+
+    /// Returns the nonce of an account in the current state and the number
+    /// of its transactions pending in the mempool
+    #[method(name = "getAccountNonce")]
+    async fn get_account_nonce(&self, address: Address) -> Result<RpcAccountNonce, RpseeError>;
 
     #[method(name = "getTransactionCount")]
     async fn get_transaction_count(&self, account: Address) -> Result<usize, RpseeError>;
@@ -140,4 +284,16 @@ pub trait RpcApi {
 
     #[method(name = "getLastBlock")]
     async fn get_last_block(&self) -> Result<Option<Block>, RpseeError>;
+
+    /// Streams blocks as they are appended to the DAG or certified
+    #[subscription(name = "subscribeBlocks", item = BlockNotification)]
+    fn subscribe_blocks(&self, filter: Option<BlockSubscriptionFilter>);
+
+    /// Streams transactions as they enter the mempool or get validated
+    #[subscription(name = "subscribeTransactions", item = TransactionNotification)]
+    fn subscribe_transactions(&self, filter: Option<TransactionSubscriptionFilter>);
+
+    /// Streams claims created by the node
+    #[subscription(name = "subscribeClaims", item = Claim)]
+    fn subscribe_claims(&self, filter: Option<ClaimSubscriptionFilter>);
 }
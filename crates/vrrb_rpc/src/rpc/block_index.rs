@@ -0,0 +1,148 @@
+//! Index of the blocks in the DAG served over JSON-RPC
+//!
+//! Walking the DAG on every query is linear in its size. The index keeps the
+//! position of every block in DAG order and the hashes of confirmed blocks by
+//! height, so queries only read the blocks they return from the DAG. It is
+//! built once when the server starts and extended as blocks are appended.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use block::Block;
+use bulldag::{graph::BullDag, vertex::Direction};
+
+/// Position of a block in DAG order: blocks are ordered by round and, within a
+/// round, proposal blocks precede the convergence block that references them
+pub type BlockOrderKey = (u128, u8, String);
+
+#[derive(Debug, Clone, Default)]
+pub struct BlockIndex {
+    order: BTreeSet<BlockOrderKey>,
+    keys: HashMap<String, BlockOrderKey>,
+    heights: BTreeSet<(u128, String)>,
+    /// Number of vertices the DAG held when the index was last known to
+    /// cover all of it
+    synced_len: usize,
+}
+
+impl BlockIndex {
+    /// Builds the index by walking the DAG from its roots
+    pub fn from_dag(dag: &BullDag<Block, String>) -> Self {
+        let mut index = Self::default();
+        index.extend_from_dag(dag);
+        index
+    }
+
+    /// Returns the position of `block` in DAG order
+    pub fn order_key(block: &Block) -> BlockOrderKey {
+        let rank = match block {
+            Block::Genesis { .. } => 0,
+            Block::Proposal { .. } => 1,
+            Block::Convergence { .. } => 2,
+        };
+
+        (block.round(), rank, block.hash())
+    }
+
+    /// Returns whether blocks were added to the DAG since the index last
+    /// covered all of it
+    pub fn is_behind(&self, dag: &BullDag<Block, String>) -> bool {
+        dag.len() != self.synced_len
+    }
+
+    /// Indexes a block announced as appended to the DAG. The index is only
+    /// considered in sync afterwards if it was before the block was added.
+    pub fn append(&mut self, block: &Block, dag: &BullDag<Block, String>) {
+        let was_synced = self.synced_len + 1 == dag.len();
+        if self.insert(block) && was_synced {
+            self.synced_len = dag.len();
+        }
+    }
+
+    /// Adds the blocks of the DAG missing from the index
+    pub fn extend_from_dag(&mut self, dag: &BullDag<Block, String>) {
+        let mut visited = HashSet::new();
+        self.synced_len = dag.len();
+
+        for root in dag.get_roots().iter() {
+            if let Some(root_vertex) = dag.get_vertex(root.clone()) {
+                let mut indices = vec![root.clone()];
+                indices.extend(dag.trace(root_vertex, Direction::Reference));
+
+                for idx in indices {
+                    if self.keys.contains_key(&idx) || !visited.insert(idx.clone()) {
+                        continue;
+                    }
+
+                    if let Some(vertex) = dag.get_vertex(idx) {
+                        self.insert(&vertex.get_data());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Indexes `block`, returning false if it was already indexed
+    pub fn insert(&mut self, block: &Block) -> bool {
+        let key = Self::order_key(block);
+        if self.keys.contains_key(&key.2) {
+            return false;
+        }
+
+        if let Some(height) = block.height() {
+            self.heights.insert((height, key.2.clone()));
+        }
+        self.keys.insert(key.2.clone(), key.clone());
+        self.order.insert(key);
+
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Returns the position of the indexed block with the given hash
+    pub fn key(&self, block_hash: &str) -> Option<&BlockOrderKey> {
+        self.keys.get(block_hash)
+    }
+
+    /// Returns the hashes of every indexed block in DAG order
+    pub fn hashes(&self) -> Vec<String> {
+        self.order.iter().map(|(_, _, hash)| hash.clone()).collect()
+    }
+
+    /// Returns the hashes of the blocks that come after the given block in
+    /// DAG order, none if the block is not indexed
+    pub fn hashes_after(&self, block_hash: &str) -> Option<Vec<String>> {
+        let key = self.keys.get(block_hash)?;
+
+        let hashes = self
+            .order
+            .range(key.clone()..)
+            .skip(1)
+            .map(|(_, _, hash)| hash.clone())
+            .collect();
+
+        Some(hashes)
+    }
+
+    /// Returns the hashes of the confirmed blocks with a height between
+    /// `start` and `end`, inclusive, ordered by height
+    pub fn hashes_by_height(&self, start: u128, end: u128) -> Vec<String> {
+        self.heights
+            .range((start, String::new())..)
+            .take_while(|(height, _)| *height <= end)
+            .map(|(_, hash)| hash.clone())
+            .collect()
+    }
+
+    /// Returns the hash of the genesis or convergence block with the greatest
+    /// height
+    pub fn last_confirmed(&self) -> Option<&String> {
+        self.heights.iter().next_back().map(|(_, hash)| hash)
+    }
+}
@@ -1,12 +1,15 @@
 pub mod api;
+pub mod block_index;
 pub mod client;
 mod server;
 mod server_impl;
+pub mod subscriptions;
 use serde::{Deserialize, Serialize};
 pub use server::*;
 pub use server_impl::*;
 use vrrb_core::transactions::Token;
 
+/// Arguments of the deprecated `signTransaction` method
 #[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq, Hash)]
 pub struct SignOpts {
     pub timestamp: i64,
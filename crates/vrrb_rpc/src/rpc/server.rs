@@ -1,13 +1,20 @@
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::{Arc, RwLock},
+};
 
-use events::{EventPublisher, DEFAULT_BUFFER};
+use block::Block;
+use bulldag::graph::BullDag;
+use events::{Event, EventMessage, EventPublisher, DEFAULT_BUFFER};
 use jsonrpsee::server::{ServerBuilder, ServerHandle};
 use mempool::{LeftRightMempool, MempoolReadHandleFactory};
 use primitives::NodeType;
 use storage::vrrbdb::{VrrbDb, VrrbDbConfig, VrrbDbReadHandle};
-use tokio::sync::mpsc::channel;
+use telemetry::warn;
+use tokio::sync::{broadcast, mpsc::channel};
+use vrrb_config::QuorumMembershipConfig;
 
-use crate::rpc::{api::RpcApiServer, server_impl::RpcServerImpl};
+use crate::rpc::{api::RpcApiServer, block_index::BlockIndex, server_impl::RpcServerImpl};
 
 #[derive(Debug, Clone)]
 pub struct JsonRpcServerConfig {
@@ -16,6 +23,11 @@ pub struct JsonRpcServerConfig {
     pub mempool_read_handle_factory: MempoolReadHandleFactory,
     pub node_type: NodeType,
     pub events_tx: EventPublisher,
+    /// Internal events forwarded to the server so they can be streamed to
+    /// subscribed clients
+    pub subscription_events_tx: broadcast::Sender<EventMessage>,
+    pub dag: Arc<RwLock<BullDag<Block, String>>>,
+    pub membership_config: Option<QuorumMembershipConfig>,
 }
 
 #[derive(Debug)]
@@ -25,13 +37,50 @@ impl JsonRpcServer {
     pub async fn run(config: &JsonRpcServerConfig) -> anyhow::Result<(ServerHandle, SocketAddr)> {
         let server = ServerBuilder::default().build(config.address).await?;
 
+        // Subscribe before indexing so blocks appended in the meantime are not missed
+        let mut events_rx = config.subscription_events_tx.subscribe();
+        let block_index = {
+            let dag = config
+                .dag
+                .read()
+                .map_err(|err| anyhow::anyhow!("failed to read DAG: {err}"))?;
+
+            Arc::new(RwLock::new(BlockIndex::from_dag(&dag)))
+        };
+
         let server_impl = RpcServerImpl {
             node_type: config.node_type,
             events_tx: config.events_tx.clone(),
+            subscription_events_tx: config.subscription_events_tx.clone(),
             vrrbdb_read_handle: config.vrrbdb_read_handle.clone(),
             mempool_read_handle_factory: config.mempool_read_handle_factory.clone(),
+            dag: config.dag.clone(),
+            block_index,
+            membership_config: config.membership_config.clone(),
         };
 
+        // Keeps the block index up to date as blocks are appended to the DAG
+        let indexer = server_impl.clone();
+        tokio::spawn(async move {
+            loop {
+                match events_rx.recv().await {
+                    Ok(message) => match Event::from(message) {
+                        Event::BlockAppended(block_hash) => {
+                            if let Err(err) = indexer.index_block(block_hash) {
+                                warn!("failed to index appended block: {err}");
+                            }
+                        }
+                        Event::Stop => break,
+                        _ => {}
+                    },
+                    // Blocks whose events were dropped are picked up by the
+                    // next query, which finds the index behind the DAG
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+
         let addr = server.local_addr()?;
         let handle = server.start(server_impl.into_rpc())?;
 
@@ -60,6 +109,8 @@ impl Default for JsonRpcServerConfig {
 
         let node_type = NodeType::Full;
         let (events_tx, _) = channel(DEFAULT_BUFFER);
+        let (subscription_events_tx, _) = broadcast::channel(DEFAULT_BUFFER);
+        let dag = Arc::new(RwLock::new(BullDag::new()));
 
         JsonRpcServerConfig {
             address,
@@ -67,6 +118,9 @@ impl Default for JsonRpcServerConfig {
             mempool_read_handle_factory,
             node_type,
             events_tx,
+            subscription_events_tx,
+            dag,
+            membership_config: None,
         }
     }
 }
@@ -1,28 +1,43 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::{Arc, RwLock},
+};
 
 use async_trait::async_trait;
 use block::block::Block;
 use block::ClaimHash;
-use events::{Event, EventPublisher};
-use jsonrpsee::core::Error as RpseeError;
+use bulldag::graph::BullDag;
+use events::{Event, EventMessage, EventPublisher};
+use jsonrpsee::{core::Error as RpseeError, types::SubscriptionResult, SubscriptionSink};
 use mempool::MempoolReadHandleFactory;
 use primitives::{Address, NodeType, Round};
 use secp256k1::{Message, SecretKey};
 use sha2::{Digest, Sha256};
-use storage::vrrbdb::{Claims, VrrbDbReadHandle};
-use telemetry::{debug, error};
+use storage::vrrbdb::{Claims, MerkleProof, StateAt, StateCheckpoint, VrrbDbReadHandle};
+use telemetry::{debug, error, warn};
+use tokio::sync::broadcast;
 use vrrb_config::QuorumMembershipConfig;
 use vrrb_core::node_health_report::NodeHealthReport;
 use vrrb_core::transactions::{
-    RpcTransactionDigest, Transaction, TransactionDigest, TransactionKind,
+    ContractAction, ContractTransaction, RpcTransactionDigest, Transaction, TransactionDigest,
+    TransactionKind, TransactionReceipt,
 };
-use vrrb_core::{account::Account, serde_helpers::encode_to_binary};
+use vrrb_core::{account::Account, claim::Claim, serde_helpers::encode_to_binary};
 
 use super::{
-    api::{FullMempoolSnapshot, RpcApiServer},
+    api::{FullMempoolSnapshot, MempoolSnapshot, RpcApiServer},
+    block_index::BlockIndex,
+    subscriptions::{
+        BlockEventKind, BlockNotification, BlockSubscriptionFilter, ClaimSubscriptionFilter,
+        TransactionEventKind, TransactionNotification, TransactionSubscriptionFilter,
+    },
     SignOpts,
 };
-use crate::rpc::api::{FullStateSnapshot, RpcTransactionRecord};
+use crate::rpc::api::{FullStateSnapshot, RpcAccountNonce, RpcProgram, RpcTransactionRecord};
+
+/// Maximum number of blocks that can be requested through a single paginated query
+pub const MAX_BLOCKS_PER_PAGE: u128 = 100;
 
 #[derive(Debug, Clone)]
 pub struct RpcServerImpl {
@@ -30,6 +45,104 @@ pub struct RpcServerImpl {
     pub vrrbdb_read_handle: VrrbDbReadHandle,
     pub mempool_read_handle_factory: MempoolReadHandleFactory,
     pub events_tx: EventPublisher,
+    pub subscription_events_tx: broadcast::Sender<EventMessage>,
+    pub dag: Arc<RwLock<BullDag<Block, String>>>,
+    pub block_index: Arc<RwLock<BlockIndex>>,
+    pub membership_config: Option<QuorumMembershipConfig>,
+}
+
+impl RpcServerImpl {
+    /// Runs `query` against the block index, first catching the index up with
+    /// blocks added to the DAG that were not announced to the server yet
+    fn with_block_index<T>(
+        &self,
+        query: impl FnOnce(&BlockIndex, &BullDag<Block, String>) -> T,
+    ) -> Result<T, RpseeError> {
+        let dag = self
+            .dag
+            .read()
+            .map_err(|err| RpseeError::Custom(format!("failed to read DAG: {err}")))?;
+
+        {
+            let index = self
+                .block_index
+                .read()
+                .map_err(|err| RpseeError::Custom(format!("failed to read block index: {err}")))?;
+
+            if !index.is_behind(&dag) {
+                return Ok(query(&index, &dag));
+            }
+        }
+
+        let mut index = self
+            .block_index
+            .write()
+            .map_err(|err| RpseeError::Custom(format!("failed to write block index: {err}")))?;
+        index.extend_from_dag(&dag);
+
+        Ok(query(&index, &dag))
+    }
+
+    /// Indexes a block announced as appended to the DAG
+    pub fn index_block(&self, block_hash: String) -> Result<(), RpseeError> {
+        let dag = self
+            .dag
+            .read()
+            .map_err(|err| RpseeError::Custom(format!("failed to read DAG: {err}")))?;
+
+        if let Some(vertex) = dag.get_vertex(block_hash) {
+            self.block_index
+                .write()
+                .map_err(|err| RpseeError::Custom(format!("failed to write block index: {err}")))?
+                .append(&vertex.get_data(), &dag);
+        }
+
+        Ok(())
+    }
+
+    /// Returns every block within the DAG ordered by round. Within a round,
+    /// proposal blocks precede the convergence block that references them.
+    fn dag_blocks(&self) -> Result<Vec<Block>, RpseeError> {
+        self.with_block_index(|index, dag| blocks_by_hash(dag, index.hashes()))
+    }
+
+    fn block_by_hash(&self, block_hash: String) -> Result<Option<Block>, RpseeError> {
+        let guard = self
+            .dag
+            .read()
+            .map_err(|err| RpseeError::Custom(format!("failed to read DAG: {err}")))?;
+
+        Ok(guard.get_vertex(block_hash).map(|vertex| vertex.get_data()))
+    }
+
+    /// Returns every block that comes after the given block in DAG order
+    fn blocks_after(&self, block_hash: &str) -> Result<Vec<Block>, RpseeError> {
+        self.with_block_index(|index, dag| {
+            index
+                .hashes_after(block_hash)
+                .map(|hashes| blocks_by_hash(dag, hashes))
+        })?
+        .ok_or_else(|| RpseeError::Custom(format!("unknown block hash: {block_hash}")))
+    }
+
+    /// Returns the genesis or convergence block with the greatest height
+    fn last_block(&self) -> Result<Option<Block>, RpseeError> {
+        self.with_block_index(|index, dag| {
+            index
+                .last_confirmed()
+                .and_then(|hash| dag.get_vertex(hash.clone()))
+                .map(|vertex| vertex.get_data())
+        })
+    }
+}
+
+/// Reads the blocks with the given hashes from the DAG, in the same order
+fn blocks_by_hash(dag: &BullDag<Block, String>, hashes: Vec<String>) -> Vec<Block> {
+    hashes
+        .into_iter()
+        .filter_map(|hash| dag.get_vertex(hash))
+        .map(|vertex| vertex.get_data())
+        .collect()
 }
 
 #[async_trait]
@@ -57,6 +170,29 @@ impl RpcApiServer for RpcServerImpl {
         Ok(values)
     }
 
+    async fn export_mempool(&self) -> Result<MempoolSnapshot, RpseeError> {
+        let mut txns = self.mempool_read_handle_factory.values();
+        txns.sort_by_key(|txn| (txn.sender_address(), txn.nonce()));
+
+        Ok(txns)
+    }
+
+    async fn import_mempool(&self, snapshot: MempoolSnapshot) -> Result<usize, RpseeError> {
+        let count = snapshot.len();
+
+        for txn in snapshot {
+            self.events_tx
+                .send(Event::NewTxnCreated(txn).into())
+                .await
+                .map_err(|err| {
+                    error!("could not queue transaction to mempool: {err}");
+                    RpseeError::Custom(err.to_string())
+                })?;
+        }
+
+        Ok(count)
+    }
+
     async fn get_node_type(&self) -> Result<NodeType, RpseeError> {
         Ok(self.node_type)
     }
@@ -137,6 +273,24 @@ impl RpcApiServer for RpcServerImpl {
     }
 
     async fn create_account(&self, address: Address, account: Account) -> Result<(), RpseeError> {
+        // Accounts created here are only stored by this node, so a policy
+        // set on them would not be enforced by the rest of the network
+        if account.multisig().is_some() {
+            return Err(RpseeError::Custom(
+                "multisig accounts are created by a transfer carrying their policy".to_string(),
+            ));
+        }
+
+        if self
+            .vrrbdb_read_handle
+            .get_account_by_address(&address)
+            .is_ok()
+        {
+            return Err(RpseeError::Custom(format!(
+                "an account already exists at {address}"
+            )));
+        }
+
         let account_bytes =
             encode_to_binary(&account).map_err(|err| RpseeError::Custom(err.to_string()))?;
 
@@ -194,11 +348,85 @@ impl RpcApiServer for RpcServerImpl {
         }
     }
 
+    async fn get_state_checkpoint(&self, at: StateAt) -> Result<StateCheckpoint, RpseeError> {
+        self.vrrbdb_read_handle
+            .checkpoint(&at)
+            .map_err(|err| RpseeError::Custom(err.to_string()))
+    }
+
+    async fn get_account_at(&self, address: Address, at: StateAt) -> Result<Account, RpseeError> {
+        telemetry::info!("retrieving account {address} at {at:?}");
+
+        self.vrrbdb_read_handle
+            .get_account_by_address_at(&address, &at)
+            .map_err(|err| RpseeError::Custom(err.to_string()))
+    }
+
+    async fn get_transaction_at(
+        &self,
+        transaction_digest: RpcTransactionDigest,
+        at: StateAt,
+    ) -> Result<RpcTransactionRecord, RpseeError> {
+        let parsed_digest = transaction_digest
+            .parse::<TransactionDigest>()
+            .map_err(|_err| RpseeError::Custom("unable to parse transaction digest".to_string()))?;
+
+        self.vrrbdb_read_handle
+            .get_transaction_at(&parsed_digest, &at)
+            .map(RpcTransactionRecord::from)
+            .map_err(|_err| RpseeError::Custom("unable to find transaction".to_string()))
+    }
+
+    async fn get_claim_at(&self, address: Address, at: StateAt) -> Result<Claim, RpseeError> {
+        self.vrrbdb_read_handle
+            .get_claim_by_address_at(&address, &at)
+            .map_err(|err| RpseeError::Custom(err.to_string()))
+    }
+
+    async fn get_account_proof(
+        &self,
+        address: Address,
+        at: Option<StateAt>,
+    ) -> Result<MerkleProof, RpseeError> {
+        self.vrrbdb_read_handle
+            .get_account_proof(&address, at.as_ref())
+            .map_err(|err| RpseeError::Custom(err.to_string()))
+    }
+
+    async fn get_transaction_proof(
+        &self,
+        transaction_digest: RpcTransactionDigest,
+        at: Option<StateAt>,
+    ) -> Result<MerkleProof, RpseeError> {
+        let parsed_digest = transaction_digest
+            .parse::<TransactionDigest>()
+            .map_err(|_err| RpseeError::Custom("unable to parse transaction digest".to_string()))?;
+
+        self.vrrbdb_read_handle
+            .get_transaction_proof(&parsed_digest, at.as_ref())
+            .map_err(|err| RpseeError::Custom(err.to_string()))
+    }
+
+    async fn get_claim_proof(
+        &self,
+        claim_hash: ClaimHash,
+        at: Option<StateAt>,
+    ) -> Result<MerkleProof, RpseeError> {
+        self.vrrbdb_read_handle
+            .get_claim_proof(&claim_hash, at.as_ref())
+            .map_err(|err| RpseeError::Custom(err.to_string()))
+    }
+
     async fn faucet_drip(&self, _address: Address) -> Result<(), RpseeError> {
         todo!()
     }
 
     async fn sign_transaction(&self, sign_opts: SignOpts) -> Result<String, RpseeError> {
+        warn!(
+            "signTransaction is deprecated and receives secret keys in plaintext, sign \
+             transactions offline and submit them through createTxn instead"
+        );
+
         let payload = format!(
             "{},{},{},{},{},{:?},{}",
             &sign_opts.timestamp,
@@ -228,32 +456,145 @@ impl RpcApiServer for RpcServerImpl {
     }
 
     async fn get_round(&self) -> Result<Round, RpseeError> {
-        todo!()
+        let last_block = self
+            .last_block()?
+            .ok_or_else(|| RpseeError::Custom("no blocks have been appended yet".to_string()))?;
+
+        Ok(last_block.round())
     }
 
     async fn get_blocks(&self) -> Result<Vec<Block>, RpseeError> {
-        error!("getBlocks is not implemented");
-        Ok(Vec::new())
+        self.dag_blocks()
     }
 
-    async fn get_program(&self) -> Result<(), RpseeError> {
-        error!("getProgram is not implemented");
-        Ok(())
+    async fn get_block_by_hash(&self, block_hash: String) -> Result<Option<Block>, RpseeError> {
+        self.block_by_hash(block_hash)
     }
 
-    async fn call_program(&self) -> Result<(), RpseeError> {
-        error!("callProgram is not implemented");
-        Ok(())
+    async fn get_blocks_by_height_range(
+        &self,
+        start: u128,
+        end: u128,
+    ) -> Result<Vec<Block>, RpseeError> {
+        if start > end {
+            return Err(RpseeError::Custom(format!(
+                "invalid height range: start {start} is greater than end {end}"
+            )));
+        }
+
+        if end - start >= MAX_BLOCKS_PER_PAGE {
+            return Err(RpseeError::Custom(format!(
+                "requested height range exceeds the limit of {MAX_BLOCKS_PER_PAGE} blocks"
+            )));
+        }
+
+        self.with_block_index(|index, dag| blocks_by_hash(dag, index.hashes_by_height(start, end)))
     }
 
-    async fn get_transaction_count(&self, _account: Address) -> Result<usize, RpseeError> {
-        error!("getTransactionCount is not implemented");
-        Ok(0)
+    async fn get_program(&self, contract_address: Address) -> Result<RpcProgram, RpseeError> {
+        let account = self
+            .vrrbdb_read_handle
+            .get_account_by_address(&contract_address)
+            .map_err(|err| RpseeError::Custom(err.to_string()))?;
+
+        let package_cid = account.package_address().clone().ok_or_else(|| {
+            RpseeError::Custom(format!("no program deployed at {contract_address}"))
+        })?;
+
+        Ok(RpcProgram {
+            contract_address,
+            package_cid,
+            state: account.storage().clone(),
+            balance: account.credits().saturating_sub(account.debits()),
+        })
+    }
+
+    async fn call_program(
+        &self,
+        txn: ContractTransaction,
+    ) -> Result<RpcTransactionRecord, RpseeError> {
+        if !matches!(txn.action(), ContractAction::Call { .. }) {
+            return Err(RpseeError::Custom(
+                "callProgram only accepts contract calls".to_string(),
+            ));
+        }
+
+        let txn = TransactionKind::from(txn);
+
+        self.events_tx
+            .send(Event::NewTxnCreated(txn.clone()).into())
+            .await
+            .map_err(|err| {
+                error!("could not queue transaction to mempool: {err}");
+                RpseeError::Custom(err.to_string())
+            })?;
+
+        Ok(RpcTransactionRecord::from(txn))
+    }
+
+    async fn get_transaction_receipt(
+        &self,
+        transaction_digest: RpcTransactionDigest,
+    ) -> Result<TransactionReceipt, RpseeError> {
+        let parsed_digest = transaction_digest
+            .parse::<TransactionDigest>()
+            .map_err(|_err| RpseeError::Custom("unable to parse transaction digest".to_string()))?;
+
+        self.vrrbdb_read_handle
+            .get_receipt(&parsed_digest)
+            .map_err(|err| RpseeError::Custom(err.to_string()))
+    }
+
+    async fn get_account_nonce(&self, address: Address) -> Result<RpcAccountNonce, RpseeError> {
+        let account = self
+            .vrrbdb_read_handle
+            .get_account_by_address(&address)
+            .ok();
+
+        let pending_txns = self
+            .mempool_read_handle_factory
+            .values()
+            .iter()
+            .filter(|txn| txn.sender_address() == address)
+            .count();
+
+        Ok(RpcAccountNonce {
+            exists: account.is_some(),
+            account_nonce: account.as_ref().map(Account::nonce).unwrap_or_default(),
+            pending_txns,
+        })
+    }
+
+    async fn get_transaction_count(&self, account: Address) -> Result<usize, RpseeError> {
+        let txns = self
+            .vrrbdb_read_handle
+            .transaction_store_values()
+            .map_err(|err| RpseeError::Custom(format!("Failed to read values: {err}")))?;
+
+        let count = txns
+            .values()
+            .filter(|txn| txn.sender_address() == account)
+            .count();
+
+        Ok(count)
     }
 
     async fn get_node_health(&self) -> Result<NodeHealthReport, RpseeError> {
-        error!("getNodeHealth is not implemented");
-        Ok(Default::default())
+        let block_count = self.with_block_index(|index, _| index.len())?;
+        let last_block = self.last_block()?;
+
+        let report = NodeHealthReport {
+            last_block_hash: last_block.as_ref().map(|block| block.hash()),
+            last_block_height: last_block.as_ref().and_then(|block| block.height()),
+            round: last_block
+                .as_ref()
+                .map(|block| block.round())
+                .unwrap_or_default(),
+            block_count,
+            mempool_size: self.mempool_read_handle_factory.handle().len(),
+        };
+
+        Ok(report)
     }
 
     async fn get_claims_by_account_id(&self, address: Address) -> Result<Claims, RpseeError> {
@@ -298,12 +639,183 @@ impl RpcApiServer for RpcServerImpl {
     }
 
     async fn get_membership_config(&self) -> Result<QuorumMembershipConfig, RpseeError> {
-        error!("getMembershipConfig is not implemented");
-        Ok(Default::default())
+        self.membership_config
+            .clone()
+            .ok_or_else(|| RpseeError::Custom("node does not belong to any quorum".to_string()))
     }
 
     async fn get_last_block(&self) -> Result<Option<Block>, RpseeError> {
-        error!("getLastBlock is not implemented");
-        Ok(None)
+        self.last_block()
+    }
+
+    fn subscribe_blocks(
+        &self,
+        mut sink: SubscriptionSink,
+        filter: Option<BlockSubscriptionFilter>,
+    ) -> SubscriptionResult {
+        let filter = filter.unwrap_or_default();
+
+        // Subscribe before replaying so blocks appended in the meantime are not missed
+        let mut events_rx = self.subscription_events_tx.subscribe();
+
+        let replayed_blocks = match &filter.resume_from {
+            Some(block_hash) => match self.blocks_after(block_hash) {
+                Ok(blocks) => blocks,
+                Err(err) => {
+                    sink.reject(err)?;
+                    return Ok(());
+                }
+            },
+            None => Vec::new(),
+        };
+
+        sink.accept()?;
+
+        let server = self.clone();
+
+        // Blocks appended while the subscription was being set up are both
+        // replayed and announced live. Live announcements of replayed blocks
+        // are skipped until the stream moves past the last replayed round.
+        let last_replayed_round = replayed_blocks.last().map(Block::round);
+        let mut replayed_hashes: HashSet<String> =
+            replayed_blocks.iter().map(Block::hash).collect();
+
+        tokio::spawn(async move {
+            for block in replayed_blocks {
+                if !filter.matches(&block) {
+                    continue;
+                }
+
+                let notification = BlockNotification {
+                    kind: BlockEventKind::Appended,
+                    block,
+                };
+
+                if !matches!(sink.send(&notification), Ok(true)) {
+                    return;
+                }
+            }
+
+            while let Some(event) = next_event(&mut events_rx).await {
+                let notification = match event {
+                    Event::BlockAppended(block_hash) => {
+                        if replayed_hashes.remove(&block_hash) {
+                            continue;
+                        }
+
+                        server
+                            .block_by_hash(block_hash)
+                            .ok()
+                            .flatten()
+                            .map(|block| {
+                                if last_replayed_round.is_some_and(|round| block.round() > round) {
+                                    replayed_hashes.clear();
+                                }
+
+                                BlockNotification {
+                                    kind: BlockEventKind::Appended,
+                                    block,
+                                }
+                            })
+                    }
+                    Event::ConvergenceBlockCertified(block) => Some(BlockNotification {
+                        kind: BlockEventKind::Certified,
+                        block: block.into(),
+                    }),
+                    _ => None,
+                };
+
+                if let Some(notification) = notification {
+                    if filter.matches(&notification.block)
+                        && !matches!(sink.send(&notification), Ok(true))
+                    {
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn subscribe_transactions(
+        &self,
+        mut sink: SubscriptionSink,
+        filter: Option<TransactionSubscriptionFilter>,
+    ) -> SubscriptionResult {
+        let filter = filter.unwrap_or_default();
+        let mut events_rx = self.subscription_events_tx.subscribe();
+        let mempool_read_handle_factory = self.mempool_read_handle_factory.clone();
+
+        sink.accept()?;
+
+        tokio::spawn(async move {
+            while let Some(event) = next_event(&mut events_rx).await {
+                let change = match event {
+                    Event::TxnAddedToMempool(digest) => mempool_read_handle_factory
+                        .get(&digest)
+                        .map(|record| (TransactionEventKind::AddedToMempool, record.txn)),
+                    Event::TxnValidated(txn) => Some((TransactionEventKind::Validated, txn)),
+                    _ => None,
+                };
+
+                if let Some((kind, txn)) = change {
+                    if !filter.matches(kind, &txn) {
+                        continue;
+                    }
+
+                    let notification = TransactionNotification {
+                        kind,
+                        txn: RpcTransactionRecord::from(txn),
+                    };
+
+                    if !matches!(sink.send(&notification), Ok(true)) {
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn subscribe_claims(
+        &self,
+        mut sink: SubscriptionSink,
+        filter: Option<ClaimSubscriptionFilter>,
+    ) -> SubscriptionResult {
+        let filter = filter.unwrap_or_default();
+        let mut events_rx = self.subscription_events_tx.subscribe();
+
+        sink.accept()?;
+
+        tokio::spawn(async move {
+            while let Some(event) = next_event(&mut events_rx).await {
+                if let Event::ClaimCreated(claim) = event {
+                    if filter.matches(&claim) && !matches!(sink.send(&claim), Ok(true)) {
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+}
+
+/// Waits for the next event forwarded to the server. Events dropped because a
+/// subscriber fell behind are skipped. Returns `None` once the node stops.
+async fn next_event(events_rx: &mut broadcast::Receiver<EventMessage>) -> Option<Event> {
+    loop {
+        match events_rx.recv().await {
+            Ok(message) => match Event::from(message) {
+                Event::Stop => return None,
+                event => return Some(event),
+            },
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                warn!("subscription fell behind, {skipped} events were dropped");
+            }
+            Err(broadcast::error::RecvError::Closed) => return None,
+        }
     }
 }
@@ -0,0 +1,159 @@
+use block::Block;
+use primitives::Address;
+use serde::{Deserialize, Serialize};
+use vrrb_core::claim::Claim;
+use vrrb_core::transactions::{Transaction, TransactionKind};
+
+use crate::rpc::api::RpcTransactionRecord;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockKind {
+    Genesis,
+    Proposal,
+    Convergence,
+}
+
+impl From<&Block> for BlockKind {
+    fn from(block: &Block) -> Self {
+        match block {
+            Block::Genesis { .. } => BlockKind::Genesis,
+            Block::Proposal { .. } => BlockKind::Proposal,
+            Block::Convergence { .. } => BlockKind::Convergence,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BlockSubscriptionFilter {
+    /// Only notify about blocks of this kind
+    pub block_kind: Option<BlockKind>,
+    /// Hash of the last block a reconnecting client has seen. Every block
+    /// stored in the DAG after it is replayed before live notifications begin.
+    pub resume_from: Option<String>,
+}
+
+impl BlockSubscriptionFilter {
+    pub fn matches(&self, block: &Block) -> bool {
+        self.block_kind
+            .map_or(true, |kind| kind == BlockKind::from(block))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockEventKind {
+    /// The block was appended to the node's DAG
+    Appended,
+    /// The convergence block received its quorum certificate
+    Certified,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BlockNotification {
+    pub kind: BlockEventKind,
+    pub block: Block,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionEventKind {
+    AddedToMempool,
+    Validated,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TransactionSubscriptionFilter {
+    pub sender_address: Option<Address>,
+    pub receiver_address: Option<Address>,
+    /// Only notify about transactions that went through this kind of change
+    pub kind: Option<TransactionEventKind>,
+}
+
+impl TransactionSubscriptionFilter {
+    pub fn matches(&self, kind: TransactionEventKind, txn: &TransactionKind) -> bool {
+        self.kind.map_or(true, |expected| expected == kind)
+            && self
+                .sender_address
+                .as_ref()
+                .map_or(true, |address| address == &txn.sender_address())
+            && self
+                .receiver_address
+                .as_ref()
+                .map_or(true, |address| address == &txn.receiver_address())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionNotification {
+    pub kind: TransactionEventKind,
+    pub txn: RpcTransactionRecord,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ClaimSubscriptionFilter {
+    pub address: Option<Address>,
+}
+
+impl ClaimSubscriptionFilter {
+    pub fn matches(&self, claim: &Claim) -> bool {
+        self.address
+            .as_ref()
+            .map_or(true, |address| address == &claim.address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use primitives::generate_mock_account_keypair;
+    use secp256k1::Message;
+
+    use super::*;
+
+    #[test]
+    fn empty_transaction_filter_matches_everything() {
+        let filter = TransactionSubscriptionFilter::default();
+        let txn = TransactionKind::default();
+
+        assert!(filter.matches(TransactionEventKind::AddedToMempool, &txn));
+        assert!(filter.matches(TransactionEventKind::Validated, &txn));
+    }
+
+    #[test]
+    fn transaction_filter_matches_on_addresses_and_kind() {
+        let (sender_secret_key, sender_public_key) = generate_mock_account_keypair();
+        let (_, receiver_public_key) = generate_mock_account_keypair();
+        let sender_address = Address::new(sender_public_key);
+        let receiver_address = Address::new(receiver_public_key);
+
+        type H = secp256k1::hashes::sha256::Hash;
+        let signature = sender_secret_key.sign_ecdsa(Message::from_hashed_data::<H>(b"vrrb"));
+
+        let txn = TransactionKind::transfer_builder()
+            .timestamp(0)
+            .sender_address(sender_address.clone())
+            .sender_public_key(sender_public_key)
+            .receiver_address(receiver_address.clone())
+            .amount(10)
+            .signature(signature)
+            .nonce(0)
+            .build_kind()
+            .unwrap();
+
+        let filter = TransactionSubscriptionFilter {
+            sender_address: Some(sender_address.clone()),
+            kind: Some(TransactionEventKind::Validated),
+            ..Default::default()
+        };
+
+        assert!(filter.matches(TransactionEventKind::Validated, &txn));
+        assert!(!filter.matches(TransactionEventKind::AddedToMempool, &txn));
+
+        let filter = TransactionSubscriptionFilter {
+            receiver_address: Some(sender_address),
+            ..Default::default()
+        };
+
+        assert!(!filter.matches(TransactionEventKind::Validated, &txn));
+    }
+}
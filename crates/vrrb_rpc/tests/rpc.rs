@@ -1,19 +1,53 @@
 use std::{collections::HashMap, net::SocketAddr};
 
-use events::{EventMessage, DEFAULT_BUFFER};
+use events::{Event, EventMessage, DEFAULT_BUFFER};
+use miner::test_helpers::{
+    add_genesis_to_dag, append_proposal_blocks_to_dag, build_single_proposal_block,
+    create_miner_from_keypair, mine_next_convergence_block, MinerDag,
+};
 use primitives::{generate_mock_account_keypair, Address};
 use secp256k1::Message;
+use signer::engine::SignerEngine;
 use storage::storage_utils::remove_vrrb_data_dir;
 use tokio::sync::mpsc::channel;
-use vrrb_core::transactions::{generate_transfer_digest_vec, Token, Transaction, TransactionKind};
+use vrrb_core::keypair::Keypair;
+use vrrb_core::transactions::{
+    generate_transfer_digest_vec, Token, Transaction, TransactionKind, BASE_FEE,
+};
 use vrrb_rpc::rpc::{
     api::{RpcApiClient, RpcTransactionRecord},
     client::create_client,
+    subscriptions::{
+        BlockEventKind, BlockSubscriptionFilter, TransactionEventKind,
+        TransactionSubscriptionFilter,
+    },
     *,
 };
 
 mod common;
 
+/// Appends a proposal block referencing `ref_hash` to the DAG and returns its
+/// hash
+fn append_proposal(dag: &mut MinerDag, ref_hash: String, round: u128) -> String {
+    let keypair = Keypair::random();
+    let miner = create_miner_from_keypair(&keypair);
+    let proposal = build_single_proposal_block(
+        ref_hash,
+        0,
+        0,
+        round,
+        0,
+        miner.claim.clone(),
+        SignerEngine::new(
+            *keypair.get_miner_public_key(),
+            *keypair.get_miner_secret_key(),
+        ),
+    );
+    append_proposal_blocks_to_dag(dag, vec![proposal.clone()]);
+
+    proposal.hash
+}
+
 #[tokio::test]
 async fn server_can_publish_transactions_to_be_created() {
     remove_vrrb_data_dir();
@@ -54,6 +88,8 @@ async fn server_can_publish_transactions_to_be_created() {
         token,
         amount,
         nonce,
+        BASE_FEE,
+        None,
     );
 
     type H = secp256k1::hashes::sha256::Hash;
@@ -87,6 +123,7 @@ async fn server_can_publish_transactions_to_be_created() {
         signature: signature.to_string().clone(),
         validators: HashMap::new(),
         nonce: 0,
+        fee: BASE_FEE,
     };
 
     let result_ser = serde_json::to_string_pretty(&rec).unwrap();
@@ -96,3 +133,243 @@ async fn server_can_publish_transactions_to_be_created() {
 
     handle.stop().expect("Unable to stop server");
 }
+
+#[tokio::test]
+async fn server_imports_mempool_snapshots() {
+    remove_vrrb_data_dir();
+
+    let (events_tx, mut events_rx) = channel::<EventMessage>(DEFAULT_BUFFER);
+
+    let json_rpc_server_config = JsonRpcServerConfig {
+        address: "127.0.0.1:0".parse().unwrap(),
+        events_tx,
+        ..Default::default()
+    };
+
+    let (handle, rpc_server_address) = JsonRpcServer::run(&json_rpc_server_config).await.unwrap();
+
+    let client = create_client(rpc_server_address).await.unwrap();
+
+    assert!(client.export_mempool().await.unwrap().is_empty());
+
+    let snapshot = vec![TransactionKind::default(), TransactionKind::default()];
+    let imported = client.import_mempool(snapshot.clone()).await.unwrap();
+
+    assert_eq!(imported, 2);
+
+    for txn in snapshot {
+        let event: Event = events_rx.recv().await.unwrap().into();
+        assert_eq!(event, Event::NewTxnCreated(txn));
+    }
+
+    handle.stop().expect("Unable to stop server");
+}
+
+#[tokio::test]
+async fn server_serves_block_queries_from_empty_dag() {
+    remove_vrrb_data_dir();
+
+    let json_rpc_server_config = JsonRpcServerConfig {
+        address: "127.0.0.1:0".parse().unwrap(),
+        ..Default::default()
+    };
+
+    let (handle, rpc_server_address) = JsonRpcServer::run(&json_rpc_server_config).await.unwrap();
+
+    let client = create_client(rpc_server_address).await.unwrap();
+
+    assert!(client.get_blocks().await.unwrap().is_empty());
+    assert!(client.get_last_block().await.unwrap().is_none());
+    assert!(client
+        .get_block_by_hash("unknown".to_string())
+        .await
+        .unwrap()
+        .is_none());
+    assert!(client
+        .get_blocks_by_height_range(0, 10)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(client.get_blocks_by_height_range(10, 0).await.is_err());
+    assert!(client.get_round().await.is_err());
+    assert!(client.get_membership_config().await.is_err());
+
+    let health = client.get_node_health().await.unwrap();
+    assert_eq!(health.block_count, 0);
+    assert_eq!(health.mempool_size, 0);
+
+    handle.stop().expect("Unable to stop server");
+}
+
+#[tokio::test]
+async fn server_serves_block_queries_from_populated_dag() {
+    remove_vrrb_data_dir();
+
+    let json_rpc_server_config = JsonRpcServerConfig {
+        address: "127.0.0.1:0".parse().unwrap(),
+        ..Default::default()
+    };
+
+    let mut dag = json_rpc_server_config.dag.clone();
+    let genesis_hash = add_genesis_to_dag(&mut dag).unwrap();
+    let convergence_hash = mine_next_convergence_block(dag.clone()).unwrap();
+
+    let (handle, rpc_server_address) = JsonRpcServer::run(&json_rpc_server_config).await.unwrap();
+
+    let client = create_client(rpc_server_address).await.unwrap();
+
+    // The genesis block, the proposal block referencing it and the
+    // convergence block
+    let blocks = client.get_blocks().await.unwrap();
+    assert_eq!(blocks.len(), 3);
+    assert_eq!(blocks.first().unwrap().hash(), genesis_hash);
+    assert_eq!(blocks.last().unwrap().hash(), convergence_hash);
+
+    let last_block = client.get_last_block().await.unwrap().unwrap();
+    assert_eq!(last_block.hash(), convergence_hash);
+    assert_eq!(client.get_round().await.unwrap(), last_block.round());
+
+    let confirmed: Vec<String> = client
+        .get_blocks_by_height_range(0, 10)
+        .await
+        .unwrap()
+        .iter()
+        .map(|block| block.hash())
+        .collect();
+    assert_eq!(
+        confirmed,
+        vec![genesis_hash.clone(), convergence_hash.clone()]
+    );
+
+    let genesis: Vec<String> = client
+        .get_blocks_by_height_range(0, 0)
+        .await
+        .unwrap()
+        .iter()
+        .map(|block| block.hash())
+        .collect();
+    assert_eq!(genesis, vec![genesis_hash.clone()]);
+
+    assert_eq!(
+        client
+            .get_block_by_hash(genesis_hash.clone())
+            .await
+            .unwrap()
+            .map(|block| block.hash()),
+        Some(genesis_hash)
+    );
+
+    // Blocks appended after the server started are indexed when announced,
+    // and picked up by the next query otherwise
+    let proposal_hash = append_proposal(&mut dag, convergence_hash.clone(), last_block.round() + 1);
+
+    let blocks = client.get_blocks().await.unwrap();
+    assert_eq!(blocks.len(), 4);
+    assert_eq!(blocks.last().unwrap().hash(), proposal_hash);
+    assert_eq!(
+        client.get_last_block().await.unwrap().unwrap().hash(),
+        convergence_hash
+    );
+
+    let health = client.get_node_health().await.unwrap();
+    assert_eq!(health.block_count, 4);
+    assert_eq!(health.last_block_hash, Some(convergence_hash));
+    assert_eq!(health.last_block_height, Some(last_block.height().unwrap()));
+
+    handle.stop().expect("Unable to stop server");
+}
+
+#[tokio::test]
+async fn server_streams_validated_transactions_to_subscribers() {
+    remove_vrrb_data_dir();
+
+    let json_rpc_server_config = JsonRpcServerConfig {
+        address: "127.0.0.1:0".parse().unwrap(),
+        ..Default::default()
+    };
+
+    let subscription_events_tx = json_rpc_server_config.subscription_events_tx.clone();
+
+    let (handle, rpc_server_address) = JsonRpcServer::run(&json_rpc_server_config).await.unwrap();
+
+    let client = create_client(rpc_server_address).await.unwrap();
+
+    let filter = TransactionSubscriptionFilter {
+        kind: Some(TransactionEventKind::Validated),
+        ..Default::default()
+    };
+
+    let mut subscription = client.subscribe_transactions(Some(filter)).await.unwrap();
+
+    let txn = TransactionKind::default();
+
+    subscription_events_tx
+        .send(Event::TxnValidated(txn.clone()).into())
+        .unwrap();
+
+    let notification = subscription.next().await.unwrap().unwrap();
+
+    assert_eq!(notification.kind, TransactionEventKind::Validated);
+    assert_eq!(notification.txn.id, txn.id().digest_string());
+
+    handle.stop().expect("Unable to stop server");
+}
+
+#[tokio::test]
+async fn server_does_not_repeat_replayed_blocks_to_subscribers() {
+    remove_vrrb_data_dir();
+
+    let json_rpc_server_config = JsonRpcServerConfig {
+        address: "127.0.0.1:0".parse().unwrap(),
+        ..Default::default()
+    };
+
+    let subscription_events_tx = json_rpc_server_config.subscription_events_tx.clone();
+    let mut dag = json_rpc_server_config.dag.clone();
+    let genesis_hash = add_genesis_to_dag(&mut dag).unwrap();
+    let convergence_hash = mine_next_convergence_block(dag.clone()).unwrap();
+
+    let (handle, rpc_server_address) = JsonRpcServer::run(&json_rpc_server_config).await.unwrap();
+
+    let client = create_client(rpc_server_address).await.unwrap();
+
+    let replayed: Vec<String> = client
+        .get_blocks()
+        .await
+        .unwrap()
+        .iter()
+        .skip(1)
+        .map(|block| block.hash())
+        .collect();
+
+    let filter = BlockSubscriptionFilter {
+        resume_from: Some(genesis_hash),
+        ..Default::default()
+    };
+    let mut subscription = client.subscribe_blocks(Some(filter)).await.unwrap();
+
+    // The convergence block is announced after it was replayed, as happens
+    // when it is appended while the subscription is set up
+    subscription_events_tx
+        .send(Event::BlockAppended(convergence_hash.clone()).into())
+        .unwrap();
+
+    let round = client.get_round().await.unwrap();
+    let proposal_hash = append_proposal(&mut dag, convergence_hash, round + 1);
+    subscription_events_tx
+        .send(Event::BlockAppended(proposal_hash.clone()).into())
+        .unwrap();
+
+    let mut notified = Vec::new();
+    for _ in 0..replayed.len() + 1 {
+        let notification = subscription.next().await.unwrap().unwrap();
+        assert_eq!(notification.kind, BlockEventKind::Appended);
+        notified.push(notification.block.hash());
+    }
+
+    let mut expected = replayed;
+    expected.push(proposal_hash);
+    assert_eq!(notified, expected);
+
+    handle.stop().expect("Unable to stop server");
+}
@@ -0,0 +1,184 @@
+//! Hierarchical deterministic key derivation
+//!
+//! Wallets are restored from a BIP-39 mnemonic phrase, and the keys of their
+//! addresses are derived from its seed following BIP-32, under the BIP-44
+//! path `m/44'/VERSATUS_COIN_TYPE'/0'/0/index`. The same phrase always
+//! yields the same addresses.
+
+use std::{fmt, str::FromStr};
+
+pub use bip39::Mnemonic;
+use hmac::{Hmac, Mac};
+use secp256k1::{PublicKey, Scalar, Secp256k1, SecretKey};
+use sha2::Sha512;
+
+use super::{WalletError, WalletResult};
+
+/// BIP-44 coin type of Versatus addresses
+pub const VERSATUS_COIN_TYPE: u32 = 7780;
+
+/// Number of words in newly generated mnemonic phrases
+pub const DEFAULT_MNEMONIC_WORD_COUNT: usize = 24;
+
+/// Offset of hardened child indices
+const HARDENED: u32 = 1 << 31;
+
+/// Key of the HMAC deriving master keys from seeds, as set by BIP-32
+const MASTER_KEY_HMAC_KEY: &[u8] = b"Bitcoin seed";
+
+/// Generates a new random mnemonic phrase of `word_count` words
+pub fn generate_mnemonic(word_count: usize) -> WalletResult<Mnemonic> {
+    Ok(Mnemonic::generate(word_count)?)
+}
+
+/// Parses a mnemonic phrase, checking its words and checksum
+pub fn parse_mnemonic(phrase: &str) -> WalletResult<Mnemonic> {
+    Ok(Mnemonic::parse_normalized(phrase.trim())?)
+}
+
+/// Returns the derivation path of the address at `index`
+pub fn address_path(index: u32) -> String {
+    format!("m/44'/{VERSATUS_COIN_TYPE}'/0'/0/{index}")
+}
+
+/// A BIP-32 extended secret key
+#[derive(Clone)]
+pub struct ExtendedSecretKey {
+    secret_key: SecretKey,
+    chain_code: [u8; 32],
+}
+
+impl ExtendedSecretKey {
+    /// Derives the master key of `seed`
+    pub fn from_seed(seed: &[u8]) -> WalletResult<Self> {
+        Self::from_hmac(MASTER_KEY_HMAC_KEY, seed, |il| {
+            SecretKey::from_slice(il).map_err(|err| WalletError::KeyDerivation(err.to_string()))
+        })
+    }
+
+    pub fn secret_key(&self) -> SecretKey {
+        self.secret_key
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey::from_secret_key(&Secp256k1::new(), &self.secret_key)
+    }
+
+    /// Derives the child key at `index`, hardened when `index` is at least
+    /// 2^31
+    pub fn derive_child(&self, index: u32) -> WalletResult<Self> {
+        let mut data = Vec::with_capacity(37);
+        if index >= HARDENED {
+            data.push(0);
+            data.extend_from_slice(&self.secret_key.secret_bytes());
+        } else {
+            data.extend_from_slice(&self.public_key().serialize());
+        }
+        data.extend_from_slice(&index.to_be_bytes());
+
+        Self::from_hmac(&self.chain_code, &data, |il| {
+            let mut tweak = [0; 32];
+            tweak.copy_from_slice(il);
+            let tweak = Scalar::from_be_bytes(tweak)
+                .map_err(|err| WalletError::KeyDerivation(err.to_string()))?;
+
+            self.secret_key
+                .add_tweak(&tweak)
+                .map_err(|err| WalletError::KeyDerivation(err.to_string()))
+        })
+    }
+
+    /// Derives the key at `path`, such as `m/44'/0'/0'/0/1`, relative to
+    /// this key
+    pub fn derive_path(&self, path: &str) -> WalletResult<Self> {
+        let mut components = path.split('/');
+        if components.next() != Some("m") {
+            return Err(WalletError::KeyDerivation(format!(
+                "derivation path {path} does not start with m"
+            )));
+        }
+
+        components.try_fold(self.clone(), |key, component| {
+            let (index, offset) = match component.strip_suffix('\'') {
+                Some(index) => (index, HARDENED),
+                None => (component, 0),
+            };
+            let index = u32::from_str(index)
+                .ok()
+                .filter(|index| *index < HARDENED)
+                .ok_or_else(|| {
+                    WalletError::KeyDerivation(format!("invalid derivation path {path}"))
+                })?;
+
+            key.derive_child(index + offset)
+        })
+    }
+
+    fn from_hmac(
+        key: &[u8],
+        data: &[u8],
+        secret_key: impl FnOnce(&[u8]) -> WalletResult<SecretKey>,
+    ) -> WalletResult<Self> {
+        let mut mac = Hmac::<Sha512>::new_from_slice(key)
+            .map_err(|err| WalletError::KeyDerivation(err.to_string()))?;
+        mac.update(data);
+        let output = mac.finalize().into_bytes();
+        let (il, ir) = output.split_at(32);
+
+        let mut chain_code = [0; 32];
+        chain_code.copy_from_slice(ir);
+
+        Ok(Self {
+            secret_key: secret_key(il)?,
+            chain_code,
+        })
+    }
+}
+
+impl fmt::Debug for ExtendedSecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtendedSecretKey")
+            .field("public_key", &self.public_key())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Derives the keys of a wallet's addresses from its seed
+#[derive(Clone)]
+pub struct HdKeychain {
+    /// The key at `m/44'/VERSATUS_COIN_TYPE'/0'/0`, the parent of every
+    /// address key
+    addresses: ExtendedSecretKey,
+}
+
+impl HdKeychain {
+    pub fn from_mnemonic(mnemonic: &Mnemonic, passphrase: &str) -> WalletResult<Self> {
+        Self::from_seed(&mnemonic.to_seed(passphrase))
+    }
+
+    pub fn from_seed(seed: &[u8]) -> WalletResult<Self> {
+        let path = format!("m/44'/{VERSATUS_COIN_TYPE}'/0'/0");
+        let addresses = ExtendedSecretKey::from_seed(seed)?.derive_path(&path)?;
+
+        Ok(Self { addresses })
+    }
+
+    /// Derives the keypair of the address at `index`
+    pub fn derive(&self, index: u32) -> WalletResult<(SecretKey, PublicKey)> {
+        if index >= HARDENED {
+            return Err(WalletError::KeyDerivation(format!(
+                "address index {index} is out of range"
+            )));
+        }
+
+        let key = self.addresses.derive_child(index)?;
+
+        Ok((key.secret_key(), key.public_key()))
+    }
+}
+
+impl fmt::Debug for HdKeychain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HdKeychain").finish_non_exhaustive()
+    }
+}
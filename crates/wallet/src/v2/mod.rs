@@ -1,5 +1,8 @@
+pub mod hd;
+pub mod sync;
+
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     net::{IpAddr, Ipv4Addr, SocketAddr},
     str::FromStr,
 };
@@ -11,12 +14,18 @@ use serde::{Deserialize, Serialize};
 use telemetry::error;
 use thiserror::Error;
 use vrrb_core::account::Account;
-use vrrb_core::transactions::{RpcTransactionDigest, Token, Transaction, TransactionKind};
+use vrrb_core::multisig::MultisigPolicy;
+use vrrb_core::transactions::{
+    placeholder_signature, RpcTransactionDigest, Token, Transaction, TransactionKind,
+    TransferBuilder, UnsignedTransaction,
+};
 use vrrb_rpc::rpc::{
-    api::{RpcApiClient, RpcTransactionRecord},
+    api::{MempoolSnapshot, RpcApiClient, RpcTransactionRecord},
     client::create_client,
 };
 
+use self::{hd::HdKeychain, sync::WalletCache};
+
 type WalletResult<Wallet> = Result<Wallet, WalletError>;
 
 #[derive(Error, Debug)]
@@ -27,11 +36,34 @@ pub enum WalletError {
     #[error("API error: {0}")]
     ApiError(#[from] vrrb_rpc::ApiError),
 
+    #[error("key derivation error: {0}")]
+    KeyDerivation(String),
+
+    #[error("mnemonic error: {0}")]
+    Mnemonic(#[from] bip39::Error),
+
     #[error("custom error")]
     Custom(String),
 }
 
-pub type AddressAlias = u32;
+/// Name given to one of a wallet's addresses
+pub type AddressAlias = String;
+
+/// Alias of the address a wallet uses unless told otherwise. For wallets
+/// restored from a mnemonic phrase, it is the address at index 0.
+pub const DEFAULT_ADDRESS_ALIAS: &str = "default";
+
+/// One of the addresses a wallet holds the key of
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletAddress {
+    pub alias: AddressAlias,
+    /// Index the key was derived at, none for wallets holding a single key
+    pub index: Option<u32>,
+    /// Derivation path of the key, none for wallets holding a single key
+    pub path: Option<String>,
+    pub public_key: PublicKey,
+    pub address: Address,
+}
 
 #[derive(Debug)]
 pub struct Wallet {
@@ -40,10 +72,10 @@ pub struct Wallet {
     client: Client,
     pub public_key: PublicKey,
     pub address: Address,
-    // TODO: revise this when we reimplement hierarchical deterministic wallets
-    // pub addresses: HashMap<AddressAlias, Address>,
-    // pub accounts: HashMap<Address, Account>,
-    pub nonce: u128,
+    keychain: Option<HdKeychain>,
+    secret_keys: HashMap<AddressAlias, SecretKey>,
+    pub addresses: BTreeMap<AddressAlias, WalletAddress>,
+    cache: WalletCache,
 }
 
 #[derive(Debug)]
@@ -51,18 +83,19 @@ pub struct WalletConfig {
     pub rpc_server_address: SocketAddr,
     pub secret_key: SecretKey,
     pub public_key: PublicKey,
-    // TODO: revise this when we reimplement hierarchical deterministic wallets
-    // pub accounts: HashMap<Address, Account>,
-    // pub addresses: HashMap<AddressAlias, Address>,
+    /// Keychain the wallet's addresses are derived from, none for wallets
+    /// holding the single key above
+    pub keychain: Option<HdKeychain>,
+    /// Indices of the derived addresses to restore, by alias
+    pub addresses: BTreeMap<AddressAlias, u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct WalletInfo {
-    pub secret_key: SecretKey,
     pub public_key: String,
-    // TODO: revise this when we reimplement hierarchical deterministic wallets
     pub address: String,
-    // pub addresses: HashMap<u32, Address>,
+    pub addresses: BTreeMap<AddressAlias, WalletAddress>,
+    /// Nonce of the next transaction sent from the default address
     pub nonce: u128,
 }
 
@@ -74,33 +107,43 @@ impl Default for WalletConfig {
         let secret_key = SecretKey::from_slice(&[0xcd; 32]).unwrap();
         let public_key = PublicKey::from_secret_key(&secp, &secret_key);
         let rpc_server_address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9293);
-        // TODO: revise this when we reimplement hierarchical deterministic wallets
-        // let accounts = HashMap::new();
-        // let addresses = HashMap::new();
 
         Self {
             rpc_server_address,
             secret_key,
             public_key,
-            // accounts,
-            // addresses,
+            keychain: None,
+            addresses: BTreeMap::new(),
         }
     }
 }
 
+impl WalletConfig {
+    /// Configures a wallet whose keys are derived from `keychain`, with its
+    /// default address at index 0 and the others at the given `addresses`
+    pub fn from_keychain(
+        rpc_server_address: SocketAddr,
+        keychain: HdKeychain,
+        addresses: BTreeMap<AddressAlias, u32>,
+    ) -> WalletResult<Self> {
+        let (secret_key, public_key) = keychain.derive(0)?;
+
+        Ok(Self {
+            rpc_server_address,
+            secret_key,
+            public_key,
+            keychain: Some(keychain),
+            addresses,
+        })
+    }
+}
+
 impl Wallet {
     /// Initiates a new wallet.
     pub async fn new(config: WalletConfig) -> WalletResult<Self> {
-        //TODO: Don't use random keypair, generate it from a
-        //mnemonic phrase seed or read from file if already generated
-
         let secret_key = config.secret_key;
         let public_key = config.public_key;
 
-        // TODO: revise this when hierarchically deterministic accounts are implemented
-        // let addresses = config.addresses;
-        // let accounts = config.accounts;
-
         //TODO: get rpc server address from config file or env variable
         let client = create_client(config.rpc_server_address).await?;
 
@@ -109,29 +152,138 @@ impl Wallet {
             "DO NOT SHARE OR LOSE YOUR SECRET KEY:", &secret_key, &public_key,
         );
 
-        let wallet = Wallet {
+        let mut wallet = Wallet {
             secret_key,
             public_key,
             address: Address::from(public_key),
             welcome_message,
             client,
-            // TODO: revise this when we reimplement hierarchical deterministic wallets
-            // addresses,
-            // accounts,
-            nonce: 0,
+            keychain: config.keychain,
+            secret_keys: HashMap::new(),
+            addresses: BTreeMap::new(),
+            cache: WalletCache::default(),
         };
 
+        let default_index = wallet.keychain.as_ref().map(|_| 0);
+        wallet.insert_address(
+            DEFAULT_ADDRESS_ALIAS.to_string(),
+            default_index,
+            secret_key,
+            public_key,
+        );
+        for (alias, index) in config.addresses {
+            if alias != DEFAULT_ADDRESS_ALIAS {
+                wallet.restore_address(alias, index)?;
+            }
+        }
+
         Ok(wallet)
     }
 
+    /// Restores a wallet from a BIP-39 mnemonic phrase, along with the
+    /// derived addresses at the given indices
+    pub async fn restore_from_mnemonic(
+        phrase: &str,
+        passphrase: &str,
+        addresses: BTreeMap<AddressAlias, u32>,
+        rpc_server: SocketAddr,
+    ) -> WalletResult<Self> {
+        let mnemonic = hd::parse_mnemonic(phrase)?;
+        let keychain = HdKeychain::from_mnemonic(&mnemonic, passphrase)?;
+
+        Wallet::new(WalletConfig::from_keychain(
+            rpc_server, keychain, addresses,
+        )?)
+        .await
+    }
+
+    /// Derives the key of a new address at the next unused index and names
+    /// it `alias`
+    pub fn derive_address(
+        &mut self,
+        alias: impl Into<AddressAlias>,
+    ) -> WalletResult<WalletAddress> {
+        let index = self
+            .addresses
+            .values()
+            .filter_map(|address| address.index)
+            .max()
+            .map_or(0, |index| index + 1);
+
+        self.restore_address(alias.into(), index)
+    }
+
+    /// Derives the key of the address at `index` and names it `alias`
+    pub fn restore_address(
+        &mut self,
+        alias: AddressAlias,
+        index: u32,
+    ) -> WalletResult<WalletAddress> {
+        let keychain = self.keychain.as_ref().ok_or_else(|| {
+            WalletError::Custom("wallet was not restored from a mnemonic phrase".to_string())
+        })?;
+        if self.addresses.contains_key(&alias) {
+            return Err(WalletError::Custom(format!(
+                "wallet already has an address named {alias}"
+            )));
+        }
+        if let Some(address) = self
+            .addresses
+            .values()
+            .find(|address| address.index == Some(index))
+        {
+            return Err(WalletError::Custom(format!(
+                "address {index} is already named {}",
+                address.alias
+            )));
+        }
+
+        let (secret_key, public_key) = keychain.derive(index)?;
+
+        Ok(self.insert_address(alias, Some(index), secret_key, public_key))
+    }
+
+    /// Looks an address up by alias
+    pub fn get_address(&self, alias: &str) -> Option<&WalletAddress> {
+        self.addresses.get(alias)
+    }
+
+    /// Returns the index of every derived address, by alias, to be passed
+    /// back in [WalletConfig::addresses] when the wallet is restored
+    pub fn address_indices(&self) -> BTreeMap<AddressAlias, u32> {
+        self.addresses
+            .values()
+            .filter_map(|address| Some((address.alias.clone(), address.index?)))
+            .collect()
+    }
+
+    fn insert_address(
+        &mut self,
+        alias: AddressAlias,
+        index: Option<u32>,
+        secret_key: SecretKey,
+        public_key: PublicKey,
+    ) -> WalletAddress {
+        let address = WalletAddress {
+            alias: alias.clone(),
+            index,
+            path: index.map(hd::address_path),
+            public_key,
+            address: Address::from(public_key),
+        };
+
+        self.secret_keys.insert(alias.clone(), secret_key);
+        self.addresses.insert(alias, address.clone());
+
+        address
+    }
+
     pub fn info(&self) -> WalletInfo {
         WalletInfo {
-            secret_key: self.secret_key,
             public_key: self.public_key.to_string(),
-            // TODO: revise this when we reimplement hierarchical deterministic wallets
             address: Address::from(self.public_key).to_string(),
-            // addresses: self.addresses.clone(),
-            nonce: self.nonce,
+            addresses: self.addresses.clone(),
+            nonce: self.next_nonce(DEFAULT_ADDRESS_ALIAS),
         }
     }
 
@@ -141,54 +293,173 @@ impl Wallet {
         Ok(mempool)
     }
 
+    /// Retrieves every pending transaction from the node's mempool, ready to
+    /// be imported elsewhere
+    pub async fn export_mempool(&self) -> Result<MempoolSnapshot, WalletError> {
+        let snapshot = self.client.export_mempool().await?;
+
+        Ok(snapshot)
+    }
+
+    /// Submits the transactions within a mempool snapshot to the node.
+    /// Returns the number of transactions submitted
+    pub async fn import_mempool(&self, snapshot: MempoolSnapshot) -> Result<usize, WalletError> {
+        let count = self.client.import_mempool(snapshot).await?;
+
+        Ok(count)
+    }
+
     pub async fn send_transaction(
         &mut self,
-        // TODO: revise this when we reimplement hierarchical deterministic wallets
-        // address_number: u32,
         receiver: Address,
         amount: u128,
         token: Token,
         timestamp: i64,
     ) -> Result<RpcTransactionDigest, WalletError> {
-        let sender_address = self.address.clone();
-        //TODO: revise this when we reimplement hierarchical deterministic wallets
-        // let addresses = self.addresses.clone();
-        // let sender_address = {
-        //     if let Some(addr) = addresses.get(&address_number) {
-        //         addr.clone()
-        //     } else if let Some(addr) = addresses.get(&0) {
-        //         addr.clone()
-        //     } else {
-        //         return Err(WalletError::Custom("wallet has no addresses".to_string()));
-        //     }
-        // };
-
-        let transfer_builder = TransactionKind::transfer_builder()
-            .timestamp(timestamp)
-            .sender_address(sender_address)
-            .sender_public_key(self.public_key)
-            .receiver_address(receiver)
-            .token(token)
-            .amount(amount)
-            .validators(HashMap::new())
-            .nonce(self.nonce);
+        self.send_transaction_from(DEFAULT_ADDRESS_ALIAS, receiver, amount, token, timestamp)
+            .await
+    }
 
-        let signature = self.sign_transaction(transfer_builder.build_payload().as_bytes());
+    /// Sends a transfer from the address named `from`
+    pub async fn send_transaction_from(
+        &mut self,
+        from: &str,
+        receiver: Address,
+        amount: u128,
+        token: Token,
+        timestamp: i64,
+    ) -> Result<RpcTransactionDigest, WalletError> {
+        let transfer_builder = self.transfer_builder(from, receiver, amount, token, timestamp)?;
+
+        let signature = self.sign_transaction(from, transfer_builder.build_payload().as_bytes())?;
 
         let transfer = transfer_builder
             .signature(signature)
             .build_kind()
             .map_err(|_| WalletError::Custom("Failed to build transfer transaction".to_string()))?;
 
-        self.client
-            .create_txn(transfer.clone())
-            .await
-            .map_err(|err| {
-                error!("{:?}", err.to_string());
-                WalletError::Custom(format!("API Error: {}", err))
-            })?;
+        self.broadcast_transaction(transfer).await
+    }
+
+    /// Builds a transfer from the address named `from` without signing it,
+    /// so it can be signed away from this machine
+    pub fn build_transaction_from(
+        &self,
+        from: &str,
+        receiver: Address,
+        amount: u128,
+        token: Token,
+        timestamp: i64,
+    ) -> WalletResult<UnsignedTransaction> {
+        let transfer = self
+            .transfer_builder(from, receiver, amount, token, timestamp)?
+            .signature(placeholder_signature())
+            .build_kind()
+            .map_err(|_| WalletError::Custom("Failed to build transfer transaction".to_string()))?;
+
+        Ok(UnsignedTransaction::new(transfer))
+    }
+
+    /// Builds a transfer from the multisig account at `multisig`, sent by the
+    /// address named `from`, whose key must be one of the account's keys.
+    /// The transfer is then passed between the other signers to be cosigned.
+    pub async fn build_multisig_transaction_from(
+        &self,
+        from: &str,
+        multisig: Address,
+        receiver: Address,
+        amount: u128,
+        token: Token,
+        timestamp: i64,
+    ) -> WalletResult<UnsignedTransaction> {
+        let account = self.client.get_account(multisig.clone()).await?;
+        let policy = account.multisig().cloned().ok_or_else(|| {
+            WalletError::Custom(format!("account {multisig} is not a multisig account"))
+        })?;
 
-        Ok(transfer.id().digest_string())
+        let builder = self.transfer_builder(from, receiver, amount, token, timestamp)?;
+        let sender = &self.addresses[from];
+        if !policy.public_keys().contains(&sender.public_key) {
+            return Err(WalletError::Custom(format!(
+                "address {from} is not one of the keys of multisig account {multisig}"
+            )));
+        }
+
+        let nonce = self.client.get_account_nonce(multisig.clone()).await?;
+        let transfer = builder
+            .sender_address(multisig)
+            .nonce(nonce.next_nonce())
+            .signature(placeholder_signature())
+            .build_kind()
+            .map_err(|_| WalletError::Custom("Failed to build transfer transaction".to_string()))?;
+
+        Ok(UnsignedTransaction::new_multisig(transfer, policy))
+    }
+
+    /// Creates the multisig account governed by `policy` by sending it
+    /// `amount` from the address named `from` in a transfer carrying the
+    /// policy. Returns the account's address and the transfer's digest.
+    pub async fn create_multisig_account(
+        &mut self,
+        from: &str,
+        policy: MultisigPolicy,
+        amount: u128,
+        timestamp: i64,
+    ) -> WalletResult<(Address, RpcTransactionDigest)> {
+        let address = policy.address();
+        let transfer_builder = self
+            .transfer_builder(from, address.clone(), amount, Token::default(), timestamp)?
+            .multisig_policy(policy);
+
+        let signature = self.sign_transaction(from, transfer_builder.build_payload().as_bytes())?;
+
+        let transfer = transfer_builder
+            .signature(signature)
+            .build_kind()
+            .map_err(|_| WalletError::Custom("Failed to build transfer transaction".to_string()))?;
+
+        let digest = self.broadcast_transaction(transfer).await?;
+
+        Ok((address, digest))
+    }
+
+    /// Submits a transaction signed elsewhere to the node, counting it as
+    /// pending if one of the wallet's addresses sent it
+    pub async fn broadcast_transaction(
+        &mut self,
+        txn: TransactionKind,
+    ) -> WalletResult<RpcTransactionDigest> {
+        self.client.create_txn(txn.clone()).await.map_err(|err| {
+            error!("{:?}", err.to_string());
+            WalletError::Custom(format!("API Error: {}", err))
+        })?;
+
+        self.record_sent(&txn);
+
+        Ok(txn.id().digest_string())
+    }
+
+    fn transfer_builder(
+        &self,
+        from: &str,
+        receiver: Address,
+        amount: u128,
+        token: Token,
+        timestamp: i64,
+    ) -> WalletResult<TransferBuilder> {
+        let sender = self
+            .get_address(from)
+            .ok_or_else(|| WalletError::Custom(format!("wallet has no address named {from}")))?;
+
+        Ok(TransactionKind::transfer_builder()
+            .timestamp(timestamp)
+            .sender_address(sender.address.clone())
+            .sender_public_key(sender.public_key)
+            .receiver_address(receiver)
+            .token(token)
+            .amount(amount)
+            .validators(HashMap::new())
+            .nonce(self.next_nonce(from)))
     }
 
     pub async fn get_transaction(
@@ -227,10 +498,15 @@ impl Wallet {
         }
     }
 
-    fn sign_transaction(&mut self, payload: &[u8]) -> Signature {
+    fn sign_transaction(&self, from: &str, payload: &[u8]) -> WalletResult<Signature> {
+        let secret_key = self
+            .secret_keys
+            .get(from)
+            .ok_or_else(|| WalletError::Custom(format!("wallet has no address named {from}")))?;
+
         type H = secp256k1::hashes::sha256::Hash;
         let msg = Message::from_hashed_data::<H>(payload);
-        self.secret_key.sign_ecdsa(msg)
+        Ok(secret_key.sign_ecdsa(msg))
     }
 
     pub fn get_welcome_message(&self) -> String {
@@ -253,28 +529,16 @@ impl Wallet {
                 client,
                 public_key: pubkey,
                 address: Address::from(pubkey),
-                // addresses: HashMap::new(),
-                // accounts: HashMap::new(),
-                nonce: 0,
+                keychain: None,
+                secret_keys: HashMap::new(),
+                addresses: BTreeMap::new(),
+                cache: WalletCache::default(),
             };
-
-            // wallet.get_new_address();
-
-            // let mut accounts = HashMap::new();
-            // let addresses = wallet.addresses.clone();
-            // for (_, addr) in addresses.iter() {
-            //     let account = wallet.get_account(addr.clone()).await?;
-            //     accounts.insert(addr.to_owned(), account);
-            // }
-
-            // wallet.accounts = accounts;
+            wallet.insert_address(DEFAULT_ADDRESS_ALIAS.to_string(), None, secretkey, pubkey);
 
             let welcome_message = format!(
                 "{}\nSECRET KEY: {:?}\nPUBLIC KEY: {:?}\n",
-                "DO NOT SHARE OR LOSE YOUR SECRET KEY:",
-                &wallet.secret_key,
-                &wallet.public_key,
-                // &wallet.addresses.get(&1).unwrap(),
+                "DO NOT SHARE OR LOSE YOUR SECRET KEY:", &wallet.secret_key, &wallet.public_key,
             );
 
             wallet.welcome_message = welcome_message;
@@ -288,19 +552,6 @@ impl Wallet {
     }
 
     // Create an account for each address created
-    //TODO: revisit this when we reimplement hierarchical deterministic wallets
-    // pub fn get_new_address(&mut self) {
-    //     let largest_address_index = self.addresses.len();
-    //     let pk = self.public_key;
-    //     let new_address = Address::new(pk);
-    //     self.addresses
-    //         .insert(largest_address_index as u32, new_address);
-    // }
-
-    // pub fn get_wallet_addresses(&self) -> HashMap<AddressAlias, Address> {
-    //     self.addresses.clone()
-    // }
-
     // pub async fn create_account(
     //     &mut self,
     //     alias: AddressAlias,
@@ -0,0 +1,298 @@
+//! Account state kept in sync with the node
+//!
+//! The nonce of a wallet's next transaction is the nonce of its sender's
+//! account plus the number of the account's transactions still pending in
+//! the mempool. Both are fetched through `getAccountNonce`, so wallets
+//! restarted or sharing an address with other senders pick up where the
+//! account actually is. Balances and history are cached alongside and
+//! refreshed from `getAccount` and `listTransactions`.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use vrrb_core::{
+    account::Account,
+    transactions::{RpcTransactionDigest, Transaction, TransactionKind, TxNonce},
+};
+use vrrb_rpc::rpc::api::{RpcAccountNonce, RpcApiClient, RpcTransactionRecord};
+
+use super::{AddressAlias, Wallet, WalletError, WalletResult};
+
+/// Cached state of the account behind one of the wallet's addresses
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressState {
+    /// Whether the account existed when last synced
+    pub exists: bool,
+    pub account_nonce: TxNonce,
+    /// Transactions of the account pending in the mempool when last synced,
+    /// plus those sent by the wallet since
+    pub pending_txns: usize,
+    pub balance: u128,
+    /// Confirmed transactions sent or received by the account
+    pub history: BTreeMap<RpcTransactionDigest, RpcTransactionRecord>,
+    /// Unix timestamp of the last sync, none if never synced
+    pub synced_at: Option<i64>,
+}
+
+impl AddressState {
+    /// Returns the nonce the address's next transaction should use
+    pub fn next_nonce(&self) -> TxNonce {
+        self.account_nonce + self.pending_txns as TxNonce
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TransactionStatus {
+    /// Submitted to the node but not seen in its confirmed transactions yet
+    Pending,
+    Confirmed,
+}
+
+/// A transaction sent by the wallet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SentTransaction {
+    pub from: AddressAlias,
+    pub nonce: TxNonce,
+    pub status: TransactionStatus,
+}
+
+/// Local cache of the state of a wallet's accounts, meant to be persisted
+/// between runs and refreshed with [Wallet::sync]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WalletCache {
+    pub addresses: BTreeMap<AddressAlias, AddressState>,
+    pub sent: BTreeMap<RpcTransactionDigest, SentTransaction>,
+}
+
+impl WalletCache {
+    /// Returns the nonce the next transaction sent from `alias` should use
+    pub fn next_nonce(&self, alias: &str) -> TxNonce {
+        self.addresses
+            .get(alias)
+            .map(AddressState::next_nonce)
+            .unwrap_or_default()
+    }
+
+    /// Digests of the transactions sent from `alias` that are still pending
+    pub fn pending_sent(&self, alias: &str) -> Vec<RpcTransactionDigest> {
+        self.sent
+            .iter()
+            .filter(|(_, sent)| sent.from == alias && sent.status == TransactionStatus::Pending)
+            .map(|(digest, _)| digest.clone())
+            .collect()
+    }
+
+    /// Records a transaction sent from `alias`, counting it as pending until
+    /// the next sync
+    pub fn record_sent(&mut self, alias: AddressAlias, txn: &TransactionKind) {
+        self.addresses
+            .entry(alias.clone())
+            .or_default()
+            .pending_txns += 1;
+        self.sent.insert(
+            txn.id().digest_string(),
+            SentTransaction {
+                from: alias,
+                nonce: txn.nonce(),
+                status: TransactionStatus::Pending,
+            },
+        );
+    }
+
+    /// Replaces the cached state of `alias` with the state fetched from the
+    /// node, marking the wallet's transactions found among its history as
+    /// confirmed
+    pub fn apply_sync(
+        &mut self,
+        alias: &str,
+        nonce: RpcAccountNonce,
+        account: Option<&Account>,
+        history: BTreeMap<RpcTransactionDigest, RpcTransactionRecord>,
+    ) {
+        for digest in history.keys() {
+            if let Some(sent) = self.sent.get_mut(digest) {
+                sent.status = TransactionStatus::Confirmed;
+            }
+        }
+
+        self.addresses.insert(
+            alias.to_string(),
+            AddressState {
+                exists: nonce.exists,
+                account_nonce: nonce.account_nonce,
+                pending_txns: nonce.pending_txns,
+                balance: account
+                    .map(|account| account.credits().saturating_sub(account.debits()))
+                    .unwrap_or_default(),
+                history,
+                synced_at: Some(chrono::Utc::now().timestamp()),
+            },
+        );
+    }
+}
+
+impl Wallet {
+    /// Returns the cached state of the wallet's accounts
+    pub fn cache(&self) -> &WalletCache {
+        &self.cache
+    }
+
+    /// Replaces the cached state of the wallet's accounts, typically with
+    /// one persisted by a previous run
+    pub fn restore_cache(&mut self, cache: WalletCache) {
+        self.cache = cache;
+    }
+
+    /// Returns the nonce the next transaction sent from `alias` should use,
+    /// as of the last sync
+    pub fn next_nonce(&self, alias: &str) -> TxNonce {
+        self.cache.next_nonce(alias)
+    }
+
+    /// Refreshes the cached state of every address of the wallet
+    pub async fn sync(&mut self) -> WalletResult<()> {
+        let aliases: Vec<AddressAlias> = self.addresses.keys().cloned().collect();
+        for alias in aliases {
+            self.sync_address(&alias).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Refreshes the nonce, balance and history of the address named
+    /// `alias` from the node
+    pub async fn sync_address(&mut self, alias: &str) -> WalletResult<&AddressState> {
+        let address = self
+            .get_address(alias)
+            .ok_or_else(|| WalletError::Custom(format!("wallet has no address named {alias}")))?
+            .address
+            .clone();
+
+        let nonce = self.client.get_account_nonce(address.clone()).await?;
+
+        let mut digests = self.cache.pending_sent(alias);
+        let account = if nonce.exists {
+            let account = self.client.get_account(address).await?;
+            digests.extend(
+                account
+                    .digests()
+                    .get_sent()
+                    .into_iter()
+                    .chain(account.digests().get_recv())
+                    .map(|digest| digest.to_string()),
+            );
+            Some(account)
+        } else {
+            None
+        };
+
+        let history = if digests.is_empty() {
+            BTreeMap::new()
+        } else {
+            self.client
+                .list_transactions(digests)
+                .await?
+                .into_iter()
+                .collect()
+        };
+
+        self.cache
+            .apply_sync(alias, nonce, account.as_ref(), history);
+
+        Ok(&self.cache.addresses[alias])
+    }
+
+    /// Records a transaction sent by the wallet, if one of its addresses is
+    /// the sender
+    pub(super) fn record_sent(&mut self, txn: &TransactionKind) {
+        let sender = txn.sender_address();
+        if let Some(alias) = self
+            .addresses
+            .values()
+            .find(|address| address.address == sender)
+            .map(|address| address.alias.clone())
+        {
+            self.cache.record_sent(alias, txn);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use primitives::Address;
+    use vrrb_core::{keypair::KeyPair, transactions::placeholder_signature};
+
+    use super::*;
+
+    fn transfer(nonce: TxNonce) -> TransactionKind {
+        let public_key = KeyPair::random().miner_public_key_owned();
+        TransactionKind::transfer_builder()
+            .timestamp(chrono::Utc::now().timestamp())
+            .sender_address(Address::new(public_key))
+            .sender_public_key(public_key)
+            .receiver_address(Address::new(KeyPair::random().miner_public_key_owned()))
+            .amount(10)
+            .validators(HashMap::new())
+            .nonce(nonce)
+            .signature(placeholder_signature())
+            .build_kind()
+            .unwrap()
+    }
+
+    #[test]
+    fn sent_transactions_advance_the_nonce_until_synced() {
+        let mut cache = WalletCache::default();
+        assert_eq!(cache.next_nonce("default"), 0);
+
+        cache.apply_sync(
+            "default",
+            RpcAccountNonce {
+                exists: true,
+                account_nonce: 3,
+                pending_txns: 1,
+            },
+            None,
+            BTreeMap::new(),
+        );
+        assert_eq!(cache.next_nonce("default"), 4);
+
+        let txn = transfer(cache.next_nonce("default"));
+        cache.record_sent("default".to_string(), &txn);
+        assert_eq!(cache.next_nonce("default"), 5);
+        assert_eq!(
+            cache.pending_sent("default"),
+            vec![txn.id().digest_string()]
+        );
+        assert!(cache.pending_sent("other").is_empty());
+    }
+
+    #[test]
+    fn syncing_confirms_sent_transactions_found_in_history() {
+        let mut cache = WalletCache::default();
+        let txn = transfer(0);
+        cache.record_sent("default".to_string(), &txn);
+
+        let digest = txn.id().digest_string();
+        let history = BTreeMap::from([(digest.clone(), RpcTransactionRecord::from(txn))]);
+        cache.apply_sync(
+            "default",
+            RpcAccountNonce {
+                exists: true,
+                account_nonce: 1,
+                pending_txns: 0,
+            },
+            None,
+            history,
+        );
+
+        assert_eq!(cache.sent[&digest].status, TransactionStatus::Confirmed);
+        assert!(cache.pending_sent("default").is_empty());
+        assert_eq!(cache.next_nonce("default"), 1);
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::BTreeMap;
+
+use serial_test::serial;
+use storage::storage_utils::remove_vrrb_data_dir;
+use vrrb_rpc::rpc::{JsonRpcServer, JsonRpcServerConfig};
+use wallet::v2::{
+    hd::{self, ExtendedSecretKey, HdKeychain},
+    Wallet, DEFAULT_ADDRESS_ALIAS,
+};
+
+const PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+                      abandon abandon about";
+
+#[test]
+fn derives_bip32_test_vector_keys() {
+    let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+    let master = ExtendedSecretKey::from_seed(&seed).unwrap();
+
+    for (path, secret_key) in [
+        (
+            "m",
+            "e8f32e723decf4051aefac8e2c93c9c5b214313817cdb01a1494b917c8436b35",
+        ),
+        (
+            "m/0'",
+            "edb2e14f9ee77d26dd93b4ecede8d16ed408ce149b6cd80b0715a2d911a0afea",
+        ),
+        (
+            "m/0'/1",
+            "3c6cb8d0f6a264c91ea8b5030fadaa8e538b020f0a387421a12de9319dc93368",
+        ),
+        (
+            "m/0'/1/2'",
+            "cbce0d719ecf7431d88e6a89fa1483e02e35092af60c042b1df2ff59fa424dca",
+        ),
+    ] {
+        let key = master.derive_path(path).unwrap();
+        assert_eq!(hex::encode(key.secret_key().secret_bytes()), secret_key);
+    }
+
+    assert!(master.derive_path("0/1").is_err());
+    assert!(master.derive_path("m/2147483648").is_err());
+}
+
+#[test]
+fn derives_bip39_seed_and_bip44_keys() {
+    let mnemonic = hd::parse_mnemonic(PHRASE).unwrap();
+    assert_eq!(
+        hex::encode(mnemonic.to_seed("TREZOR")),
+        "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04"
+    );
+
+    // The first address of the widely used m/44'/60'/0'/0 path
+    let master = ExtendedSecretKey::from_seed(&mnemonic.to_seed("")).unwrap();
+    let key = master.derive_path("m/44'/60'/0'/0/0").unwrap();
+    assert_eq!(
+        hex::encode(key.secret_key().secret_bytes()),
+        "1ab42cc412b618bdea3a599e3c9bae199ebf030895b039e9db1e30dafb12b727"
+    );
+
+    let keychain = HdKeychain::from_mnemonic(&mnemonic, "").unwrap();
+    let path_key = master.derive_path(&hd::address_path(3)).unwrap();
+    assert_eq!(keychain.derive(3).unwrap().0, path_key.secret_key());
+    assert_ne!(keychain.derive(3).unwrap(), keychain.derive(4).unwrap());
+
+    assert!(hd::parse_mnemonic("abandon abandon abandon").is_err());
+    assert!(hd::parse_mnemonic(&PHRASE.replace("about", "abandon")).is_err());
+
+    let generated = hd::generate_mnemonic(hd::DEFAULT_MNEMONIC_WORD_COUNT).unwrap();
+    assert_eq!(
+        hd::parse_mnemonic(&generated.to_string()).unwrap(),
+        generated
+    );
+}
+
+#[tokio::test]
+#[serial]
+pub async fn restores_aliased_addresses_from_mnemonic() {
+    remove_vrrb_data_dir();
+
+    let (handle, socket_addr) = JsonRpcServer::run(&JsonRpcServerConfig::default())
+        .await
+        .unwrap();
+
+    tokio::spawn(handle.stopped());
+
+    let mut wallet = Wallet::restore_from_mnemonic(PHRASE, "", BTreeMap::new(), socket_addr)
+        .await
+        .unwrap();
+
+    let default = wallet.get_address(DEFAULT_ADDRESS_ALIAS).unwrap().clone();
+    assert_eq!(default.index, Some(0));
+    assert_eq!(default.address, wallet.address);
+
+    let savings = wallet.derive_address("savings").unwrap();
+    assert_eq!(savings.index, Some(1));
+    assert_eq!(savings.path, Some(hd::address_path(1)));
+    assert!(wallet.derive_address("savings").is_err());
+
+    let restored = Wallet::restore_from_mnemonic(PHRASE, "", wallet.address_indices(), socket_addr)
+        .await
+        .unwrap();
+    let restored_savings = restored.get_address("savings").unwrap();
+    assert_eq!(restored_savings.address, savings.address);
+    assert_eq!(restored.address, wallet.address);
+
+    let other = Wallet::restore_from_mnemonic(PHRASE, "passphrase", BTreeMap::new(), socket_addr)
+        .await
+        .unwrap();
+    assert_ne!(other.address, wallet.address);
+}
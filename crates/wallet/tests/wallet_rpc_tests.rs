@@ -7,7 +7,7 @@ use storage::storage_utils::remove_vrrb_data_dir;
 use tokio::sync::mpsc::channel;
 use vrrb_core::transactions::Token;
 use vrrb_rpc::rpc::{JsonRpcServer, JsonRpcServerConfig};
-use wallet::v2::{Wallet, WalletConfig};
+use wallet::v2::{sync::TransactionStatus, Wallet, WalletConfig, DEFAULT_ADDRESS_ALIAS};
 
 #[tokio::test]
 #[serial]
@@ -87,6 +87,50 @@ pub async fn wallet_sends_txn_to_rpc_server() {
     );
 }
 
+#[tokio::test]
+#[serial]
+pub async fn wallet_syncs_nonce_from_rpc_server() {
+    remove_vrrb_data_dir();
+
+    let (events_tx, _events_rx) = channel(100);
+
+    let json_rpc_server_config = JsonRpcServerConfig {
+        events_tx,
+        ..Default::default()
+    };
+
+    let (handle, socket_addr) = JsonRpcServer::run(&json_rpc_server_config).await.unwrap();
+
+    tokio::spawn(handle.stopped());
+
+    let wallet_config = WalletConfig {
+        rpc_server_address: socket_addr,
+        ..Default::default()
+    };
+
+    let mut wallet = Wallet::new(wallet_config).await.unwrap();
+
+    let state = wallet.sync_address(DEFAULT_ADDRESS_ALIAS).await.unwrap();
+    assert!(!state.exists);
+    assert_eq!(state.balance, 0);
+    assert_eq!(wallet.next_nonce(DEFAULT_ADDRESS_ALIAS), 0);
+
+    type H = secp256k1::hashes::sha256::Hash;
+    let recv_sk = SecretKey::from_hashed_data::<H>(b"recv_vrrb");
+    let recv_pk = PublicKey::from_secret_key(&Secp256k1::new(), &recv_sk);
+
+    let txn_digest = wallet
+        .send_transaction(Address::new(recv_pk), 10, Token::default(), 0)
+        .await
+        .unwrap();
+
+    assert_eq!(wallet.next_nonce(DEFAULT_ADDRESS_ALIAS), 1);
+    assert_eq!(
+        wallet.cache().sent[&txn_digest].status,
+        TransactionStatus::Pending
+    );
+}
+
 // TODO: reconsider this when hierarchically deterministic accounts are implemented
 // #[tokio::test]
 // #[serial]
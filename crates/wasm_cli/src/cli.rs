@@ -1,6 +1,9 @@
 use clap::{Parser, Subcommand};
 
-use crate::commands::{describe::DescribeOpts, execute::ExecuteOpts, validate::ValidateOpts};
+use crate::commands::{
+    describe::DescribeOpts, execute::ExecuteOpts, profile::ProfileOpts, test::TestOpts,
+    validate::ValidateOpts,
+};
 
 #[derive(Parser)]
 #[clap(author, version, about)]
@@ -16,6 +19,10 @@ pub enum WasmCommands {
     Describe(DescribeOpts),
     /// Execute a Web Assembly module
     Execute(ExecuteOpts),
+    /// Execute a Web Assembly module and report where its metering points go
+    Profile(ProfileOpts),
+    /// Runs contract test suites against mock chain state
+    Test(TestOpts),
     /// Validates a WASM module's ability to execute
     Validate(ValidateOpts),
 }
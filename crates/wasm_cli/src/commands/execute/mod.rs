@@ -4,11 +4,13 @@ use anyhow::{anyhow, Result};
 use clap::Parser;
 use telemetry::info;
 use wasm_runtime::{
-    metering::{cost_function, MeteringConfig},
+    module_cache::{ModuleCache, DEFAULT_MODULE_CACHE_SIZE},
     wasm_runtime::WasmRuntime,
 };
 use wasmer::{Cranelift, Target};
 
+use crate::commands::GasScheduleOpts;
+
 #[derive(Parser, Debug)]
 pub struct ExecuteOpts {
     /// The path to the WASM object file to load and describe
@@ -25,6 +27,21 @@ pub struct ExecuteOpts {
     /// operation expenses.
     #[clap(short = 'l', long, value_parser, value_name = "UINT64")]
     pub meter_limit: u64,
+    #[clap(flatten)]
+    pub gas_schedule: GasScheduleOpts,
+    /// A directory to cache compiled WASM modules in, so executing the same
+    /// module again skips compilation. Cached modules are trusted, so the
+    /// directory must not be writable by anyone else.
+    #[clap(long, value_parser, value_name = "DIR")]
+    pub cache_dir: Option<PathBuf>,
+    /// The maximum size of the compiled module cache, in bytes.
+    #[clap(long, value_parser, value_name = "UINT64")]
+    #[clap(default_value_t = DEFAULT_MODULE_CACHE_SIZE)]
+    pub cache_size: u64,
+    /// The path to the Javy QuickJS provider module that JavaScript modules
+    /// compiled by Javy are linked against.
+    #[clap(long, value_parser, value_name = "FILE")]
+    pub javy_provider: Option<PathBuf>,
     /// Remaining arguments (after '--') are passed to the WASM module command
     /// line.
     #[clap(last = true)]
@@ -65,21 +82,46 @@ pub fn run(opts: &ExecuteOpts) -> Result<()> {
     }
 
     let target = Target::default();
+    let metering_config = opts
+        .gas_schedule
+        .schedule()?
+        .metering_config(opts.meter_limit);
+    let wasm = match &opts.cache_dir {
+        Some(cache_dir) => {
+            let cache = ModuleCache::new(cache_dir, opts.cache_size)?;
+            let wasm = WasmRuntime::new_cached::<Cranelift>(
+                &target,
+                &wasm_bytes,
+                metering_config,
+                &cache,
+            )?;
+            if wasm.cache_hit() {
+                eprintln!("Module cache hit in {}", cache_dir.display());
+            } else {
+                eprintln!("Module cache miss in {}", cache_dir.display());
+            }
+            wasm
+        }
+        None => WasmRuntime::new::<Cranelift>(&target, &wasm_bytes, metering_config)?,
+    };
+
+    let wasm = match &opts.javy_provider {
+        Some(provider) => wasm.javy_provider(&std::fs::read(provider)?)?,
+        None => wasm,
+    };
+
     // Execute the WASM module.
-    let mut wasm = WasmRuntime::new::<Cranelift>(
-        &target,
-        &wasm_bytes,
-        MeteringConfig::new(opts.meter_limit, cost_function),
-    )?
-    .stdin(&json_data)
-    .env(&env_vars)
-    .args(&opts.args);
-    wasm.execute()?;
+    let mut wasm = wasm.stdin(&json_data).env(&env_vars).args(&opts.args);
+    let usage = wasm.execute()?;
 
     // Temporary output for user -- will eventually be more structured and both
     // human and machine readable.
     println!("{}", &wasm.stdout());
     eprintln!("Contract errors: {}", &wasm.stderr());
+    eprintln!(
+        "Metering points consumed: {}, remaining: {}",
+        usage.consumed, usage.remaining
+    );
 
     Ok(())
 }
@@ -1,3 +1,40 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use clap::Args;
+use wasm_runtime::metering::GasSchedule;
+
 pub mod describe;
 pub mod execute;
+pub mod profile;
+pub mod test;
 pub mod validate;
+
+/// Options selecting the gas schedule modules are metered with
+#[derive(Args, Debug)]
+pub struct GasScheduleOpts {
+    /// The version of the published gas schedule to meter the module with,
+    /// the latest version when omitted.
+    #[clap(long, value_parser, value_name = "UINT32")]
+    pub gas_schedule_version: Option<u32>,
+    /// The path to a JSON file holding a gas schedule to meter the module
+    /// with instead of a published one.
+    #[clap(long, value_parser, value_name = "FILE")]
+    #[clap(conflicts_with = "gas-schedule-version")]
+    pub gas_schedule: Option<PathBuf>,
+}
+
+impl GasScheduleOpts {
+    /// Returns the selected gas schedule
+    pub fn schedule(&self) -> Result<GasSchedule> {
+        if let Some(path) = &self.gas_schedule {
+            return Ok(serde_json::from_slice(&std::fs::read(path)?)?);
+        }
+
+        match self.gas_schedule_version {
+            Some(version) => GasSchedule::from_version(version)
+                .ok_or_else(|| anyhow!("Unknown gas schedule version {version}")),
+            None => Ok(GasSchedule::default()),
+        }
+    }
+}
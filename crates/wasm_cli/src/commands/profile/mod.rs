@@ -0,0 +1,150 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use telemetry::info;
+use wasm_runtime::{profiler::ExecutionProfile, wasm_runtime::WasmRuntime};
+use wasmer::{Cranelift, Target};
+
+use crate::commands::GasScheduleOpts;
+
+/// Root frame of the folded stacks, matching the module's argv[0]
+const FOLDED_ROOT: &str = "vrrb-contract";
+/// Number of functions listed in the summary
+const SUMMARY_FUNCTIONS: usize = 10;
+
+#[derive(Parser, Debug)]
+pub struct ProfileOpts {
+    /// The path to the WASM object file to load and profile
+    #[clap(short, long, value_parser, value_name = "FILE")]
+    pub wasm: PathBuf,
+    /// The path to a JSON file to become input to the running WASM module
+    #[clap(short, long, value_parser, value_name = "FILE")]
+    pub json: PathBuf,
+    /// An environment variable to pass to the running WASM module. May be used
+    /// multiple times.
+    #[clap(short, long, value_parser, value_name = "KEY=VALUE")]
+    pub env: Vec<String>,
+    /// The initial limit of credits that the WASM module's meter will use to track
+    /// operation expenses.
+    #[clap(short = 'l', long, value_parser, value_name = "UINT64")]
+    pub meter_limit: u64,
+    #[clap(flatten)]
+    pub gas_schedule: GasScheduleOpts,
+    /// A file to write the profile to as JSON. The profile is printed to
+    /// stdout when omitted.
+    #[clap(short, long, value_parser, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+    /// A file to write the metering points spent in each call stack to, in
+    /// the folded-stack format read by flamegraph tools.
+    #[clap(short, long, value_parser, value_name = "FILE")]
+    pub folded: Option<PathBuf>,
+    /// Remaining arguments (after '--') are passed to the WASM module command
+    /// line.
+    #[clap(last = true)]
+    pub args: Vec<String>,
+}
+
+/// Execute a WASM module the same way `execute` does while recording where
+/// its metering points go: the calls, instructions, points and memory growth
+/// of each function, and the host functions it called. Executions that fail,
+/// for instance by running out of gas, are still profiled.
+pub fn run(opts: &ProfileOpts) -> Result<()> {
+    let wasmfile = opts
+        .wasm
+        .to_str()
+        .ok_or(anyhow!("Failed to convert WASM filename to valid string."))?;
+    let jsonfile = opts
+        .json
+        .to_str()
+        .ok_or(anyhow!("Failed to convert JSON filename to valid string."))?;
+    let wasm_bytes = std::fs::read(wasmfile)?;
+    info!(
+        "Loaded {} bytes of WASM data from {} to profile.",
+        wasm_bytes.len(),
+        wasmfile
+    );
+    let json_data = std::fs::read(jsonfile)?;
+    info!(
+        "Loaded {} bytes of JSON data from {} as input.",
+        json_data.len(),
+        jsonfile
+    );
+
+    let mut env_vars: HashMap<String, String> = HashMap::new();
+    for var in opts.env.iter() {
+        if let Some((key, value)) = var.split_once('=') {
+            env_vars.insert(key.to_string(), value.to_string());
+        }
+    }
+
+    let target = Target::default();
+    let metering_config = opts
+        .gas_schedule
+        .schedule()?
+        .metering_config(opts.meter_limit);
+    let mut wasm = WasmRuntime::new_profiled::<Cranelift>(&target, &wasm_bytes, metering_config)?
+        .stdin(&json_data)
+        .env(&env_vars)
+        .args(&opts.args);
+
+    // Report the profile before any execution error
+    let result = wasm.execute();
+    eprintln!("Contract output: {}", &wasm.stdout());
+    eprintln!("Contract errors: {}", &wasm.stderr());
+
+    let profile = wasm
+        .profile()
+        .ok_or(anyhow!("The module could not be profiled."))?;
+    print_summary(profile);
+
+    match &opts.output {
+        Some(output) => std::fs::write(output, profile.to_json()?)?,
+        None => println!("{}", profile.to_json()?),
+    }
+    if let Some(folded) = &opts.folded {
+        std::fs::write(folded, profile.to_folded(FOLDED_ROOT))?;
+    }
+
+    result?;
+
+    Ok(())
+}
+
+fn print_summary(profile: &ExecutionProfile) {
+    eprintln!(
+        "Metering points consumed: {}, remaining: {} ({} spent in module code)",
+        profile.points_consumed,
+        profile.points_remaining,
+        profile.total_points()
+    );
+    eprintln!(
+        "Memory pages: {} initial, {} final",
+        profile.initial_memory_pages, profile.final_memory_pages
+    );
+
+    eprintln!(
+        "{:>12} {:>12} {:>14} {:>8}  function",
+        "points", "calls", "instructions", "grows"
+    );
+    for function in profile.functions.iter().take(SUMMARY_FUNCTIONS) {
+        eprintln!(
+            "{:>12} {:>12} {:>14} {:>8}  {}",
+            function.points,
+            function.calls,
+            function.instructions,
+            function.memory_grows,
+            function.name
+        );
+    }
+    if profile.functions.len() > SUMMARY_FUNCTIONS {
+        eprintln!(
+            "... and {} more functions",
+            profile.functions.len() - SUMMARY_FUNCTIONS
+        );
+    }
+
+    for (name, calls) in &profile.host_calls {
+        eprintln!("Host calls to {}: {}", name, calls);
+    }
+}
@@ -0,0 +1,536 @@
+//! Runs the cases of a [TestSuite] against mock chain state
+//!
+//! Calls are executed the way validators execute contracts: in
+//! deterministic mode, with the contract input document on stdin and the
+//! VRRB host ABI backed by an [InMemoryContractState]. The amount sent with
+//! a call is moved to the contract before it runs, and a call only succeeds
+//! when the contract writes a valid output document to stdout. The state is
+//! snapshotted before every call so the changes a call made can be compared
+//! with the ones it was expected to make, and so failed calls can be rolled
+//! back.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use wasm_loader::wasm_loader::{WasmLoader, WasmLoaderBuilder};
+use wasm_runtime::{
+    deterministic::DeterministicContext,
+    host_abi::{ContractState, HostAddress, HostContext, InMemoryContractState},
+    metering::GasSchedule,
+    wasm_runtime::WasmRuntime,
+};
+use wasmer::{Cranelift, Target};
+
+use super::suite::{
+    format_bytes, parse_address, parse_bytes, Expectations, TestCall, TestCase, TestSuite,
+};
+
+/// Version of the contract input document, as written by validators
+const CONTRACT_INPUT_VERSION: i32 = 1;
+
+/// The document validators write to a contract's stdin when it is called
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ContractInput<'a> {
+    version: i32,
+    tx_id: &'a str,
+    last_block_time: i64,
+    block_height: u128,
+    sender: String,
+    contract_address: String,
+    amount: u128,
+    /// The contract account's storage as left by its previous call
+    state: Option<&'a str>,
+    input: &'a str,
+}
+
+/// The document a contract is expected to write to its stdout
+#[derive(Debug, Deserialize)]
+struct ContractOutput {
+    /// New storage for the contract account, left untouched when `None`
+    #[serde(default)]
+    state: Option<String>,
+}
+
+/// Storage entries and balances a call changed
+#[derive(Debug, Default, PartialEq, Eq)]
+struct StateDiff {
+    storage: BTreeMap<HostAddress, BTreeMap<Vec<u8>, Option<Vec<u8>>>>,
+    balances: BTreeMap<HostAddress, u128>,
+}
+
+impl StateDiff {
+    fn between(before: &InMemoryContractState, after: &InMemoryContractState) -> Self {
+        let mut diff = Self::default();
+
+        let addresses: BTreeSet<&HostAddress> =
+            before.storage.keys().chain(after.storage.keys()).collect();
+        for address in addresses {
+            let before = before.entries(address);
+            let after = after.entries(address);
+
+            for (key, value) in &after {
+                if before.get(key) != Some(value) {
+                    diff.storage
+                        .entry(*address)
+                        .or_default()
+                        .insert(key.clone(), Some(value.clone()));
+                }
+            }
+            for key in before.keys() {
+                if !after.contains_key(key) {
+                    diff.storage
+                        .entry(*address)
+                        .or_default()
+                        .insert(key.clone(), None);
+                }
+            }
+        }
+
+        for (address, balance) in &after.balances {
+            if before.balances.get(address).copied().unwrap_or_default() != *balance {
+                diff.balances.insert(*address, *balance);
+            }
+        }
+
+        diff
+    }
+}
+
+/// What happened when a call was executed
+struct CallOutcome {
+    result: Result<()>,
+    stdout: String,
+    gas_used: u64,
+    diff: StateDiff,
+}
+
+/// Outcome of a single test case
+#[derive(Debug)]
+pub struct CaseReport {
+    pub name: String,
+    /// Unmet expectations, empty when the case passed
+    pub failures: Vec<String>,
+}
+
+impl CaseReport {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Loads the module of `suite` and runs every one of its cases
+pub fn run_suite(suite: &TestSuite) -> Result<Vec<CaseReport>> {
+    let module = load_module(&suite.module)?;
+    // The provider runs as part of the module, so it is held to the same
+    // checks
+    let provider = suite
+        .javy_provider
+        .as_deref()
+        .map(|provider| {
+            load_module(provider)
+                .with_context(|| format!("Javy provider {} rejected", provider.display()))
+        })
+        .transpose()?;
+    let schedule = match suite.gas_schedule_version {
+        Some(version) => GasSchedule::from_version(version)
+            .ok_or_else(|| anyhow!("Unknown gas schedule version {version}"))?,
+        None => GasSchedule::default(),
+    };
+
+    suite
+        .cases
+        .iter()
+        .map(|case| {
+            run_case(
+                &module,
+                provider
+                    .as_ref()
+                    .map(|provider| provider.wasm_bytes.as_slice()),
+                schedule,
+                suite.meter_limit,
+                case,
+            )
+        })
+        .collect()
+}
+
+/// Loads and checks a module the way contracts are loaded on chain
+fn load_module(path: &Path) -> Result<WasmLoader> {
+    let bytes = std::fs::read(path)?;
+    let mut builder = WasmLoaderBuilder::default();
+    if path.extension().and_then(|ext| ext.to_str()) == Some("wat") {
+        builder.wat_text(bytes);
+    } else {
+        builder.wasm_bytes(bytes);
+    }
+
+    Ok(builder.deterministic(true).parse()?.build()?)
+}
+
+fn run_case(
+    module: &WasmLoader,
+    provider: Option<&[u8]>,
+    schedule: GasSchedule,
+    meter_limit: u64,
+    case: &TestCase,
+) -> Result<CaseReport> {
+    let mut state = InMemoryContractState::default();
+    for (address, account) in &case.accounts {
+        let address = parse_address(address)?;
+        state.balances.insert(address, account.balance);
+        for (key, value) in &account.storage {
+            state.storage_set(&address, &parse_bytes(key)?, &parse_bytes(value)?);
+        }
+    }
+    let state = Arc::new(Mutex::new(state));
+
+    let mut failures = vec![];
+    for (index, call) in case.calls.iter().enumerate() {
+        let seed = format!("{}:{}", case.name, index);
+        let outcome = execute_call(module, provider, schedule, meter_limit, call, &state, &seed)?;
+        for failure in check_expectations(&call.expect, &outcome)? {
+            failures.push(format!("call {index}: {failure}"));
+        }
+    }
+
+    Ok(CaseReport {
+        name: case.name.clone(),
+        failures,
+    })
+}
+
+fn execute_call(
+    module: &WasmLoader,
+    provider: Option<&[u8]>,
+    schedule: GasSchedule,
+    meter_limit: u64,
+    call: &TestCall,
+    state: &Arc<Mutex<InMemoryContractState>>,
+    seed: &str,
+) -> Result<CallOutcome> {
+    let context = HostContext {
+        caller: parse_address(&call.caller)?,
+        contract_address: parse_address(&call.contract)?,
+        block_height: call.block_height,
+        block_time: call.block_time,
+    };
+    let before = lock(state)?.clone();
+
+    // The amount sent with the call is spendable by the contract while it
+    // runs
+    {
+        let mut state = lock(state)?;
+        let remaining = match state.balance(&context.caller).checked_sub(call.amount) {
+            Some(remaining) => remaining,
+            None => {
+                return Ok(CallOutcome {
+                    result: Err(anyhow!(
+                        "Caller cannot pay the {} sent with the call",
+                        call.amount
+                    )),
+                    stdout: String::new(),
+                    gas_used: 0,
+                    diff: StateDiff::default(),
+                })
+            }
+        };
+        state.balances.insert(context.caller, remaining);
+        *state.balances.entry(context.contract_address).or_default() += call.amount;
+    }
+    let stdin = contract_input(call, &context, &before, seed)?;
+
+    let runtime = WasmRuntime::new::<Cranelift>(
+        &Target::default(),
+        &module.wasm_bytes,
+        schedule.metering_config(call.meter_limit.unwrap_or(meter_limit)),
+    )?;
+    let runtime = match provider {
+        Some(provider) => runtime.javy_provider(provider)?,
+        None => runtime,
+    };
+
+    let mut runtime = runtime
+        .stdin(&stdin)
+        .args(&call.args)
+        .env(&call.env)
+        .host_state(context, state.clone())
+        .deterministic(DeterministicContext::new(call.block_time, seed.as_bytes()));
+
+    let output = runtime
+        .execute()
+        .map_err(anyhow::Error::from)
+        .and_then(|_| {
+            serde_json::from_str::<ContractOutput>(runtime.stdout().trim())
+                .map_err(|err| anyhow!("Contract returned invalid output: {err}"))
+        });
+
+    let mut after = lock(state)?;
+    let result = output.map(|output| {
+        // Storage written through the ABI takes precedence over the state
+        // the contract returned
+        let address = context.contract_address;
+        if after.storage.get(&address) == before.storage.get(&address) {
+            if let Some(storage) = output.state {
+                after.storage.insert(address, storage);
+            }
+        }
+    });
+    let diff = if result.is_ok() {
+        StateDiff::between(&before, &after)
+    } else {
+        // Failed calls do not change the chain state
+        *after = before;
+        StateDiff::default()
+    };
+
+    Ok(CallOutcome {
+        result,
+        stdout: runtime.stdout(),
+        gas_used: runtime.consumed_points(),
+        diff,
+    })
+}
+
+/// Serializes the contract input document for `call`, given the state
+/// before the call
+fn contract_input(
+    call: &TestCall,
+    context: &HostContext,
+    state: &InMemoryContractState,
+    tx_id: &str,
+) -> Result<Vec<u8>> {
+    let input = ContractInput {
+        version: CONTRACT_INPUT_VERSION,
+        tx_id,
+        last_block_time: context.block_time,
+        block_height: context.block_height as u128,
+        sender: format!("0x{}", hex::encode(context.caller)),
+        contract_address: format!("0x{}", hex::encode(context.contract_address)),
+        amount: call.amount,
+        state: state
+            .storage
+            .get(&context.contract_address)
+            .map(String::as_str),
+        input: &call.input,
+    };
+
+    let mut stdin = serde_json::to_vec(&input)?;
+    // Contracts read their input a line at a time
+    stdin.push(b'\n');
+    Ok(stdin)
+}
+
+fn lock(
+    state: &Mutex<InMemoryContractState>,
+) -> Result<std::sync::MutexGuard<'_, InMemoryContractState>> {
+    state
+        .lock()
+        .map_err(|_| anyhow!("Mock chain state lock poisoned"))
+}
+
+/// Returns a description of every expectation `outcome` does not meet
+fn check_expectations(expect: &Expectations, outcome: &CallOutcome) -> Result<Vec<String>> {
+    let mut failures = vec![];
+
+    match (&outcome.result, expect.success) {
+        (Ok(()), false) => failures.push("expected the call to fail, but it succeeded".into()),
+        (Err(err), true) => failures.push(format!("call failed: {err:#}")),
+        (Err(err), false) => {
+            if let Some(text) = &expect.error_contains {
+                let err = format!("{err:#}");
+                if !err.contains(text.as_str()) {
+                    failures.push(format!("expected error containing {text:?}, got {err:?}"));
+                }
+            }
+        }
+        (Ok(()), true) => {}
+    }
+
+    if let Some(stdout) = &expect.stdout {
+        if &outcome.stdout != stdout {
+            failures.push(format!(
+                "expected stdout {stdout:?}, got {:?}",
+                outcome.stdout
+            ));
+        }
+    }
+
+    if let Some(expected) = &expect.storage {
+        let mut storage = BTreeMap::new();
+        for (address, entries) in expected {
+            let mut parsed = BTreeMap::new();
+            for (key, value) in entries {
+                let value = value.as_deref().map(parse_bytes).transpose()?;
+                parsed.insert(parse_bytes(key)?, value);
+            }
+            storage.insert(parse_address(address)?, parsed);
+        }
+
+        if storage != outcome.diff.storage {
+            failures.push(format!(
+                "expected storage changes {}, got {}",
+                format_storage(&storage),
+                format_storage(&outcome.diff.storage)
+            ));
+        }
+    }
+
+    if let Some(expected) = &expect.balances {
+        let balances = expected
+            .iter()
+            .map(|(address, balance)| Ok((parse_address(address)?, *balance)))
+            .collect::<Result<BTreeMap<_, _>>>()?;
+
+        if balances != outcome.diff.balances {
+            failures.push(format!(
+                "expected balance changes {}, got {}",
+                format_balances(&balances),
+                format_balances(&outcome.diff.balances)
+            ));
+        }
+    }
+
+    if let Some(gas_used) = expect.gas_used {
+        if outcome.gas_used != gas_used {
+            failures.push(format!(
+                "expected {gas_used} points to be consumed, got {}",
+                outcome.gas_used
+            ));
+        }
+    }
+    if let Some(max_gas) = expect.max_gas {
+        if outcome.gas_used > max_gas {
+            failures.push(format!(
+                "expected at most {max_gas} points to be consumed, got {}",
+                outcome.gas_used
+            ));
+        }
+    }
+
+    Ok(failures)
+}
+
+fn format_storage(storage: &BTreeMap<HostAddress, BTreeMap<Vec<u8>, Option<Vec<u8>>>>) -> String {
+    let entries: Vec<String> = storage
+        .iter()
+        .flat_map(|(address, entries)| {
+            entries.iter().map(move |(key, value)| {
+                let value = value.as_deref().map_or("null".to_string(), format_bytes);
+                format!("{}/{}={}", hex::encode(address), format_bytes(key), value)
+            })
+        })
+        .collect();
+
+    format!("{{{}}}", entries.join(", "))
+}
+
+fn format_balances(balances: &BTreeMap<HostAddress, u128>) -> String {
+    let entries: Vec<String> = balances
+        .iter()
+        .map(|(address, balance)| format!("{}={}", hex::encode(address), balance))
+        .collect();
+
+    format!("{{{}}}", entries.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CALLER: &str = "0101010101010101010101010101010101010101";
+    const CONTRACT: &str = "0202020202020202020202020202020202020202";
+
+    fn abi_suite() -> TestSuite {
+        TestSuite::from_file(Path::new("test_data/vrrb_abi_test.json")).unwrap()
+    }
+
+    #[test]
+    fn runs_the_vrrb_abi_suite() {
+        let reports = run_suite(&abi_suite()).unwrap();
+
+        assert_eq!(reports.len(), 4);
+        for report in reports {
+            assert!(report.passed(), "{}: {:?}", report.name, report.failures);
+        }
+    }
+
+    #[test]
+    fn reports_unmet_expectations() {
+        let mut suite = abi_suite();
+        suite.cases.truncate(1);
+        let expect = &mut suite.cases[0].calls[0].expect;
+        expect.balances = Some(BTreeMap::from([(CALLER.to_string(), 6)]));
+        expect.gas_used = Some(1);
+
+        let reports = run_suite(&suite).unwrap();
+        assert_eq!(reports[0].failures.len(), 2);
+        assert!(reports[0].failures[0].contains("expected balance changes"));
+    }
+
+    #[test]
+    fn fails_calls_the_caller_cannot_pay_for() {
+        let mut suite = abi_suite();
+        suite.cases.truncate(1);
+        suite.cases[0].accounts.clear();
+        suite.cases[0].calls.truncate(1);
+        let call = &mut suite.cases[0].calls[0];
+        call.amount = 1;
+        call.expect = Expectations {
+            success: false,
+            error_contains: Some("Caller cannot pay".to_string()),
+            storage: Some(BTreeMap::new()),
+            balances: Some(BTreeMap::new()),
+            gas_used: Some(0),
+            ..Expectations::default()
+        };
+
+        let reports = run_suite(&suite).unwrap();
+        assert!(reports[0].passed(), "{:?}", reports[0].failures);
+    }
+
+    #[test]
+    fn writes_the_contract_input_document() {
+        let call: TestCall = serde_json::from_value(serde_json::json!({
+            "caller": CALLER,
+            "contract": CONTRACT,
+            "blockHeight": 7,
+            "blockTime": 1689897402,
+            "amount": 3,
+            "input": "hello",
+        }))
+        .unwrap();
+        let context = HostContext {
+            caller: parse_address(CALLER).unwrap(),
+            contract_address: parse_address(CONTRACT).unwrap(),
+            block_height: call.block_height,
+            block_time: call.block_time,
+        };
+        let mut state = InMemoryContractState::default();
+        state.storage_set(&context.contract_address, b"key", b"value");
+
+        let stdin = contract_input(&call, &context, &state, "case:0").unwrap();
+        assert_eq!(stdin.last(), Some(&b'\n'));
+
+        let input: serde_json::Value = serde_json::from_slice(&stdin).unwrap();
+        assert_eq!(
+            input,
+            serde_json::json!({
+                "version": CONTRACT_INPUT_VERSION,
+                "txId": "case:0",
+                "lastBlockTime": 1689897402,
+                "blockHeight": 7,
+                "sender": format!("0x{CALLER}"),
+                "contractAddress": format!("0x{CONTRACT}"),
+                "amount": 3,
+                "state": format!(r#"{{"{}":"{}"}}"#, hex::encode("key"), hex::encode("value")),
+                "input": "hello",
+            })
+        );
+    }
+}
@@ -0,0 +1,79 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+use telemetry::info;
+
+mod harness;
+mod suite;
+
+use suite::TestSuite;
+
+/// Extension of the files treated as test suites when searching directories
+const SUITE_EXTENSION: &str = "json";
+
+#[derive(Parser, Debug)]
+pub struct TestOpts {
+    /// Test suite files to run. Directories are searched for `.json` suites
+    /// recursively.
+    #[clap(value_parser, value_name = "PATH", required = true)]
+    pub paths: Vec<PathBuf>,
+}
+
+/// Run declarative contract test suites against mock chain state, without a
+/// node. Each suite names a module and a list of cases seeding accounts and
+/// storage, issuing calls and asserting their output, state changes and gas.
+/// Fails if any case does.
+pub fn run(opts: &TestOpts) -> Result<()> {
+    let mut suites = vec![];
+    for path in &opts.paths {
+        find_suites(path, &mut suites)?;
+    }
+    suites.sort();
+
+    let mut passed = 0;
+    let mut failed = 0;
+    for path in suites {
+        info!("Running test suite {}", path.display());
+        let suite = TestSuite::from_file(&path)?;
+        println!("{} ({})", path.display(), suite.module.display());
+
+        for report in harness::run_suite(&suite)? {
+            if report.passed() {
+                passed += 1;
+                println!("  ok      {}", report.name);
+            } else {
+                failed += 1;
+                println!("  FAILED  {}", report.name);
+                for failure in &report.failures {
+                    println!("          {}", failure);
+                }
+            }
+        }
+    }
+
+    println!("{} passed, {} failed", passed, failed);
+    if failed > 0 {
+        return Err(anyhow!("{} contract test case(s) failed", failed));
+    }
+
+    Ok(())
+}
+
+fn find_suites(path: &Path, suites: &mut Vec<PathBuf>) -> Result<()> {
+    if !path.is_dir() {
+        suites.push(path.to_path_buf());
+        return Ok(());
+    }
+
+    for entry in std::fs::read_dir(path)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_suites(&path, suites)?;
+        } else if path.extension().and_then(|ext| ext.to_str()) == Some(SUITE_EXTENSION) {
+            suites.push(path);
+        }
+    }
+
+    Ok(())
+}
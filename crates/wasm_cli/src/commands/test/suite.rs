@@ -0,0 +1,185 @@
+//! Declarative contract test suites
+//!
+//! A suite is a JSON file naming the module under test and a list of cases.
+//! Every case starts from its own mock chain state, seeded from `accounts`,
+//! and issues its calls in order. Addresses are 20 bytes of hex. Storage
+//! keys and values prefixed with `0x` are hex encoded, anything else is
+//! taken as UTF-8 text.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use wasm_runtime::host_abi::HostAddress;
+
+/// Points available to each call unless the suite says otherwise
+pub const DEFAULT_METER_LIMIT: u64 = 10_000_000;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TestSuite {
+    /// Path to the WASM or WAT module, relative to the suite file
+    pub module: PathBuf,
+    /// Path to the Javy QuickJS provider the module is linked against,
+    /// relative to the suite file
+    #[serde(default)]
+    pub javy_provider: Option<PathBuf>,
+    /// Points available to each call
+    #[serde(default = "default_meter_limit")]
+    pub meter_limit: u64,
+    /// Version of the gas schedule calls are charged with, the latest when
+    /// omitted
+    #[serde(default)]
+    pub gas_schedule_version: Option<u32>,
+    pub cases: Vec<TestCase>,
+}
+
+fn default_meter_limit() -> u64 {
+    DEFAULT_METER_LIMIT
+}
+
+impl TestSuite {
+    /// Reads the suite at `path`, resolving its module path against the
+    /// directory the suite is in.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let json = std::fs::read(path)?;
+        let mut suite: TestSuite = serde_json::from_slice(&json)
+            .with_context(|| format!("Failed to parse test suite {}", path.display()))?;
+
+        if let Some(dir) = path.parent() {
+            suite.module = dir.join(&suite.module);
+            suite.javy_provider = suite.javy_provider.map(|provider| dir.join(provider));
+        }
+
+        Ok(suite)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TestCase {
+    pub name: String,
+    /// Balances and storage of the accounts that exist before the first call
+    #[serde(default)]
+    pub accounts: BTreeMap<String, MockAccount>,
+    pub calls: Vec<TestCall>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct MockAccount {
+    #[serde(default)]
+    pub balance: u128,
+    #[serde(default)]
+    pub storage: BTreeMap<String, String>,
+}
+
+/// A single call into the module
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TestCall {
+    pub caller: String,
+    pub contract: String,
+    #[serde(default)]
+    pub block_height: u64,
+    #[serde(default)]
+    pub block_time: i64,
+    /// Tokens sent from the caller to the contract with the call
+    #[serde(default)]
+    pub amount: u128,
+    /// Passed to the module as the `input` of the contract input document
+    /// it reads from stdin
+    #[serde(default)]
+    pub input: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Points available to this call, overriding the suite's limit
+    #[serde(default)]
+    pub meter_limit: Option<u64>,
+    #[serde(default)]
+    pub expect: Expectations,
+}
+
+/// What a call is expected to do. Only the expectations that are present are
+/// checked.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Expectations {
+    /// Whether the call completes. Failed calls leave the state untouched.
+    #[serde(default = "default_success")]
+    pub success: bool,
+    /// The exact output written to stdout
+    #[serde(default)]
+    pub stdout: Option<String>,
+    /// Text the error of a failed call must contain
+    #[serde(default)]
+    pub error_contains: Option<String>,
+    /// Every storage entry the call changed, by account. A `null` value
+    /// means the entry was removed.
+    #[serde(default)]
+    pub storage: Option<BTreeMap<String, BTreeMap<String, Option<String>>>>,
+    /// The new balance of every account whose balance the call changed
+    #[serde(default)]
+    pub balances: Option<BTreeMap<String, u128>>,
+    /// The exact number of metering points consumed
+    #[serde(default)]
+    pub gas_used: Option<u64>,
+    /// The most metering points the call may consume
+    #[serde(default)]
+    pub max_gas: Option<u64>,
+}
+
+fn default_success() -> bool {
+    true
+}
+
+impl Default for Expectations {
+    fn default() -> Self {
+        Self {
+            success: default_success(),
+            stdout: None,
+            error_contains: None,
+            storage: None,
+            balances: None,
+            gas_used: None,
+            max_gas: None,
+        }
+    }
+}
+
+/// Parses a 20 byte hex address, with or without a `0x` prefix
+pub fn parse_address(address: &str) -> Result<HostAddress> {
+    let bytes = hex::decode(address.trim_start_matches("0x"))
+        .with_context(|| format!("Invalid address {address}"))?;
+
+    bytes
+        .try_into()
+        .map_err(|_| anyhow!("Address {address} is not 20 bytes long"))
+}
+
+/// Parses a storage key or value, hex encoded when prefixed with `0x`
+pub fn parse_bytes(value: &str) -> Result<Vec<u8>> {
+    match value.strip_prefix("0x") {
+        Some(hex) => hex::decode(hex).with_context(|| format!("Invalid hex bytes {value}")),
+        None => Ok(value.as_bytes().to_vec()),
+    }
+}
+
+/// Formats bytes the way [parse_bytes] reads them, as text when they are
+/// printable
+pub fn format_bytes(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text)
+            if !text.starts_with("0x")
+                && text.chars().all(|c| c.is_ascii_graphic() || c == ' ') =>
+        {
+            text.to_string()
+        }
+        _ => format!("0x{}", hex::encode(bytes)),
+    }
+}
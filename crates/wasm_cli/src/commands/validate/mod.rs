@@ -38,6 +38,11 @@ pub fn run(opts: &ValidateOpts) -> Result<()> {
         expected_to_run = false;
     }
 
+    if w.has_nondeterministic_ops {
+        println!("WASM module uses nondeterministic floating point operators");
+        expected_to_run = false;
+    }
+
     if !w.has_vrrb {
         // This, unlike the other checks, is not fatal
         println!("WASM module doesn't make use of any VRRB extensions (not fatal)");
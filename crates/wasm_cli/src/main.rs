@@ -15,6 +15,12 @@ fn main() -> Result<()> {
         Some(cli::WasmCommands::Execute(opts)) => {
             commands::execute::run(opts)?;
         }
+        Some(cli::WasmCommands::Profile(opts)) => {
+            commands::profile::run(opts)?;
+        }
+        Some(cli::WasmCommands::Test(opts)) => {
+            commands::test::run(opts)?;
+        }
         Some(cli::WasmCommands::Validate(opts)) => {
             commands::validate::run(opts)?;
         }
@@ -16,6 +16,8 @@ mod loader_tests {
     // A WASM module represented as Web Assembly Text (WAT) to be assembled/compiled
     // to binary
     const SIMPLE_WAT_TEST_MODULE: &str = "test_data/simple.wat";
+    // A WAT module doing floating point arithmetic, which isn't deterministic
+    const FLOAT_WAT_TEST_MODULE: &str = "test_data/float.wat";
 
     #[test]
     fn builder_wasm_load() {
@@ -128,4 +130,56 @@ mod loader_tests {
             assert!(wasm.needs_javy, "Didn't find expected Javy dependency");
         }
     }
+
+    #[test]
+    fn builder_check_nondeterministic_ops() {
+        let w = WasmLoaderBuilder::default()
+            .wat_text(std::fs::read(FLOAT_WAT_TEST_MODULE).unwrap())
+            .parse()
+            .unwrap()
+            .build();
+        assert!(w.is_ok());
+        if let Ok(wasm) = w {
+            debug!("w: {:02x?}", wasm);
+            assert!(
+                wasm.has_nondeterministic_ops,
+                "Didn't find floating point arithmetic"
+            );
+        }
+    }
+
+    #[test]
+    fn builder_deterministic_rejects_float_ops() {
+        let w = WasmLoaderBuilder::default()
+            .wat_text(std::fs::read(FLOAT_WAT_TEST_MODULE).unwrap())
+            .deterministic(true)
+            .parse();
+        assert!(w.is_err());
+
+        let w = WasmLoaderBuilder::default()
+            .wat_text(std::fs::read(SIMPLE_WAT_TEST_MODULE).unwrap())
+            .deterministic(true)
+            .parse()
+            .unwrap()
+            .build();
+        assert!(w.is_ok());
+        if let Ok(wasm) = w {
+            assert!(!wasm.has_nondeterministic_ops);
+        }
+    }
+
+    #[test]
+    fn builder_deterministic_rejects_start_function() {
+        let wat = r#"(module (func $init) (start $init) (func (export "_start")))"#;
+        let w = WasmLoaderBuilder::default()
+            .wat_text(wat.as_bytes().to_vec())
+            .parse();
+        assert!(w.is_ok());
+
+        let w = WasmLoaderBuilder::default()
+            .wat_text(wat.as_bytes().to_vec())
+            .deterministic(true)
+            .parse();
+        assert!(w.is_err());
+    }
 }
@@ -6,7 +6,7 @@
 
 use std::collections::HashMap;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use derive_builder::Builder;
 // Use and review of log macros within this crate:
 //   * error for *user-actionable* information to be visible to a developer or operator
@@ -15,7 +15,7 @@ use derive_builder::Builder;
 //     issues
 use telemetry::log::{debug, error};
 use wasmer::wat2wasm;
-use wasmparser::{Parser, Payload};
+use wasmparser::{Operator, Parser, Payload};
 
 use crate::constants;
 
@@ -63,6 +63,17 @@ pub struct WasmLoader {
     #[builder(default = "false")]
     #[builder(private)]
     pub has_vrrb: bool,
+    /// True if this WASM module uses floating point operators whose NaN
+    /// results may differ from one platform to the next.
+    #[builder(default = "false")]
+    #[builder(private)]
+    pub has_nondeterministic_ops: bool,
+    /// Requires the module to execute identically everywhere, as smart
+    /// contracts must. [WasmLoaderBuilder::parse] rejects modules using
+    /// NaN-nondeterministic floating point operators, or a start function,
+    /// when set.
+    #[builder(default = "false")]
+    pub deterministic: bool,
     /// A HashMap where the key is a string representing the namespace, and
     /// the value is a set of strings representing symbols this
     /// module expects to be present. Used in determining
@@ -159,6 +170,36 @@ impl WasmLoaderBuilder {
                                     // too
                                 }
                             }
+                            Payload::StartSection { func, .. } => {
+                                // Start functions run while the module is
+                                // instantiated, before the runtime gives it
+                                // any metering points
+                                if self.deterministic == Some(true) {
+                                    error!("Start function: {}", func);
+                                    return Err(anyhow!(
+                                        "WASM module has a start function, contracts must start from {}",
+                                        constants::WASI_ENTRY_POINT
+                                    ));
+                                }
+                            }
+                            Payload::CodeSectionEntry(body) => {
+                                let mut reader = body.get_operators_reader()?;
+                                while !reader.eof() {
+                                    let operator = reader.read()?;
+                                    if !is_nan_nondeterministic(&operator) {
+                                        continue;
+                                    }
+
+                                    if self.deterministic == Some(true) {
+                                        error!("Nondeterministic operator: {:?}", operator);
+                                        return Err(anyhow!(
+                                            "WASM module uses the NaN-nondeterministic operator {:?}",
+                                            operator
+                                        ));
+                                    }
+                                    new.has_nondeterministic_ops = Some(true);
+                                }
+                            }
                             Payload::ImportSection(s) => {
                                 for import in s {
                                     let import = import.expect("Import section is malformed");
@@ -205,3 +246,60 @@ impl WasmLoaderBuilder {
         Ok(new)
     }
 }
+
+/// Returns whether the NaN produced by `operator` may differ between
+/// platforms. Floating point arithmetic is free to return any NaN bit
+/// pattern, whereas moving, comparing or changing the sign of floats is not.
+fn is_nan_nondeterministic(operator: &Operator) -> bool {
+    matches!(
+        operator,
+        Operator::F32Add
+            | Operator::F32Sub
+            | Operator::F32Mul
+            | Operator::F32Div
+            | Operator::F32Sqrt
+            | Operator::F32Min
+            | Operator::F32Max
+            | Operator::F32Ceil
+            | Operator::F32Floor
+            | Operator::F32Trunc
+            | Operator::F32Nearest
+            | Operator::F64Add
+            | Operator::F64Sub
+            | Operator::F64Mul
+            | Operator::F64Div
+            | Operator::F64Sqrt
+            | Operator::F64Min
+            | Operator::F64Max
+            | Operator::F64Ceil
+            | Operator::F64Floor
+            | Operator::F64Trunc
+            | Operator::F64Nearest
+            | Operator::F32DemoteF64
+            | Operator::F64PromoteF32
+            | Operator::F32x4Add
+            | Operator::F32x4Sub
+            | Operator::F32x4Mul
+            | Operator::F32x4Div
+            | Operator::F32x4Sqrt
+            | Operator::F32x4Min
+            | Operator::F32x4Max
+            | Operator::F32x4Ceil
+            | Operator::F32x4Floor
+            | Operator::F32x4Trunc
+            | Operator::F32x4Nearest
+            | Operator::F64x2Add
+            | Operator::F64x2Sub
+            | Operator::F64x2Mul
+            | Operator::F64x2Div
+            | Operator::F64x2Sqrt
+            | Operator::F64x2Min
+            | Operator::F64x2Max
+            | Operator::F64x2Ceil
+            | Operator::F64x2Floor
+            | Operator::F64x2Trunc
+            | Operator::F64x2Nearest
+            | Operator::F32x4DemoteF64x2Zero
+            | Operator::F64x2PromoteLowF32x4
+    )
+}
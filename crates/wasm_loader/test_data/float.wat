@@ -0,0 +1,9 @@
+(module
+  (type $average_t (func (param f64 f64) (result f64)))
+  (func $average_f (type $average_t) (param $a f64) (param $b f64) (result f64)
+    local.get $a
+    local.get $b
+    f64.add
+    f64.const 2
+    f64.div)
+  (export "average" (func $average_f)))
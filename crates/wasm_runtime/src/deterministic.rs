@@ -0,0 +1,253 @@
+//! Deterministic execution
+//!
+//! Contract results have to be identical on every validator, so modules
+//! executed in deterministic mode do not get to see the host. The WASI clock
+//! reports the time of the block being applied, `random_get` is served from a
+//! generator seeded from the block context and every WASI call that is not
+//! needed to read input and write output, such as filesystem and network
+//! access, fails with `ENOTCAPABLE`.
+
+use wasmer::{
+    AsStoreMut, Extern, ExternType, Function, FunctionEnv, FunctionEnvMut, Memory, Module,
+    RuntimeError, Type, Value,
+};
+use wasmer_wasix_types::wasi::Errno;
+
+/// Namespaces of the WASI interfaces whose imports are stubbed
+const WASI_NAMESPACES: &[&str] = &[
+    "wasi_unstable",
+    "wasi_snapshot_preview1",
+    "wasix_32v1",
+    "wasix_64v1",
+];
+
+/// WASI calls left to the regular WASI implementation. They only touch the
+/// arguments, environment and standard streams set up by the runtime.
+const ALLOWED_WASI_CALLS: &[&str] = &[
+    "args_get",
+    "args_sizes_get",
+    "environ_get",
+    "environ_sizes_get",
+    "fd_close",
+    "fd_fdstat_get",
+    "fd_prestat_get",
+    "fd_prestat_dir_name",
+    "fd_read",
+    "fd_seek",
+    "fd_write",
+    "proc_exit",
+    "sched_yield",
+];
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+/// Random bytes are generated and written to the module's memory this many
+/// at a time
+const RANDOM_CHUNK_LEN: usize = 256;
+
+/// Block context deterministic executions are derived from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeterministicContext {
+    /// Reported by the WASI clocks, in seconds since the epoch
+    pub block_time: i64,
+    /// Seed of the generator backing `random_get`
+    pub seed: u64,
+}
+
+impl DeterministicContext {
+    /// Creates a context for a block with the given timestamp, deriving the
+    /// random seed from `seed_material`, e.g. the digest of the transaction
+    /// being executed.
+    pub fn new(block_time: i64, seed_material: &[u8]) -> Self {
+        // FNV-1a, stable across platforms and releases
+        let seed = seed_material
+            .iter()
+            .fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+                (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+            });
+
+        Self { block_time, seed }
+    }
+}
+
+/// Data shared by the stubbed WASI calls of a single execution
+pub(crate) struct DeterministicEnv {
+    context: DeterministicContext,
+    rng_state: u64,
+    memory: Option<Memory>,
+}
+
+impl DeterministicEnv {
+    pub(crate) fn new(context: DeterministicContext) -> Self {
+        Self {
+            context,
+            rng_state: context.seed,
+            memory: None,
+        }
+    }
+
+    pub(crate) fn set_memory(&mut self, memory: Memory) {
+        self.memory = Some(memory);
+    }
+
+    /// SplitMix64, which is enough to hand modules reproducible bytes
+    fn next_random(&mut self) -> u64 {
+        self.rng_state = self.rng_state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.rng_state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn block_time_nanos(&self) -> u64 {
+        u64::try_from(self.context.block_time)
+            .unwrap_or_default()
+            .saturating_mul(NANOS_PER_SECOND)
+    }
+}
+
+fn errno(errno: Errno) -> Result<Vec<Value>, RuntimeError> {
+    Ok(vec![Value::I32(errno as i32)])
+}
+
+fn arg_ptr(args: &[Value], index: usize) -> Option<u64> {
+    match args.get(index) {
+        Some(Value::I32(ptr)) => u64::try_from(*ptr).ok(),
+        Some(Value::I64(ptr)) => u64::try_from(*ptr).ok(),
+        _ => None,
+    }
+}
+
+fn write_memory(
+    env: &mut FunctionEnvMut<DeterministicEnv>,
+    ptr: Option<u64>,
+    bytes: &[u8],
+) -> Result<Vec<Value>, RuntimeError> {
+    let (data, store) = env.data_and_store_mut();
+    let written = match (&data.memory, ptr) {
+        (Some(memory), Some(ptr)) => memory.view(&store).write(ptr, bytes).is_ok(),
+        _ => false,
+    };
+
+    if written {
+        errno(Errno::Success)
+    } else {
+        errno(Errno::Fault)
+    }
+}
+
+fn clock_time_get(
+    mut env: FunctionEnvMut<DeterministicEnv>,
+    args: &[Value],
+) -> Result<Vec<Value>, RuntimeError> {
+    let time = env.data().block_time_nanos();
+
+    write_memory(
+        &mut env,
+        arg_ptr(args, args.len().saturating_sub(1)),
+        &time.to_le_bytes(),
+    )
+}
+
+fn clock_res_get(
+    mut env: FunctionEnvMut<DeterministicEnv>,
+    args: &[Value],
+) -> Result<Vec<Value>, RuntimeError> {
+    // The clock only moves from one block to the next
+    write_memory(
+        &mut env,
+        arg_ptr(args, args.len().saturating_sub(1)),
+        &NANOS_PER_SECOND.to_le_bytes(),
+    )
+}
+
+fn random_get(
+    mut env: FunctionEnvMut<DeterministicEnv>,
+    args: &[Value],
+) -> Result<Vec<Value>, RuntimeError> {
+    let (ptr, len) = match (arg_ptr(args, 0), arg_ptr(args, 1)) {
+        (Some(ptr), Some(len)) => (ptr, len),
+        _ => return errno(Errno::Inval),
+    };
+
+    // The buffer is checked against the module's memory before anything is
+    // generated, so its length never has to be trusted
+    let (data, store) = env.data_and_store_mut();
+    let memory = match &data.memory {
+        Some(memory) if ptr.saturating_add(len) <= memory.view(&store).data_size() => {
+            memory.clone()
+        }
+        _ => return errno(Errno::Fault),
+    };
+
+    let mut chunk = [0u8; RANDOM_CHUNK_LEN];
+    for offset in (0..len).step_by(RANDOM_CHUNK_LEN) {
+        let chunk_len = (len - offset).min(RANDOM_CHUNK_LEN as u64) as usize;
+        for word in chunk[..chunk_len].chunks_mut(8) {
+            word.copy_from_slice(&data.next_random().to_le_bytes()[..word.len()]);
+        }
+
+        if memory
+            .view(&store)
+            .write(ptr + offset, &chunk[..chunk_len])
+            .is_err()
+        {
+            return errno(Errno::Fault);
+        }
+    }
+
+    errno(Errno::Success)
+}
+
+/// Returns the functions overriding the module's WASI imports, along with
+/// the namespace and name they are imported under. The stubs take on the
+/// signature of the import they replace, so they link against every WASI
+/// flavour.
+pub(crate) fn wasi_overrides(
+    store: &mut impl AsStoreMut,
+    module: &Module,
+    env: &FunctionEnv<DeterministicEnv>,
+) -> Vec<(String, String, Extern)> {
+    module
+        .imports()
+        .filter(|import| WASI_NAMESPACES.contains(&import.module()))
+        .filter(|import| !ALLOWED_WASI_CALLS.contains(&import.name()))
+        .filter_map(|import| {
+            let ty = match import.ty() {
+                ExternType::Function(ty) => ty.clone(),
+                _ => return None,
+            };
+
+            let function = match import.name() {
+                "clock_time_get" => Function::new_with_env(store, env, ty, clock_time_get),
+                "clock_res_get" => Function::new_with_env(store, env, ty, clock_res_get),
+                "random_get" => Function::new_with_env(store, env, ty, random_get),
+                name => {
+                    let name = name.to_string();
+                    let returns_errno = ty.results() == [Type::I32];
+                    Function::new_with_env(
+                        store,
+                        env,
+                        ty,
+                        move |_env: FunctionEnvMut<DeterministicEnv>, _args: &[Value]| {
+                            telemetry::debug!("{name} is not available in deterministic mode");
+                            if returns_errno {
+                                errno(Errno::Notcapable)
+                            } else {
+                                Err(RuntimeError::new(format!(
+                                    "{name} is not available in deterministic mode"
+                                )))
+                            }
+                        },
+                    )
+                }
+            };
+
+            Some((
+                import.module().to_string(),
+                import.name().to_string(),
+                function.into(),
+            ))
+        })
+        .collect()
+}
@@ -0,0 +1,112 @@
+use wasmer::{Cranelift, Target};
+
+use crate::{
+    deterministic::DeterministicContext,
+    metering::{cost_function, MeteringConfig},
+    wasm_runtime::WasmRuntime,
+};
+
+const TEST_SPENDING_LIMIT: u64 = 1000000;
+const TEST_BLOCK_TIME: i64 = 1689897402;
+const TEST_TX_ID: &[u8] = b"81b067ac-8693-483a-8354-d7de15ab6f2c";
+/// `ENOTCAPABLE` as written by the test module, a little-endian `u32`
+const NOT_CAPABLE_HEX: &str = "4c000000";
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Executes a module in deterministic mode and returns what it wrote to
+/// stdout
+fn execute_deterministic(wasm_bytes: &[u8], stdin: &[u8], context: DeterministicContext) -> String {
+    let metering_config = MeteringConfig::new(TEST_SPENDING_LIMIT, cost_function);
+    let mut runtime =
+        WasmRuntime::new::<Cranelift>(&Target::default(), wasm_bytes, metering_config)
+            .unwrap()
+            .stdin(stdin)
+            .deterministic(context);
+    runtime.execute().unwrap();
+
+    runtime.stdout()
+}
+
+/// This test checks that executing the same contract with the same input and
+/// block context produces byte-identical output, run after run.
+#[test]
+fn test_deterministic_outputs_are_identical() {
+    let wasm_bytes = std::fs::read("test_data/wasm_test.wasm").unwrap();
+    let json_data = std::fs::read("test_data/wasm_test_oneline.json").unwrap();
+    let context = DeterministicContext::new(TEST_BLOCK_TIME, TEST_TX_ID);
+
+    let first = execute_deterministic(&wasm_bytes, &json_data, context);
+    assert!(!first.is_empty());
+
+    for _ in 0..4 {
+        let next = execute_deterministic(&wasm_bytes, &json_data, context);
+        assert_eq!(first.as_bytes(), next.as_bytes());
+    }
+}
+
+/// This test checks that the WASI clock and random source are derived from
+/// the block context, and that filesystem calls are refused.
+#[test]
+fn test_deterministic_wasi_calls() {
+    let wasm_bytes = std::fs::read("test_data/deterministic_test.wat").unwrap();
+    let context = DeterministicContext::new(TEST_BLOCK_TIME, TEST_TX_ID);
+
+    let first = execute_deterministic(&wasm_bytes, &[], context);
+    let second = execute_deterministic(&wasm_bytes, &[], context);
+    assert_eq!(first.as_bytes(), second.as_bytes());
+
+    // 8 bytes of clock, 24 random bytes and the errno of path_open
+    assert_eq!(first.len(), 72);
+    let block_time_nanos = TEST_BLOCK_TIME as u64 * 1_000_000_000;
+    assert_eq!(&first[..16], hex(&block_time_nanos.to_le_bytes()));
+    assert_eq!(&first[64..], NOT_CAPABLE_HEX);
+
+    let reseeded = execute_deterministic(
+        &wasm_bytes,
+        &[],
+        DeterministicContext::new(TEST_BLOCK_TIME, b"another transaction"),
+    );
+    assert_eq!(first[..16], reseeded[..16]);
+    assert_ne!(first[16..64], reseeded[16..64]);
+    assert_eq!(first[64..], reseeded[64..]);
+}
+
+/// This test checks that seeds only depend on the seed material.
+#[test]
+fn test_deterministic_context_seed() {
+    assert_eq!(
+        DeterministicContext::new(TEST_BLOCK_TIME, TEST_TX_ID),
+        DeterministicContext::new(TEST_BLOCK_TIME, TEST_TX_ID)
+    );
+    assert_ne!(
+        DeterministicContext::new(TEST_BLOCK_TIME, TEST_TX_ID).seed,
+        DeterministicContext::new(TEST_BLOCK_TIME, b"").seed
+    );
+}
+
+/// This test checks that random buffers are checked against the module's
+/// memory rather than allocated from the length the module asks for.
+#[test]
+fn test_deterministic_random_bounds() {
+    let wat = r#"
+        (module
+          (import "wasi_snapshot_preview1" "random_get"
+            (func $random_get (param i32 i32) (result i32)))
+          (memory (export "memory") 1)
+          (func $expect (param $errno i32) (param $expected i32)
+            (if (i32.ne (local.get $errno) (local.get $expected)) (then unreachable)))
+          (func (export "_start")
+            ;; EFAULT, larger than the module's memory
+            (call $expect (call $random_get (i32.const 0) (i32.const 0x7fffffff)) (i32.const 21))
+            ;; EFAULT, runs past the end of the module's memory
+            (call $expect (call $random_get (i32.const 65000) (i32.const 1000)) (i32.const 21))
+            ;; spans several chunks
+            (call $expect (call $random_get (i32.const 1024) (i32.const 1000)) (i32.const 0))))
+    "#;
+    let context = DeterministicContext::new(TEST_BLOCK_TIME, TEST_TX_ID);
+
+    execute_deterministic(wat.as_bytes(), &[], context);
+}
@@ -56,6 +56,27 @@ origin: {:?}",
 
     #[error(transparent)]
     ExportError(#[from] ExportError),
+
+    #[error("execution ran out of gas after consuming all {limit} metering points")]
+    OutOfGas { limit: u64 },
+
+    #[error("module declares the VRRB ABI but no host state was provided")]
+    HostStateMissing,
+
+    #[error("module exports an invalid VRRB ABI version")]
+    InvalidAbiVersion,
+
+    #[error("module requires VRRB ABI version {0}, which is not supported")]
+    UnsupportedAbiVersion(u32),
+
+    #[error("module imports the Javy QuickJS provider but none was provided")]
+    JavyProviderMissing,
+
+    #[error("profiler counters could not be read from the module")]
+    ProfilerUnavailable,
+
+    #[error("profiled modules cannot be linked against the Javy QuickJS provider")]
+    ProfiledJavyProvider,
 }
 
 impl WasmRuntimeError {
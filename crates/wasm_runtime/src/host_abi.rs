@@ -0,0 +1,485 @@
+//! VRRB host ABI
+//!
+//! Host functions imported by WASM modules that declare the VRRB ABI by
+//! exporting [VRRB_ABI_VERSION_EXPORT]. They let contracts read and write
+//! the storage of their own account, look up balances, learn who called them
+//! and when, and move tokens out of their account. All chain state is reached
+//! through the [ContractState] trait, so the ABI can be exercised without a
+//! node.
+//!
+//! Byte buffers are passed as pointer/length pairs into the module's linear
+//! memory. Addresses are 20 bytes and amounts are 16 byte little-endian
+//! integers. Functions returning `i32` return [HOST_OK] (or a length) on
+//! success and one of the negative `HOST_ERR_*` codes otherwise.
+//!
+//! Every call into the host is charged the host call cost of the metering
+//! configuration, and `storage_set` is also charged for every byte of key and
+//! value it writes. Calls that cannot be paid for trap, ending execution as
+//! out of gas.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+};
+
+use wasmer::{
+    AsStoreMut, AsStoreRef, Extern, Function, FunctionEnv, FunctionEnvMut, Instance, Memory,
+    RuntimeError, Value,
+};
+use wasmer_middlewares::metering::{get_remaining_points, set_remaining_points, MeteringPoints};
+
+use crate::errors::WasmRuntimeError;
+
+/// The import namespace the host functions are registered under
+pub const VRRB_ABI_NAMESPACE: &str = "vrrb_abi_v1";
+/// The version of the ABI implemented by this runtime
+pub const VRRB_ABI_VERSION: u32 = 1;
+/// Modules declare the ABI by exporting a static `u32` holding the ABI
+/// version they were built against under this name.
+pub const VRRB_ABI_VERSION_EXPORT: &str = "_vrrb_abi_version";
+
+pub const HOST_OK: i32 = 0;
+/// The requested storage key does not exist
+pub const HOST_ERR_NOT_FOUND: i32 = -1;
+/// A pointer or length passed to the host was out of the module's memory,
+/// or the buffer was larger than [MAX_HOST_BUFFER_LEN]
+pub const HOST_ERR_MEMORY: i32 = -2;
+/// The state backend refused the operation, e.g. an overdrawn transfer
+pub const HOST_ERR_REJECTED: i32 = -3;
+
+/// Largest buffer the host copies out of a module's memory, such as a
+/// storage key or value
+pub const MAX_HOST_BUFFER_LEN: usize = 64 * 1024;
+
+/// Raw bytes of an account address
+pub type HostAddress = [u8; 20];
+
+/// Details of the call being executed, exposed read-only to the contract.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HostContext {
+    pub caller: HostAddress,
+    pub contract_address: HostAddress,
+    pub block_height: u64,
+    pub block_time: i64,
+}
+
+/// Chain state backing the host functions. The host always passes the
+/// address of the executing contract, so contracts can only touch their
+/// own storage and only spend their own balance.
+pub trait ContractState: Send {
+    fn storage_get(&self, contract: &HostAddress, key: &[u8]) -> Option<Vec<u8>>;
+
+    fn storage_set(&mut self, contract: &HostAddress, key: &[u8], value: &[u8]);
+
+    fn balance(&self, address: &HostAddress) -> u128;
+
+    fn transfer(
+        &mut self,
+        from: &HostAddress,
+        to: &HostAddress,
+        amount: u128,
+    ) -> Result<(), String>;
+}
+
+/// A [ContractState] kept entirely in memory, handy for testing contracts.
+///
+/// It behaves the way contract accounts do on chain: the storage of each
+/// account is kept as the JSON object mapping hex encoded keys to hex
+/// encoded values that accounts store, storage that is not such an object
+/// is treated as empty, and contracts cannot transfer to themselves.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryContractState {
+    /// The storage of each account, as kept on the account
+    pub storage: HashMap<HostAddress, String>,
+    pub balances: HashMap<HostAddress, u128>,
+}
+
+impl InMemoryContractState {
+    /// The storage entries of `address`, decoded
+    pub fn entries(&self, address: &HostAddress) -> BTreeMap<Vec<u8>, Vec<u8>> {
+        self.encoded_entries(address)
+            .into_iter()
+            .filter_map(|(key, value)| Some((hex::decode(key).ok()?, hex::decode(value).ok()?)))
+            .collect()
+    }
+
+    fn encoded_entries(&self, address: &HostAddress) -> BTreeMap<String, String> {
+        self.storage
+            .get(address)
+            .and_then(|storage| serde_json::from_str(storage).ok())
+            .unwrap_or_default()
+    }
+}
+
+impl ContractState for InMemoryContractState {
+    fn storage_get(&self, contract: &HostAddress, key: &[u8]) -> Option<Vec<u8>> {
+        self.encoded_entries(contract)
+            .get(&hex::encode(key))
+            .and_then(|value| hex::decode(value).ok())
+    }
+
+    fn storage_set(&mut self, contract: &HostAddress, key: &[u8], value: &[u8]) {
+        let mut entries = self.encoded_entries(contract);
+        entries.insert(hex::encode(key), hex::encode(value));
+        if let Ok(storage) = serde_json::to_string(&entries) {
+            self.storage.insert(*contract, storage);
+        }
+    }
+
+    fn balance(&self, address: &HostAddress) -> u128 {
+        self.balances.get(address).copied().unwrap_or_default()
+    }
+
+    fn transfer(
+        &mut self,
+        from: &HostAddress,
+        to: &HostAddress,
+        amount: u128,
+    ) -> Result<(), String> {
+        if to == from {
+            return Err("contracts cannot transfer to themselves".to_string());
+        }
+
+        let remaining = self
+            .balance(from)
+            .checked_sub(amount)
+            .ok_or_else(|| format!("insufficient balance to transfer {amount}"))?;
+
+        self.balances.insert(*from, remaining);
+        *self.balances.entry(*to).or_default() += amount;
+
+        Ok(())
+    }
+}
+
+/// Data shared by the host functions of a single execution
+pub(crate) struct HostEnv {
+    context: HostContext,
+    state: Arc<Mutex<dyn ContractState>>,
+    host_call_cost: u64,
+    storage_byte_cost: u64,
+    instance: Option<Instance>,
+    memory: Option<Memory>,
+    out_of_gas: bool,
+}
+
+impl HostEnv {
+    pub(crate) fn new(
+        context: HostContext,
+        state: Arc<Mutex<dyn ContractState>>,
+        host_call_cost: u64,
+        storage_byte_cost: u64,
+    ) -> Self {
+        Self {
+            context,
+            state,
+            host_call_cost,
+            storage_byte_cost,
+            instance: None,
+            memory: None,
+            out_of_gas: false,
+        }
+    }
+
+    /// Binds the environment to the instance calling the host functions
+    pub(crate) fn attach(&mut self, instance: Instance, memory: Memory) {
+        self.instance = Some(instance);
+        self.memory = Some(memory);
+    }
+
+    /// Whether a host function could not be paid for
+    pub(crate) fn is_out_of_gas(&self) -> bool {
+        self.out_of_gas
+    }
+
+    /// Copies `len` bytes at `ptr` out of the module's memory. The range is
+    /// checked before anything is allocated, so modules cannot make the host
+    /// allocate more than [MAX_HOST_BUFFER_LEN] bytes per buffer.
+    fn read(&self, store: &impl AsStoreRef, ptr: i32, len: i32) -> Option<Vec<u8>> {
+        let memory = self.memory.as_ref()?;
+        let view = memory.view(store);
+        let ptr = u64::try_from(ptr).ok()?;
+        let len = usize::try_from(len)
+            .ok()
+            .filter(|len| *len <= MAX_HOST_BUFFER_LEN)?;
+        if ptr.checked_add(len as u64)? > view.data_size() {
+            return None;
+        }
+
+        let mut buf = vec![0u8; len];
+        view.read(ptr, &mut buf).ok()?;
+
+        Some(buf)
+    }
+
+    fn read_address(&self, store: &impl AsStoreRef, ptr: i32) -> Option<HostAddress> {
+        self.read(store, ptr, 20)?.try_into().ok()
+    }
+
+    fn read_amount(&self, store: &impl AsStoreRef, ptr: i32) -> Option<u128> {
+        let bytes: [u8; 16] = self.read(store, ptr, 16)?.try_into().ok()?;
+
+        Some(u128::from_le_bytes(bytes))
+    }
+
+    fn write(&self, store: &impl AsStoreRef, ptr: i32, bytes: &[u8]) -> i32 {
+        let written = self.memory.as_ref().and_then(|memory| {
+            memory
+                .view(store)
+                .write(u64::try_from(ptr).ok()?, bytes)
+                .ok()
+        });
+
+        match written {
+            Some(_) => HOST_OK,
+            None => HOST_ERR_MEMORY,
+        }
+    }
+
+    fn with_state<R>(&self, f: impl FnOnce(&mut dyn ContractState) -> R) -> Option<R> {
+        let mut state = self.state.lock().ok()?;
+
+        Some(f(&mut *state))
+    }
+}
+
+/// Deducts the host call cost from the calling instance's metering points
+fn charge(env: &mut FunctionEnvMut<HostEnv>) -> Result<(), RuntimeError> {
+    let cost = env.data().host_call_cost;
+    charge_points(env, cost)
+}
+
+/// Deducts `cost` points from the calling instance's metering points
+fn charge_points(env: &mut FunctionEnvMut<HostEnv>, cost: u64) -> Result<(), RuntimeError> {
+    let (host, mut store) = env.data_and_store_mut();
+    let instance = match &host.instance {
+        Some(instance) if cost > 0 => instance.clone(),
+        _ => return Ok(()),
+    };
+
+    let remaining = match get_remaining_points(&mut store, &instance) {
+        MeteringPoints::Remaining(remaining) => remaining,
+        MeteringPoints::Exhausted => 0,
+    };
+
+    match remaining.checked_sub(cost) {
+        Some(remaining) => {
+            set_remaining_points(&mut store, &instance, remaining);
+            Ok(())
+        }
+        None => {
+            set_remaining_points(&mut store, &instance, 0);
+            host.out_of_gas = true;
+            Err(RuntimeError::new("out of gas"))
+        }
+    }
+}
+
+fn storage_get(
+    mut env: FunctionEnvMut<HostEnv>,
+    key_ptr: i32,
+    key_len: i32,
+    out_ptr: i32,
+    out_cap: i32,
+) -> Result<i32, RuntimeError> {
+    charge(&mut env)?;
+
+    let (host, store) = env.data_and_store_mut();
+    let key = match host.read(&store, key_ptr, key_len) {
+        Some(key) => key,
+        None => return Ok(HOST_ERR_MEMORY),
+    };
+
+    let contract = host.context.contract_address;
+    let value = match host.with_state(|state| state.storage_get(&contract, &key)) {
+        Some(Some(value)) => value,
+        Some(None) => return Ok(HOST_ERR_NOT_FOUND),
+        None => return Ok(HOST_ERR_REJECTED),
+    };
+
+    // Values larger than the output buffer are not written, the returned
+    // length lets the module retry with a large enough buffer
+    if value.len() <= usize::try_from(out_cap).unwrap_or_default()
+        && host.write(&store, out_ptr, &value) != HOST_OK
+    {
+        return Ok(HOST_ERR_MEMORY);
+    }
+
+    Ok(i32::try_from(value.len()).unwrap_or(HOST_ERR_MEMORY))
+}
+
+fn storage_set(
+    mut env: FunctionEnvMut<HostEnv>,
+    key_ptr: i32,
+    key_len: i32,
+    value_ptr: i32,
+    value_len: i32,
+) -> Result<i32, RuntimeError> {
+    // The declared lengths are paid for before the buffers are read, so
+    // buffers that turn out to be out of bounds are charged as well
+    let bytes = u64::try_from(key_len)
+        .unwrap_or_default()
+        .saturating_add(u64::try_from(value_len).unwrap_or_default());
+    let host = env.data();
+    let cost = host
+        .host_call_cost
+        .saturating_add(host.storage_byte_cost.saturating_mul(bytes));
+    charge_points(&mut env, cost)?;
+
+    let (host, store) = env.data_and_store_mut();
+    let (key, value) = match (
+        host.read(&store, key_ptr, key_len),
+        host.read(&store, value_ptr, value_len),
+    ) {
+        (Some(key), Some(value)) => (key, value),
+        _ => return Ok(HOST_ERR_MEMORY),
+    };
+
+    let contract = host.context.contract_address;
+    match host.with_state(|state| state.storage_set(&contract, &key, &value)) {
+        Some(_) => Ok(HOST_OK),
+        None => Ok(HOST_ERR_REJECTED),
+    }
+}
+
+fn balance(
+    mut env: FunctionEnvMut<HostEnv>,
+    address_ptr: i32,
+    out_ptr: i32,
+) -> Result<i32, RuntimeError> {
+    charge(&mut env)?;
+
+    let (host, store) = env.data_and_store_mut();
+    let address = match host.read_address(&store, address_ptr) {
+        Some(address) => address,
+        None => return Ok(HOST_ERR_MEMORY),
+    };
+
+    match host.with_state(|state| state.balance(&address)) {
+        Some(balance) => Ok(host.write(&store, out_ptr, &balance.to_le_bytes())),
+        None => Ok(HOST_ERR_REJECTED),
+    }
+}
+
+fn caller(mut env: FunctionEnvMut<HostEnv>, out_ptr: i32) -> Result<i32, RuntimeError> {
+    charge(&mut env)?;
+
+    let (host, store) = env.data_and_store_mut();
+
+    Ok(host.write(&store, out_ptr, &host.context.caller))
+}
+
+fn contract_address(mut env: FunctionEnvMut<HostEnv>, out_ptr: i32) -> Result<i32, RuntimeError> {
+    charge(&mut env)?;
+
+    let (host, store) = env.data_and_store_mut();
+
+    Ok(host.write(&store, out_ptr, &host.context.contract_address))
+}
+
+fn block_height(mut env: FunctionEnvMut<HostEnv>) -> Result<i64, RuntimeError> {
+    charge(&mut env)?;
+
+    Ok(env.data().context.block_height as i64)
+}
+
+fn block_time(mut env: FunctionEnvMut<HostEnv>) -> Result<i64, RuntimeError> {
+    charge(&mut env)?;
+
+    Ok(env.data().context.block_time)
+}
+
+fn transfer(
+    mut env: FunctionEnvMut<HostEnv>,
+    to_ptr: i32,
+    amount_ptr: i32,
+) -> Result<i32, RuntimeError> {
+    charge(&mut env)?;
+
+    let (host, store) = env.data_and_store_mut();
+    let (to, amount) = match (
+        host.read_address(&store, to_ptr),
+        host.read_amount(&store, amount_ptr),
+    ) {
+        (Some(to), Some(amount)) => (to, amount),
+        _ => return Ok(HOST_ERR_MEMORY),
+    };
+
+    let contract = host.context.contract_address;
+    match host.with_state(|state| state.transfer(&contract, &to, amount)) {
+        Some(Ok(_)) => Ok(HOST_OK),
+        Some(Err(err)) => {
+            telemetry::debug!("contract transfer rejected: {err}");
+            Ok(HOST_ERR_REJECTED)
+        }
+        None => Ok(HOST_ERR_REJECTED),
+    }
+}
+
+/// Returns the host functions to register under [VRRB_ABI_NAMESPACE]
+pub(crate) fn host_functions(
+    store: &mut impl AsStoreMut,
+    env: &FunctionEnv<HostEnv>,
+) -> Vec<(&'static str, Extern)> {
+    vec![
+        (
+            "storage_get",
+            Function::new_typed_with_env(store, env, storage_get).into(),
+        ),
+        (
+            "storage_set",
+            Function::new_typed_with_env(store, env, storage_set).into(),
+        ),
+        (
+            "balance",
+            Function::new_typed_with_env(store, env, balance).into(),
+        ),
+        (
+            "caller",
+            Function::new_typed_with_env(store, env, caller).into(),
+        ),
+        (
+            "contract_address",
+            Function::new_typed_with_env(store, env, contract_address).into(),
+        ),
+        (
+            "block_height",
+            Function::new_typed_with_env(store, env, block_height).into(),
+        ),
+        (
+            "block_time",
+            Function::new_typed_with_env(store, env, block_time).into(),
+        ),
+        (
+            "transfer",
+            Function::new_typed_with_env(store, env, transfer).into(),
+        ),
+    ]
+}
+
+/// Reads the ABI version an instantiated module declares. The export is a
+/// global holding the address of the little-endian `u32` version, which is
+/// how statics are exported by compilers targeting WASM.
+pub(crate) fn declared_abi_version(
+    store: &mut impl AsStoreMut,
+    instance: &Instance,
+    memory: &Memory,
+) -> Result<u32, WasmRuntimeError> {
+    let address = match instance
+        .exports
+        .get_global(VRRB_ABI_VERSION_EXPORT)?
+        .get(store)
+    {
+        Value::I32(address) => u64::try_from(address).ok(),
+        _ => None,
+    }
+    .ok_or(WasmRuntimeError::InvalidAbiVersion)?;
+
+    let mut version = [0u8; 4];
+    memory
+        .view(&*store)
+        .read(address, &mut version)
+        .map_err(|_| WasmRuntimeError::InvalidAbiVersion)?;
+
+    Ok(u32::from_le_bytes(version))
+}
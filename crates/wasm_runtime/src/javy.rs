@@ -0,0 +1,112 @@
+//! Javy JavaScript contracts
+//!
+//! Javy compiles JavaScript into small modules holding the script's
+//! bytecode. They run the bytecode through a QuickJS engine imported from a
+//! separate provider module under [JAVY_PROVIDER_NAMESPACE], including the
+//! linear memory they share with it. The runtime instantiates the provider
+//! alongside the contract and links the contract's imports to its exports.
+//!
+//! The provider is compiled with the same metering as the contract, but
+//! every instance keeps its own count of points. Calls from the contract
+//! into the provider hand the contract's remaining points over to the
+//! provider and take what is left back once the call returns, so the
+//! provider's work is paid for out of the contract's limit.
+
+use wasmer::{
+    AsStoreMut, Extern, Function, FunctionEnv, FunctionEnvMut, Instance, RuntimeError, Value,
+};
+use wasmer_middlewares::metering::{get_remaining_points, set_remaining_points, MeteringPoints};
+
+/// The import namespace of the Javy QuickJS provider
+pub const JAVY_PROVIDER_NAMESPACE: &str = "javy_quickjs_provider_v1";
+
+/// Data shared by the functions linking a contract to its provider
+pub(crate) struct JavyEnv {
+    provider: Instance,
+    contract: Option<Instance>,
+    out_of_gas: bool,
+}
+
+impl JavyEnv {
+    pub(crate) fn new(provider: Instance) -> Self {
+        Self {
+            provider,
+            contract: None,
+            out_of_gas: false,
+        }
+    }
+
+    pub(crate) fn provider(&self) -> &Instance {
+        &self.provider
+    }
+
+    /// Binds the environment to the contract calling into the provider
+    pub(crate) fn attach(&mut self, contract: Instance) {
+        self.contract = Some(contract);
+    }
+
+    /// Whether the provider ran out of points during a call
+    pub(crate) fn is_out_of_gas(&self) -> bool {
+        self.out_of_gas
+    }
+}
+
+/// Moves the points left in `from` over to `to`, returning false when `from`
+/// has exhausted its points.
+pub(crate) fn transfer_points(store: &mut impl AsStoreMut, from: &Instance, to: &Instance) -> bool {
+    match get_remaining_points(store, from) {
+        MeteringPoints::Remaining(remaining) => {
+            set_remaining_points(store, to, remaining);
+            true
+        }
+        MeteringPoints::Exhausted => {
+            set_remaining_points(store, to, 0);
+            false
+        }
+    }
+}
+
+/// Returns the exports of the provider to link the contract against. Its
+/// functions are wrapped so the points they consume are charged to the
+/// contract.
+pub(crate) fn provider_exports(
+    store: &mut impl AsStoreMut,
+    env: &FunctionEnv<JavyEnv>,
+) -> Vec<(String, Extern)> {
+    let provider = env.as_ref(store).provider.clone();
+
+    provider
+        .exports
+        .iter()
+        .map(|(name, export)| {
+            let function = match export {
+                Extern::Function(function) => function.clone(),
+                other => return (name.clone(), other.clone()),
+            };
+
+            let ty = function.ty(store);
+            let linked = Function::new_with_env(
+                store,
+                env,
+                ty,
+                move |mut env: FunctionEnvMut<JavyEnv>, args: &[Value]| {
+                    let (javy, mut store) = env.data_and_store_mut();
+                    let provider = javy.provider.clone();
+                    let contract = javy.contract.clone().ok_or_else(|| {
+                        RuntimeError::new("Javy provider called before the contract was linked")
+                    })?;
+
+                    transfer_points(&mut store, &contract, &provider);
+                    let result = function.call(&mut store, args);
+                    if !transfer_points(&mut store, &provider, &contract) {
+                        javy.out_of_gas = true;
+                    }
+
+                    result.map(|results| results.to_vec())
+                },
+            );
+
+            (name.clone(), linked.into())
+        })
+        .collect()
+}
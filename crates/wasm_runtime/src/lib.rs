@@ -1,8 +1,15 @@
+pub mod deterministic;
 pub mod errors;
+pub mod host_abi;
+pub mod javy;
 pub mod limiting_tunables;
 pub mod metering;
+pub mod module_cache;
+pub mod profiler;
 mod rust2wasm;
 pub mod wasm_runtime;
 
+#[cfg(test)]
+pub mod deterministic_tests;
 #[cfg(test)]
 pub mod runtime_tests;
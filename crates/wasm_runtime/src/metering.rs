@@ -1,27 +1,358 @@
-use wasmer::wasmparser::Operator;
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use serde_derive::{Deserialize, Serialize};
+use wasmer::{
+    wasmparser::{BlockType, Operator},
+    ExportIndex, FunctionMiddleware, GlobalInit, GlobalType, LocalFunctionIndex, MiddlewareError,
+    MiddlewareReaderState, ModuleMiddleware, Mutability, Type,
+};
 use wasmer_middlewares::Metering;
+use wasmer_types::{GlobalIndex, ModuleInfo};
+
+use crate::profiler::SharedCostFunction;
+
+/// The latest version of the gas schedule
+pub const GAS_SCHEDULE_VERSION: u32 = 2;
+
+/// Names of the globals the `wasmer_middlewares::Metering` middleware keeps
+/// its points in
+const REMAINING_POINTS_EXPORT: &str = "wasmer_metering_remaining_points";
+const POINTS_EXHAUSTED_EXPORT: &str = "wasmer_metering_points_exhausted";
+
+/// Classes of WASM operators that are charged the same amount of points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OperatorClass {
+    /// Structured control flow, branches, `drop` and `select`
+    Control,
+    /// Direct calls to functions of the module or to host functions
+    Call,
+    /// Calls through a table
+    CallIndirect,
+    Local,
+    Global,
+    Const,
+    Load,
+    Store,
+    MemorySize,
+    MemoryGrow,
+    /// Bulk memory operators such as `memory.copy` and `memory.fill`
+    BulkMemory,
+    IntegerMul,
+    /// Integer division and remainder
+    IntegerDiv,
+    /// Floating point arithmetic
+    Float,
+    /// Every other numeric operator: integer arithmetic, comparisons,
+    /// conversions and the like
+    Numeric,
+}
+
+impl OperatorClass {
+    pub fn of(operator: &Operator) -> Self {
+        match operator {
+            Operator::Unreachable { .. }
+            | Operator::Nop { .. }
+            | Operator::Block { .. }
+            | Operator::Loop { .. }
+            | Operator::If { .. }
+            | Operator::Else { .. }
+            | Operator::End { .. }
+            | Operator::Br { .. }
+            | Operator::BrIf { .. }
+            | Operator::BrTable { .. }
+            | Operator::Return { .. }
+            | Operator::Drop { .. }
+            | Operator::Select { .. }
+            | Operator::TypedSelect { .. } => Self::Control,
+
+            Operator::Call { .. } | Operator::ReturnCall { .. } => Self::Call,
+            Operator::CallIndirect { .. } | Operator::ReturnCallIndirect { .. } => {
+                Self::CallIndirect
+            }
+
+            Operator::LocalGet { .. } | Operator::LocalSet { .. } | Operator::LocalTee { .. } => {
+                Self::Local
+            }
+            Operator::GlobalGet { .. } | Operator::GlobalSet { .. } => Self::Global,
+
+            Operator::I32Const { .. }
+            | Operator::I64Const { .. }
+            | Operator::F32Const { .. }
+            | Operator::F64Const { .. } => Self::Const,
+
+            Operator::I32Load { .. }
+            | Operator::I64Load { .. }
+            | Operator::F32Load { .. }
+            | Operator::F64Load { .. }
+            | Operator::I32Load8S { .. }
+            | Operator::I32Load8U { .. }
+            | Operator::I32Load16S { .. }
+            | Operator::I32Load16U { .. }
+            | Operator::I64Load8S { .. }
+            | Operator::I64Load8U { .. }
+            | Operator::I64Load16S { .. }
+            | Operator::I64Load16U { .. }
+            | Operator::I64Load32S { .. }
+            | Operator::I64Load32U { .. } => Self::Load,
+
+            Operator::I32Store { .. }
+            | Operator::I64Store { .. }
+            | Operator::F32Store { .. }
+            | Operator::F64Store { .. }
+            | Operator::I32Store8 { .. }
+            | Operator::I32Store16 { .. }
+            | Operator::I64Store8 { .. }
+            | Operator::I64Store16 { .. }
+            | Operator::I64Store32 { .. } => Self::Store,
+
+            Operator::MemorySize { .. } => Self::MemorySize,
+            Operator::MemoryGrow { .. } => Self::MemoryGrow,
+            Operator::MemoryCopy { .. }
+            | Operator::MemoryFill { .. }
+            | Operator::MemoryInit { .. }
+            | Operator::DataDrop { .. } => Self::BulkMemory,
+
+            Operator::I32Mul { .. } | Operator::I64Mul { .. } => Self::IntegerMul,
+            Operator::I32DivS { .. }
+            | Operator::I32DivU { .. }
+            | Operator::I32RemS { .. }
+            | Operator::I32RemU { .. }
+            | Operator::I64DivS { .. }
+            | Operator::I64DivU { .. }
+            | Operator::I64RemS { .. }
+            | Operator::I64RemU { .. } => Self::IntegerDiv,
+
+            Operator::F32Add { .. }
+            | Operator::F32Sub { .. }
+            | Operator::F32Mul { .. }
+            | Operator::F32Div { .. }
+            | Operator::F32Sqrt { .. }
+            | Operator::F32Min { .. }
+            | Operator::F32Max { .. }
+            | Operator::F64Add { .. }
+            | Operator::F64Sub { .. }
+            | Operator::F64Mul { .. }
+            | Operator::F64Div { .. }
+            | Operator::F64Sqrt { .. }
+            | Operator::F64Min { .. }
+            | Operator::F64Max { .. } => Self::Float,
+
+            _ => Self::Numeric,
+        }
+    }
+}
+
+/// Points charged per operator class, along with the cost of growing memory
+/// and of calling into the VRRB host ABI. Schedules are versioned so the cost
+/// of a contract call only changes when the network moves to a new version:
+/// contract transactions record the version they are executed under, see
+/// [GasSchedule::from_version].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasSchedule {
+    pub version: u32,
+    pub control: u64,
+    pub call: u64,
+    pub call_indirect: u64,
+    pub local: u64,
+    pub global: u64,
+    pub constant: u64,
+    pub load: u64,
+    pub store: u64,
+    pub memory_size: u64,
+    /// Charged for every `memory.grow` instruction executed
+    pub memory_grow: u64,
+    pub bulk_memory: u64,
+    pub integer_mul: u64,
+    pub integer_div: u64,
+    pub float: u64,
+    pub numeric: u64,
+    /// Charged on top of the `call` cost every time the module calls one of
+    /// the VRRB host functions
+    pub host_call: u64,
+    /// Charged for every page a `memory.grow` instruction asks for, on top of
+    /// the `memory_grow` cost
+    #[serde(default)]
+    pub memory_grow_page: u64,
+    /// Charged for every byte copied, filled or initialized by a bulk memory
+    /// instruction, on top of the `bulk_memory` cost
+    #[serde(default)]
+    pub bulk_memory_byte: u64,
+    /// Charged for every byte of key and value written through the
+    /// `storage_set` host function, on top of the `host_call` cost
+    #[serde(default)]
+    pub storage_byte: u64,
+}
+
+impl GasSchedule {
+    pub fn v1() -> Self {
+        Self {
+            version: 1,
+            control: 1,
+            call: 4,
+            call_indirect: 6,
+            local: 1,
+            global: 2,
+            constant: 1,
+            load: 2,
+            store: 3,
+            memory_size: 2,
+            memory_grow: 1000,
+            bulk_memory: 10,
+            integer_mul: 2,
+            integer_div: 4,
+            float: 4,
+            numeric: 1,
+            host_call: 50,
+            memory_grow_page: 0,
+            bulk_memory_byte: 0,
+            storage_byte: 0,
+        }
+    }
+
+    /// Like [GasSchedule::v1], but memory growth, bulk memory instructions
+    /// and storage writes are also charged for the amount of memory they
+    /// touch.
+    pub fn v2() -> Self {
+        Self {
+            version: 2,
+            memory_grow_page: 8192,
+            bulk_memory_byte: 1,
+            storage_byte: 10,
+            ..Self::v1()
+        }
+    }
+
+    /// Returns the schedule published under `version`, if there is one
+    pub fn from_version(version: u32) -> Option<Self> {
+        match version {
+            1 => Some(Self::v1()),
+            2 => Some(Self::v2()),
+            _ => None,
+        }
+    }
+
+    /// Returns the points charged per unit of memory touched
+    pub fn unit_costs(&self) -> UnitCosts {
+        UnitCosts {
+            memory_grow_page: self.memory_grow_page,
+            bulk_memory_byte: self.bulk_memory_byte,
+            storage_byte: self.storage_byte,
+        }
+    }
+
+    /// Returns the points charged for a single class of operators
+    pub fn class_cost(&self, class: OperatorClass) -> u64 {
+        match class {
+            OperatorClass::Control => self.control,
+            OperatorClass::Call => self.call,
+            OperatorClass::CallIndirect => self.call_indirect,
+            OperatorClass::Local => self.local,
+            OperatorClass::Global => self.global,
+            OperatorClass::Const => self.constant,
+            OperatorClass::Load => self.load,
+            OperatorClass::Store => self.store,
+            OperatorClass::MemorySize => self.memory_size,
+            OperatorClass::MemoryGrow => self.memory_grow,
+            OperatorClass::BulkMemory => self.bulk_memory,
+            OperatorClass::IntegerMul => self.integer_mul,
+            OperatorClass::IntegerDiv => self.integer_div,
+            OperatorClass::Float => self.float,
+            OperatorClass::Numeric => self.numeric,
+        }
+    }
+
+    /// Returns the points charged for executing `operator`
+    pub fn cost(&self, operator: &Operator) -> u64 {
+        self.class_cost(OperatorClass::of(operator))
+    }
+
+    /// Creates a [MeteringConfig] charging according to this schedule
+    pub fn metering_config(
+        self,
+        initial_limit: u64,
+    ) -> MeteringConfig<impl Fn(&Operator) -> u64 + Send + Sync> {
+        MeteringConfig::new(initial_limit, move |operator: &Operator| {
+            self.cost(operator)
+        })
+        .with_host_call_cost(self.host_call)
+        .with_unit_costs(self.unit_costs())
+        .with_cost_id(format!("gas-schedule-{:?}", self))
+    }
+}
+
+impl Default for GasSchedule {
+    fn default() -> Self {
+        Self::v2()
+    }
+}
+
+/// Points charged per unit of memory an instruction or host call touches,
+/// on top of its flat cost. Zero costs are not charged at all.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnitCosts {
+    pub memory_grow_page: u64,
+    pub bulk_memory_byte: u64,
+    pub storage_byte: u64,
+}
+
+impl UnitCosts {
+    /// Returns the points charged per unit of the count operand of
+    /// `operator`, for the instructions charged per unit
+    fn operand_cost(&self, operator: &Operator) -> Option<u64> {
+        let cost = match operator {
+            Operator::MemoryGrow { .. } => self.memory_grow_page,
+            Operator::MemoryCopy { .. }
+            | Operator::MemoryFill { .. }
+            | Operator::MemoryInit { .. } => self.bulk_memory_byte,
+            _ => return None,
+        };
+
+        Some(cost).filter(|cost| *cost > 0)
+    }
+
+    /// Whether any instruction is charged per unit
+    fn charges_instructions(&self) -> bool {
+        self.memory_grow_page > 0 || self.bulk_memory_byte > 0
+    }
+}
 
 // This function will be called for each `Operator` encountered during
 // the Wasm module execution. It should return the cost of the operator
 // that it received as it first argument.
-pub fn cost_function(_operator: &Operator) -> u64 {
-    // Cost fn from wasmer examples:
-    //
-    // match operator {
-    //     Operator::LocalGet { .. } | Operator::I32Const { .. } => 1,
-    //     Operator::I32Add { .. } => 2,
-    //     _ => 0,
-    // }
+pub fn cost_function(operator: &Operator) -> u64 {
+    GasSchedule::v1().cost(operator)
+}
 
-    1 /* for now we just return 1 regardless of the instruction */
+/// Points consumed by an execution and the points it had left when it ended.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasUsage {
+    pub consumed: u64,
+    pub remaining: u64,
 }
 
 /// A convenience wrapper for creating a new `wasmer_middlewares::Metering`.
+///
+/// Modules are compiled without any points. The initial limit is only handed
+/// to an instance once it is created, so a compiled module can be reused,
+/// and cached, whatever limit it runs with.
 pub struct MeteringConfig<F: Fn(&Operator) -> u64 + Send + Sync> {
     /// Initial limit of points.
     initial_limit: u64,
     /// Function that maps each operator to a cost in "points".
     cost_function: F,
+    /// Points charged for each call into the VRRB host ABI.
+    host_call_cost: u64,
+    /// Points charged per unit of memory touched.
+    unit_costs: UnitCosts,
+    /// Identifies the cost function, so modules compiled with it can be
+    /// cached.
+    cost_id: Option<String>,
 }
 impl<F> MeteringConfig<F>
 where
@@ -31,9 +362,289 @@ where
         Self {
             initial_limit,
             cost_function,
+            host_call_cost: 0,
+            unit_costs: UnitCosts::default(),
+            cost_id: None,
         }
     }
-    pub(crate) fn into_metering(self) -> Metering<F> {
-        Metering::new(self.initial_limit, self.cost_function)
+    /// Sets the points charged for each call into the VRRB host ABI.
+    pub fn with_host_call_cost(mut self, host_call_cost: u64) -> Self {
+        self.host_call_cost = host_call_cost;
+        self
+    }
+    /// Sets the points charged per unit of memory touched.
+    pub fn with_unit_costs(mut self, unit_costs: UnitCosts) -> Self {
+        self.unit_costs = unit_costs;
+        self
+    }
+    /// Sets an identifier unique to the cost function. Modules are only
+    /// cached when their metering configuration has one.
+    pub fn with_cost_id(mut self, cost_id: impl Into<String>) -> Self {
+        self.cost_id = Some(cost_id.into());
+        self
+    }
+    /// Returns an identifier of everything in this configuration that ends
+    /// up in compiled modules, if the cost function has an identifier.
+    pub fn cache_id(&self) -> Option<String> {
+        self.cost_id
+            .as_ref()
+            .map(|cost_id| format!("{}:{:?}", cost_id, self.unit_costs))
+    }
+    /// Returns the number of points execution starts out with.
+    pub fn initial_limit(&self) -> u64 {
+        self.initial_limit
+    }
+    /// Returns the points charged for each call into the VRRB host ABI.
+    pub fn host_call_cost(&self) -> u64 {
+        self.host_call_cost
+    }
+    /// Returns the points charged per unit of memory touched.
+    pub fn unit_costs(&self) -> UnitCosts {
+        self.unit_costs
+    }
+    /// Returns the middleware charging instructions per unit of memory, if
+    /// any instruction is charged per unit. It must run after the metering
+    /// middleware.
+    pub(crate) fn unit_metering(&self) -> Option<PerModule<UnitMetering>> {
+        let unit_costs = self.unit_costs;
+        unit_costs
+            .charges_instructions()
+            .then(|| PerModule::new(move || UnitMetering::new(unit_costs)))
+    }
+    pub(crate) fn into_metering(self) -> PerModule<impl ModuleMiddleware>
+    where
+        F: 'static,
+    {
+        self.into_shared_metering().0
+    }
+    /// Like [MeteringConfig::into_metering], but also hands out the cost
+    /// function so a [crate::profiler::Profiler] charges the same points.
+    pub(crate) fn into_shared_metering(
+        self,
+    ) -> (PerModule<impl ModuleMiddleware>, SharedCostFunction)
+    where
+        F: 'static,
+    {
+        let cost_function = Arc::new(self.cost_function);
+        let shared: SharedCostFunction = cost_function.clone();
+        let metering = PerModule::new(move || {
+            let cost_function = cost_function.clone();
+            Metering::new(0, move |operator: &Operator| cost_function(operator))
+        });
+
+        (metering, shared)
+    }
+}
+
+/// Instruments every module compiled with it using a middleware of its own.
+///
+/// Metering middlewares can only instrument a single module, as they keep
+/// the globals they added to it, but a module and the Javy provider it is
+/// linked against are compiled with the same engine. A module is compiled
+/// in full before the next one is, so the middleware created for the module
+/// whose info was transformed last is the one instrumenting its functions.
+pub(crate) struct PerModule<M> {
+    new_middleware: Box<dyn Fn() -> M + Send + Sync>,
+    current: Mutex<Option<Arc<M>>>,
+}
+
+impl<M> PerModule<M> {
+    pub(crate) fn new(new_middleware: impl Fn() -> M + Send + Sync + 'static) -> Self {
+        Self {
+            new_middleware: Box::new(new_middleware),
+            current: Mutex::new(None),
+        }
+    }
+}
+
+impl<M> fmt::Debug for PerModule<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PerModule").finish_non_exhaustive()
+    }
+}
+
+impl<M: ModuleMiddleware> ModuleMiddleware for PerModule<M> {
+    fn generate_function_middleware<'a>(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware<'a> + 'a> {
+        self.current
+            .lock()
+            .expect("per module middleware lock poisoned")
+            .as_ref()
+            .expect("middleware used before the module info was transformed")
+            .generate_function_middleware(local_function_index)
+    }
+
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) -> Result<(), MiddlewareError> {
+        let middleware = Arc::new((self.new_middleware)());
+        middleware.transform_module_info(module_info)?;
+
+        *self
+            .current
+            .lock()
+            .map_err(|_| MiddlewareError::new("per module", "middleware lock poisoned"))? =
+            Some(middleware);
+
+        Ok(())
+    }
+}
+
+/// A compiler middleware charging `memory.grow` per page and bulk memory
+/// instructions per byte. Before each of these instructions it deducts the
+/// unit cost times the instruction's count operand from the points kept by
+/// the metering middleware, and traps as out of gas if there are not enough
+/// points left. Like metering, it can only instrument a single module.
+#[derive(Debug)]
+pub(crate) struct UnitMetering {
+    costs: UnitCosts,
+    globals: Mutex<Option<UnitMeteringGlobals>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct UnitMeteringGlobals {
+    remaining_points: GlobalIndex,
+    points_exhausted: GlobalIndex,
+    /// Holds the count operand while it is charged for
+    count: GlobalIndex,
+}
+
+impl UnitMetering {
+    pub(crate) fn new(costs: UnitCosts) -> Self {
+        Self {
+            costs,
+            globals: Mutex::new(None),
+        }
+    }
+}
+
+impl ModuleMiddleware for UnitMetering {
+    fn generate_function_middleware<'a>(
+        &self,
+        _local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware<'a> + 'a> {
+        let globals = self
+            .globals
+            .lock()
+            .expect("unit metering globals lock poisoned")
+            .as_ref()
+            .copied()
+            .expect("unit metering used before the module info was transformed");
+
+        Box::new(FunctionUnitMetering {
+            costs: self.costs,
+            globals,
+        })
+    }
+
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) -> Result<(), MiddlewareError> {
+        let mut globals = self
+            .globals
+            .lock()
+            .map_err(|_| MiddlewareError::new("unit metering", "globals lock poisoned"))?;
+        if globals.is_some() {
+            return Err(MiddlewareError::new(
+                "unit metering",
+                "unit metering can only instrument a single module",
+            ));
+        }
+
+        let metering_global = |name: &str| match module_info.exports.get(name) {
+            Some(ExportIndex::Global(global)) => Ok(*global),
+            _ => Err(MiddlewareError::new(
+                "unit metering",
+                "unit metering must run after the metering middleware",
+            )),
+        };
+        let remaining_points = metering_global(REMAINING_POINTS_EXPORT)?;
+        let points_exhausted = metering_global(POINTS_EXHAUSTED_EXPORT)?;
+
+        let count = module_info
+            .globals
+            .push(GlobalType::new(Type::I32, Mutability::Var));
+        module_info
+            .global_initializers
+            .push(GlobalInit::I32Const(0));
+
+        *globals = Some(UnitMeteringGlobals {
+            remaining_points,
+            points_exhausted,
+            count,
+        });
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct FunctionUnitMetering {
+    costs: UnitCosts,
+    globals: UnitMeteringGlobals,
+}
+
+impl FunctionUnitMetering {
+    /// Pushes `count * cost` for the count held in the count global
+    fn push_charge<'a>(&self, state: &mut MiddlewareReaderState<'a>, cost: u64) {
+        state.push_operator(Operator::GlobalGet {
+            global_index: self.globals.count.as_u32(),
+        });
+        state.push_operator(Operator::I64ExtendI32U);
+        state.push_operator(Operator::I64Const { value: cost as i64 });
+        state.push_operator(Operator::I64Mul);
+    }
+}
+
+impl<'a> FunctionMiddleware<'a> for FunctionUnitMetering {
+    fn feed(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        // Counts are 32 bit, so capping the cost at 32 bits keeps the charge
+        // from overflowing
+        let cost = match self.costs.operand_cost(&operator) {
+            Some(cost) => cost.min(u32::MAX as u64),
+            None => {
+                state.push_operator(operator);
+                return Ok(());
+            }
+        };
+        let remaining_points = self.globals.remaining_points.as_u32();
+
+        // The count is the last operand of every instruction charged per unit
+        state.push_operator(Operator::GlobalSet {
+            global_index: self.globals.count.as_u32(),
+        });
+
+        state.push_operator(Operator::GlobalGet {
+            global_index: remaining_points,
+        });
+        self.push_charge(state, cost);
+        state.push_operator(Operator::I64LtU);
+        state.push_operator(Operator::If {
+            blockty: BlockType::Empty,
+        });
+        state.push_operator(Operator::I32Const { value: 1 });
+        state.push_operator(Operator::GlobalSet {
+            global_index: self.globals.points_exhausted.as_u32(),
+        });
+        state.push_operator(Operator::Unreachable);
+        state.push_operator(Operator::End);
+
+        state.push_operator(Operator::GlobalGet {
+            global_index: remaining_points,
+        });
+        self.push_charge(state, cost);
+        state.push_operator(Operator::I64Sub);
+        state.push_operator(Operator::GlobalSet {
+            global_index: remaining_points,
+        });
+
+        state.push_operator(Operator::GlobalGet {
+            global_index: self.globals.count.as_u32(),
+        });
+        state.push_operator(operator);
+
+        Ok(())
     }
 }
@@ -0,0 +1,296 @@
+//! Compiled module cache
+//!
+//! Compiling a module with Cranelift dominates the cost of running small
+//! contracts, so compiled modules can be kept on disk and deserialized on the
+//! next run. Entries are addressed by the SHA-256 of everything that affects
+//! the compiled artifact: the WASM bytes, the compiler and target, and the
+//! gas schedule. The meter limit is not part of it, it is only applied once
+//! the module is instantiated.
+//!
+//! Deserializing a module runs the machine code it contains, so entries must
+//! only ever come from this node's own compiler. Each entry starts with an
+//! HMAC-SHA256 of its key and payload, under a secret generated when the
+//! cache is first opened and kept next to the entries. Entries that are
+//! corrupted, copied from another cache or written by anyone who does not
+//! know the secret are dropped instead of being loaded. This relies on the
+//! cache directory being private to the node: it is created accessible to
+//! its owner only, and anyone able to read the secret can forge entries.
+//!
+//! The cache is bounded in size. Once it grows past its limit, the entries
+//! written least recently are evicted first.
+
+use std::{
+    fmt, fs,
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use telemetry::debug;
+use wasmer::{Module, Store, Target};
+
+use crate::limiting_tunables::DEFAULT_PAGE_LIMIT;
+
+/// Extension of cache entries, so unrelated files are never evicted
+const ENTRY_EXTENSION: &str = "wasmu";
+/// Name of the file holding the secret entries are authenticated with
+const SECRET_FILE: &str = "cache.secret";
+const SECRET_LEN: usize = 32;
+const MAC_LEN: usize = 32;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 512 MiB
+pub const DEFAULT_MODULE_CACHE_SIZE: u64 = 512 * 1024 * 1024;
+
+/// Identifies a compiled module in a [ModuleCache]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ModuleCacheKey(String);
+
+impl ModuleCacheKey {
+    /// Derives the key of `wasm_bytes` compiled by compiler `C` for `target`
+    /// with the metering configuration identified by `metering_id`, see
+    /// [crate::metering::MeteringConfig::cache_id].
+    pub fn new<C>(wasm_bytes: &[u8], target: &Target, metering_id: &str) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(Sha256::digest(wasm_bytes));
+        hasher.update(std::any::type_name::<C>());
+        hasher.update(target.triple().to_string());
+        hasher.update(format!("{:?}", target.cpu_features()));
+        hasher.update(DEFAULT_PAGE_LIMIT.0.to_le_bytes());
+        hasher.update(metering_id);
+        hasher.update(env!("CARGO_PKG_VERSION"));
+
+        Self(hex::encode(hasher.finalize()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// The secret cache entries are authenticated with
+#[derive(Clone)]
+struct CacheSecret(Arc<[u8; SECRET_LEN]>);
+
+impl fmt::Debug for CacheSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CacheSecret(..)")
+    }
+}
+
+impl CacheSecret {
+    /// Reads the secret kept in `dir`, generating it the first time
+    fn open(dir: &Path) -> std::io::Result<Self> {
+        let path = dir.join(SECRET_FILE);
+        match fs::read(&path) {
+            Ok(bytes) => {
+                let secret = bytes.try_into().map_err(|_| {
+                    std::io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!("{} is not a module cache secret", path.display()),
+                    )
+                })?;
+                return Ok(Self(Arc::new(secret)));
+            }
+            Err(err) if err.kind() != ErrorKind::NotFound => return Err(err),
+            Err(_) => {}
+        }
+
+        let mut secret = [0u8; SECRET_LEN];
+        rand::thread_rng().fill_bytes(&mut secret);
+        let mut file = private_options().write(true).create_new(true).open(&path)?;
+        file.write_all(&secret)?;
+        file.sync_all()?;
+
+        Ok(Self(Arc::new(secret)))
+    }
+
+    /// Authenticates the payload stored under `key`
+    fn mac(&self, key: &ModuleCacheKey, payload: &[u8]) -> HmacSha256 {
+        let mut mac =
+            HmacSha256::new_from_slice(&self.0[..]).expect("HMAC accepts keys of any length");
+        mac.update(key.as_str().as_bytes());
+        mac.update(payload);
+        mac
+    }
+}
+
+/// Options creating files readable and writable by their owner only
+fn private_options() -> fs::OpenOptions {
+    let mut options = fs::OpenOptions::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+}
+
+/// An on-disk, size-bounded cache of compiled modules
+#[derive(Debug, Clone)]
+pub struct ModuleCache {
+    dir: PathBuf,
+    max_size: u64,
+    secret: CacheSecret,
+}
+
+impl ModuleCache {
+    /// Opens the cache kept in `dir`, creating the directory and the secret
+    /// its entries are authenticated with if needed. The directory must be
+    /// private to this node. The cache evicts entries once they take up more
+    /// than `max_size` bytes.
+    pub fn new(dir: impl AsRef<Path>, max_size: u64) -> std::io::Result<Self> {
+        let dir = dir.as_ref();
+        let mut builder = fs::DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::DirBuilderExt;
+            builder.mode(0o700);
+        }
+        builder.create(dir)?;
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            max_size,
+            secret: CacheSecret::open(dir)?,
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn entry_path(&self, key: &ModuleCacheKey) -> PathBuf {
+        self.dir.join(key.as_str()).with_extension(ENTRY_EXTENSION)
+    }
+
+    /// Returns the module cached under `key`, if there is a valid one.
+    /// Entries that fail authentication or no longer deserialize are
+    /// removed.
+    pub fn get(&self, store: &Store, key: &ModuleCacheKey) -> Option<Module> {
+        let path = self.entry_path(key);
+        let entry = match fs::read(&path) {
+            Ok(entry) => entry,
+            Err(err) => {
+                if err.kind() != ErrorKind::NotFound {
+                    debug!("Failed to read cached module {}: {}", path.display(), err);
+                }
+                return None;
+            }
+        };
+
+        let payload = match self.verify_entry(key, &entry) {
+            Some(payload) => payload,
+            None => {
+                debug!(
+                    "Cached module {} failed authentication, removing it",
+                    path.display()
+                );
+                let _ = fs::remove_file(&path);
+                return None;
+            }
+        };
+
+        // SAFETY: the payload was authenticated with this cache's secret, so
+        // it was written by `insert` from `Module::serialize`, under `key`.
+        match unsafe { Module::deserialize(store, payload) } {
+            Ok(module) => Some(module),
+            Err(err) => {
+                debug!(
+                    "Failed to deserialize cached module {}: {}",
+                    path.display(),
+                    err
+                );
+                let _ = fs::remove_file(&path);
+                None
+            }
+        }
+    }
+
+    /// Serializes `module` into the cache under `key`, then evicts entries
+    /// until the cache fits within its size limit again.
+    pub fn insert(&self, key: &ModuleCacheKey, module: &Module) -> std::io::Result<()> {
+        let payload = module
+            .serialize()
+            .map_err(|err| std::io::Error::new(ErrorKind::Other, err))?;
+
+        let mac = self.secret.mac(key, &payload).finalize().into_bytes();
+        let mut entry = Vec::with_capacity(MAC_LEN + payload.len());
+        entry.extend_from_slice(&mac);
+        entry.extend_from_slice(&payload);
+
+        // Write to a temporary file first so readers never see partial entries
+        let path = self.entry_path(key);
+        let tmp_path = path.with_extension("tmp");
+        private_options()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&tmp_path)?
+            .write_all(&entry)?;
+        fs::rename(&tmp_path, &path)?;
+
+        self.evict(&path)
+    }
+
+    /// Returns the payload of the entry stored under `key` if its MAC is
+    /// valid
+    fn verify_entry<'a>(&self, key: &ModuleCacheKey, entry: &'a [u8]) -> Option<&'a [u8]> {
+        if entry.len() < MAC_LEN {
+            return None;
+        }
+
+        let (mac, payload) = entry.split_at(MAC_LEN);
+        self.secret.mac(key, payload).verify_slice(mac).ok()?;
+
+        Some(payload)
+    }
+
+    /// Returns the total size of the cached entries, in bytes
+    pub fn size(&self) -> std::io::Result<u64> {
+        Ok(self.entries()?.iter().map(|(_, size, _)| size).sum())
+    }
+
+    fn entries(&self) -> std::io::Result<Vec<(PathBuf, u64, std::time::SystemTime)>> {
+        let mut entries = vec![];
+        for dir_entry in fs::read_dir(&self.dir)? {
+            let path = dir_entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(ENTRY_EXTENSION) {
+                continue;
+            }
+
+            let metadata = fs::metadata(&path)?;
+            entries.push((path, metadata.len(), metadata.modified()?));
+        }
+
+        Ok(entries)
+    }
+
+    /// Removes the oldest entries, other than `keep`, until the cache fits
+    /// within `max_size`
+    fn evict(&self, keep: &Path) -> std::io::Result<()> {
+        let mut entries = self.entries()?;
+        let mut size: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        entries.sort_by_key(|(_, _, modified)| *modified);
+
+        for (path, entry_size, _) in entries {
+            if size <= self.max_size {
+                break;
+            }
+            if path == keep {
+                continue;
+            }
+
+            debug!("Evicting cached module {}", path.display());
+            fs::remove_file(&path)?;
+            size = size.saturating_sub(entry_size);
+        }
+
+        Ok(())
+    }
+}
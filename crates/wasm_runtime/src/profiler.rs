@@ -0,0 +1,540 @@
+//! Execution profiler
+//!
+//! A compiler middleware that adds counters to every function of a module:
+//! how many times it was called, how many instructions it executed, the
+//! metering points those instructions cost and how many times it grew
+//! memory, along with how many times it called each of the module's other
+//! functions directly. Along with the host calls made during execution, the
+//! counters are collected into an [ExecutionProfile] that can be exported as
+//! JSON or in the folded-stack format read by flamegraph tools.
+//!
+//! Counters are brought up to date before every instruction that may trap,
+//! so executions that fail are profiled up to the instruction they failed
+//! at.
+//!
+//! The profiler sees the module's own instructions, before metering
+//! instruments them, so the points it reports are what the module costs when
+//! it is not profiled. The counters themselves are metered as well, which
+//! makes profiled executions consume more points than regular ones.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use serde_derive::{Deserialize, Serialize};
+use wasmer::{
+    wasmparser::{BinaryReaderError, Operator, Parser, Payload, TypeRef},
+    wat2wasm, AsStoreRef, CompileError, ExportIndex, FunctionMiddleware, GlobalInit, GlobalType,
+    Instance, LocalFunctionIndex, MiddlewareError, MiddlewareReaderState, ModuleMiddleware,
+    Mutability, Type, Value,
+};
+use wasmer_types::{entity::EntityRef, GlobalIndex, ModuleInfo};
+
+use crate::{errors::WasmRuntimeError, metering::OperatorClass};
+
+/// Maps an operator to the metering points it costs
+pub type SharedCostFunction = Arc<dyn Fn(&Operator) -> u64 + Send + Sync>;
+
+/// Folded stacks stop growing past this many frames
+const MAX_FOLDED_DEPTH: usize = 64;
+/// Points split off a stack below this are left with the stack rather than
+/// followed further up the call graph
+const MIN_FOLDED_POINTS: f64 = 0.5;
+
+/// What a single function did during an execution
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FunctionProfile {
+    pub index: u32,
+    pub name: String,
+    pub calls: u64,
+    pub instructions: u64,
+    pub points: u64,
+    pub memory_grows: u64,
+    /// Number of direct calls made to this function by each of the module's
+    /// functions, by function index. Calls from the host or through a table
+    /// are not included.
+    #[serde(default)]
+    pub callers: BTreeMap<u32, u64>,
+}
+
+/// Where the metering points of an execution went
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionProfile {
+    /// Functions that were called, the most expensive first
+    pub functions: Vec<FunctionProfile>,
+    /// Number of calls to each imported host function, keyed by
+    /// `namespace::name`
+    pub host_calls: BTreeMap<String, u64>,
+    pub initial_memory_pages: u32,
+    pub final_memory_pages: u32,
+    /// Points consumed by the profiled execution, including the points spent
+    /// updating the profiler's counters
+    pub points_consumed: u64,
+    pub points_remaining: u64,
+}
+
+impl ExecutionProfile {
+    /// Sum of the points spent in the module's functions
+    pub fn total_points(&self) -> u64 {
+        self.functions.iter().map(|function| function.points).sum()
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Renders the points spent in each function in the folded-stack format,
+    /// one `root;caller;function points` line per call stack.
+    ///
+    /// Stacks are rebuilt from the calls between functions, the way gprof
+    /// attributes time: the points a function spent are split between its
+    /// callers in proportion to the number of calls each of them made.
+    /// Calls from the host or through a table start a stack of their own
+    /// under `root`, and recursive calls are folded into the outermost one.
+    pub fn to_folded(&self, root: &str) -> String {
+        let functions: BTreeMap<u32, &FunctionProfile> = self
+            .functions
+            .iter()
+            .map(|function| (function.index, function))
+            .collect();
+
+        let mut stacks = BTreeMap::new();
+        for function in self.functions.iter().filter(|function| function.points > 0) {
+            fold_stacks(
+                &functions,
+                &mut vec![function.index],
+                function.points as f64,
+                &mut stacks,
+            );
+        }
+
+        stacks
+            .into_iter()
+            .filter_map(|(stack, points): (Vec<u32>, f64)| {
+                let points = points.round() as u64;
+                if points == 0 {
+                    return None;
+                }
+
+                // Frames are separated by semicolons and the count by a space
+                let frames: Vec<String> = stack
+                    .iter()
+                    .rev()
+                    .map(|index| functions[index].name.replace([';', ' '], "_"))
+                    .collect();
+                Some(format!("{root};{} {points}\n", frames.join(";")))
+            })
+            .collect()
+    }
+}
+
+/// Splits the `points` spent at the bottom of `stack` between the callers of
+/// the function at its top. `stack` lists function indexes, innermost first.
+fn fold_stacks(
+    functions: &BTreeMap<u32, &FunctionProfile>,
+    stack: &mut Vec<u32>,
+    points: f64,
+    stacks: &mut BTreeMap<Vec<u32>, f64>,
+) {
+    let function = functions[stack.last().expect("stacks are never empty")];
+    let direct_calls: u64 = function.callers.values().sum();
+    let callers: Vec<(u32, u64)> = function
+        .callers
+        .iter()
+        .filter(|(caller, _)| functions.contains_key(caller) && !stack.contains(caller))
+        .map(|(caller, calls)| (*caller, *calls))
+        .collect();
+    let from_root = function.calls.saturating_sub(direct_calls);
+    let total = from_root + callers.iter().map(|(_, calls)| calls).sum::<u64>();
+
+    if total == 0 || stack.len() >= MAX_FOLDED_DEPTH {
+        *stacks.entry(stack.clone()).or_default() += points;
+        return;
+    }
+
+    let mut remaining = points * from_root as f64 / total as f64;
+    for (caller, calls) in callers {
+        let share = points * calls as f64 / total as f64;
+        if share < MIN_FOLDED_POINTS {
+            remaining += share;
+            continue;
+        }
+
+        stack.push(caller);
+        fold_stacks(functions, stack, share, stacks);
+        stack.pop();
+    }
+
+    if remaining > 0.0 {
+        *stacks.entry(stack.clone()).or_default() += remaining;
+    }
+}
+
+/// Finds the functions each function of the module calls directly, by
+/// function index
+fn call_graph(wasm_bytes: &[u8]) -> Result<BTreeMap<u32, BTreeSet<u32>>, WasmRuntimeError> {
+    let wasm = wat2wasm(wasm_bytes).map_err(CompileError::Wasm)?;
+    let invalid = |err: BinaryReaderError| CompileError::Validate(err.to_string());
+
+    let mut graph = BTreeMap::new();
+    let mut function = 0;
+    for payload in Parser::new(0).parse_all(&wasm) {
+        match payload.map_err(invalid)? {
+            // Imported functions come first in the function index space
+            Payload::ImportSection(imports) => {
+                for import in imports {
+                    if matches!(import.map_err(invalid)?.ty, TypeRef::Func(_)) {
+                        function += 1;
+                    }
+                }
+            }
+            Payload::CodeSectionEntry(body) => {
+                let mut callees = BTreeSet::new();
+                let mut reader = body.get_operators_reader().map_err(invalid)?;
+                while !reader.eof() {
+                    if let Operator::Call { function_index } = reader.read().map_err(invalid)? {
+                        callees.insert(function_index);
+                    }
+                }
+                graph.insert(function, callees);
+                function += 1;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(graph)
+}
+
+/// Names of the counters added to a function, exported from the module
+#[derive(Debug, Clone)]
+struct FunctionCounters {
+    index: u32,
+    name: String,
+    calls: GlobalIndex,
+    instructions: GlobalIndex,
+    points: GlobalIndex,
+    memory_grows: GlobalIndex,
+    /// Number of calls made to each local function, by function index
+    callees: BTreeMap<u32, GlobalIndex>,
+}
+
+impl FunctionCounters {
+    fn export_name(&self, counter: &str) -> String {
+        format!("__vrrb_profile_{}_{}", self.index, counter)
+    }
+}
+
+/// Name of the counter of calls made to `callee`
+fn callee_counter(callee: u32) -> String {
+    format!("calls_{callee}")
+}
+
+/// The profiling middleware. Like metering, a profiler instruments a single
+/// module and cannot be shared between modules.
+pub struct Profiler {
+    cost_function: SharedCostFunction,
+    call_graph: BTreeMap<u32, BTreeSet<u32>>,
+    counters: Mutex<Option<Vec<FunctionCounters>>>,
+}
+
+impl Profiler {
+    /// Creates a profiler for the module in `wasm_bytes`, which is read
+    /// ahead of compilation to find the calls between its functions.
+    pub fn new(
+        cost_function: SharedCostFunction,
+        wasm_bytes: &[u8],
+    ) -> Result<Self, WasmRuntimeError> {
+        Ok(Self {
+            cost_function,
+            call_graph: call_graph(wasm_bytes)?,
+            counters: Mutex::new(None),
+        })
+    }
+
+    /// Reads the counters of every function that was called out of an
+    /// instance of the profiled module.
+    pub(crate) fn function_profiles(
+        &self,
+        store: &impl AsStoreRef,
+        instance: &Instance,
+    ) -> Result<Vec<FunctionProfile>, WasmRuntimeError> {
+        let counters = self
+            .counters
+            .lock()
+            .map_err(|_| WasmRuntimeError::ProfilerUnavailable)?
+            .clone()
+            .ok_or(WasmRuntimeError::ProfilerUnavailable)?;
+
+        let mut profiles = BTreeMap::new();
+        let mut callers: BTreeMap<u32, BTreeMap<u32, u64>> = BTreeMap::new();
+        for function in counters {
+            let read = |counter: &str| -> Result<u64, WasmRuntimeError> {
+                match instance
+                    .exports
+                    .get_global(&function.export_name(counter))?
+                    .get(store)
+                {
+                    Value::I64(value) => Ok(value as u64),
+                    _ => Err(WasmRuntimeError::ProfilerUnavailable),
+                }
+            };
+
+            for callee in function.callees.keys() {
+                let calls = read(&callee_counter(*callee))?;
+                if calls > 0 {
+                    callers
+                        .entry(*callee)
+                        .or_default()
+                        .insert(function.index, calls);
+                }
+            }
+
+            let profile = FunctionProfile {
+                index: function.index,
+                name: function.name.clone(),
+                calls: read("calls")?,
+                instructions: read("instructions")?,
+                points: read("points")?,
+                memory_grows: read("memory_grows")?,
+                callers: BTreeMap::new(),
+            };
+            if profile.calls > 0 {
+                profiles.insert(function.index, profile);
+            }
+        }
+
+        for (callee, callers) in callers {
+            if let Some(profile) = profiles.get_mut(&callee) {
+                profile.callers = callers;
+            }
+        }
+        let mut profiles: Vec<FunctionProfile> = profiles.into_values().collect();
+
+        profiles.sort_by(|a, b| b.points.cmp(&a.points).then(a.index.cmp(&b.index)));
+
+        Ok(profiles)
+    }
+}
+
+impl fmt::Debug for Profiler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Profiler")
+            .field("counters", &self.counters)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ModuleMiddleware for Profiler {
+    fn generate_function_middleware<'a>(
+        &self,
+        local_function_index: LocalFunctionIndex,
+    ) -> Box<dyn FunctionMiddleware<'a> + 'a> {
+        let counters = self
+            .counters
+            .lock()
+            .expect("profiler counters lock poisoned")
+            .as_ref()
+            .and_then(|counters| counters.get(local_function_index.index()).cloned())
+            .expect("profiler used before the module info was transformed");
+
+        Box::new(FunctionProfiler {
+            cost_function: self.cost_function.clone(),
+            counters,
+            entered: false,
+            instructions: 0,
+            points: 0,
+        })
+    }
+
+    fn transform_module_info(&self, module_info: &mut ModuleInfo) -> Result<(), MiddlewareError> {
+        let mut counters = self
+            .counters
+            .lock()
+            .map_err(|_| MiddlewareError::new("profiler", "counters lock poisoned"))?;
+        if counters.is_some() {
+            return Err(MiddlewareError::new(
+                "profiler",
+                "a profiler can only instrument a single module",
+            ));
+        }
+
+        let imported_functions = module_info.num_imported_functions;
+        let local_functions = module_info.functions.len() - imported_functions;
+        let mut functions = Vec::with_capacity(local_functions);
+        for local_index in 0..local_functions {
+            let function_index = module_info.func_index(LocalFunctionIndex::new(local_index));
+            let index = function_index.index() as u32;
+            let name = module_info
+                .function_names
+                .get(&function_index)
+                .cloned()
+                .unwrap_or_else(|| format!("func[{index}]"));
+
+            let mut counter = || {
+                let global = module_info
+                    .globals
+                    .push(GlobalType::new(Type::I64, Mutability::Var));
+                module_info
+                    .global_initializers
+                    .push(GlobalInit::I64Const(0));
+                global
+            };
+            // Only calls to local functions are counted, host calls are
+            // counted by the runtime
+            let callees = self
+                .call_graph
+                .get(&index)
+                .into_iter()
+                .flatten()
+                .filter(|callee| **callee as usize >= imported_functions)
+                .map(|callee| (*callee, counter()))
+                .collect();
+            let function = FunctionCounters {
+                index,
+                name,
+                calls: counter(),
+                instructions: counter(),
+                points: counter(),
+                memory_grows: counter(),
+                callees,
+            };
+
+            let callee_counters = function
+                .callees
+                .iter()
+                .map(|(callee, global)| (callee_counter(*callee), *global));
+            for (counter, global) in [
+                ("calls".to_string(), function.calls),
+                ("instructions".to_string(), function.instructions),
+                ("points".to_string(), function.points),
+                ("memory_grows".to_string(), function.memory_grows),
+            ]
+            .into_iter()
+            .chain(callee_counters)
+            {
+                module_info
+                    .exports
+                    .insert(function.export_name(&counter), ExportIndex::Global(global));
+            }
+
+            functions.push(function);
+        }
+
+        *counters = Some(functions);
+
+        Ok(())
+    }
+}
+
+struct FunctionProfiler {
+    cost_function: SharedCostFunction,
+    counters: FunctionCounters,
+    entered: bool,
+    /// Instructions and points accumulated since the counters were last
+    /// updated
+    instructions: u64,
+    points: u64,
+}
+
+impl fmt::Debug for FunctionProfiler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FunctionProfiler")
+            .field("counters", &self.counters)
+            .field("instructions", &self.instructions)
+            .field("points", &self.points)
+            .finish_non_exhaustive()
+    }
+}
+
+fn increment<'a>(state: &mut MiddlewareReaderState<'a>, global: GlobalIndex, amount: u64) {
+    let global_index = global.as_u32();
+    state.push_operator(Operator::GlobalGet { global_index });
+    state.push_operator(Operator::I64Const {
+        value: amount as i64,
+    });
+    state.push_operator(Operator::I64Add);
+    state.push_operator(Operator::GlobalSet { global_index });
+}
+
+impl<'a> FunctionMiddleware<'a> for FunctionProfiler {
+    fn feed(
+        &mut self,
+        operator: Operator<'a>,
+        state: &mut MiddlewareReaderState<'a>,
+    ) -> Result<(), MiddlewareError> {
+        if !self.entered {
+            self.entered = true;
+            increment(state, self.counters.calls, 1);
+        }
+
+        self.instructions += 1;
+        self.points += (self.cost_function)(&operator);
+
+        if matches!(operator, Operator::MemoryGrow { .. }) {
+            increment(state, self.counters.memory_grows, 1);
+        }
+
+        // Flush the counters wherever control may leave the current block,
+        // the same places metering checks its points, and before anything
+        // that may trap
+        if matches!(
+            operator,
+            Operator::Loop { .. }
+                | Operator::End
+                | Operator::Else
+                | Operator::Br { .. }
+                | Operator::BrTable { .. }
+                | Operator::BrIf { .. }
+                | Operator::Call { .. }
+                | Operator::CallIndirect { .. }
+                | Operator::Return
+                | Operator::Unreachable
+        ) || may_trap(&operator)
+        {
+            increment(state, self.counters.instructions, self.instructions);
+            increment(state, self.counters.points, self.points);
+            self.instructions = 0;
+            self.points = 0;
+        }
+
+        if let Operator::Call { function_index } = &operator {
+            if let Some(counter) = self.counters.callees.get(function_index) {
+                increment(state, *counter, 1);
+            }
+        }
+
+        state.push_operator(operator);
+
+        Ok(())
+    }
+}
+
+/// Whether `operator` may trap on its own, rather than by running out of
+/// points where metering checks them
+fn may_trap(operator: &Operator) -> bool {
+    matches!(
+        OperatorClass::of(operator),
+        OperatorClass::Load
+            | OperatorClass::Store
+            | OperatorClass::IntegerDiv
+            | OperatorClass::MemoryGrow
+            | OperatorClass::BulkMemory
+    ) || matches!(
+        operator,
+        Operator::I32TruncF32S
+            | Operator::I32TruncF32U
+            | Operator::I32TruncF64S
+            | Operator::I32TruncF64U
+            | Operator::I64TruncF32S
+            | Operator::I64TruncF32U
+            | Operator::I64TruncF64S
+            | Operator::I64TruncF64U
+            | Operator::TableGet { .. }
+            | Operator::TableSet { .. }
+    )
+}
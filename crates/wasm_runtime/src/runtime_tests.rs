@@ -1,11 +1,17 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+};
 
 use serde_derive::{Deserialize, Serialize};
-use wasmer::{Cranelift, Target};
+use wasmer::{wasmparser::Operator, Cranelift, Target};
 use wasmer_vm::TrapCode;
 
 use crate::{
-    metering::{cost_function, MeteringConfig},
+    errors::WasmRuntimeError,
+    host_abi::{ContractState, HostContext, InMemoryContractState},
+    metering::{cost_function, GasSchedule, MeteringConfig, GAS_SCHEDULE_VERSION},
+    module_cache::ModuleCache,
     wasm_runtime::WasmRuntime,
 };
 
@@ -87,6 +93,75 @@ fn test_single_line_input() {
     assert_eq!(out.stdin.last_block_time, TEST_LAST_BLOCK_TIME);
 }
 
+/// This test checks that the metering points consumed by an execution are
+/// reported back once it completes.
+#[test]
+fn test_consumed_points() {
+    let wasm_bytes = std::fs::read("test_data/wasm_test.wasm").unwrap();
+    let json_data = std::fs::read("test_data/wasm_test_oneline.json").unwrap();
+    let target = Target::default();
+    let mut runtime = create_test_wasm_runtime(&target, &wasm_bytes)
+        .unwrap()
+        .stdin(&json_data);
+    assert_eq!(runtime.consumed_points(), 0);
+
+    let usage = runtime.execute().unwrap();
+
+    assert!(runtime.consumed_points() > 0);
+    assert!(runtime.consumed_points() < TEST_SPENDING_LIMIT);
+    assert_eq!(usage.consumed, runtime.consumed_points());
+    assert_eq!(usage.consumed + usage.remaining, TEST_SPENDING_LIMIT);
+}
+
+/// This test checks that running out of metering points is reported as its
+/// own error, with every point consumed.
+#[test]
+fn test_out_of_gas() {
+    let wasm_bytes = std::fs::read("test_data/wasm_test.wasm").unwrap();
+    let json_data = std::fs::read("test_data/wasm_test_oneline.json").unwrap();
+    let target = Target::default();
+    let mut runtime = WasmRuntime::new::<Cranelift>(
+        &target,
+        &wasm_bytes,
+        GasSchedule::default().metering_config(10),
+    )
+    .unwrap()
+    .stdin(&json_data);
+
+    assert!(matches!(
+        runtime.execute(),
+        Err(WasmRuntimeError::OutOfGas { limit: 10 })
+    ));
+    assert_eq!(runtime.consumed_points(), 10);
+    assert_eq!(runtime.remaining_points(), 0);
+}
+
+/// This test checks that operators are charged according to the class they
+/// belong to in the gas schedule.
+#[test]
+fn test_gas_schedule() {
+    let schedule = GasSchedule::from_version(GAS_SCHEDULE_VERSION).unwrap();
+
+    assert_eq!(schedule, GasSchedule::default());
+    assert_eq!(GasSchedule::from_version(1), Some(GasSchedule::v1()));
+    assert_eq!(GasSchedule::from_version(0), None);
+    assert_eq!(schedule.cost(&Operator::Nop), schedule.control);
+    assert_eq!(schedule.cost(&Operator::I32Add), schedule.numeric);
+    assert_eq!(schedule.cost(&Operator::I64DivU), schedule.integer_div);
+    assert_eq!(schedule.cost(&Operator::F64Mul), schedule.float);
+    assert_eq!(
+        schedule.cost(&Operator::LocalGet { local_index: 0 }),
+        schedule.local
+    );
+    assert_eq!(cost_function(&Operator::I32Mul), schedule.integer_mul);
+
+    let json = serde_json::to_string(&schedule).unwrap();
+    assert_eq!(
+        serde_json::from_str::<GasSchedule>(&json).unwrap(),
+        schedule
+    );
+}
+
 /// This test checks for correctness of command line arguments in the WASM
 /// object's output as having been passed through untouched.
 #[test]
@@ -216,3 +291,483 @@ fn test_process_exit() {
         Some("Exit(ExitCode::2147483647)".to_string())
     );
 }
+
+fn abi_test_context() -> HostContext {
+    HostContext {
+        caller: [1; 20],
+        contract_address: [2; 20],
+        block_height: 42,
+        block_time: TEST_LAST_BLOCK_TIME,
+    }
+}
+
+/// This test checks that a module declaring the VRRB ABI can write to the
+/// storage of its own account and transfer tokens out of it.
+#[test]
+fn test_vrrb_abi_host_functions() {
+    let wasm_bytes = std::fs::read("test_data/vrrb_abi_test.wat").unwrap();
+    let context = abi_test_context();
+    let mut state = InMemoryContractState::default();
+    state.balances.insert(context.contract_address, 10);
+    let state = Arc::new(Mutex::new(state));
+
+    let target = Target::default();
+    let mut runtime = create_test_wasm_runtime(&target, &wasm_bytes)
+        .unwrap()
+        .host_state(context.clone(), state.clone());
+    runtime.execute().unwrap();
+
+    let state = state.lock().unwrap();
+    assert_eq!(
+        state.storage_get(&context.contract_address, b"counter"),
+        Some(42u64.to_le_bytes().to_vec())
+    );
+    // Storage is kept the way contract accounts keep it
+    assert_eq!(
+        state.storage.get(&context.contract_address),
+        Some(&format!(
+            r#"{{"{}":"2a00000000000000"}}"#,
+            hex::encode("counter")
+        ))
+    );
+    assert_eq!(state.balances.get(&context.caller), Some(&5));
+    assert_eq!(state.balances.get(&context.contract_address), Some(&5));
+}
+
+/// This test checks that contracts cannot transfer tokens to themselves
+/// through the in-memory state, as on chain.
+#[test]
+fn test_in_memory_state_rejects_self_transfer() {
+    let context = abi_test_context();
+    let mut state = InMemoryContractState::default();
+    state.balances.insert(context.contract_address, 10);
+
+    assert!(state
+        .transfer(&context.contract_address, &context.contract_address, 5)
+        .is_err());
+    assert_eq!(state.balance(&context.contract_address), 10);
+}
+
+/// This test checks that modules declaring the VRRB ABI are not run without
+/// host state, or when they require an ABI version we do not implement.
+#[test]
+fn test_vrrb_abi_requirements() {
+    let wasm_bytes = std::fs::read("test_data/vrrb_abi_test.wat").unwrap();
+    let target = Target::default();
+
+    let mut runtime = create_test_wasm_runtime(&target, &wasm_bytes).unwrap();
+    assert!(matches!(
+        runtime.execute(),
+        Err(WasmRuntimeError::HostStateMissing)
+    ));
+
+    let wat = String::from_utf8(wasm_bytes)
+        .unwrap()
+        .replace(r#""\01\00\00\00""#, r#""\02\00\00\00""#);
+    let mut runtime = create_test_wasm_runtime(&target, wat.as_bytes())
+        .unwrap()
+        .host_state(
+            abi_test_context(),
+            Arc::new(Mutex::new(InMemoryContractState::default())),
+        );
+    assert!(matches!(
+        runtime.execute(),
+        Err(WasmRuntimeError::UnsupportedAbiVersion(2))
+    ));
+}
+
+/// This test checks that calls into the VRRB host ABI are charged the host
+/// call cost, running out of gas when it cannot be paid.
+#[test]
+fn test_vrrb_abi_host_call_cost() {
+    let wasm_bytes = std::fs::read("test_data/vrrb_abi_test.wat").unwrap();
+    let target = Target::default();
+    let metering_config = MeteringConfig::new(TEST_SPENDING_LIMIT, cost_function)
+        .with_host_call_cost(TEST_SPENDING_LIMIT + 1);
+    let state = Arc::new(Mutex::new(InMemoryContractState::default()));
+
+    let mut runtime = WasmRuntime::new::<Cranelift>(&target, &wasm_bytes, metering_config)
+        .unwrap()
+        .host_state(abi_test_context(), state.clone());
+
+    assert!(matches!(
+        runtime.execute(),
+        Err(WasmRuntimeError::OutOfGas {
+            limit: TEST_SPENDING_LIMIT
+        })
+    ));
+    assert!(state.lock().unwrap().storage.is_empty());
+}
+
+/// This test checks that buffers passed to the VRRB host ABI are checked
+/// against the module's memory and the host's limit before they are copied.
+#[test]
+fn test_vrrb_abi_buffer_bounds() {
+    let wat = r#"
+        (module
+          (import "vrrb_abi_v1" "storage_set"
+            (func $storage_set (param i32 i32 i32 i32) (result i32)))
+          (memory (export "memory") 2)
+          (global (export "_vrrb_abi_version") i32 (i32.const 0))
+          (data (i32.const 0) "\01\00\00\00")
+          (func $expect_memory_error (param i32)
+            (if (i32.ne (local.get 0) (i32.const -2)) (then unreachable)))
+          (func (export "_start")
+            ;; larger than the module's memory
+            (call $expect_memory_error
+              (call $storage_set (i32.const 16) (i32.const 1) (i32.const 0) (i32.const 0x7fffffff)))
+            ;; runs past the end of the module's memory
+            (call $expect_memory_error
+              (call $storage_set (i32.const 16) (i32.const 1) (i32.const 131071) (i32.const 2)))
+            ;; fits in the module's memory, but not within the host's limit
+            (call $expect_memory_error
+              (call $storage_set (i32.const 16) (i32.const 1) (i32.const 0) (i32.const 65537)))))
+    "#;
+    let target = Target::default();
+    let state = Arc::new(Mutex::new(InMemoryContractState::default()));
+
+    let mut runtime = create_test_wasm_runtime(&target, wat.as_bytes())
+        .unwrap()
+        .host_state(abi_test_context(), state.clone());
+
+    runtime.execute().unwrap();
+    assert!(state.lock().unwrap().storage.is_empty());
+}
+
+/// This test checks that newer gas schedules charge memory growth, bulk
+/// memory instructions and storage writes for the memory they touch, and
+/// that the unit costs are paid out of the module's metering points.
+#[test]
+fn test_gas_schedule_unit_costs() {
+    let wat = r#"
+        (module
+          (import "vrrb_abi_v1" "storage_set"
+            (func $storage_set (param i32 i32 i32 i32) (result i32)))
+          (memory (export "memory") 2)
+          (global (export "_vrrb_abi_version") i32 (i32.const 0))
+          (data (i32.const 0) "\01\00\00\00")
+          (func (export "_start")
+            (drop (memory.grow (i32.const 1)))
+            (memory.fill (i32.const 1024) (i32.const 7) (i32.const 65536))
+            (drop (call $storage_set (i32.const 0) (i32.const 1) (i32.const 1024) (i32.const 4)))))
+    "#;
+    let execute = |schedule: GasSchedule, limit: u64| {
+        let state = Arc::new(Mutex::new(InMemoryContractState::default()));
+        let mut runtime = WasmRuntime::new::<Cranelift>(
+            &Target::default(),
+            wat.as_bytes(),
+            schedule.metering_config(limit),
+        )
+        .unwrap()
+        .host_state(abi_test_context(), state);
+        runtime.execute()
+    };
+
+    assert_eq!(GasSchedule::v1().unit_costs(), Default::default());
+    let v1 = execute(GasSchedule::v1(), TEST_SPENDING_LIMIT).unwrap();
+    let schedule = GasSchedule::v2();
+    let v2 = execute(schedule, TEST_SPENDING_LIMIT).unwrap();
+    assert_eq!(
+        v2.consumed - v1.consumed,
+        schedule.memory_grow_page + 65536 * schedule.bulk_memory_byte + 5 * schedule.storage_byte
+    );
+
+    // Enough points for the flat costs, but not for the bytes filled
+    assert!(matches!(
+        execute(schedule, v1.consumed + schedule.memory_grow_page),
+        Err(WasmRuntimeError::OutOfGas { .. })
+    ));
+}
+
+fn test_module_cache(name: &str, max_size: u64) -> ModuleCache {
+    let dir =
+        std::env::temp_dir().join(format!("vrrb_module_cache_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    ModuleCache::new(dir, max_size).unwrap()
+}
+
+fn cached_entries(cache: &ModuleCache) -> Vec<std::path::PathBuf> {
+    std::fs::read_dir(cache.dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "wasmu"))
+        .collect()
+}
+
+fn create_cached_wasm_runtime(
+    wasm_bytes: &[u8],
+    cache: &ModuleCache,
+) -> crate::wasm_runtime::RuntimeResult<WasmRuntime> {
+    WasmRuntime::new_cached::<Cranelift>(
+        &Target::default(),
+        wasm_bytes,
+        GasSchedule::default().metering_config(TEST_SPENDING_LIMIT),
+        cache,
+    )
+}
+
+/// This test checks that compiled modules are loaded from the cache the
+/// second time they are needed, and behave like freshly compiled ones.
+#[test]
+fn test_module_cache_hit() {
+    let wasm_bytes = std::fs::read("test_data/wasm_test.wasm").unwrap();
+    let json_data = std::fs::read("test_data/wasm_test_oneline.json").unwrap();
+    let cache = test_module_cache("hit", u64::MAX);
+
+    let mut compiled = create_cached_wasm_runtime(&wasm_bytes, &cache)
+        .unwrap()
+        .stdin(&json_data);
+    assert!(!compiled.cache_hit());
+    compiled.execute().unwrap();
+
+    let mut cached = create_cached_wasm_runtime(&wasm_bytes, &cache)
+        .unwrap()
+        .stdin(&json_data);
+    assert!(cached.cache_hit());
+    cached.execute().unwrap();
+
+    assert_eq!(compiled.stdout(), cached.stdout());
+    assert_eq!(compiled.consumed_points(), cached.consumed_points());
+
+    // The limit is applied when the module is instantiated, so modules are
+    // shared between limits
+    let mut other_limit = WasmRuntime::new_cached::<Cranelift>(
+        &Target::default(),
+        &wasm_bytes,
+        GasSchedule::default().metering_config(TEST_SPENDING_LIMIT + 1),
+        &cache,
+    )
+    .unwrap()
+    .stdin(&json_data);
+    assert!(other_limit.cache_hit());
+    let usage = other_limit.execute().unwrap();
+    assert_eq!(usage.consumed, compiled.consumed_points());
+    assert_eq!(usage.consumed + usage.remaining, TEST_SPENDING_LIMIT + 1);
+
+    // Modules metered with a different schedule are compiled separately
+    let other_schedule = WasmRuntime::new_cached::<Cranelift>(
+        &Target::default(),
+        &wasm_bytes,
+        GasSchedule::v1().metering_config(TEST_SPENDING_LIMIT),
+        &cache,
+    )
+    .unwrap();
+    assert!(!other_schedule.cache_hit());
+
+    std::fs::remove_dir_all(cache.dir()).unwrap();
+}
+
+/// This test checks that corrupted cache entries, and entries written by
+/// another cache, are discarded and the module compiled again, and that
+/// modules are not cached when the cost function can't be identified.
+#[test]
+fn test_module_cache_integrity() {
+    let wasm_bytes = std::fs::read("test_data/wasm_test.wasm").unwrap();
+    let cache = test_module_cache("integrity", u64::MAX);
+
+    create_cached_wasm_runtime(&wasm_bytes, &cache).unwrap();
+    let entries = cached_entries(&cache);
+    assert_eq!(entries.len(), 1);
+
+    let mut entry = std::fs::read(&entries[0]).unwrap();
+    let last = entry.len() - 1;
+    entry[last] ^= 0xff;
+    std::fs::write(&entries[0], entry).unwrap();
+
+    let runtime = create_cached_wasm_runtime(&wasm_bytes, &cache).unwrap();
+    assert!(!runtime.cache_hit());
+    assert!(create_cached_wasm_runtime(&wasm_bytes, &cache)
+        .unwrap()
+        .cache_hit());
+
+    // Entries are authenticated with a secret of their own cache
+    let other_cache = test_module_cache("integrity_other", u64::MAX);
+    create_cached_wasm_runtime(&wasm_bytes, &other_cache).unwrap();
+    std::fs::copy(&cached_entries(&other_cache)[0], &entries[0]).unwrap();
+    assert!(!create_cached_wasm_runtime(&wasm_bytes, &cache)
+        .unwrap()
+        .cache_hit());
+    std::fs::remove_dir_all(other_cache.dir()).unwrap();
+
+    let uncached = WasmRuntime::new_cached::<Cranelift>(
+        &Target::default(),
+        &wasm_bytes,
+        MeteringConfig::new(TEST_SPENDING_LIMIT, cost_function),
+        &cache,
+    )
+    .unwrap();
+    assert!(!uncached.cache_hit());
+    assert_eq!(cached_entries(&cache).len(), 1);
+
+    std::fs::remove_dir_all(cache.dir()).unwrap();
+}
+
+/// This test checks that the cache evicts older entries to stay within its
+/// size limit.
+#[test]
+fn test_module_cache_eviction() {
+    let wasm_bytes = std::fs::read("test_data/wasm_test.wasm").unwrap();
+    let other_wasm_bytes = std::fs::read("test_data/deterministic_test.wat").unwrap();
+    let cache = test_module_cache("eviction", 1);
+
+    create_cached_wasm_runtime(&wasm_bytes, &cache).unwrap();
+    create_cached_wasm_runtime(&other_wasm_bytes, &cache).unwrap();
+
+    // Only the entry written last is kept
+    assert_eq!(cached_entries(&cache).len(), 1);
+    assert!(create_cached_wasm_runtime(&other_wasm_bytes, &cache)
+        .unwrap()
+        .cache_hit());
+    assert!(!create_cached_wasm_runtime(&wasm_bytes, &cache)
+        .unwrap()
+        .cache_hit());
+
+    std::fs::remove_dir_all(cache.dir()).unwrap();
+}
+
+fn create_profiled_wasm_runtime(
+    wasm_bytes: &[u8],
+    limit: u64,
+) -> crate::wasm_runtime::RuntimeResult<WasmRuntime> {
+    let metering_config = MeteringConfig::new(limit, cost_function);
+    WasmRuntime::new_profiled::<Cranelift>(&Target::default(), wasm_bytes, metering_config)
+}
+
+/// This test checks that a profiled execution records the calls,
+/// instructions, points and memory growth of each function, along with the
+/// host calls the module made.
+#[test]
+fn test_execution_profile() {
+    let wasm_bytes = std::fs::read("test_data/profile_test.wat").unwrap();
+    let mut runtime = create_profiled_wasm_runtime(&wasm_bytes, TEST_SPENDING_LIMIT).unwrap();
+    let usage = runtime.execute().unwrap();
+    assert_eq!(runtime.stdout(), "done\n");
+
+    let profile = runtime.profile().unwrap();
+    let function = |name: &str| {
+        profile
+            .functions
+            .iter()
+            .find(|function| function.name == name)
+            .unwrap()
+    };
+
+    let square = function("square");
+    assert_eq!(square.calls, 10);
+    assert_eq!(square.instructions, 40);
+    assert_eq!(square.points, 50);
+    assert_eq!(square.memory_grows, 0);
+
+    let grow = function("grow");
+    assert_eq!(grow.calls, 1);
+    assert_eq!(grow.memory_grows, 1);
+    assert_eq!(function("func[3]").calls, 1);
+
+    // Both are called by `_start`, which the host calls
+    assert_eq!(square.callers, BTreeMap::from([(3, 10)]));
+    assert_eq!(grow.callers, BTreeMap::from([(3, 1)]));
+    assert!(function("func[3]").callers.is_empty());
+
+    assert_eq!(
+        profile.host_calls.get("wasi_snapshot_preview1::fd_write"),
+        Some(&1)
+    );
+    assert_eq!(profile.initial_memory_pages, 1);
+    assert_eq!(profile.final_memory_pages, 2);
+
+    // The profiler's counters are metered on top of the module's own code
+    assert_eq!(profile.points_consumed, usage.consumed);
+    assert!(profile.total_points() < usage.consumed);
+
+    let folded = profile.to_folded(VRRB_CONTRACT_NAME);
+    assert!(folded.contains("vrrb-contract;func[3];square 50\n"));
+    assert!(folded.contains("vrrb-contract;func[3];grow "));
+    assert_eq!(folded.lines().count(), 3);
+}
+
+/// This test checks that the profile of an execution that ran out of gas is
+/// still available.
+#[test]
+fn test_execution_profile_out_of_gas() {
+    let wasm_bytes = std::fs::read("test_data/profile_test.wat").unwrap();
+    let mut runtime = create_profiled_wasm_runtime(&wasm_bytes, 100).unwrap();
+
+    assert!(matches!(
+        runtime.execute(),
+        Err(WasmRuntimeError::OutOfGas { limit: 100 })
+    ));
+    let profile = runtime.profile().unwrap();
+    assert_eq!(profile.points_consumed, 100);
+    assert_eq!(profile.points_remaining, 0);
+    assert!(!profile.functions.is_empty());
+}
+
+/// This test checks that the instructions run before a trap other than
+/// running out of gas are still profiled.
+#[test]
+fn test_execution_profile_trap() {
+    let wasm_bytes = br#"
+        (module
+          (func (export "_start")
+            (drop (i32.div_u (i32.const 1) (i32.const 0)))))
+    "#;
+    let mut runtime = create_profiled_wasm_runtime(wasm_bytes, TEST_SPENDING_LIMIT).unwrap();
+
+    assert!(runtime.execute().is_err());
+    let profile = runtime.profile().unwrap();
+    assert_eq!(profile.functions.len(), 1);
+    assert_eq!(profile.functions[0].calls, 1);
+    // Both constants and the division itself
+    assert_eq!(profile.functions[0].instructions, 3);
+}
+
+/// This test checks that modules importing the Javy QuickJS provider run
+/// against the provider they are linked with, and are charged for its work.
+#[test]
+fn test_javy_provider() {
+    let wasm_bytes = std::fs::read("test_data/javy_test.wat").unwrap();
+    let provider_bytes = std::fs::read("test_data/javy_provider.wat").unwrap();
+    let target = Target::default();
+
+    let mut runtime = create_test_wasm_runtime(&target, &wasm_bytes).unwrap();
+    assert!(matches!(
+        runtime.execute(),
+        Err(WasmRuntimeError::JavyProviderMissing)
+    ));
+
+    let mut runtime = create_test_wasm_runtime(&target, &wasm_bytes)
+        .unwrap()
+        .javy_provider(&provider_bytes)
+        .unwrap();
+    let usage = runtime.execute().unwrap();
+    assert_eq!(runtime.stdout(), "console.log(42)\n");
+    // The module's own instructions cost less than this
+    assert!(usage.consumed > 40);
+
+    let metering_config = MeteringConfig::new(usage.consumed - 1, cost_function);
+    let mut runtime = WasmRuntime::new::<Cranelift>(&target, &wasm_bytes, metering_config)
+        .unwrap()
+        .javy_provider(&provider_bytes)
+        .unwrap();
+    assert!(matches!(
+        runtime.execute(),
+        Err(WasmRuntimeError::OutOfGas { .. })
+    ));
+
+    // The provider gets metering of its own, unit metering included
+    let metering_config = GasSchedule::default().metering_config(TEST_SPENDING_LIMIT);
+    let mut runtime = WasmRuntime::new::<Cranelift>(&target, &wasm_bytes, metering_config)
+        .unwrap()
+        .javy_provider(&provider_bytes)
+        .unwrap();
+    runtime.execute().unwrap();
+    assert_eq!(runtime.stdout(), "console.log(42)\n");
+
+    assert!(matches!(
+        create_profiled_wasm_runtime(&wasm_bytes, TEST_SPENDING_LIMIT)
+            .unwrap()
+            .javy_provider(&provider_bytes),
+        Err(WasmRuntimeError::ProfiledJavyProvider)
+    ));
+}
@@ -6,21 +6,30 @@
 //! runtime.
 
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     io::{Read, Write},
-    sync::Arc,
+    sync::{Arc, Mutex},
 };
 
 use super::{
+    deterministic::{self, DeterministicContext, DeterministicEnv},
+    host_abi::{
+        self, ContractState, HostContext, HostEnv, VRRB_ABI_NAMESPACE, VRRB_ABI_VERSION,
+        VRRB_ABI_VERSION_EXPORT,
+    },
+    javy::{self, JavyEnv, JAVY_PROVIDER_NAMESPACE},
     limiting_tunables::{LimitingTunables, DEFAULT_PAGE_LIMIT},
-    metering::MeteringConfig,
+    metering::{GasUsage, MeteringConfig, PerModule, UnitMetering},
+    module_cache::{ModuleCache, ModuleCacheKey},
+    profiler::{ExecutionProfile, Profiler},
 };
 use telemetry::debug;
 use wasmer::{
-    wasmparser::Operator, BaseTunables, CompilerConfig, Engine, Instance, Module, NativeEngineExt,
-    Store, Target,
+    wasmparser::Operator, AsStoreMut, BaseTunables, CompilerConfig, Engine, Extern, ExternType,
+    Function, FunctionEnv, FunctionEnvMut, Imports, Instance, Module, ModuleMiddleware,
+    NativeEngineExt, Store, Target, Value,
 };
-use wasmer_middlewares::metering::get_remaining_points;
+use wasmer_middlewares::metering::{get_remaining_points, set_remaining_points, MeteringPoints};
 use wasmer_wasix::{Pipe, WasiEnv};
 
 /// This is the first command line argument, traditionally reserved for the
@@ -38,6 +47,16 @@ pub struct WasmRuntime {
     stderr: String,
     args: Vec<String>,
     env: HashMap<String, String>,
+    initial_points: u64,
+    remaining_points: Option<MeteringPoints>,
+    host_call_cost: u64,
+    storage_byte_cost: u64,
+    host: Option<(HostContext, Arc<Mutex<dyn ContractState>>)>,
+    deterministic: Option<DeterministicContext>,
+    javy_provider: Option<Module>,
+    cache_hit: bool,
+    profiler: Option<Arc<Profiler>>,
+    profile: Option<ExecutionProfile>,
 }
 #[allow(clippy::result_large_err)]
 impl WasmRuntime {
@@ -50,25 +69,171 @@ impl WasmRuntime {
         wasm_bytes: &[u8],
         metering_config: MeteringConfig<impl Fn(&Operator<'_>) -> u64 + Send + Sync + 'static>,
     ) -> RuntimeResult<Self>
+    where
+        C: Default + Into<Engine> + CompilerConfig,
+    {
+        let initial_points = metering_config.initial_limit();
+        let host_call_cost = metering_config.host_call_cost();
+        let storage_byte_cost = metering_config.unit_costs().storage_byte;
+        let unit_metering = metering_config.unit_metering();
+        let store =
+            Self::create_store::<C>(target, metering_config.into_metering(), unit_metering, None);
+
+        debug!("Compiling {} bytes of WASM", wasm_bytes.len());
+
+        // Compile module into in-memory store
+        let module = Module::new(&store, wasm_bytes)?;
+        Ok(Self::with_module(
+            store,
+            module,
+            initial_points,
+            host_call_cost,
+            storage_byte_cost,
+        ))
+    }
+
+    /// Creates a new WasmRuntime like [WasmRuntime::new], but looks the
+    /// compiled module up in `cache` first. Freshly compiled modules are added
+    /// to the cache. Modules are only cached when the metering configuration
+    /// identifies its cost function, see [MeteringConfig::with_cost_id].
+    pub fn new_cached<C>(
+        target: &Target,
+        wasm_bytes: &[u8],
+        metering_config: MeteringConfig<impl Fn(&Operator<'_>) -> u64 + Send + Sync + 'static>,
+        cache: &ModuleCache,
+    ) -> RuntimeResult<Self>
+    where
+        C: Default + Into<Engine> + CompilerConfig,
+    {
+        let key = metering_config
+            .cache_id()
+            .map(|cache_id| ModuleCacheKey::new::<C>(wasm_bytes, target, &cache_id));
+        let initial_points = metering_config.initial_limit();
+        let host_call_cost = metering_config.host_call_cost();
+        let storage_byte_cost = metering_config.unit_costs().storage_byte;
+        let unit_metering = metering_config.unit_metering();
+        let store =
+            Self::create_store::<C>(target, metering_config.into_metering(), unit_metering, None);
+
+        let key = match key {
+            Some(key) => key,
+            None => {
+                debug!("Metering config has no cost id, not caching the module");
+                let module = Module::new(&store, wasm_bytes)?;
+                return Ok(Self::with_module(
+                    store,
+                    module,
+                    initial_points,
+                    host_call_cost,
+                    storage_byte_cost,
+                ));
+            }
+        };
+
+        if let Some(module) = cache.get(&store, &key) {
+            debug!("Loaded module {} from the cache", key.as_str());
+            let mut runtime = Self::with_module(
+                store,
+                module,
+                initial_points,
+                host_call_cost,
+                storage_byte_cost,
+            );
+            runtime.cache_hit = true;
+            return Ok(runtime);
+        }
+
+        debug!("Compiling {} bytes of WASM", wasm_bytes.len());
+        let module = Module::new(&store, wasm_bytes)?;
+        if let Err(err) = cache.insert(&key, &module) {
+            // The module can still be executed, it just has to be compiled
+            // again next time
+            telemetry::warn!("Failed to cache module {}: {}", key.as_str(), err);
+        }
+
+        Ok(Self::with_module(
+            store,
+            module,
+            initial_points,
+            host_call_cost,
+            storage_byte_cost,
+        ))
+    }
+
+    /// Creates a new WasmRuntime like [WasmRuntime::new] that also profiles
+    /// the execution of the module. Profiled modules are never cached, and
+    /// the counters added to them consume metering points of their own, see
+    /// [crate::profiler].
+    pub fn new_profiled<C>(
+        target: &Target,
+        wasm_bytes: &[u8],
+        metering_config: MeteringConfig<impl Fn(&Operator<'_>) -> u64 + Send + Sync + 'static>,
+    ) -> RuntimeResult<Self>
+    where
+        C: Default + Into<Engine> + CompilerConfig,
+    {
+        let initial_points = metering_config.initial_limit();
+        let host_call_cost = metering_config.host_call_cost();
+        let storage_byte_cost = metering_config.unit_costs().storage_byte;
+        let unit_metering = metering_config.unit_metering();
+        let (metering, cost_function) = metering_config.into_shared_metering();
+        let profiler = Arc::new(Profiler::new(cost_function, wasm_bytes)?);
+        let store =
+            Self::create_store::<C>(target, metering, unit_metering, Some(profiler.clone()));
+
+        debug!("Compiling {} bytes of WASM for profiling", wasm_bytes.len());
+        let module = Module::new(&store, wasm_bytes)?;
+
+        let mut runtime = Self::with_module(
+            store,
+            module,
+            initial_points,
+            host_call_cost,
+            storage_byte_cost,
+        );
+        runtime.profiler = Some(profiler);
+        Ok(runtime)
+    }
+
+    fn create_store<C>(
+        target: &Target,
+        metering: impl ModuleMiddleware + 'static,
+        unit_metering: Option<PerModule<UnitMetering>>,
+        profiler: Option<Arc<Profiler>>,
+    ) -> Store
     where
         C: Default + Into<Engine> + CompilerConfig,
     {
         // Setup Tunables
         let mut compiler = C::default();
-        compiler.push_middleware(Arc::new(metering_config.into_metering()));
+        // The profiler goes first so it sees the module's own instructions
+        // rather than the ones added by metering
+        if let Some(profiler) = profiler {
+            compiler.push_middleware(profiler);
+        }
+        compiler.push_middleware(Arc::new(metering));
+        // Unit metering charges the points kept by metering, so it has to
+        // come after it
+        if let Some(unit_metering) = unit_metering {
+            compiler.push_middleware(Arc::new(unit_metering));
+        }
         let base = BaseTunables::for_target(target);
         let tunables = LimitingTunables::new(base, DEFAULT_PAGE_LIMIT);
         let mut engine: Engine = compiler.into();
         engine.set_tunables(tunables);
         // Create an in-memory store for everything required to compile and run a WASM
         // module
-        let store = Store::new(engine);
-
-        debug!("Compiling {} bytes of WASM", wasm_bytes.len());
+        Store::new(engine)
+    }
 
-        // Compile module into in-memory store
-        let module = Module::new(&store, wasm_bytes)?;
-        Ok(Self {
+    fn with_module(
+        store: Store,
+        module: Module,
+        initial_points: u64,
+        host_call_cost: u64,
+        storage_byte_cost: u64,
+    ) -> Self {
+        Self {
             store,
             module,
             stdin: vec![],
@@ -76,7 +241,17 @@ impl WasmRuntime {
             stderr: String::new(),
             args: vec![],
             env: HashMap::new(),
-        })
+            initial_points,
+            remaining_points: None,
+            host_call_cost,
+            storage_byte_cost,
+            host: None,
+            deterministic: None,
+            javy_provider: None,
+            cache_hit: false,
+            profiler: None,
+            profile: None,
+        }
     }
 
     /// Adds a set of command line arguments to the WASM module's execution
@@ -97,6 +272,47 @@ impl WasmRuntime {
         self
     }
 
+    /// Provides the chain state and call details backing the VRRB host ABI.
+    /// Required to execute modules that declare the ABI, see [host_abi].
+    pub fn host_state<S: ContractState + 'static>(
+        mut self,
+        context: HostContext,
+        state: Arc<Mutex<S>>,
+    ) -> Self {
+        let state: Arc<Mutex<dyn ContractState>> = state;
+        self.host = Some((context, state));
+        self
+    }
+
+    /// Executes the module in deterministic mode, where the WASI clocks and
+    /// random source are derived from `context` and the module cannot reach
+    /// the filesystem or the network, see [deterministic].
+    pub fn deterministic(mut self, context: DeterministicContext) -> Self {
+        self.deterministic = Some(context);
+        self
+    }
+
+    /// Links the module against the Javy QuickJS provider in `provider_bytes`.
+    /// Required to execute JavaScript contracts compiled by Javy, see [javy].
+    /// The provider is compiled with the module's metering, and its work is
+    /// charged to the module. Runtimes created with
+    /// [WasmRuntime::new_profiled] cannot link a provider.
+    ///
+    /// The provider is executed as is, so providers of contracts must be
+    /// checked for determinism the same way the contracts are.
+    pub fn javy_provider(mut self, provider_bytes: &[u8]) -> RuntimeResult<Self> {
+        if self.profiler.is_some() {
+            return Err(WasmRuntimeError::ProfiledJavyProvider);
+        }
+
+        debug!(
+            "Compiling {} bytes of Javy provider WASM",
+            provider_bytes.len()
+        );
+        self.javy_provider = Some(Module::new(&self.store, provider_bytes)?);
+        Ok(self)
+    }
+
     /// Returns a string containing the output written to the WASM module's
     /// stdout stream.
     pub fn stdout(&self) -> String {
@@ -109,19 +325,66 @@ impl WasmRuntime {
         self.stderr.clone()
     }
 
-    /// Execute the compiled WASM module and retrieve the result.
-    pub fn execute(&mut self) -> RuntimeResult<()> {
+    /// Returns whether the compiled module was loaded from a [ModuleCache]
+    pub fn cache_hit(&self) -> bool {
+        self.cache_hit
+    }
+
+    /// Returns the profile of the last execution, if the runtime was created
+    /// with [WasmRuntime::new_profiled]
+    pub fn profile(&self) -> Option<&ExecutionProfile> {
+        self.profile.as_ref()
+    }
+
+    /// Returns the number of metering points consumed by the last execution,
+    /// or 0 if the module has not been executed yet.
+    pub fn consumed_points(&self) -> u64 {
+        match self.remaining_points {
+            Some(MeteringPoints::Remaining(remaining)) => {
+                self.initial_points.saturating_sub(remaining)
+            }
+            Some(MeteringPoints::Exhausted) => self.initial_points,
+            None => 0,
+        }
+    }
+
+    /// Returns the number of metering points left after the last execution,
+    /// or the initial limit if the module has not been executed yet.
+    pub fn remaining_points(&self) -> u64 {
+        match self.remaining_points {
+            Some(MeteringPoints::Remaining(remaining)) => remaining,
+            Some(MeteringPoints::Exhausted) => 0,
+            None => self.initial_points,
+        }
+    }
+
+    /// Execute the compiled WASM module and retrieve the result, returning
+    /// the metering points it consumed and the points it had left. Running
+    /// out of points is reported as [WasmRuntimeError::OutOfGas].
+    pub fn execute(&mut self) -> RuntimeResult<GasUsage> {
         let (mut stdin, in_wasm) = Pipe::channel();
         let (out_wasm, mut stdout) = Pipe::channel();
         let (err_wasm, mut stderr) = Pipe::channel();
         stdin.write_all(&self.stdin)?;
         stdin.flush()?;
 
-        self.init_wasi_fn_env((in_wasm, out_wasm, err_wasm))?;
+        let result = self.init_wasi_fn_env((in_wasm, out_wasm, err_wasm));
+        // Profiles are kept for failed executions too, running out of gas is
+        // when they are needed the most
+        let (consumed, remaining) = (self.consumed_points(), self.remaining_points());
+        if let Some(profile) = &mut self.profile {
+            profile.points_consumed = consumed;
+            profile.points_remaining = remaining;
+        }
+        result?;
 
         stdout.read_to_string(&mut self.stdout)?;
         stderr.read_to_string(&mut self.stderr)?;
-        Ok(())
+
+        Ok(GasUsage {
+            consumed: self.consumed_points(),
+            remaining: self.remaining_points(),
+        })
     }
 
     fn init_wasi_fn_env(
@@ -138,22 +401,191 @@ impl WasmRuntime {
             .envs(Box::new(self.env.iter()))
             .finalize(store)?;
 
-        let import_obj = wasi_fn_env.import_object(store, module)?;
+        let mut import_obj = wasi_fn_env.import_object(store, module)?;
+
+        // Only modules declaring the VRRB ABI get the host functions
+        let declares_abi = module
+            .exports()
+            .any(|export| export.name() == VRRB_ABI_VERSION_EXPORT);
+        let host_env = if declares_abi {
+            let (context, state) = self
+                .host
+                .clone()
+                .ok_or(WasmRuntimeError::HostStateMissing)?;
+            let env = FunctionEnv::new(
+                store,
+                HostEnv::new(context, state, self.host_call_cost, self.storage_byte_cost),
+            );
+            for (name, function) in host_abi::host_functions(store, &env) {
+                import_obj.define(VRRB_ABI_NAMESPACE, name, function);
+            }
+            Some(env)
+        } else {
+            None
+        };
+
+        let deterministic_env = self.deterministic.map(|context| {
+            let env = FunctionEnv::new(store, DeterministicEnv::new(context));
+            for (namespace, name, function) in deterministic::wasi_overrides(store, module, &env) {
+                import_obj.define(&namespace, &name, function);
+            }
+            env
+        });
+
+        // Javy modules import the QuickJS engine, and the memory it runs in,
+        // from a provider instantiated next to them
+        let needs_javy = module
+            .imports()
+            .any(|import| import.module() == JAVY_PROVIDER_NAMESPACE);
+        let javy_env = if needs_javy {
+            let provider = self
+                .javy_provider
+                .as_ref()
+                .ok_or(WasmRuntimeError::JavyProviderMissing)?;
+            let mut provider_imports = wasi_fn_env.import_object(store, provider)?;
+            if let Some(env) = &deterministic_env {
+                for (namespace, name, function) in
+                    deterministic::wasi_overrides(store, provider, env)
+                {
+                    provider_imports.define(&namespace, &name, function);
+                }
+            }
+
+            let provider = Instance::new(store, provider, &provider_imports)?;
+            set_remaining_points(store, &provider, self.initial_points);
+            let env = FunctionEnv::new(store, JavyEnv::new(provider));
+            for (name, export) in javy::provider_exports(store, &env) {
+                import_obj.define(JAVY_PROVIDER_NAMESPACE, &name, export);
+            }
+            Some(env)
+        } else {
+            None
+        };
+
+        let host_calls = self
+            .profiler
+            .as_ref()
+            .map(|_| count_host_calls(store, module, &mut import_obj));
+
         let instance = Instance::new(store, module, &import_obj)?;
+        // Modules are compiled without points, so they can be reused whatever
+        // limit they run with
+        set_remaining_points(store, &instance, self.initial_points);
+
+        let javy_provider = javy_env
+            .as_ref()
+            .map(|env| env.as_ref(store).provider().clone());
+        let memory = match (instance.exports.get_memory("memory"), &javy_provider) {
+            (Ok(memory), _) => memory.clone(),
+            (Err(_), Some(provider)) => provider.exports.get_memory("memory")?.clone(),
+            (Err(err), None) => return Err(err.into()),
+        };
+        let initial_memory_pages = memory.view(store).size();
+        telemetry::info!("Memory: {:?}", initial_memory_pages);
+
+        if let Some(env) = &host_env {
+            let version = host_abi::declared_abi_version(store, &instance, &memory)?;
+            if version != VRRB_ABI_VERSION {
+                return Err(WasmRuntimeError::UnsupportedAbiVersion(version));
+            }
+            env.as_mut(store).attach(instance.clone(), memory.clone());
+        }
+
+        if let Some(env) = &deterministic_env {
+            env.as_mut(store).set_memory(memory.clone());
+        }
 
-        let mem_view = instance.exports.get_memory("memory")?.view(store);
-        telemetry::info!("Memory: {:?}", mem_view.size());
+        if let Some(env) = &javy_env {
+            env.as_mut(store).attach(instance.clone());
+        }
 
-        wasi_fn_env.initialize(store, instance.clone())?;
+        match &javy_provider {
+            Some(provider) => {
+                // The provider makes the WASI calls, from the memory it shares
+                // with the module. What setting it up cost is charged to the
+                // module.
+                wasi_fn_env.initialize(store, provider.clone())?;
+                javy::transfer_points(store, provider, &instance);
+            }
+            None => wasi_fn_env.initialize(store, instance.clone())?,
+        }
         let start = instance.exports.get_function("_start")?;
-        start.call(store, &[])?;
+        let result = start.call(store, &[]);
 
-        telemetry::info!(
-            "MeteringPoints::{:?}",
-            get_remaining_points(store, &instance)
-        );
+        let mut remaining_points = get_remaining_points(store, &instance);
+        // Host functions zero the remaining points when they cannot be paid
+        // for, rather than exhausting them like the metering middleware does
+        if host_env
+            .as_ref()
+            .map_or(false, |env| env.as_ref(store).is_out_of_gas())
+            || javy_env
+                .as_ref()
+                .map_or(false, |env| env.as_ref(store).is_out_of_gas())
+        {
+            remaining_points = MeteringPoints::Exhausted;
+        }
+        telemetry::info!("MeteringPoints::{:?}", remaining_points);
+        let exhausted = remaining_points == MeteringPoints::Exhausted;
+        self.remaining_points = Some(remaining_points);
+
+        if let (Some(profiler), Some(host_calls)) = (&self.profiler, host_calls) {
+            self.profile = Some(ExecutionProfile {
+                functions: profiler.function_profiles(store, &instance)?,
+                host_calls: host_calls.as_ref(store).clone(),
+                initial_memory_pages: initial_memory_pages.0,
+                final_memory_pages: memory.view(store).size().0,
+                ..Default::default()
+            });
+        }
+
+        if let Err(err) = result {
+            if exhausted {
+                return Err(WasmRuntimeError::OutOfGas {
+                    limit: self.initial_points,
+                });
+            }
+            return Err(err.into());
+        }
 
         wasi_fn_env.cleanup(store, None);
         Ok(())
     }
 }
+
+/// Replaces every function imported by `module` with one that counts its
+/// calls before forwarding them, returning the counts keyed by
+/// `namespace::name`.
+fn count_host_calls(
+    store: &mut impl AsStoreMut,
+    module: &Module,
+    import_obj: &mut Imports,
+) -> FunctionEnv<BTreeMap<String, u64>> {
+    let env = FunctionEnv::new(store, BTreeMap::new());
+
+    for import in module.imports() {
+        if !matches!(import.ty(), ExternType::Function(_)) {
+            continue;
+        }
+        let function = match import_obj.get_export(import.module(), import.name()) {
+            Some(Extern::Function(function)) => function,
+            _ => continue,
+        };
+
+        let key = format!("{}::{}", import.module(), import.name());
+        let ty = function.ty(store);
+        let counting = Function::new_with_env(
+            store,
+            &env,
+            ty,
+            move |mut env: FunctionEnvMut<BTreeMap<String, u64>>, args: &[Value]| {
+                *env.data_mut().entry(key.clone()).or_default() += 1;
+                function
+                    .call(&mut env, args)
+                    .map(|results| results.to_vec())
+            },
+        );
+        import_obj.define(import.module(), import.name(), counting);
+    }
+
+    env
+}
@@ -0,0 +1,49 @@
+;; Exercises the WASI calls that are stubbed in deterministic mode: writes the
+;; realtime clock, 24 random bytes and the errno returned when trying to open
+;; a file to stdout, hex encoded.
+(module
+  (import "wasi_snapshot_preview1" "clock_time_get"
+    (func $clock_time_get (param i32 i64 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "random_get"
+    (func $random_get (param i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "path_open"
+    (func $path_open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_write"
+    (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 512) "0123456789abcdef")
+  (data (i32.const 528) "contract.json")
+
+  ;; hex encodes $len bytes at $src into $dst
+  (func $hex (param $src i32) (param $len i32) (param $dst i32)
+    (local $i i32)
+    (local $byte i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+        (local.set $byte (i32.load8_u (i32.add (local.get $src) (local.get $i))))
+        (i32.store8
+          (i32.add (local.get $dst) (i32.shl (local.get $i) (i32.const 1)))
+          (i32.load8_u
+            (i32.add (i32.const 512) (i32.shr_u (local.get $byte) (i32.const 4)))))
+        (i32.store8
+          (i32.add
+            (local.get $dst)
+            (i32.add (i32.shl (local.get $i) (i32.const 1)) (i32.const 1)))
+          (i32.load8_u
+            (i32.add (i32.const 512) (i32.and (local.get $byte) (i32.const 15)))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next))))
+
+  (func (export "_start")
+    (drop (call $clock_time_get (i32.const 0) (i64.const 1) (i32.const 0)))
+    (drop (call $random_get (i32.const 8) (i32.const 24)))
+    (i32.store (i32.const 32)
+      (call $path_open
+        (i32.const 3) (i32.const 0) (i32.const 528) (i32.const 13) (i32.const 0)
+        (i64.const 0) (i64.const 0) (i32.const 0) (i32.const 36)))
+    (call $hex (i32.const 0) (i32.const 36) (i32.const 64))
+    ;; iovec pointing at the 72 hex characters
+    (i32.store (i32.const 256) (i32.const 64))
+    (i32.store (i32.const 260) (i32.const 72))
+    (drop (call $fd_write (i32.const 1) (i32.const 256) (i32.const 1) (i32.const 300)))))
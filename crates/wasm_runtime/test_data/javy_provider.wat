@@ -0,0 +1,22 @@
+;; A stand-in for the Javy QuickJS provider, exporting the same functions and
+;; memory. "Evaluates" bytecode by writing it to stdout.
+(module
+  (import "wasi_snapshot_preview1" "fd_write"
+    (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (global $heap (mut i32) (i32.const 1024))
+
+  ;; bump allocator with the signature of the canonical ABI's
+  (func (export "canonical_abi_realloc")
+    (param $ptr i32) (param $old_size i32) (param $align i32) (param $size i32)
+    (result i32)
+    (local $new i32)
+    (local.set $new (global.get $heap))
+    (global.set $heap (i32.add (global.get $heap) (local.get $size)))
+    (local.get $new))
+
+  (func (export "eval_bytecode") (param $ptr i32) (param $len i32)
+    ;; iovec pointing at the bytecode
+    (i32.store (i32.const 0) (local.get $ptr))
+    (i32.store (i32.const 4) (local.get $len))
+    (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))))
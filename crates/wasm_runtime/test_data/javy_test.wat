@@ -0,0 +1,14 @@
+;; Shaped like the modules `javy compile -d` produces: copies its bytecode into
+;; the memory of the QuickJS provider and has the provider evaluate it.
+(module
+  (import "javy_quickjs_provider_v1" "canonical_abi_realloc"
+    (func $realloc (param i32 i32 i32 i32) (result i32)))
+  (import "javy_quickjs_provider_v1" "eval_bytecode"
+    (func $eval_bytecode (param i32 i32)))
+  (import "javy_quickjs_provider_v1" "memory" (memory 0))
+  (data $bytecode "console.log(42)\n")
+  (func (export "_start")
+    (local $ptr i32)
+    (local.set $ptr (call $realloc (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 16)))
+    (memory.init $bytecode (local.get $ptr) (i32.const 0) (i32.const 16))
+    (call $eval_bytecode (local.get $ptr) (i32.const 16))))
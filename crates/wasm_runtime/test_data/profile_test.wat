@@ -0,0 +1,28 @@
+;; Calls $square ten times, grows memory by a page and writes "done" to
+;; stdout, so each part of an execution profile has something to show.
+(module
+  (import "wasi_snapshot_preview1" "fd_write"
+    (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 16) "done\n")
+
+  (func $square (param $x i32) (result i32)
+    (i32.mul (local.get $x) (local.get $x)))
+
+  (func $grow
+    (drop (memory.grow (i32.const 1))))
+
+  (func (export "_start")
+    (local $i i32)
+    (local $sum i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (i32.const 10)))
+        (local.set $sum (i32.add (local.get $sum) (call $square (local.get $i))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (call $grow)
+    ;; iovec pointing at "done\n"
+    (i32.store (i32.const 0) (i32.const 16))
+    (i32.store (i32.const 4) (i32.const 5))
+    (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))))
@@ -0,0 +1,19 @@
+;; Exercises the VRRB host ABI: stores the current block height under the
+;; "counter" key of the contract's storage and sends 5 tokens back to the
+;; caller.
+(module
+  (import "vrrb_abi_v1" "storage_set" (func $storage_set (param i32 i32 i32 i32) (result i32)))
+  (import "vrrb_abi_v1" "caller" (func $caller (param i32) (result i32)))
+  (import "vrrb_abi_v1" "block_height" (func $block_height (result i64)))
+  (import "vrrb_abi_v1" "transfer" (func $transfer (param i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  ;; address of the ABI version this module was built against
+  (global (export "_vrrb_abi_version") i32 (i32.const 0))
+  (data (i32.const 0) "\01\00\00\00")
+  (data (i32.const 16) "counter")
+  (func (export "_start")
+    (i64.store (i32.const 64) (call $block_height))
+    (drop (call $storage_set (i32.const 16) (i32.const 7) (i32.const 64) (i32.const 8)))
+    (drop (call $caller (i32.const 128)))
+    (i64.store (i32.const 160) (i64.const 5))
+    (drop (call $transfer (i32.const 128) (i32.const 160)))))
@@ -0,0 +1,185 @@
+//! Content IDs of package blocks
+//!
+//! Packages are stored as two kinds of block: package manifests as DAG-JSON and the objects they
+//! link to as raw bytes. Both are addressed by a CIDv1 with a SHA2-256 multihash, rendered in
+//! base32. That is what an IPFS node returns for `dag put --store-codec dag-json` and for
+//! `block put`, so computing it here lets other backends address blocks the same way. Objects
+//! larger than [OBJECT_CHUNK_SIZE] are split into raw blocks listed by a DAG-JSON index.
+//!
+//! Objects published before then were added as UnixFS files, made of DAG-PB blocks. They are
+//! still read, block by block, with [decode_unixfs].
+
+use anyhow::{anyhow, bail, Result};
+use cid::Cid;
+use multihash::{Code, MultihashDigest};
+use serde_json::{Map, Value};
+
+/// Multicodec of raw blocks
+pub const RAW_CODEC: u64 = 0x55;
+
+/// Multicodec of DAG-JSON blocks
+pub const DAG_JSON_CODEC: u64 = 0x0129;
+
+/// Multicodec of DAG-PB blocks, which UnixFS files are made of
+pub const DAG_PB_CODEC: u64 = 0x70;
+
+/// Size of the blocks large objects are split into, well under the 1 MiB IPFS nodes accept
+pub const OBJECT_CHUNK_SIZE: usize = 256 * 1024;
+
+/// Deepest UnixFS file that is read, far deeper than any file `ipfs add` writes
+pub const MAX_UNIXFS_DEPTH: usize = 32;
+
+/// UnixFS data types of the nodes files are made of
+const UNIXFS_RAW: u64 = 0;
+const UNIXFS_FILE: u64 = 2;
+
+/// A node of a UnixFS file: the file data it holds itself, which comes before the data of the
+/// nodes it links to
+#[derive(Debug, Default)]
+pub struct UnixFsNode {
+    pub data: Vec<u8>,
+    pub links: Vec<String>,
+}
+
+/// Returns the CID of `data` stored as a raw block
+pub fn raw_cid(data: &[u8]) -> String {
+    Cid::new_v1(RAW_CODEC, Code::Sha2_256.digest(data)).to_string()
+}
+
+/// Encodes a JSON document as a DAG-JSON block, the way an IPFS node does before storing it:
+/// without whitespace, with map keys sorted and with links in their canonical form. Returns
+/// the CID of the block along with the block itself.
+pub fn encode_dag_json(data: &[u8]) -> Result<(String, Vec<u8>)> {
+    let value: Value = serde_json::from_slice(data)?;
+    let block = serde_json::to_vec(&canonicalize(value)?)?;
+    let cid = Cid::new_v1(DAG_JSON_CODEC, Code::Sha2_256.digest(&block)).to_string();
+
+    Ok((cid, block))
+}
+
+/// Returns the multicodec of the block addressed by `cid`
+pub fn codec(cid: &str) -> Result<u64> {
+    Ok(Cid::try_from(cid)?.codec())
+}
+
+/// Renders `cid` in its canonical form, base32 for CIDv1 and base58 for CIDv0
+pub fn normalize(cid: &str) -> Result<String> {
+    Ok(Cid::try_from(cid)?.to_string())
+}
+
+/// Checks that `block` is the content addressed by `cid`
+pub fn verify(cid: &str, block: &[u8]) -> Result<()> {
+    let cid = Cid::try_from(cid)?;
+    let hash = cid.hash();
+    if Code::try_from(hash.code())?.digest(block) != *hash {
+        bail!("block does not match CID {}", cid);
+    }
+
+    Ok(())
+}
+
+/// Decodes a DAG-PB block of a UnixFS file, as written by `ipfs add`. The block is expected to
+/// have been checked against its CID already.
+pub fn decode_unixfs(block: &[u8]) -> Result<UnixFsNode> {
+    let mut node = UnixFsNode::default();
+    let mut unixfs = None;
+    for (field, value) in protobuf_fields(block)? {
+        match (field, value) {
+            (1, ProtobufValue::Bytes(data)) => unixfs = Some(data),
+            (2, ProtobufValue::Bytes(link)) => {
+                for (field, value) in protobuf_fields(link)? {
+                    if let (1, ProtobufValue::Bytes(hash)) = (field, value) {
+                        node.links.push(Cid::try_from(hash)?.to_string());
+                    }
+                }
+            }
+            _ => bail!("invalid DAG-PB block"),
+        }
+    }
+
+    let unixfs = unixfs.ok_or_else(|| anyhow!("DAG-PB block holds no UnixFS data"))?;
+    let mut data_type = None;
+    for (field, value) in protobuf_fields(unixfs)? {
+        match (field, value) {
+            (1, ProtobufValue::Varint(value)) => data_type = Some(value),
+            (2, ProtobufValue::Bytes(data)) => node.data = data.to_vec(),
+            _ => {}
+        }
+    }
+    if !matches!(data_type, Some(UNIXFS_RAW | UNIXFS_FILE)) {
+        bail!("UnixFS node is not part of a file");
+    }
+
+    Ok(node)
+}
+
+enum ProtobufValue<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+}
+
+/// Splits a protobuf message into its fields, along with their field numbers
+fn protobuf_fields(mut message: &[u8]) -> Result<Vec<(u64, ProtobufValue)>> {
+    let mut fields = vec![];
+    while !message.is_empty() {
+        let key = read_varint(&mut message)?;
+        let value = match key & 0x7 {
+            0 => ProtobufValue::Varint(read_varint(&mut message)?),
+            2 => {
+                let len = usize::try_from(read_varint(&mut message)?)?;
+                if len > message.len() {
+                    bail!("truncated protobuf field");
+                }
+                let (bytes, rest) = message.split_at(len);
+                message = rest;
+                ProtobufValue::Bytes(bytes)
+            }
+            wire_type => bail!("unsupported protobuf wire type {}", wire_type),
+        };
+        fields.push((key >> 3, value));
+    }
+
+    Ok(fields)
+}
+
+fn read_varint(buf: &mut &[u8]) -> Result<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = buf
+            .split_first()
+            .ok_or_else(|| anyhow!("truncated protobuf varint"))?;
+        *buf = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    bail!("protobuf varint too long")
+}
+
+fn canonicalize(value: Value) -> Result<Value> {
+    match value {
+        Value::Array(items) => Ok(Value::Array(
+            items.into_iter().map(canonicalize).collect::<Result<_>>()?,
+        )),
+        Value::Object(map) => {
+            // A map holding nothing but a string under "/" is a link
+            if let (1, Some(Value::String(link))) = (map.len(), map.get("/")) {
+                let mut canonical = Map::new();
+                canonical.insert("/".to_string(), Value::String(normalize(link)?));
+                return Ok(Value::Object(canonical));
+            }
+
+            let mut entries: Vec<(String, Value)> = map.into_iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+            let mut sorted = Map::new();
+            for (key, value) in entries {
+                sorted.insert(key, canonicalize(value)?);
+            }
+            Ok(Value::Object(sorted))
+        }
+        other => Ok(other),
+    }
+}
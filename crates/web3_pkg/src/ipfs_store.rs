@@ -0,0 +1,131 @@
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use ipfs_api::{
+    request::{DagCodec, DagPut},
+    IpfsApi, IpfsClient, TryFromUri,
+};
+use std::io::Cursor;
+
+use crate::content_id;
+use crate::web3_store::{
+    Web3StoreBackend, Web3StoreBandwidthStats, Web3StoreBitswapStats, Web3StoreRepoStats,
+    Web3StoreStats,
+};
+
+/// A [Web3StoreBackend] talking to the RPC service of an IPFS node, such as Kubo.
+pub struct IpfsStore {
+    client: IpfsClient,
+}
+
+impl IpfsStore {
+    /// A constructor that uses the default configuration to connect to a local IPFS
+    /// implementation's RPC service on the default port of TCP/5001.
+    pub fn local() -> Result<Self> {
+        Ok(IpfsStore {
+            client: IpfsClient::default(),
+        })
+    }
+
+    /// A constructor that takes a multiaddr string (eg, "/ip4/127.0.0.1/tcp/5001") to connect to
+    /// the RPC service on an IPFS instance.
+    pub fn from_multiaddr(addr: &str) -> Result<Self> {
+        Ok(IpfsStore {
+            client: IpfsClient::from_multiaddr_str(addr)?,
+        })
+    }
+}
+
+#[async_trait]
+impl Web3StoreBackend for IpfsStore {
+    /// Writes the DAG with `dag put`, storing it as DAG-JSON rather than the default of
+    /// DAG-CBOR.
+    async fn write_dag(&self, data: Vec<u8>) -> Result<String> {
+        let curs = Cursor::new(data);
+        let options = DagPut {
+            input_codec: Some(DagCodec::Json),
+            store_codec: Some(DagCodec::Json),
+            ..Default::default()
+        };
+        let cid = self.client.dag_put_with_options(curs, options).await?;
+        Ok(cid.cid.cid_string)
+    }
+
+    /// Writes the object with `block put`, which stores it as a single CIDv1 raw block rather
+    /// than splitting it into a UnixFS DAG the way `add` does. Larger objects are split into
+    /// blocks by [crate::web3_store::Web3Store] beforehand.
+    async fn write_object(&self, data: Vec<u8>) -> Result<String> {
+        let curs = Cursor::new(data);
+        let block = self.client.block_put(curs).await?;
+        Ok(block.key)
+    }
+
+    async fn read_dag(&self, cid: &str) -> Result<Vec<u8>> {
+        let ret = self
+            .client
+            .dag_get(cid)
+            .map_ok(|chunk| chunk.to_vec())
+            .try_concat()
+            .await?;
+        Ok(ret)
+    }
+
+    /// Reads the object with `cat`, so objects added as UnixFS files can still be read.
+    async fn read_object(&self, cid: &str) -> Result<Vec<u8>> {
+        let ret = self
+            .client
+            .cat(cid)
+            .map_ok(|chunk| chunk.to_vec())
+            .try_concat()
+            .await?;
+        Ok(ret)
+    }
+
+    async fn read_block(&self, cid: &str) -> Result<Vec<u8>> {
+        let block = self
+            .client
+            .block_get(cid)
+            .map_ok(|chunk| chunk.to_vec())
+            .try_concat()
+            .await?;
+        content_id::verify(cid, &block)?;
+        Ok(block)
+    }
+
+    /// Re-imports the file with `add`, using the defaults files were published with, and checks
+    /// that it gets the same CID.
+    async fn import_unixfs(&self, cid: &str, from: &dyn Web3StoreBackend) -> Result<()> {
+        let data = from.read_object(cid).await?;
+        let added = self.client.add(Cursor::new(data)).await?;
+        if content_id::normalize(&added.hash)? != content_id::normalize(cid)? {
+            bail!("UnixFS file {} was imported as {}", cid, added.hash);
+        }
+        Ok(())
+    }
+
+    async fn stats(&self) -> Result<Web3StoreStats> {
+        let repo = self.client.stats_repo().await?;
+        let bw = self.client.stats_bw().await?;
+        let bs = self.client.stats_bitswap().await?;
+
+        Ok(Web3StoreStats {
+            repo: Web3StoreRepoStats {
+                num_objects: repo.num_objects,
+                repo_size: repo.repo_size,
+                repo_path: repo.repo_path,
+            },
+            bandwidth: Web3StoreBandwidthStats {
+                total_in: bw.total_in,
+                total_out: bw.total_out,
+                rate_in: bw.rate_in,
+                rate_out: bw.rate_out,
+            },
+            bitswap: Web3StoreBitswapStats {
+                blocks_in: bs.blocks_received,
+                blocks_out: bs.blocks_sent,
+                data_in: bs.data_received,
+                data_out: bs.data_sent,
+            },
+        })
+    }
+}
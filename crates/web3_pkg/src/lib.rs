@@ -1,3 +1,6 @@
+pub mod content_id;
+pub mod ipfs_store;
+pub mod local_store;
 pub mod web3_pkg;
 pub mod web3_store;
 
@@ -0,0 +1,150 @@
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::content_id;
+use crate::web3_store::{Web3StoreBackend, Web3StoreRepoStats, Web3StoreStats};
+
+/// A [Web3StoreBackend] keeping blocks as files in a directory on the local filesystem, named
+/// after their CIDs. Blocks get the same CIDs an IPFS node would give them, so packages written
+/// here can be mirrored to IPFS and back. Every block is checked against its CID when it is
+/// read.
+pub struct LocalStore {
+    root: PathBuf,
+}
+
+impl LocalStore {
+    /// A constructor for a store rooted at `path`, which is created if it does not exist.
+    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
+        let root = path.as_ref().to_path_buf();
+        fs::create_dir_all(&root)?;
+        Ok(LocalStore { root })
+    }
+
+    fn block_path(&self, cid: &str) -> Result<PathBuf> {
+        Ok(self.root.join(content_id::normalize(cid)?))
+    }
+
+    fn write_block(&self, cid: &str, block: &[u8]) -> Result<()> {
+        let path = self.block_path(cid)?;
+        if path.exists() {
+            return Ok(());
+        }
+
+        // Write to a temporary file first so readers never see a partial block
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, block)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    fn read_local_block(&self, cid: &str) -> Result<Vec<u8>> {
+        let path = self.block_path(cid)?;
+        let block = fs::read(&path).map_err(|err| anyhow!("block {} not found: {}", cid, err))?;
+        content_id::verify(cid, &block)?;
+        Ok(block)
+    }
+
+    /// Appends the data of the UnixFS file, or part of a file, at `cid` to `data`
+    fn read_unixfs(&self, cid: &str, depth: usize, data: &mut Vec<u8>) -> Result<()> {
+        if depth > content_id::MAX_UNIXFS_DEPTH {
+            bail!("UnixFS file {} is too deep", cid);
+        }
+
+        let block = self.read_local_block(cid)?;
+        match content_id::codec(cid)? {
+            content_id::RAW_CODEC => data.extend(block),
+            content_id::DAG_PB_CODEC => {
+                let node = content_id::decode_unixfs(&block)?;
+                data.extend(node.data);
+                for link in &node.links {
+                    self.read_unixfs(link, depth + 1, data)?;
+                }
+            }
+            codec => bail!("UnixFS file {} links to a block of codec {:#x}", cid, codec),
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Web3StoreBackend for LocalStore {
+    async fn write_dag(&self, data: Vec<u8>) -> Result<String> {
+        let (cid, block) = content_id::encode_dag_json(&data)?;
+        self.write_block(&cid, &block)?;
+        Ok(cid)
+    }
+
+    async fn write_object(&self, data: Vec<u8>) -> Result<String> {
+        let cid = content_id::raw_cid(&data);
+        self.write_block(&cid, &data)?;
+        Ok(cid)
+    }
+
+    async fn read_dag(&self, cid: &str) -> Result<Vec<u8>> {
+        self.read_local_block(cid)
+    }
+
+    async fn read_object(&self, cid: &str) -> Result<Vec<u8>> {
+        if content_id::codec(cid)? == content_id::DAG_PB_CODEC {
+            let mut data = vec![];
+            self.read_unixfs(cid, 0, &mut data)?;
+            return Ok(data);
+        }
+
+        self.read_local_block(cid)
+    }
+
+    async fn read_block(&self, cid: &str) -> Result<Vec<u8>> {
+        self.read_local_block(cid)
+    }
+
+    /// Copies every block of the file, checking each of them against its CID
+    async fn import_unixfs(&self, cid: &str, from: &dyn Web3StoreBackend) -> Result<()> {
+        let mut pending = vec![(cid.to_string(), 0)];
+        while let Some((cid, depth)) = pending.pop() {
+            if depth > content_id::MAX_UNIXFS_DEPTH {
+                bail!("UnixFS file {} is too deep", cid);
+            }
+
+            let block = from.read_block(&cid).await?;
+            content_id::verify(&cid, &block)?;
+            match content_id::codec(&cid)? {
+                content_id::RAW_CODEC => {}
+                content_id::DAG_PB_CODEC => {
+                    let node = content_id::decode_unixfs(&block)?;
+                    pending.extend(node.links.into_iter().map(|link| (link, depth + 1)));
+                }
+                codec => bail!("UnixFS file {} links to a block of codec {:#x}", cid, codec),
+            }
+            self.write_block(&cid, &block)?;
+        }
+
+        Ok(())
+    }
+
+    /// Only the repository stats apply, as blocks are never sent or received.
+    async fn stats(&self) -> Result<Web3StoreStats> {
+        let mut repo = Web3StoreRepoStats {
+            repo_path: self.root.display().to_string(),
+            ..Default::default()
+        };
+        for entry in fs::read_dir(&self.root)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if metadata.is_file() && entry.path().extension().is_none() {
+                repo.num_objects += 1;
+                repo.repo_size += metadata.len();
+            }
+        }
+
+        Ok(Web3StoreStats {
+            repo,
+            ..Default::default()
+        })
+    }
+}
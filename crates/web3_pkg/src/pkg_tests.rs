@@ -1,16 +1,26 @@
+use crate::content_id;
+use crate::local_store::LocalStore;
 use crate::web3_pkg::{
     Web3ContentId, Web3ObjectType, Web3Package, Web3PackageArchitecture, Web3PackageBuilder,
     Web3PackageObject, Web3PackageObjectBuilder, Web3PackageType,
 };
-use crate::web3_store::Web3Store;
+use crate::web3_store::{Web3Store, Web3StoreBackend, Web3StoreStats};
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use cid::Cid;
+use multihash::{Code, MultihashDigest};
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::str;
 use tokio;
+use vrrb_core::keypair::KeyPair;
 
 // The majority of the tests in this module are marked as #[ignore]. This is because in order for
 // them to run, they require a separate IPFS service (such as Kubo) to be running. There is a task
 // open to also embed an IPFS implementation (ipfs-embed) into the blob storage stack. Once this
 // works, these tests will be able to be executed without the external dependency and can then be
-// re-enabled. They're here in the meantime to allow testing inside my workspace.
+// re-enabled. They're here in the meantime to allow testing inside my workspace. The tests using
+// the local store backend run without it.
 
 /// This test just shows that we can use both builder interfaces and have them return Ok. A basic
 /// smoke test if anything.
@@ -140,3 +150,306 @@ async fn write_test() {
     let cid = store.write_dag(json.into()).await.unwrap();
     eprintln!("DAG write of root (package) returned CID: {}", cid);
 }
+
+/// Returns an empty directory for a local store, unique to the calling test
+fn local_store_dir(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("web3_pkg-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    path
+}
+
+/// Builds a package out of the files in test_data, writing its objects to `store`
+async fn write_test_package(store: &Web3Store) -> Web3Package {
+    let wasm_cid = store
+        .write_object(std::fs::read("test_data/wasm_test-opt.wasm").unwrap())
+        .await
+        .unwrap();
+    let readme_cid = store
+        .write_object(std::fs::read("test_data/README.md").unwrap())
+        .await
+        .unwrap();
+
+    let objects = vec![
+        Web3PackageObjectBuilder::default()
+            .object_arch(Web3PackageArchitecture::Wasm32Wasi)
+            .object_path("wasm_test-opt.wasm".to_string())
+            .object_cid(Web3ContentId { cid: wasm_cid })
+            .object_type(Web3ObjectType::Executable)
+            .build()
+            .unwrap(),
+        Web3PackageObjectBuilder::default()
+            .object_arch(Web3PackageArchitecture::None)
+            .object_path("README.md".to_string())
+            .object_cid(Web3ContentId { cid: readme_cid })
+            .object_type(Web3ObjectType::Document)
+            .build()
+            .unwrap(),
+    ];
+
+    Web3PackageBuilder::default()
+        .pkg_version(5)
+        .pkg_name("Versatus Smart Contract".to_string())
+        .pkg_author("Versatus Labs".to_string())
+        .pkg_type(Web3PackageType::SmartContract)
+        .pkg_objects(objects)
+        .pkg_replaces(vec![Web3ContentId {
+            cid: "bafyreialcti7pn4eqgrdkr3aug45mhcqm65htuwkmtdw5pwth73a5o7piu".to_string(),
+        }])
+        .build()
+        .unwrap()
+}
+
+/// Raw blocks get the CIDs `ipfs block put` gives them
+#[test]
+fn raw_cid_test() {
+    assert_eq!(
+        content_id::raw_cid(b""),
+        "bafkreihdwdcefgh4dqkjv67uzcmw7ojee6xedzdetojuzjevtenxquvyku"
+    );
+    assert_eq!(
+        content_id::raw_cid(b"hello world"),
+        "bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e"
+    );
+}
+
+/// DAG-JSON is re-encoded canonically before it is hashed, so formatting and key order do not
+/// change the CID
+#[test]
+fn dag_json_cid_test() {
+    let (cid, block) = content_id::encode_dag_json(b"{ \"b\": 1,\n  \"a\": 2 }").unwrap();
+    assert_eq!(block, b"{\"a\":2,\"b\":1}");
+    assert_eq!(
+        cid,
+        "baguqeera2nrgvqykq7tppjscqiz3hructglwqzp2kueoijt4kqk4o2xxu5za"
+    );
+
+    assert!(content_id::encode_dag_json(b"{\"/\": \"not a cid\"}").is_err());
+}
+
+/// Publishes a package to a local store and retrieves it along with its objects, without an
+/// IPFS service.
+#[tokio::test]
+async fn local_store_test() {
+    let store = Web3Store::local_dir(local_store_dir("local_store_test")).unwrap();
+    let pkg = write_test_package(&store).await;
+    let cid = store
+        .write_dag(serde_json::to_vec(&pkg).unwrap())
+        .await
+        .unwrap();
+    assert!(cid.starts_with("baguqeera"));
+
+    let dag = store.read_dag(&cid).await.unwrap();
+    let read: Web3Package = serde_json::from_slice(&dag).unwrap();
+    assert_eq!(read.pkg_version, 5);
+    assert_eq!(read.pkg_objects.len(), 2);
+
+    let wasm = store
+        .read_object(&read.pkg_objects[0].object_cid.cid)
+        .await
+        .unwrap();
+    assert_eq!(wasm, std::fs::read("test_data/wasm_test-opt.wasm").unwrap());
+
+    let stats = store.stats().await.unwrap();
+    assert_eq!(stats.repo.num_objects, 3);
+}
+
+/// Mirrors a package from one local store to another, which addresses it by the same CIDs
+#[tokio::test]
+async fn mirror_test() {
+    let source = Web3Store::local_dir(local_store_dir("mirror_test_source")).unwrap();
+    let mirror = Web3Store::local_dir(local_store_dir("mirror_test_mirror")).unwrap();
+    let pkg = write_test_package(&source).await;
+    let cid = source
+        .write_dag(serde_json::to_vec(&pkg).unwrap())
+        .await
+        .unwrap();
+
+    let mirrored = source.mirror_package(&cid, &mirror).await.unwrap();
+    assert_eq!(
+        mirror.read_dag(&cid).await.unwrap(),
+        source.read_dag(&cid).await.unwrap()
+    );
+    for obj in &mirrored.pkg_objects {
+        assert_eq!(
+            mirror.read_object(&obj.object_cid.cid).await.unwrap(),
+            source.read_object(&obj.object_cid.cid).await.unwrap()
+        );
+    }
+}
+
+/// A signed package verifies until any of its metadata changes
+#[tokio::test]
+async fn sign_test() {
+    let store = Web3Store::local_dir(local_store_dir("sign_test")).unwrap();
+    let keypair = KeyPair::random();
+    let mut pkg = write_test_package(&store).await;
+    assert!(pkg.verify().is_err());
+
+    pkg.sign(&keypair).unwrap();
+    let public_key = hex::encode(keypair.to_miner_pk_bytes().unwrap());
+    assert_eq!(pkg.verify().unwrap(), public_key);
+
+    let mut forged = pkg.clone();
+    forged.pkg_author = "Someone Else".to_string();
+    assert!(forged.verify().is_err());
+
+    let mut resigned = pkg.clone();
+    resigned.sign(&KeyPair::random()).unwrap();
+    assert_ne!(resigned.verify().unwrap(), public_key);
+}
+
+/// Signatures survive a round trip through the store, and forged packages are rejected when
+/// they are read
+#[tokio::test]
+async fn read_signed_package_test() {
+    let store = Web3Store::local_dir(local_store_dir("read_signed_package_test")).unwrap();
+    let mut pkg = write_test_package(&store).await;
+    pkg.sign(&KeyPair::random()).unwrap();
+
+    let cid = store
+        .write_dag(serde_json::to_vec(&pkg).unwrap())
+        .await
+        .unwrap();
+    let read = store.read_package(&cid).await.unwrap();
+    assert_eq!(read.pkg_signature, pkg.pkg_signature);
+
+    pkg.pkg_version += 1;
+    let cid = store
+        .write_dag(serde_json::to_vec(&pkg).unwrap())
+        .await
+        .unwrap();
+    assert!(store.read_package(&cid).await.is_err());
+}
+
+/// Objects too large for a single block are split into chunks and put back together when they
+/// are read
+#[tokio::test]
+async fn chunked_object_test() {
+    let store = Web3Store::local_dir(local_store_dir("chunked_object_test")).unwrap();
+    let data: Vec<u8> = (0..content_id::OBJECT_CHUNK_SIZE * 2 + 100)
+        .map(|i| (i % 251) as u8)
+        .collect();
+
+    let cid = store.write_object(data.clone()).await.unwrap();
+    assert!(cid.starts_with("baguqeera"));
+    assert_eq!(store.read_object(&cid).await.unwrap(), data);
+
+    // Three chunks and the index listing them
+    let stats = store.stats().await.unwrap();
+    assert_eq!(stats.repo.num_objects, 4);
+
+    // Objects that fit in a block are still written as a single raw block
+    let small = store.write_object(b"hello world".to_vec()).await.unwrap();
+    assert_eq!(small, content_id::raw_cid(b"hello world"));
+}
+
+/// Encodes a DAG-PB block of a UnixFS file node, the way `ipfs add` does. Every length and size
+/// must be under 128 bytes.
+fn unixfs_block(data: &[u8], links: &[(&Cid, u8, u8)]) -> Vec<u8> {
+    let mut block = vec![];
+    for (cid, tsize, _) in links {
+        let hash = cid.to_bytes();
+        let mut link = vec![0x0a, hash.len() as u8];
+        link.extend(hash);
+        link.extend([0x12, 0x00, 0x18, *tsize]);
+        block.extend([0x12, link.len() as u8]);
+        block.extend(link);
+    }
+
+    let file_size = data.len() as u8 + links.iter().map(|(_, _, size)| size).sum::<u8>();
+    let mut unixfs = vec![0x08, 0x02];
+    if !data.is_empty() {
+        unixfs.extend([0x12, data.len() as u8]);
+        unixfs.extend(data);
+    }
+    unixfs.extend([0x18, file_size]);
+    for (_, _, size) in links {
+        unixfs.extend([0x20, *size]);
+    }
+    block.extend([0x0a, unixfs.len() as u8]);
+    block.extend(unixfs);
+    block
+}
+
+/// Serves the blocks of UnixFS files, the way an IPFS node holding files published with
+/// `ipfs add` would
+#[derive(Default)]
+struct UnixFsSource {
+    blocks: HashMap<String, Vec<u8>>,
+}
+
+impl UnixFsSource {
+    fn insert(&mut self, block: Vec<u8>) -> Cid {
+        let cid = Cid::new_v0(Code::Sha2_256.digest(&block)).unwrap();
+        self.blocks.insert(cid.to_string(), block);
+        cid
+    }
+}
+
+#[async_trait]
+impl Web3StoreBackend for UnixFsSource {
+    async fn write_dag(&self, _data: Vec<u8>) -> Result<String> {
+        bail!("read only")
+    }
+
+    async fn write_object(&self, _data: Vec<u8>) -> Result<String> {
+        bail!("read only")
+    }
+
+    async fn read_dag(&self, _cid: &str) -> Result<Vec<u8>> {
+        bail!("no DAG-JSON blocks")
+    }
+
+    async fn read_object(&self, _cid: &str) -> Result<Vec<u8>> {
+        bail!("only blocks are served")
+    }
+
+    /// Returns blocks as they were stored, so tampered blocks can be served
+    async fn read_block(&self, cid: &str) -> Result<Vec<u8>> {
+        self.blocks
+            .get(cid)
+            .cloned()
+            .ok_or_else(|| anyhow!("block {} not found", cid))
+    }
+
+    async fn import_unixfs(&self, _cid: &str, _from: &dyn Web3StoreBackend) -> Result<()> {
+        bail!("read only")
+    }
+
+    async fn stats(&self) -> Result<Web3StoreStats> {
+        Ok(Web3StoreStats::default())
+    }
+}
+
+/// Objects published as UnixFS files before objects were chunked can still be copied to a local
+/// store, block by block, and read back from it
+#[tokio::test]
+async fn unixfs_object_test() {
+    let mut source = UnixFsSource::default();
+    let hello = unixfs_block(b"hello ", &[]);
+    let world = unixfs_block(b"world", &[]);
+    let (hello_size, world_size) = (hello.len() as u8, world.len() as u8);
+    let hello = source.insert(hello);
+    let world = source.insert(world);
+    let root = source.insert(unixfs_block(
+        b"",
+        &[(&hello, hello_size, 6), (&world, world_size, 5)],
+    ));
+    let root = root.to_string();
+    assert!(root.starts_with("Qm"));
+
+    let content = content_id::decode_unixfs(&source.blocks[&root]).unwrap();
+    assert!(content.data.is_empty());
+    assert_eq!(content.links, vec![hello.to_string(), world.to_string()]);
+
+    let store = LocalStore::new(local_store_dir("unixfs_object_test")).unwrap();
+    store.import_unixfs(&root, &source).await.unwrap();
+    assert_eq!(store.read_object(&root).await.unwrap(), b"hello world");
+
+    // Blocks that do not match their CIDs are not imported
+    source
+        .blocks
+        .insert(world.to_string(), unixfs_block(b"there", &[]));
+    let store = LocalStore::new(local_store_dir("unixfs_object_tampered_test")).unwrap();
+    assert!(store.import_unixfs(&root, &source).await.is_err());
+}
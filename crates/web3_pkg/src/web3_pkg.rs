@@ -1,6 +1,10 @@
+use anyhow::{anyhow, Result};
 use clap::clap_derive::ArgEnum;
 use derive_builder::Builder;
 use serde_derive::{Deserialize, Serialize};
+use vrrb_core::keypair::KeyPair;
+
+use crate::content_id;
 
 /// An enum representing different flavours of package payload. In some cases, a package might
 /// contain a smart contract (or potentially multiple smart contracts), in other cases it could be
@@ -79,6 +83,17 @@ pub struct Web3PackageObject {
     pub object_cid: Web3ContentId,
 }
 
+/// A struct representing a publisher's signature over a package, embedded in the package
+/// metadata.
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Web3PackageSignature {
+    /// Hex encoded secp256k1 public key of the publisher.
+    pub public_key: String,
+    /// ECDSA signature over the package, as returned by [Web3Package::signing_payload].
+    pub signature: String,
+}
+
 /// A structure representing the metadata of a compute package. A compute package may contain one
 /// or more objects (see [Web3PackageObject] above) that represent binaries compatible with the
 /// compute stack.
@@ -102,4 +117,52 @@ pub struct Web3Package {
     /// A vector of packages that this replaces. XXX: This could be problematic when exporting a
     /// DAG when there's a long history.
     pub pkg_replaces: Vec<Web3ContentId>,
+    /// The publisher's signature over the package. Set by [Web3Package::sign], packages
+    /// without one are unsigned.
+    #[builder(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pkg_signature: Option<Web3PackageSignature>,
+}
+
+impl Web3Package {
+    /// Returns the bytes a publisher signs: the package without its signature, encoded as
+    /// canonical DAG-JSON so the payload does not depend on how the package was serialized.
+    pub fn signing_payload(&self) -> Result<Vec<u8>> {
+        let unsigned = Web3Package {
+            pkg_signature: None,
+            ..self.clone()
+        };
+        let (_, block) = content_id::encode_dag_json(&serde_json::to_vec(&unsigned)?)?;
+        Ok(block)
+    }
+
+    /// Signs the package with the secp256k1 key of `keypair`, replacing any previous signature.
+    pub fn sign(&mut self, keypair: &KeyPair) -> Result<()> {
+        let payload = self.signing_payload()?;
+        let secret_key = keypair.get_miner_secret_key().secret_bytes().to_vec();
+        let signature = KeyPair::ecdsa_sign(&payload, secret_key)?;
+
+        self.pkg_signature = Some(Web3PackageSignature {
+            public_key: hex::encode(keypair.to_miner_pk_bytes()?),
+            signature,
+        });
+        Ok(())
+    }
+
+    /// Checks the package's signature, failing if it is unsigned or was modified after it was
+    /// signed. On success, returns the public key of the publisher.
+    pub fn verify(&self) -> Result<&str> {
+        let pkg_signature = self
+            .pkg_signature
+            .as_ref()
+            .ok_or_else(|| anyhow!("package {} is not signed", self.pkg_name))?;
+        let public_key = hex::decode(&pkg_signature.public_key)?;
+
+        KeyPair::verify_ecdsa_sign(
+            pkg_signature.signature.clone(),
+            &self.signing_payload()?,
+            public_key,
+        )?;
+        Ok(&pkg_signature.public_key)
+    }
 }
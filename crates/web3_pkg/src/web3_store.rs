@@ -1,13 +1,57 @@
-use anyhow::Result;
-use futures::TryStreamExt;
-use ipfs_api::{IpfsApi, IpfsClient, TryFromUri};
+use anyhow::{bail, Result};
+use async_trait::async_trait;
 use serde_derive::{Deserialize, Serialize};
-use std::io::Cursor;
+use std::path::Path;
 
-/// A structure representing a content-addressable Web3 store. Currently closely tied to IPFS
-/// specifically, but could be expanded to others, such as Iroh.
+use crate::content_id;
+use crate::ipfs_store::IpfsStore;
+use crate::local_store::LocalStore;
+use crate::web3_pkg::{Web3ContentId, Web3Package};
+
+/// A content-addressable storage backend. Implementations must address blocks the way
+/// [crate::content_id] does, so that the same content gets the same CID from every backend and
+/// packages can be copied between them.
+#[async_trait]
+pub trait Web3StoreBackend: Send + Sync {
+    /// Writes a DAG-JSON document and returns its CID
+    async fn write_dag(&self, data: Vec<u8>) -> Result<String>;
+
+    /// Writes opaque bytes, no more than [content_id::OBJECT_CHUNK_SIZE] of them, as a raw block
+    /// and returns its CID
+    async fn write_object(&self, data: Vec<u8>) -> Result<String>;
+
+    /// Reads a DAG object by CID, in DAG-JSON format
+    async fn read_dag(&self, cid: &str) -> Result<Vec<u8>>;
+
+    /// Reads an unstructured object stored as a raw block, or as a UnixFS file, by CID
+    async fn read_object(&self, cid: &str) -> Result<Vec<u8>>;
+
+    /// Reads a single block by CID, checked against the CID
+    async fn read_block(&self, cid: &str) -> Result<Vec<u8>>;
+
+    /// Copies the UnixFS file published under `cid` from another backend, failing unless it is
+    /// addressed by the same CID here
+    async fn import_unixfs(&self, cid: &str, from: &dyn Web3StoreBackend) -> Result<()>;
+
+    /// Returns stats about the backend
+    async fn stats(&self) -> Result<Web3StoreStats>;
+}
+
+/// A structure representing a content-addressable Web3 store. Backed by an IPFS node by default,
+/// or by a directory on the local filesystem for tests and offline environments.
 pub struct Web3Store {
-    client: IpfsClient,
+    backend: Box<dyn Web3StoreBackend>,
+}
+
+/// The index of an object too large for a single block, written as DAG-JSON. Its CID is the CID
+/// of the object.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ObjectChunks {
+    /// Size of the whole object in bytes
+    size: u64,
+    /// The raw blocks the object is split into, in order
+    chunks: Vec<Web3ContentId>,
 }
 
 /// A structure representing stats for a content-addressable Web3 store. Currently closely tied to
@@ -60,84 +104,136 @@ impl Web3Store {
     /// A constructor that uses the default configuration to connect to a local IPFS
     /// implementation's RPC service on the default port of TCP/5001.
     pub fn local() -> Result<Self> {
-        Ok(Web3Store {
-            client: IpfsClient::default(),
-        })
+        Ok(Self::from_backend(IpfsStore::local()?))
     }
 
     /// A constructor that takes a multiaddr string (eg, "/ip4/127.0.0.1/tcp/5001") to connect to
     /// the RPC service on an IPFS instance.
     pub fn from_multiaddr(addr: &str) -> Result<Self> {
-        Ok(Web3Store {
-            client: IpfsClient::from_multiaddr_str(addr)?,
-        })
+        Ok(Self::from_backend(IpfsStore::from_multiaddr(addr)?))
+    }
+
+    /// A constructor for a store keeping its blocks in a directory on the local filesystem,
+    /// which is created if it does not exist. No IPFS service is needed.
+    pub fn local_dir(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::from_backend(LocalStore::new(path)?))
     }
 
-    /// A method to take a vector of bytes and write them as a DAG to IPFS. It is expected that the
-    /// bytes are either in DAG-JSON or DAG-CBOR by the underlying protocol.
-    /// On success, returns a string representation of the CID of the object written.
+    /// A constructor for a store using any other backend
+    pub fn from_backend(backend: impl Web3StoreBackend + 'static) -> Self {
+        Web3Store {
+            backend: Box::new(backend),
+        }
+    }
+
+    /// A method to take a vector of bytes and write them as a DAG-JSON object. On success,
+    /// returns a string representation of the CID of the object written.
     pub async fn write_dag(&self, data: Vec<u8>) -> Result<String> {
-        let curs = Cursor::new(data);
-        let cid = self.client.dag_put(curs).await?;
-        Ok(cid.cid.cid_string)
+        self.backend.write_dag(data).await
     }
 
-    /// A method to take a vector of bytes and write them to IPFS as a DAG-PB object. This is the
-    /// IPFS file interface and the data is treated as opaque, but will be split into DAG blocks.
-    /// On success, returns a string representation of the CID of the object written.
+    /// A method to take a vector of bytes and write them as a raw block. The data is treated as
+    /// opaque. Data larger than [content_id::OBJECT_CHUNK_SIZE] is split into raw blocks, listed
+    /// in a DAG-JSON index whose CID is returned. On success, returns a string representation of
+    /// the CID of the object written.
     pub async fn write_object(&self, data: Vec<u8>) -> Result<String> {
-        let curs = Cursor::new(data);
-        let cid = self.client.add(curs).await?;
-        Ok(cid.hash)
+        if data.len() <= content_id::OBJECT_CHUNK_SIZE {
+            return self.backend.write_object(data).await;
+        }
+
+        let mut chunks = vec![];
+        for chunk in data.chunks(content_id::OBJECT_CHUNK_SIZE) {
+            let cid = self.backend.write_object(chunk.to_vec()).await?;
+            chunks.push(Web3ContentId { cid });
+        }
+        let index = ObjectChunks {
+            size: data.len() as u64,
+            chunks,
+        };
+        self.backend.write_dag(serde_json::to_vec(&index)?).await
     }
 
     /// A method to retrieve a DAG object by CID from the web3 datastore. Returns it in DAG-JSON
     /// format.
     pub async fn read_dag(&self, cid: &str) -> Result<Vec<u8>> {
-        let ret = self
-            .client
-            .dag_get(cid)
-            .map_ok(|chunk| chunk.to_vec())
-            .try_concat()
-            .await?;
-        Ok(ret)
+        self.backend.read_dag(cid).await
     }
 
-    /// A method to retrieve an unstructured object from the web3 store by CID
+    /// A method to retrieve an unstructured object from the web3 store by CID. Objects split into
+    /// chunks are put back together.
     pub async fn read_object(&self, cid: &str) -> Result<Vec<u8>> {
-        let ret = self
-            .client
-            .cat(cid)
-            .map_ok(|chunk| chunk.to_vec())
-            .try_concat()
-            .await?;
-        Ok(ret)
+        if content_id::codec(cid)? != content_id::DAG_JSON_CODEC {
+            return self.backend.read_object(cid).await;
+        }
+
+        let index: ObjectChunks = serde_json::from_slice(&self.read_dag(cid).await?)?;
+        let mut data = vec![];
+        for chunk in &index.chunks {
+            if content_id::codec(&chunk.cid)? != content_id::RAW_CODEC {
+                bail!(
+                    "object {} has a chunk {} that is not a raw block",
+                    cid,
+                    chunk.cid
+                );
+            }
+            data.extend(self.backend.read_object(&chunk.cid).await?);
+        }
+        if data.len() as u64 != index.size {
+            bail!(
+                "object {} is {} bytes rather than {}",
+                cid,
+                data.len(),
+                index.size
+            );
+        }
+
+        Ok(data)
+    }
+
+    /// A method to retrieve a package by CID. The signature of a signed package is verified,
+    /// unsigned packages are returned as they are.
+    pub async fn read_package(&self, cid: &str) -> Result<Web3Package> {
+        let dag = self.read_dag(cid).await?;
+        let pkg: Web3Package = serde_json::from_slice(&dag)?;
+        if pkg.pkg_signature.is_some() {
+            pkg.verify()?;
+        }
+        Ok(pkg)
     }
 
-    /// A method to retrieve stats from the IPFS service and return them
+    /// A method to retrieve stats from the backend and return them
     pub async fn stats(&self) -> Result<Web3StoreStats> {
-        let repo = self.client.stats_repo().await?;
-        let bw = self.client.stats_bw().await?;
-        let bs = self.client.stats_bitswap().await?;
-
-        Ok(Web3StoreStats {
-            repo: Web3StoreRepoStats {
-                num_objects: repo.num_objects,
-                repo_size: repo.repo_size,
-                repo_path: repo.repo_path,
-            },
-            bandwidth: Web3StoreBandwidthStats {
-                total_in: bw.total_in,
-                total_out: bw.total_out,
-                rate_in: bw.rate_in,
-                rate_out: bw.rate_out,
-            },
-            bitswap: Web3StoreBitswapStats {
-                blocks_in: bs.blocks_received,
-                blocks_out: bs.blocks_sent,
-                data_in: bs.data_received,
-                data_out: bs.data_sent,
-            },
-        })
+        self.backend.stats().await
+    }
+
+    /// A method to copy the package with the given CID, along with every object it contains,
+    /// into another store. Fails if the other store addresses any of them by a different CID.
+    pub async fn mirror_package(&self, cid: &str, to: &Web3Store) -> Result<Web3Package> {
+        let pkg = self.read_package(cid).await?;
+        let dag = self.read_dag(cid).await?;
+
+        for obj in &pkg.pkg_objects {
+            // Objects published as UnixFS files are copied as they are, as writing them again
+            // would split them up differently
+            if content_id::codec(&obj.object_cid.cid)? == content_id::DAG_PB_CODEC {
+                to.backend
+                    .import_unixfs(&obj.object_cid.cid, self.backend.as_ref())
+                    .await?;
+                continue;
+            }
+
+            let data = self.read_object(&obj.object_cid.cid).await?;
+            let mirrored = to.write_object(data).await?;
+            if mirrored != content_id::normalize(&obj.object_cid.cid)? {
+                bail!("object {} was mirrored as {}", obj.object_cid.cid, mirrored);
+            }
+        }
+
+        let mirrored = to.write_dag(dag).await?;
+        if mirrored != content_id::normalize(cid)? {
+            bail!("package {} was mirrored as {}", cid, mirrored);
+        }
+
+        Ok(pkg)
     }
 }